@@ -0,0 +1,153 @@
+const COMMANDS: &[&str] = &[
+    "get_audio_devices",
+    "start_monitoring",
+    "stop_monitoring",
+    "pause_monitoring",
+    "get_stream_state",
+    "list_streams",
+    "get_volume",
+    "get_stream_info",
+    "get_stream_stats",
+    "set_stream_auto_restart",
+    "set_input_gain",
+    "set_mid_side",
+    "set_stream_mute",
+    "set_stream_solo",
+    "set_dynamics",
+    "set_input_chain",
+    "set_master_chain",
+    "set_ducking",
+    "read_wav_file",
+    "read_raw_pcm",
+    "convert_audio_file",
+    "crossfade_files",
+    "mix_files",
+    "get_wav_metadata",
+    "set_wav_metadata",
+    "test_output_device",
+    "start_passthrough",
+    "stop_passthrough",
+    "set_passthrough_buffer",
+    "get_passthrough_status",
+    "set_passthrough_convolution",
+    "scan_plugins",
+    "insert_passthrough_plugin",
+    "remove_passthrough_plugin",
+    "get_passthrough_plugins",
+    "get_plugin_parameters",
+    "set_plugin_parameter",
+    "process_file_with_plugins",
+    "convolve_file",
+    "capture_impulse_response",
+    "measure_frequency_response",
+    "analyze_rt60",
+    "measure_snr",
+    "list_whisper_models",
+    "download_whisper_model",
+    "delete_whisper_model",
+    "transcribe_file",
+    "detect_language",
+    "export_subtitles",
+    "start_live_transcription",
+    "stop_live_transcription",
+    "set_live_keywords",
+    "start_mixer",
+    "stop_mixer",
+    "add_to_mix",
+    "remove_from_mix",
+    "set_mix_gain",
+    "get_mix_levels",
+    "reset_mix_loudness",
+    "start_mix_recording",
+    "stop_mix_recording",
+    "get_spectrum",
+    "get_band_levels",
+    "start_goniometer",
+    "stop_goniometer",
+    "start_oscilloscope",
+    "stop_oscilloscope",
+    "get_phase_relationship",
+    "get_loudness",
+    "reset_loudness",
+    "analyze_loudness",
+    "scan_folder_levels",
+    "get_waveform_overview",
+    "analyze_spectrum",
+    "render_spectrogram",
+    "render_waveform",
+    "detect_clipping",
+    "detect_dropouts",
+    "detect_silence",
+    "split_on_silence",
+    "export_regions",
+    "get_markers",
+    "set_markers",
+    "read_cue_sheet",
+    "write_cue_sheet",
+    "start_recording",
+    "stop_recording",
+    "save_recording",
+    "discard_recording",
+    "play_file",
+    "stop_playback",
+    "pause_playback",
+    "seek_playback",
+    "set_ab_loop",
+    "set_playback_eq",
+    "set_playback_convolution",
+    "scrub_to",
+    "end_scrub",
+    "set_position_interval",
+    "set_playback_fades",
+    "get_playback_status",
+    "open_clip_output",
+    "preload_clip",
+    "trigger_clip",
+    "stop_clips",
+    "unload_clip",
+    "list_clips",
+    "assign_pad",
+    "clear_pad",
+    "load_soundboard",
+    "trigger_pad",
+    "stop_pad",
+    "get_soundboard",
+    "start_dual_recording",
+    "stop_dual_recording",
+    "get_dual_recording_status",
+    "get_recording_presets",
+    "save_recording_preset",
+    "delete_recording_preset",
+    "recover_recording",
+    "start_overdub",
+    "stop_overdub",
+    "start_punch",
+    "stop_punch",
+    "set_device_alias",
+    "get_device_aliases",
+    "get_event_rates",
+    "set_event_rates",
+    "get_lifecycle_states",
+    "subscribe",
+    "check_audio_permission",
+    "get_output_latency",
+    "set_output_latency",
+    "request_audio_permission",
+    "unsubscribe",
+    "get_sync_status",
+    "run_dsp_benchmark",
+    "get_metering_settings",
+    "set_metering_settings",
+    "get_recent_audio",
+    "save_recent_audio",
+    "capture_snapshot",
+    "get_recent_audio_settings",
+    "set_recent_audio_settings",
+    "set_hotkey",
+    "remove_hotkey",
+    "get_hotkeys",
+];
+
+fn main() {
+    tauri_plugin::Builder::new(COMMANDS).build();
+}
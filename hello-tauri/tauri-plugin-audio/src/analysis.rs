@@ -0,0 +1,495 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+
+use crate::audio_file::read_audio_file;
+use crate::dsp::bands::{band_levels, BandLevel, BandResolution};
+use crate::dsp::correlation::cross_correlation;
+use crate::dsp::fft::{amplitude_to_db, Spectrum, SpectrumAnalyzer};
+use crate::dsp::loudness::{LoudnessMeter, LoudnessReport};
+use crate::events;
+use crate::settings::SettingsStore;
+use crate::stream::{StreamShared, ANALYSIS_WINDOW, STEREO_WINDOW};
+use crate::subscriptions;
+use crate::ticker::Ticker;
+use crate::validate::{self, ErrorCode};
+use crate::AudioState;
+
+const DEFAULT_GONIOMETER_POINTS: usize = 512;
+const DEFAULT_OSCILLOSCOPE_POINTS: usize = 256;
+const DEFAULT_OSCILLOSCOPE_WINDOW_MS: f32 = 50.0;
+
+const DEFAULT_METERING_RATE_HZ: f32 = 30.0;
+const METERING_RATE_RANGE_HZ: (f32, f32) = (10.0, 120.0);
+/// Allowed FFT lengths for the live spectrum, up to the analysis window.
+const FFT_SIZE_RANGE: (usize, usize) = (512, ANALYSIS_WINDOW);
+
+/// How often and at what resolution the live analyzers run. A lower rate
+/// and a smaller FFT keep metering cheap, e.g. on battery.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct MeteringSettings {
+    /// `audio://spectrum` frames per second, also the default for the
+    /// goniometer and oscilloscope. The `audio://spectrum` event rate limit
+    /// still applies on top.
+    pub rate_hz: f32,
+    /// FFT length for the live spectrum and band levels, a power of two.
+    pub fft_size: usize,
+}
+
+impl Default for MeteringSettings {
+    fn default() -> Self {
+        MeteringSettings {
+            rate_hz: DEFAULT_METERING_RATE_HZ,
+            fft_size: ANALYSIS_WINDOW,
+        }
+    }
+}
+
+impl MeteringSettings {
+    fn validate(&self) -> Result<(), String> {
+        validate::range("Metering rate", self.rate_hz, METERING_RATE_RANGE_HZ, "Hz")?;
+        validate::range("FFT size", self.fft_size, FFT_SIZE_RANGE, "samples")?;
+        if !self.fft_size.is_power_of_two() {
+            return Err(validate::error(
+                ErrorCode::OutOfRange,
+                format!("FFT size must be a power of two, got {}", self.fft_size),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SpectrumData {
+    pub bin_hz: f32,
+    pub magnitudes_db: Vec<f32>,
+}
+
+/// Runs `analyzer` over the newest samples of a monitored stream. The
+/// stream locks are only held while the samples are copied.
+fn live_spectrum(stream_id: &str, analyzer: &mut SpectrumAnalyzer, state: &AudioState) -> Result<Spectrum, String> {
+    let fft_size = analyzer.fft_size();
+    let (samples, sample_rate) = {
+        let streams = state.streams.lock().unwrap();
+        let stream = streams
+            .get(stream_id)
+            .ok_or_else(|| validate::stream_not_monitored(stream_id))?;
+        let samples = stream.shared.analysis.lock().unwrap().latest(fft_size);
+        (samples, stream.sample_rate)
+    };
+
+    if samples.len() < fft_size {
+        return Err("Not enough audio captured yet".to_string());
+    }
+
+    Ok(Spectrum {
+        bin_hz: sample_rate as f32 / fft_size as f32,
+        amplitudes: analyzer.amplitudes(&samples),
+    })
+}
+
+fn spectrum_data(spectrum: &Spectrum) -> SpectrumData {
+    SpectrumData {
+        bin_hz: spectrum.bin_hz,
+        magnitudes_db: spectrum.amplitudes.iter().map(|&a| amplitude_to_db(a)).collect(),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_spectrum(
+    stream_id: String,
+    feed: State<SpectrumFeed>,
+    state: State<AudioState>,
+) -> Result<SpectrumData, String> {
+    Ok(spectrum_data(&feed.spectrum(&stream_id, &state)?))
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://spectrum")]
+pub(crate) struct SpectrumFrame {
+    stream_id: String,
+    #[serde(flatten)]
+    spectrum: SpectrumData,
+}
+
+/// Emits `audio://spectrum` for each monitored stream some window has
+/// subscribed to, paced by the metering settings. No FFT runs while nobody
+/// has.
+pub struct SpectrumFeed {
+    app: AppHandle,
+    metering: Mutex<MeteringSettings>,
+    /// Planned at the metering FFT size for on-demand spectra; the feed's
+    /// ticker has its own.
+    analyzer: Mutex<SpectrumAnalyzer>,
+    ticker: Mutex<Ticker>,
+}
+
+impl SpectrumFeed {
+    /// Starts with the saved `metering`, or the defaults if they are invalid.
+    pub fn start(app: AppHandle, metering: MeteringSettings) -> Self {
+        let metering = match metering.validate() {
+            Ok(()) => metering,
+            Err(e) => {
+                eprintln!("Ignoring invalid metering settings: {}", e);
+                MeteringSettings::default()
+            }
+        };
+
+        SpectrumFeed {
+            ticker: Mutex::new(spawn_spectrum_ticker(app.clone(), metering)),
+            app,
+            metering: Mutex::new(metering),
+            analyzer: Mutex::new(SpectrumAnalyzer::new(metering.fft_size)),
+        }
+    }
+
+    pub fn metering(&self) -> MeteringSettings {
+        *self.metering.lock().unwrap()
+    }
+
+    /// Spectrum of a monitored stream at the metering FFT size.
+    fn spectrum(&self, stream_id: &str, state: &AudioState) -> Result<Spectrum, String> {
+        live_spectrum(stream_id, &mut self.analyzer.lock().unwrap(), state)
+    }
+
+    /// Restarts the feed at the new rate and FFT size.
+    fn set_metering(&self, metering: MeteringSettings) {
+        *self.metering.lock().unwrap() = metering;
+        *self.analyzer.lock().unwrap() = SpectrumAnalyzer::new(metering.fft_size);
+        *self.ticker.lock().unwrap() = spawn_spectrum_ticker(self.app.clone(), metering);
+    }
+}
+
+fn spawn_spectrum_ticker(app: AppHandle, metering: MeteringSettings) -> Ticker {
+    let mut analyzer = SpectrumAnalyzer::new(metering.fft_size);
+    Ticker::spawn(Duration::from_secs_f32(1.0 / metering.rate_hz), move || {
+        let state = app.state::<AudioState>();
+        let stream_ids: Vec<String> = state.streams.lock().unwrap().keys().cloned().collect();
+        for stream_id in stream_ids {
+            if !subscriptions::wanted(&app, "audio://spectrum", &stream_id) {
+                continue;
+            }
+            // Streams still filling their window are skipped
+            if let Ok(spectrum) = live_spectrum(&stream_id, &mut analyzer, &state) {
+                events::emit(&app, "audio://spectrum", &stream_id, SpectrumFrame {
+                    stream_id: stream_id.clone(),
+                    spectrum: spectrum_data(&spectrum),
+                });
+            }
+        }
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_metering_settings(feed: State<SpectrumFeed>) -> MeteringSettings {
+    feed.metering()
+}
+
+/// Changes the live analyzers' rate and FFT size. The setting is saved and
+/// takes effect straight away; running goniometers and oscilloscopes keep
+/// their rate.
+#[tauri::command]
+#[specta::specta]
+pub fn set_metering_settings(
+    metering: MeteringSettings,
+    feed: State<SpectrumFeed>,
+    settings: State<SettingsStore>,
+) -> Result<(), String> {
+    metering.validate()?;
+    settings.update(|s| s.metering = metering)?;
+    feed.set_metering(metering);
+    Ok(())
+}
+
+/// Graphic-analyzer style band levels (31-band third-octave by default).
+#[tauri::command]
+#[specta::specta]
+pub fn get_band_levels(
+    stream_id: String,
+    resolution: Option<BandResolution>,
+    feed: State<SpectrumFeed>,
+    state: State<AudioState>,
+) -> Result<Vec<BandLevel>, String> {
+    let spectrum = feed.spectrum(&stream_id, &state)?;
+    Ok(band_levels(&spectrum, resolution.unwrap_or_default()))
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://goniometer")]
+pub(crate) struct GoniometerFrame {
+    stream_id: String,
+    /// Lissajous points as (side, mid) pairs: a mono signal is a vertical
+    /// line, out-of-phase content spreads horizontally.
+    points: Vec<[f32; 2]>,
+}
+
+/// Decimates the newest L/R pairs to `count` points rotated 45 degrees.
+fn goniometer_points(shared: &StreamShared, count: usize) -> Vec<[f32; 2]> {
+    let pairs = shared.stereo.lock().unwrap().latest(STEREO_WINDOW * 2);
+    let frames = pairs.len() / 2;
+    if frames == 0 {
+        return Vec::new();
+    }
+
+    let count = count.min(frames);
+    (0..count)
+        .map(|i| {
+            let frame = i * frames / count;
+            let (left, right) = (pairs[frame * 2], pairs[frame * 2 + 1]);
+            [(right - left) * FRAC_1_SQRT_2, (left + right) * FRAC_1_SQRT_2]
+        })
+        .collect()
+}
+
+/// Starts emitting `audio://goniometer` frames for a stereo stream, at the
+/// metering rate unless `rate_hz` is given.
+#[tauri::command]
+#[specta::specta]
+pub fn start_goniometer(
+    stream_id: String,
+    rate_hz: Option<f32>,
+    points: Option<usize>,
+    app: AppHandle,
+    feed: State<SpectrumFeed>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    let rate_hz = rate_hz.unwrap_or(feed.metering().rate_hz);
+    validate::range("Goniometer rate", rate_hz, (1.0, 120.0), "Hz")?;
+    let points = points.unwrap_or(DEFAULT_GONIOMETER_POINTS).clamp(1, STEREO_WINDOW);
+
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    if stream.channels < 2 {
+        return Err(format!("Stream '{}' is not stereo", stream_id));
+    }
+
+    let shared = Arc::clone(&stream.shared);
+    let event_stream_id = stream_id.clone();
+    stream.goniometer = Some(Ticker::spawn(Duration::from_secs_f32(1.0 / rate_hz), move || {
+        if !subscriptions::wanted(&app, "audio://goniometer", &event_stream_id) {
+            return;
+        }
+        events::emit(&app, "audio://goniometer", &event_stream_id, GoniometerFrame {
+            stream_id: event_stream_id.clone(),
+            points: goniometer_points(&shared, points),
+        });
+    }));
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_goniometer(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    stream.goniometer = None;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://oscilloscope")]
+pub(crate) struct OscilloscopeFrame {
+    stream_id: String,
+    /// Length of audio the peaks cover, oldest first.
+    window_ms: f32,
+    /// `[min, max]` of the mono mix per point.
+    peaks: Vec<[f32; 2]>,
+}
+
+/// Reduces the newest `frames` mono samples to `count` min/max pairs.
+fn oscilloscope_peaks(shared: &StreamShared, frames: usize, count: usize) -> Vec<[f32; 2]> {
+    let samples = shared.analysis.lock().unwrap().latest(frames);
+    if samples.is_empty() {
+        return Vec::new();
+    }
+
+    let count = count.min(samples.len());
+    (0..count)
+        .map(|i| {
+            let start = i * samples.len() / count;
+            let end = ((i + 1) * samples.len() / count).max(start + 1);
+            samples[start..end]
+                .iter()
+                .fold([f32::MAX, f32::MIN], |[min, max], &s| [min.min(s), max.max(s)])
+        })
+        .collect()
+}
+
+/// Starts emitting `audio://oscilloscope` frames of the newest `window_ms`
+/// of a stream (50 ms by default), at the metering rate unless `rate_hz`
+/// is given.
+#[tauri::command]
+#[specta::specta]
+pub fn start_oscilloscope(
+    stream_id: String,
+    rate_hz: Option<f32>,
+    window_ms: Option<f32>,
+    points: Option<usize>,
+    app: AppHandle,
+    feed: State<SpectrumFeed>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    let rate_hz = rate_hz.unwrap_or(feed.metering().rate_hz);
+    validate::range("Oscilloscope rate", rate_hz, (1.0, 120.0), "Hz")?;
+    let points = points.unwrap_or(DEFAULT_OSCILLOSCOPE_POINTS).max(1);
+
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    // The window is limited by what the analysis buffer holds
+    let max_window_ms = ANALYSIS_WINDOW as f32 * 1000.0 / stream.sample_rate as f32;
+    let window_ms = window_ms.unwrap_or(DEFAULT_OSCILLOSCOPE_WINDOW_MS);
+    validate::range("Oscilloscope window", window_ms, (1.0, max_window_ms), "ms")?;
+    let frames = (window_ms / 1000.0 * stream.sample_rate as f32).round() as usize;
+
+    let shared = Arc::clone(&stream.shared);
+    let event_stream_id = stream_id.clone();
+    stream.oscilloscope = Some(Ticker::spawn(Duration::from_secs_f32(1.0 / rate_hz), move || {
+        if !subscriptions::wanted(&app, "audio://oscilloscope", &event_stream_id) {
+            return;
+        }
+        events::emit(&app, "audio://oscilloscope", &event_stream_id, OscilloscopeFrame {
+            stream_id: event_stream_id.clone(),
+            window_ms,
+            peaks: oscilloscope_peaks(&shared, frames, points),
+        });
+    }));
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_oscilloscope(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    stream.oscilloscope = None;
+    Ok(())
+}
+
+/// Largest alignment offset searched when comparing two streams.
+const MAX_PHASE_LAG_MS: f32 = 10.0;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PhaseRelationship {
+    /// Correlation with no time offset: +1 in phase, -1 inverted, ~0 unrelated.
+    pub correlation: f32,
+    /// Offset of `stream_b` relative to `stream_a` that lines them up best.
+    /// Separate devices also contribute their own buffering to this value.
+    pub best_lag_samples: i32,
+    pub best_lag_ms: f32,
+    pub best_correlation: f32,
+    /// The strongest match is negative, i.e. one source is wired backwards.
+    pub polarity_inverted: bool,
+}
+
+/// Compares the latest audio of two monitored streams to spot polarity and
+/// alignment problems, e.g. two mics on the same source.
+#[tauri::command]
+#[specta::specta]
+pub fn get_phase_relationship(
+    stream_a: String,
+    stream_b: String,
+    state: State<AudioState>,
+) -> Result<PhaseRelationship, String> {
+    let streams = state.streams.lock().unwrap();
+    let get = |id: &str| {
+        streams
+            .get(id)
+            .ok_or_else(|| validate::stream_not_monitored(id))
+    };
+    let (a, b) = (get(&stream_a)?, get(&stream_b)?);
+
+    if a.sample_rate != b.sample_rate {
+        return Err("Both streams must run at the same sample rate".to_string());
+    }
+
+    let samples_a = a.shared.analysis.lock().unwrap().latest(ANALYSIS_WINDOW);
+    let samples_b = b.shared.analysis.lock().unwrap().latest(ANALYSIS_WINDOW);
+    if samples_a.len() < ANALYSIS_WINDOW || samples_b.len() < ANALYSIS_WINDOW {
+        return Err("Not enough audio captured yet".to_string());
+    }
+
+    let max_lag = (MAX_PHASE_LAG_MS / 1000.0 * a.sample_rate as f32) as usize;
+    let correlation = cross_correlation(&samples_a, &samples_b, max_lag);
+
+    let (best_index, &best_correlation) = correlation
+        .iter()
+        .enumerate()
+        .max_by(|(_, x), (_, y)| x.abs().total_cmp(&y.abs()))
+        .ok_or_else(|| "Correlation produced no values".to_string())?;
+    let best_lag_samples = best_index as i32 - max_lag as i32;
+
+    Ok(PhaseRelationship {
+        correlation: correlation[max_lag],
+        best_lag_samples,
+        best_lag_ms: best_lag_samples as f32 * 1000.0 / a.sample_rate as f32,
+        best_correlation,
+        polarity_inverted: best_correlation < 0.0,
+    })
+}
+
+/// Live EBU R128 loudness of a monitored stream since it started or was reset.
+#[tauri::command]
+#[specta::specta]
+pub fn get_loudness(stream_id: String, state: State<AudioState>) -> Result<LoudnessReport, String> {
+    let shared = {
+        let streams = state.streams.lock().unwrap();
+        let stream = streams
+            .get(&stream_id)
+            .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+        Arc::clone(&stream.shared)
+    };
+
+    // Read from the meter's running totals, so the callback is held up
+    // no longer however long the stream has run
+    let report = shared.loudness.lock().unwrap().report();
+    Ok(report)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn reset_loudness(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    *stream.shared.loudness.lock().unwrap() = LoudnessMeter::new(stream.sample_rate, stream.channels as usize);
+    Ok(())
+}
+
+/// Measures the loudness of a whole file, including loudness range.
+#[tauri::command]
+#[specta::specta]
+pub async fn analyze_loudness(file_path: String) -> Result<LoudnessReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&file_path))?;
+
+        let mut meter = LoudnessMeter::new(audio.sample_rate, audio.channels as usize);
+        meter.process(&audio.samples);
+        Ok(meter.report())
+    })
+    .await
+    .map_err(|e| format!("Loudness analysis failed: {}", e))?
+}
@@ -0,0 +1,257 @@
+use std::fs;
+use std::path::Path;
+
+use super::pcm::{self, PcmLayout};
+use super::{AudioBuffer, WavEncoding};
+
+/// Decodes the 80-bit IEEE extended float AIFF uses for the sample rate.
+fn read_extended(bytes: &[u8]) -> f64 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff) as i32;
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+fn write_extended(value: u32) -> [u8; 10] {
+    let mut bytes = [0u8; 10];
+    if value == 0 {
+        return bytes;
+    }
+
+    let top_bit = 31 - value.leading_zeros();
+    let exponent = (16383 + top_bit) as u16;
+    let mantissa = (value as u64) << (63 - top_bit);
+
+    bytes[..2].copy_from_slice(&exponent.to_be_bytes());
+    bytes[2..].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes(bytes[..2].try_into().unwrap())
+}
+
+struct Common {
+    channels: u16,
+    frames: u32,
+    sample_rate: u32,
+    layout: PcmLayout,
+}
+
+fn parse_common(chunk: &[u8], is_aifc: bool) -> Result<Common, String> {
+    if chunk.len() < 18 {
+        return Err("AIFF COMM chunk is too short".to_string());
+    }
+
+    let bits = be_u16(&chunk[6..8]);
+    let (bits, float, little_endian) = if is_aifc && chunk.len() >= 22 {
+        match &chunk[18..22] {
+            b"NONE" | b"twos" => (bits, false, false),
+            b"sowt" => (bits, false, true),
+            b"fl32" | b"FL32" => (32, true, false),
+            b"fl64" | b"FL64" => (64, true, false),
+            other => {
+                return Err(format!("Unsupported AIFC compression: {}", String::from_utf8_lossy(other)))
+            }
+        }
+    } else {
+        (bits, false, false)
+    };
+
+    Ok(Common {
+        channels: be_u16(&chunk[0..2]),
+        frames: be_u32(&chunk[2..6]),
+        sample_rate: read_extended(&chunk[8..18]).round() as u32,
+        layout: PcmLayout { bits, float, little_endian },
+    })
+}
+
+pub fn read_aiff(path: &Path) -> Result<AudioBuffer, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open AIFF file: {}", e))?;
+
+    if bytes.len() < 12 || &bytes[0..4] != b"FORM" {
+        return Err("Not an AIFF file".to_string());
+    }
+    let is_aifc = match &bytes[8..12] {
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return Err("Not an AIFF file".to_string()),
+    };
+
+    let mut common = None;
+    let mut sound = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = be_u32(&bytes[offset + 4..]) as usize;
+        let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+
+        match id {
+            b"COMM" => common = Some(parse_common(body, is_aifc)?),
+            b"SSND" if body.len() >= 8 => {
+                let data_offset = be_u32(body) as usize;
+                sound = Some(&body[(8 + data_offset).min(body.len())..]);
+            }
+            _ => {}
+        }
+
+        // Chunks are padded to an even length
+        offset += 8 + size + (size & 1);
+    }
+
+    let common = common.ok_or_else(|| "AIFF file has no COMM chunk".to_string())?;
+    let data = sound.unwrap_or_default();
+    let mut samples = pcm::decode(data, common.layout)?;
+    samples.truncate(common.frames as usize * common.channels as usize);
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate: common.sample_rate,
+        channels: common.channels,
+    })
+}
+
+/// Writes integer encodings as plain AIFF and float as AIFC `fl32`, which
+/// is what Logic and Pro Tools produce for the same formats.
+pub fn write_aiff(path: &Path, audio: &AudioBuffer, encoding: WavEncoding) -> Result<(), String> {
+    let (bits, compression): (u16, Option<(&[u8; 4], &str)>) = match encoding {
+        WavEncoding::Pcm16 => (16, None),
+        WavEncoding::Pcm24 => (24, None),
+        WavEncoding::Float32 => (32, Some((b"fl32", "32-bit floating point"))),
+    };
+
+    let data = pcm::encode(&audio.samples, PcmLayout::from_encoding(encoding, false));
+
+    let mut comm = Vec::new();
+    comm.extend_from_slice(&audio.channels.to_be_bytes());
+    comm.extend_from_slice(&(audio.frames() as u32).to_be_bytes());
+    comm.extend_from_slice(&bits.to_be_bytes());
+    comm.extend_from_slice(&write_extended(audio.sample_rate));
+    if let Some((id, name)) = compression {
+        comm.extend_from_slice(id);
+        comm.push(name.len() as u8);
+        comm.extend_from_slice(name.as_bytes());
+        if comm.len() % 2 == 1 {
+            comm.push(0);
+        }
+    }
+
+    let mut chunks = Vec::new();
+    if compression.is_some() {
+        // AIFC version 1 timestamp, required by the spec
+        chunks.extend_from_slice(b"FVER");
+        chunks.extend_from_slice(&4u32.to_be_bytes());
+        chunks.extend_from_slice(&0xA2805140u32.to_be_bytes());
+    }
+    chunks.extend_from_slice(b"COMM");
+    chunks.extend_from_slice(&(comm.len() as u32).to_be_bytes());
+    chunks.extend_from_slice(&comm);
+    chunks.extend_from_slice(b"SSND");
+    chunks.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+    chunks.extend_from_slice(&[0; 8]);
+    chunks.extend_from_slice(&data);
+    if data.len() % 2 == 1 {
+        chunks.push(0);
+    }
+
+    let mut file = Vec::with_capacity(chunks.len() + 12);
+    file.extend_from_slice(b"FORM");
+    file.extend_from_slice(&(chunks.len() as u32 + 4).to_be_bytes());
+    file.extend_from_slice(if compression.is_some() { b"AIFC" } else { b"AIFF" });
+    file.extend_from_slice(&chunks);
+
+    fs::write(path, file).map_err(|e| format!("Failed to write AIFF file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// A stereo ramp across most of the full scale.
+    fn test_audio(sample_rate: u32) -> AudioBuffer {
+        AudioBuffer {
+            samples: (0..202).map(|i| (i as f32 / 101.0 - 1.0) * 0.9).collect(),
+            sample_rate,
+            channels: 2,
+        }
+    }
+
+    fn round_trip(name: &str, audio: &AudioBuffer, encoding: WavEncoding) -> (Vec<u8>, AudioBuffer) {
+        let path = env::temp_dir().join(format!("aiff-{}-{}.aif", name, process::id()));
+        write_aiff(&path, audio, encoding).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let decoded = read_aiff(&path);
+        fs::remove_file(&path).unwrap();
+        (bytes, decoded.unwrap())
+    }
+
+    #[test]
+    fn extended_float_sample_rates() {
+        // 44.1 kHz as written by every AIFF encoder
+        assert_eq!(write_extended(44100), [0x40, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(write_extended(0), [0; 10]);
+        assert_eq!(read_extended(&[0; 10]), 0.0);
+        for rate in [1, 8000, 11025, 22050, 44100, 48000, 88200, 96000, 176400, 192000, 384000] {
+            assert_eq!(read_extended(&write_extended(rate)), rate as f64);
+        }
+        // The sign bit is ignored
+        assert_eq!(read_extended(&[0xC0, 0x0E, 0xAC, 0x44, 0, 0, 0, 0, 0, 0]), 44100.0);
+    }
+
+    #[test]
+    fn integer_aiff_round_trips() {
+        for (encoding, bits) in [(WavEncoding::Pcm16, 16), (WavEncoding::Pcm24, 24)] {
+            // Two steps, as in the pcm round trips
+            let tolerance = 2f32.powi(2 - bits);
+            let audio = test_audio(44100);
+            let (bytes, decoded) = round_trip(&format!("pcm{}", bits), &audio, encoding);
+            assert_eq!(&bytes[8..12], b"AIFF");
+            assert_eq!(bytes.len() % 2, 0);
+            assert_eq!(decoded.sample_rate, 44100);
+            assert_eq!(decoded.channels, 2);
+            assert_eq!(decoded.samples.len(), audio.samples.len());
+            for (a, b) in decoded.samples.iter().zip(&audio.samples) {
+                assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn float_aifc_round_trips() {
+        let audio = test_audio(96000);
+        let (bytes, decoded) = round_trip("fl32", &audio, WavEncoding::Float32);
+        assert_eq!(&bytes[8..12], b"AIFC");
+        assert!(bytes.windows(4).any(|w| w == b"FVER"));
+        assert!(bytes.windows(4).any(|w| w == b"fl32"));
+        assert_eq!(decoded.sample_rate, 96000);
+        assert_eq!(decoded.channels, 2);
+        assert_eq!(decoded.samples, audio.samples);
+    }
+
+    #[test]
+    fn odd_length_sound_data_is_padded() {
+        let audio = AudioBuffer { samples: vec![0.25; 3], sample_rate: 8000, channels: 1 };
+        let (bytes, decoded) = round_trip("odd", &audio, WavEncoding::Pcm24);
+        // 9 bytes of samples, padded to an even chunk
+        assert_eq!(bytes.len() % 2, 0);
+        assert_eq!(decoded.frames(), 3);
+        assert!(decoded.samples.iter().all(|&s| (s - 0.25).abs() <= 2f32.powi(-22)));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = env::temp_dir().join(format!("aiff-riff-{}.aif", process::id()));
+        fs::write(&path, b"RIFF\0\0\0\0WAVE").unwrap();
+        let result = read_aiff(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::fs;
+use std::path::Path;
+
+use super::pcm::{self, PcmLayout};
+use super::{AudioBuffer, WavEncoding};
+
+const FLAG_FLOAT: u32 = 1;
+const FLAG_LITTLE_ENDIAN: u32 = 2;
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// Reads linear PCM CAF files. Chunk sizes are 64-bit, so unlike WAV
+/// there is no 4 GB limit; a data size of -1 means "until end of file",
+/// which is what recorders leave behind when they are interrupted.
+pub fn read_caf(path: &Path) -> Result<AudioBuffer, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open CAF file: {}", e))?;
+
+    if bytes.len() < 8 || &bytes[0..4] != b"caff" {
+        return Err("Not a CAF file".to_string());
+    }
+
+    let mut description = None;
+    let mut data: &[u8] = &[];
+    let mut offset = 8;
+    while offset + 12 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = i64::from_be_bytes(bytes[offset + 4..offset + 12].try_into().unwrap());
+        let start = offset + 12;
+        let end = if size < 0 { bytes.len() } else { (start + size as usize).min(bytes.len()) };
+        let body = &bytes[start..end];
+
+        match id {
+            b"desc" if body.len() >= 32 => description = Some(body),
+            // The first four bytes are an edit counter
+            b"data" if body.len() >= 4 => data = &body[4..],
+            _ => {}
+        }
+
+        offset = end;
+    }
+
+    let desc = description.ok_or_else(|| "CAF file has no desc chunk".to_string())?;
+    let sample_rate = f64::from_be_bytes(desc[0..8].try_into().unwrap());
+    if &desc[8..12] != b"lpcm" {
+        return Err(format!("Unsupported CAF format: {}", String::from_utf8_lossy(&desc[8..12])));
+    }
+    let flags = be_u32(&desc[12..16]);
+    let channels = be_u32(&desc[24..28]);
+    let bits = be_u32(&desc[28..32]);
+
+    let layout = PcmLayout {
+        bits: bits as u16,
+        float: flags & FLAG_FLOAT != 0,
+        little_endian: flags & FLAG_LITTLE_ENDIAN != 0,
+    };
+    let mut samples = pcm::decode(data, layout)?;
+    samples.truncate(samples.len() - samples.len() % channels.max(1) as usize);
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate: sample_rate.round() as u32,
+        channels: channels as u16,
+    })
+}
+
+pub fn write_caf(path: &Path, audio: &AudioBuffer, encoding: WavEncoding) -> Result<(), String> {
+    let layout = PcmLayout::from_encoding(encoding, false);
+    let bytes_per_frame = (layout.bytes_per_sample() * audio.channels as usize) as u32;
+    let flags = if layout.float { FLAG_FLOAT } else { 0 };
+
+    let mut file = Vec::new();
+    file.extend_from_slice(b"caff");
+    file.extend_from_slice(&1u16.to_be_bytes());
+    file.extend_from_slice(&0u16.to_be_bytes());
+
+    file.extend_from_slice(b"desc");
+    file.extend_from_slice(&32i64.to_be_bytes());
+    file.extend_from_slice(&(audio.sample_rate as f64).to_be_bytes());
+    file.extend_from_slice(b"lpcm");
+    file.extend_from_slice(&flags.to_be_bytes());
+    file.extend_from_slice(&bytes_per_frame.to_be_bytes());
+    file.extend_from_slice(&1u32.to_be_bytes());
+    file.extend_from_slice(&(audio.channels as u32).to_be_bytes());
+    file.extend_from_slice(&(layout.bits as u32).to_be_bytes());
+
+    let data = pcm::encode(&audio.samples, layout);
+    file.extend_from_slice(b"data");
+    file.extend_from_slice(&(data.len() as i64 + 4).to_be_bytes());
+    file.extend_from_slice(&0u32.to_be_bytes());
+    file.extend_from_slice(&data);
+
+    fs::write(path, file).map_err(|e| format!("Failed to write CAF file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        env::temp_dir().join(format!("caf-{}-{}.caf", name, process::id()))
+    }
+
+    fn read_back(name: &str, bytes: &[u8]) -> Result<AudioBuffer, String> {
+        let path = temp_path(name);
+        fs::write(&path, bytes).unwrap();
+        let decoded = read_caf(&path);
+        fs::remove_file(&path).unwrap();
+        decoded
+    }
+
+    fn test_audio() -> AudioBuffer {
+        AudioBuffer {
+            samples: (0..300).map(|i| (i as f32 / 150.0 - 1.0) * 0.9).collect(),
+            sample_rate: 48000,
+            channels: 3,
+        }
+    }
+
+    #[test]
+    fn round_trips_every_encoding() {
+        let audio = test_audio();
+        for (encoding, tolerance) in [
+            (WavEncoding::Pcm16, 2f32.powi(-14)),
+            (WavEncoding::Pcm24, 2f32.powi(-22)),
+            (WavEncoding::Float32, 0.0),
+        ] {
+            let path = temp_path(&format!("{:?}", encoding));
+            write_caf(&path, &audio, encoding).unwrap();
+            let decoded = read_caf(&path);
+            fs::remove_file(&path).unwrap();
+
+            let decoded = decoded.unwrap();
+            assert_eq!(decoded.sample_rate, 48000);
+            assert_eq!(decoded.channels, 3);
+            assert_eq!(decoded.samples.len(), audio.samples.len());
+            for (a, b) in decoded.samples.iter().zip(&audio.samples) {
+                assert!((a - b).abs() <= tolerance, "{:?}: {} != {}", encoding, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_data_running_to_end_of_file() {
+        let audio = AudioBuffer { samples: vec![0.5, -0.5, 0.25, -0.25], sample_rate: 44100, channels: 2 };
+        let path = temp_path("unsized");
+        write_caf(&path, &audio, WavEncoding::Float32).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // What an interrupted recorder leaves: a data size of -1 and a
+        // trailing partial frame
+        let data = bytes.windows(4).position(|w| w == b"data").unwrap();
+        bytes[data + 4..data + 12].copy_from_slice(&(-1i64).to_be_bytes());
+        bytes.extend_from_slice(&0.75f32.to_be_bytes());
+
+        let decoded = read_back("unsized", &bytes).unwrap();
+        assert_eq!(decoded.sample_rate, 44100);
+        assert_eq!(decoded.samples, audio.samples);
+    }
+
+    #[test]
+    fn reads_little_endian_integers() {
+        let mut desc = Vec::new();
+        desc.extend_from_slice(&22050f64.to_be_bytes());
+        desc.extend_from_slice(b"lpcm");
+        desc.extend_from_slice(&FLAG_LITTLE_ENDIAN.to_be_bytes());
+        desc.extend_from_slice(&2u32.to_be_bytes());
+        desc.extend_from_slice(&1u32.to_be_bytes());
+        desc.extend_from_slice(&1u32.to_be_bytes());
+        desc.extend_from_slice(&16u32.to_be_bytes());
+
+        let mut bytes = b"caff\x00\x01\x00\x00desc".to_vec();
+        bytes.extend_from_slice(&32i64.to_be_bytes());
+        bytes.extend_from_slice(&desc);
+        bytes.extend_from_slice(b"data");
+        bytes.extend_from_slice(&8i64.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0x00, 0x40, 0x00, 0xC0]);
+
+        let decoded = read_back("sowt", &bytes).unwrap();
+        assert_eq!(decoded.sample_rate, 22050);
+        assert_eq!(decoded.channels, 1);
+        assert_eq!(decoded.samples, [0.5, -0.5]);
+    }
+
+    #[test]
+    fn rejects_compressed_formats() {
+        let audio = AudioBuffer { samples: vec![0.0; 2], sample_rate: 44100, channels: 1 };
+        let path = temp_path("aac");
+        write_caf(&path, &audio, WavEncoding::Pcm16).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let format = bytes.windows(4).position(|w| w == b"lpcm").unwrap();
+        bytes[format..format + 4].copy_from_slice(b"aac ");
+        let error = read_back("aac", &bytes).unwrap_err();
+        assert!(error.contains("aac"), "{}", error);
+    }
+}
@@ -0,0 +1,406 @@
+use claxon::frame::{Block, FrameReader};
+use claxon::input::BufferedReader;
+use claxon::metadata::StreamInfo;
+use flacenc::component::BitRepr;
+use flacenc::error::{SourceError, Verify};
+use flacenc::source::{Fill, Source};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs::{self, File};
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::progressive::{Blocks, DecodeStream};
+use super::AudioBuffer;
+
+/// Matches the reference encoder's default level.
+const DEFAULT_COMPRESSION_LEVEL: u8 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct FlacOptions {
+    /// 0 (fastest) to 8 (smallest), as with the reference `flac` tool.
+    pub compression_level: u8,
+    /// 16 or 24.
+    pub bits_per_sample: u16,
+    /// Decode the encoded stream and compare it against the input before
+    /// writing anything to disk.
+    pub verify: bool,
+}
+
+impl Default for FlacOptions {
+    fn default() -> Self {
+        FlacOptions {
+            compression_level: DEFAULT_COMPRESSION_LEVEL,
+            bits_per_sample: 16,
+            verify: false,
+        }
+    }
+}
+
+/// Approximates the reference encoder's presets: block size, whether LPC
+/// is tried, its order, and whether stereo decorrelation is searched.
+fn encoder_config(level: u8) -> Result<flacenc::config::Encoder, String> {
+    let (block_size, use_lpc, lpc_order, stereo) = match level {
+        0 => (1152, false, 1, false),
+        1 => (1152, false, 1, true),
+        2 => (1152, false, 1, true),
+        3 => (4096, true, 6, false),
+        4 => (4096, true, 8, true),
+        5 => (4096, true, 8, true),
+        6 => (4096, true, 8, true),
+        7 => (4096, true, 12, true),
+        8 => (4096, true, 12, true),
+        _ => return Err(format!("FLAC compression level must be 0-8, got {}", level)),
+    };
+
+    let mut config = flacenc::config::Encoder::default();
+    config.block_size = block_size;
+    config.subframe_coding.use_lpc = use_lpc;
+    config.subframe_coding.qlpc.lpc_order = lpc_order;
+    config.stereo_coding.use_leftside = stereo;
+    config.stereo_coding.use_rightside = stereo;
+    config.stereo_coding.use_midside = stereo;
+    Ok(config)
+}
+
+fn quantize_max(bits: u16) -> f32 {
+    ((1i32 << (bits - 1)) - 1) as f32
+}
+
+fn quantize_sample(sample: f32, max: f32) -> i32 {
+    (sample.clamp(-1.0, 1.0) * max) as i32
+}
+
+fn quantize(samples: &[f32], bits: u16) -> Vec<i32> {
+    let max = quantize_max(bits);
+    samples.iter().map(|&s| quantize_sample(s, max)).collect()
+}
+
+/// Reads a float WAV one block at a time for the encoder, so a long take
+/// is never held in memory as samples.
+struct WavSource {
+    reader: hound::WavReader<BufReader<File>>,
+    bits_per_sample: u16,
+    block: Vec<i32>,
+}
+
+impl WavSource {
+    fn open(path: &Path, bits_per_sample: u16) -> Result<Self, String> {
+        let reader = hound::WavReader::open(path).map_err(|e| format!("Failed to open WAV file: {}", e))?;
+        if reader.spec().sample_format != hound::SampleFormat::Float {
+            return Err("Expected a 32-bit float WAV file".to_string());
+        }
+        Ok(WavSource { reader, bits_per_sample, block: Vec::new() })
+    }
+}
+
+impl Source for WavSource {
+    fn channels(&self) -> usize {
+        self.reader.spec().channels as usize
+    }
+
+    fn bits_per_sample(&self) -> usize {
+        self.bits_per_sample as usize
+    }
+
+    fn sample_rate(&self) -> usize {
+        self.reader.spec().sample_rate as usize
+    }
+
+    fn read_samples<F: Fill>(&mut self, block_size: usize, dest: &mut F) -> Result<usize, SourceError> {
+        let channels = self.channels();
+        let max = quantize_max(self.bits_per_sample);
+        self.block.clear();
+        for sample in self.reader.samples::<f32>().take(block_size * channels) {
+            self.block.push(quantize_sample(sample.map_err(SourceError::from_io_error)?, max));
+        }
+        dest.fill_interleaved(&self.block)?;
+        Ok(self.block.len() / channels)
+    }
+
+    fn len_hint(&self) -> Option<usize> {
+        Some(self.reader.duration() as usize)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<(claxon::metadata::StreamInfo, Vec<i32>), String> {
+    let mut reader = claxon::FlacReader::new(Cursor::new(bytes))
+        .map_err(|e| format!("Failed to open FLAC stream: {}", e))?;
+    let info = reader.streaminfo();
+    let samples = reader.samples()
+        .collect::<Result<Vec<i32>, _>>()
+        .map_err(|e| format!("Failed to decode FLAC samples: {}", e))?;
+    Ok((info, samples))
+}
+
+pub fn read_flac(path: &Path) -> Result<AudioBuffer, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open FLAC file: {}", e))?;
+    let (info, samples) = decode(&bytes)?;
+    let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+
+    Ok(AudioBuffer {
+        samples: samples.into_iter().map(|s| s as f32 / scale).collect(),
+        sample_rate: info.sample_rate,
+        channels: info.channels as u16,
+    })
+}
+
+/// Metadata block type of the seek table.
+const SEEKTABLE_BLOCK: u8 = 3;
+/// Bytes searched for a frame header from each bisection point.
+const SYNC_SCAN_BYTES: u64 = 1 << 16;
+/// Bisection stops once the span left is this small; decoding on from there
+/// to the seek target is quicker than narrowing it further.
+const BISECT_SPAN_BYTES: u64 = 1 << 16;
+
+/// Where the audio frames start and the seek points left by the encoder,
+/// which claxon reads past without exposing.
+struct FlacIndex {
+    /// Byte offset of the first frame header.
+    first_frame: u64,
+    file_len: u64,
+    /// `(first sample, byte offset from the first frame)` of each point.
+    seek_points: Vec<(u64, u64)>,
+}
+
+fn read_index(path: &Path) -> Result<FlacIndex, String> {
+    let read_error = |e: std::io::Error| format!("Failed to read FLAC metadata: {}", e);
+    let mut file = BufReader::new(File::open(path).map_err(|e| format!("Failed to open FLAC file: {}", e))?);
+    let file_len = file.get_ref().metadata().map_err(read_error)?.len();
+
+    let mut marker = [0u8; 4];
+    file.read_exact(&mut marker).map_err(read_error)?;
+    if &marker != b"fLaC" {
+        return Err("Not a FLAC file".to_string());
+    }
+
+    let mut seek_points = Vec::new();
+    loop {
+        let mut header = [0u8; 4];
+        file.read_exact(&mut header).map_err(read_error)?;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+        if header[0] & 0x7F == SEEKTABLE_BLOCK {
+            let mut table = vec![0u8; length as usize];
+            file.read_exact(&mut table).map_err(read_error)?;
+            seek_points.extend(
+                table.chunks_exact(18)
+                    .map(|point| {
+                        let sample = u64::from_be_bytes(point[..8].try_into().unwrap());
+                        (sample, u64::from_be_bytes(point[8..16].try_into().unwrap()))
+                    })
+                    // All ones marks a placeholder point
+                    .filter(|&(sample, _)| sample != u64::MAX),
+            );
+        } else {
+            file.seek_relative(length as i64).map_err(read_error)?;
+        }
+        if header[0] & 0x80 != 0 {
+            break;
+        }
+    }
+
+    Ok(FlacIndex {
+        first_frame: file.stream_position().map_err(read_error)?,
+        file_len,
+        seek_points,
+    })
+}
+
+/// Start of `block` in frames. Fixed-blocksize streams number their frames
+/// rather than samples, which claxon multiplies by the block's own size;
+/// that comes out wrong for the shorter final block.
+fn block_start(block: &Block, info: &StreamInfo) -> u64 {
+    let block_size = info.max_block_size as u32;
+    if info.min_block_size == info.max_block_size && (1..block_size).contains(&block.duration()) {
+        block.time() / block.duration() as u64 * block_size as u64
+    } else {
+        block.time()
+    }
+}
+
+/// Decodes blocks from the frame header at `offset` on.
+fn blocks_from(path: &Path, offset: u64, info: StreamInfo) -> Result<Blocks, String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open FLAC file: {}", e))?;
+    file.seek(SeekFrom::Start(offset)).map_err(|e| format!("Failed to seek in FLAC file: {}", e))?;
+    let mut reader = FrameReader::new(BufferedReader::new(file));
+    let scale = (1i64 << (info.bits_per_sample - 1)) as f32;
+    let channels = info.channels;
+
+    let mut buffer = Vec::new();
+    Ok(Box::new(std::iter::from_fn(move || {
+        let block = match reader.read_next_or_eof(std::mem::take(&mut buffer)) {
+            Ok(Some(block)) => block,
+            Ok(None) => return None,
+            Err(e) => return Some(Err(format!("Failed to decode FLAC frame: {}", e))),
+        };
+
+        let mut samples = Vec::with_capacity(block.len() as usize);
+        for frame in 0..block.duration() {
+            samples.extend((0..channels).map(|channel| block.sample(channel, frame) as f32 / scale));
+        }
+        buffer = block.into_buffer();
+        Some(Ok(samples))
+    })))
+}
+
+/// The first frame whose header starts within `SYNC_SCAN_BYTES` of `from`,
+/// as its offset and start. Each sync code found is confirmed by decoding
+/// the frame, whose CRCs rule out audio data that looks like a header.
+fn next_frame(path: &Path, from: u64, info: &StreamInfo) -> Result<Option<(u64, u64)>, String> {
+    let seek_error = |e: std::io::Error| format!("Failed to seek in FLAC file: {}", e);
+    let mut file = File::open(path).map_err(|e| format!("Failed to open FLAC file: {}", e))?;
+    file.seek(SeekFrom::Start(from)).map_err(seek_error)?;
+    let mut window = Vec::new();
+    (&mut file).take(SYNC_SCAN_BYTES).read_to_end(&mut window).map_err(seek_error)?;
+
+    for (index, pair) in window.windows(2).enumerate() {
+        if pair[0] != 0xFF || pair[1] & 0xFE != 0xF8 {
+            continue;
+        }
+        let offset = from + index as u64;
+        file.seek(SeekFrom::Start(offset)).map_err(seek_error)?;
+        let mut reader = FrameReader::new(BufferedReader::new(&mut file));
+        if let Ok(Some(block)) = reader.read_next_or_eof(Vec::new()) {
+            if block.channels() == info.channels {
+                return Ok(Some((offset, block_start(&block, info))));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Finds a frame starting at or shortly before `target`: the closest seek
+/// point narrows the search, then the bytes up to the next one are
+/// bisected for frame headers. Returns the frame's offset and start.
+fn seek_frame(path: &Path, index: &FlacIndex, info: &StreamInfo, target: u64) -> Result<(u64, u64), String> {
+    let mut best = (index.first_frame, 0);
+    let mut end = index.file_len;
+    for &(sample, offset) in &index.seek_points {
+        let offset = index.first_frame.saturating_add(offset);
+        if sample > target {
+            end = end.min(offset);
+            break;
+        }
+        best = (offset, sample);
+    }
+
+    let mut low = best.0;
+    while end.saturating_sub(low) > BISECT_SPAN_BYTES {
+        let middle = low + (end - low) / 2;
+        match next_frame(path, middle, info)? {
+            Some((offset, start)) if start <= target => {
+                best = (offset, start);
+                low = offset;
+            }
+            _ => end = middle,
+        }
+    }
+    Ok(best)
+}
+
+/// Decodes frame by frame, for files that should start playing before
+/// they are fully decoded. Seeking restarts at the frame found by
+/// `seek_frame`.
+pub fn stream_flac(path: &Path) -> Result<DecodeStream, String> {
+    let reader = claxon::FlacReader::open(path)
+        .map_err(|e| format!("Failed to open FLAC file: {}", e))?;
+    let info = reader.streaminfo();
+    let frames = info.samples.ok_or_else(|| "FLAC file does not state its length".to_string())? as usize;
+    let index = read_index(path)?;
+    let blocks = blocks_from(path, index.first_frame, info)?;
+
+    let path = path.to_path_buf();
+    let seek = move |target: usize| {
+        let (offset, start) = seek_frame(&path, &index, &info, target as u64)?;
+        Ok((start as usize, blocks_from(&path, offset, info)?))
+    };
+
+    Ok(DecodeStream {
+        sample_rate: info.sample_rate,
+        channels: info.channels as u16,
+        frames,
+        blocks,
+        seek: Some(Box::new(seek)),
+    })
+}
+
+pub fn write_flac(path: &Path, audio: &AudioBuffer, options: &FlacOptions) -> Result<(), String> {
+    let bytes = encode_flac(audio, options)?;
+    fs::write(path, bytes).map_err(|e| format!("Failed to write FLAC file: {}", e))
+}
+
+/// Encodes a 32-bit float WAV file to `path` block by block, for takes too
+/// long to decode into memory first. With `verify`, the WAV is read a
+/// second time to compare against the decoded stream.
+pub fn write_flac_from_wav(wav_path: &Path, path: &Path, options: &FlacOptions) -> Result<(), String> {
+    check_bit_depth(options.bits_per_sample)?;
+    let bytes = encode(WavSource::open(wav_path, options.bits_per_sample)?, options)?;
+
+    if options.verify {
+        let mut wav = WavSource::open(wav_path, options.bits_per_sample)?;
+        let mut flac = claxon::FlacReader::new(Cursor::new(&bytes))
+            .map_err(|e| format!("Failed to open FLAC stream: {}", e))?;
+        let mut decoded = flac.samples();
+        let max = quantize_max(options.bits_per_sample);
+        for sample in wav.reader.samples::<f32>() {
+            let expected = quantize_sample(sample.map_err(|e| format!("Failed to read samples: {}", e))?, max);
+            match decoded.next() {
+                Some(Ok(sample)) if sample == expected => {}
+                Some(Err(e)) => return Err(format!("Failed to decode FLAC samples: {}", e)),
+                _ => return Err(VERIFY_FAILED.to_string()),
+            }
+        }
+        if decoded.next().is_some() {
+            return Err(VERIFY_FAILED.to_string());
+        }
+    }
+
+    fs::write(path, bytes).map_err(|e| format!("Failed to write FLAC file: {}", e))
+}
+
+const VERIFY_FAILED: &str = "FLAC verification failed: decoded audio differs from the input";
+
+/// Encodes `audio` to a complete FLAC stream in memory.
+pub fn encode_flac(audio: &AudioBuffer, options: &FlacOptions) -> Result<Vec<u8>, String> {
+    check_bit_depth(options.bits_per_sample)?;
+    let samples = quantize(&audio.samples, options.bits_per_sample);
+    let source = flacenc::source::MemSource::from_samples(
+        &samples,
+        audio.channels as usize,
+        options.bits_per_sample as usize,
+        audio.sample_rate as usize,
+    );
+    let bytes = encode(source, options)?;
+
+    if options.verify {
+        let (_, decoded) = decode(&bytes)?;
+        if decoded != samples {
+            return Err(VERIFY_FAILED.to_string());
+        }
+    }
+
+    Ok(bytes)
+}
+
+fn check_bit_depth(bits_per_sample: u16) -> Result<(), String> {
+    match bits_per_sample {
+        16 | 24 => Ok(()),
+        _ => Err(format!("FLAC bit depth must be 16 or 24, got {}", bits_per_sample)),
+    }
+}
+
+fn encode<S: Source>(source: S, options: &FlacOptions) -> Result<Vec<u8>, String> {
+    check_bit_depth(options.bits_per_sample)?;
+    let config = encoder_config(options.compression_level)?
+        .into_verified()
+        .map_err(|(_, e)| format!("Invalid FLAC encoder settings: {}", e))?;
+
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| format!("Failed to encode FLAC: {}", e))?;
+
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream.write(&mut sink)
+        .map_err(|e| format!("Failed to encode FLAC: {}", e))?;
+    Ok(sink.as_slice().to_vec())
+}
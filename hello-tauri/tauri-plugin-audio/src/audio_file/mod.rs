@@ -0,0 +1,234 @@
+mod aiff;
+mod caf;
+mod flac;
+mod mp3;
+mod opus;
+mod pcm;
+mod progressive;
+mod raw;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+
+use crate::dsp::mix::{apply_matrix, Downmix, Upmix};
+use crate::dsp::resample::resample;
+use crate::validate;
+
+pub use flac::{encode_flac, write_flac, write_flac_from_wav, FlacOptions};
+pub use mp3::{write_mp3, Mp3Options};
+pub use opus::{write_opus, OpusFileWriter, OpusFrameEncoder, OpusOptions};
+pub use progressive::{open_progressive, ProgressiveAudio};
+pub use raw::{read_raw, Endianness, RawSampleFormat};
+
+/// Decoded audio as interleaved f32 samples in the range [-1.0, 1.0].
+#[derive(Debug, Clone)]
+pub struct AudioBuffer {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl AudioBuffer {
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+
+    /// Copies frames `start..end` into a new buffer with the same format.
+    pub fn slice(&self, start: usize, end: usize) -> AudioBuffer {
+        let channels = self.channels.max(1) as usize;
+        let end = end.min(self.frames());
+        let start = start.min(end);
+
+        AudioBuffer {
+            samples: self.samples[start * channels..end * channels].to_vec(),
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+        }
+    }
+
+    /// De-interleaves one zero-based channel.
+    pub fn channel(&self, index: usize) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        self.samples.iter().skip(index).step_by(channels).copied().collect()
+    }
+
+    /// Returns the buffer converted to `sample_rate`.
+    pub fn resampled(&self, sample_rate: u32) -> Result<AudioBuffer, String> {
+        validate::sample_rate(sample_rate)?;
+        Ok(AudioBuffer {
+            samples: resample(&self.samples, self.channels as usize, self.sample_rate, sample_rate)?,
+            sample_rate,
+            channels: self.channels,
+        })
+    }
+
+    /// Folds the buffer down through `downmix`.
+    pub fn downmix(&self, downmix: &Downmix) -> Result<AudioBuffer, String> {
+        Ok(self.remix(&downmix.matrix(self.channels as usize)?))
+    }
+
+    /// Spreads a mono buffer to stereo.
+    pub fn upmix(&self, upmix: &Upmix) -> Result<AudioBuffer, String> {
+        Ok(self.remix(&upmix.matrix(self.channels as usize)?))
+    }
+
+    fn remix(&self, matrix: &[Vec<f32>]) -> AudioBuffer {
+        AudioBuffer {
+            samples: apply_matrix(&self.samples, self.channels.max(1) as usize, matrix),
+            sample_rate: self.sample_rate,
+            channels: matrix.len() as u16,
+        }
+    }
+
+    /// Averages all channels into a single mono signal.
+    pub fn to_mono(&self) -> Vec<f32> {
+        let channels = self.channels.max(1) as usize;
+        self.samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+}
+
+/// File extensions the reader can decode.
+const SUPPORTED_EXTENSIONS: [&str; 7] = ["wav", "aif", "aiff", "aifc", "caf", "flac", "opus"];
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+pub fn is_supported_audio_file(path: &Path) -> bool {
+    SUPPORTED_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+/// Reads any supported audio file, choosing the decoder from the extension.
+pub fn read_audio_file(path: &Path) -> Result<AudioBuffer, String> {
+    validate::input_file(path)?;
+    match extension(path).as_str() {
+        "aif" | "aiff" | "aifc" => aiff::read_aiff(path),
+        "caf" => caf::read_caf(path),
+        "flac" => flac::read_flac(path),
+        "opus" => opus::read_opus(path),
+        _ => read_wav(path),
+    }
+}
+
+/// Writes `audio` in the container implied by the extension of `path`.
+pub fn write_audio_file(path: &Path, audio: &AudioBuffer, encoding: WavEncoding) -> Result<(), String> {
+    validate::output_file(path)?;
+    match extension(path).as_str() {
+        "wav" => write_wav(path, audio, encoding),
+        "aif" | "aiff" | "aifc" => aiff::write_aiff(path, audio, encoding),
+        "caf" => caf::write_caf(path, audio, encoding),
+        "flac" => {
+            let bits_per_sample = if encoding == WavEncoding::Pcm16 { 16 } else { 24 };
+            write_flac(path, audio, &FlacOptions { bits_per_sample, ..Default::default() })
+        }
+        "mp3" => write_mp3(path, audio, &Mp3Options::default()),
+        "opus" => write_opus(path, audio, &OpusOptions::default()),
+        other => Err(format!("Unsupported output format: {}", other)),
+    }
+}
+
+pub fn read_wav(path: &Path) -> Result<AudioBuffer, String> {
+    let mut reader = hound::WavReader::open(path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+
+    let spec = reader.spec();
+
+    // Read samples and convert to f32 in range [-1.0, 1.0]
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => {
+            reader.samples::<f32>()
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| format!("Failed to read samples: {}", e))?
+        }
+        hound::SampleFormat::Int => {
+            match spec.bits_per_sample {
+                16 => {
+                    reader.samples::<i16>()
+                        .map(|s| s.map(|sample| sample as f32 / i16::MAX as f32))
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|e| format!("Failed to read samples: {}", e))?
+                }
+                24 => {
+                    reader.samples::<i32>()
+                        .map(|s| s.map(|sample| sample as f32 / 8388608.0)) // 2^23
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|e| format!("Failed to read samples: {}", e))?
+                }
+                32 => {
+                    reader.samples::<i32>()
+                        .map(|s| s.map(|sample| sample as f32 / i32::MAX as f32))
+                        .collect::<Result<Vec<f32>, _>>()
+                        .map_err(|e| format!("Failed to read samples: {}", e))?
+                }
+                _ => return Err(format!("Unsupported bit depth: {}", spec.bits_per_sample))
+            }
+        }
+    };
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+/// Sample encodings the WAV writer can produce.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum WavEncoding {
+    Pcm16,
+    Pcm24,
+    /// 32-bit float; holds any decoded source without loss of precision.
+    #[default]
+    Float32,
+}
+
+impl WavEncoding {
+    pub fn spec(self, sample_rate: u32, channels: u16) -> hound::WavSpec {
+        let (bits_per_sample, sample_format) = match self {
+            WavEncoding::Pcm16 => (16, hound::SampleFormat::Int),
+            WavEncoding::Pcm24 => (24, hound::SampleFormat::Int),
+            WavEncoding::Float32 => (32, hound::SampleFormat::Float),
+        };
+
+        hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample,
+            sample_format,
+        }
+    }
+
+    pub fn write_sample<W>(self, writer: &mut hound::WavWriter<W>, sample: f32) -> Result<(), String>
+    where
+        W: std::io::Write + std::io::Seek,
+    {
+        let result = match self {
+            WavEncoding::Pcm16 => writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+            WavEncoding::Pcm24 => writer.write_sample((sample.clamp(-1.0, 1.0) * 8388607.0) as i32),
+            WavEncoding::Float32 => writer.write_sample(sample),
+        };
+        result.map_err(|e| format!("Failed to write sample: {}", e))
+    }
+}
+
+pub fn write_wav(path: &Path, audio: &AudioBuffer, encoding: WavEncoding) -> Result<(), String> {
+    validate::output_file(path)?;
+    let spec = encoding.spec(audio.sample_rate, audio.channels);
+    let mut writer = hound::WavWriter::create(path, spec)
+        .map_err(|e| format!("Failed to create WAV file: {}", e))?;
+
+    for &sample in &audio.samples {
+        encoding.write_sample(&mut writer, sample)?;
+    }
+
+    writer.finalize()
+        .map_err(|e| format!("Failed to finalize WAV file: {}", e))
+}
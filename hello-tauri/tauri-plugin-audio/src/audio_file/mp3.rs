@@ -0,0 +1,131 @@
+use mp3lame_encoder::{Bitrate, Builder, FlushNoGap, Id3Tag, InterleavedPcm, MonoPcm, Quality, VbrMode};
+use serde::Deserialize;
+use specta::Type;
+use std::fs;
+use std::path::Path;
+
+use super::AudioBuffer;
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct Mp3Options {
+    /// Constant bitrate in kbps, or the target average when `vbr` is set.
+    pub bitrate_kbps: u16,
+    pub vbr: bool,
+    /// VBR quality from 0 (best) to 9 (smallest).
+    pub vbr_quality: u8,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+}
+
+impl Default for Mp3Options {
+    fn default() -> Self {
+        Mp3Options {
+            bitrate_kbps: 192,
+            vbr: false,
+            vbr_quality: 2,
+            title: None,
+            artist: None,
+            album: None,
+        }
+    }
+}
+
+fn bitrate(kbps: u16) -> Result<Bitrate, String> {
+    let bitrate = match kbps {
+        32 => Bitrate::Kbps32,
+        40 => Bitrate::Kbps40,
+        48 => Bitrate::Kbps48,
+        64 => Bitrate::Kbps64,
+        80 => Bitrate::Kbps80,
+        96 => Bitrate::Kbps96,
+        112 => Bitrate::Kbps112,
+        128 => Bitrate::Kbps128,
+        160 => Bitrate::Kbps160,
+        192 => Bitrate::Kbps192,
+        224 => Bitrate::Kbps224,
+        256 => Bitrate::Kbps256,
+        320 => Bitrate::Kbps320,
+        _ => return Err(format!("Unsupported MP3 bitrate: {} kbps", kbps)),
+    };
+    Ok(bitrate)
+}
+
+fn quality(level: u8) -> Result<Quality, String> {
+    let quality = match level {
+        0 => Quality::Best,
+        1 => Quality::SecondBest,
+        2 => Quality::NearBest,
+        3 => Quality::VeryNice,
+        4 => Quality::Nice,
+        5 => Quality::Good,
+        6 => Quality::Decent,
+        7 => Quality::Ok,
+        8 => Quality::SecondWorst,
+        9 => Quality::Worst,
+        _ => return Err(format!("MP3 VBR quality must be 0-9, got {}", level)),
+    };
+    Ok(quality)
+}
+
+pub fn write_mp3(path: &Path, audio: &AudioBuffer, options: &Mp3Options) -> Result<(), String> {
+    if !matches!(audio.channels, 1 | 2) {
+        return Err(format!("MP3 supports mono or stereo only, got {} channels", audio.channels));
+    }
+
+    let build_error = |e| format!("Failed to configure MP3 encoder: {}", e);
+    let mut builder = Builder::new().ok_or_else(|| "Failed to create MP3 encoder".to_string())?;
+    builder.set_num_channels(audio.channels as u8).map_err(build_error)?;
+    builder.set_sample_rate(audio.sample_rate).map_err(build_error)?;
+    builder.set_brate(bitrate(options.bitrate_kbps)?).map_err(build_error)?;
+    builder.set_quality(Quality::NearBest).map_err(build_error)?;
+    if options.vbr {
+        builder.set_vbr_mode(VbrMode::Mtrh).map_err(build_error)?;
+        builder.set_vbr_quality(quality(options.vbr_quality)?).map_err(build_error)?;
+        // The Xing/LAME header lets players show the right duration for VBR
+        builder.set_to_write_vbr_tag(true).map_err(build_error)?;
+    } else {
+        builder.set_vbr_mode(VbrMode::Off).map_err(build_error)?;
+    }
+
+    let text = |value: &Option<String>| value.as_deref().unwrap_or_default().as_bytes().to_vec();
+    let (title, artist, album) = (text(&options.title), text(&options.artist), text(&options.album));
+    builder
+        .set_id3_tag(Id3Tag {
+            title: &title,
+            artist: &artist,
+            album: &album,
+            album_art: &[],
+            year: &[],
+            comment: &[],
+        })
+        .map_err(|e| format!("Failed to set ID3 tag: {:?}", e))?;
+
+    let mut encoder = builder.build().map_err(build_error)?;
+
+    let frames = audio.frames();
+    let mut output = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(frames));
+    let encode_error = |e| format!("Failed to encode MP3: {}", e);
+    if audio.channels == 1 {
+        encoder.encode_to_vec(MonoPcm(&audio.samples), &mut output).map_err(encode_error)?;
+    } else {
+        encoder.encode_to_vec(InterleavedPcm(&audio.samples), &mut output).map_err(encode_error)?;
+    }
+    encoder.flush_to_vec::<FlushNoGap>(&mut output).map_err(encode_error)?;
+
+    // The VBR header goes between the ID3v2 tag and the first audio frame
+    let mut lame_tag = Vec::new();
+    if encoder.lame_tag_size() > 0 {
+        lame_tag.reserve(encoder.lame_tag_size());
+        encoder.lame_tag_encode_to_vec(&mut lame_tag);
+    }
+    let boundary = encoder.id3v2_tag_size().min(output.len());
+
+    let mut file = Vec::with_capacity(output.len() + lame_tag.len());
+    file.extend_from_slice(&output[..boundary]);
+    file.extend_from_slice(&lame_tag);
+    file.extend_from_slice(&output[boundary..]);
+
+    fs::write(path, file).map_err(|e| format!("Failed to write MP3 file: {}", e))
+}
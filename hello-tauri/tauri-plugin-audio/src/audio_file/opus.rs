@@ -0,0 +1,414 @@
+use opus_rs::{Application, OpusDecoder, OpusEncoder};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use super::progressive::{reserved_samples, Blocks, DecodeStream};
+use super::AudioBuffer;
+
+/// Sample rates the Opus encoder accepts as input.
+const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12000, 16000, 24000, 48000];
+/// Packets are 20 ms, the usual trade-off between latency and efficiency.
+const FRAMES_PER_SECOND: u32 = 50;
+/// Encoder delay at 48 kHz (2.5 ms lookahead plus 4 ms delay compensation),
+/// written as the Ogg pre-skip so decoders trim it.
+const PRE_SKIP: u16 = 312;
+/// Largest packet Opus can produce for one frame.
+const MAX_PACKET_BYTES: usize = 4000;
+/// Longest frame a packet can decode to: 120 ms at 48 kHz.
+const MAX_DECODED_FRAMES: usize = 5760;
+/// Packets are collected into pages of about a second to keep Ogg overhead low.
+const PACKETS_PER_PAGE: usize = 50;
+/// Decoding restarts this far ahead of a seek target so the decoder has
+/// converged by the time it gets there. RFC 7845 suggests 80 ms, but this
+/// decoder still differs audibly from an in-order decode after that; by
+/// 400 ms the difference is below 16-bit resolution.
+const SEEK_PREROLL: usize = 19200;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct OpusOptions {
+    pub bitrate_kbps: u32,
+    /// Tune the encoder for speech rather than music.
+    pub voice: bool,
+}
+
+impl Default for OpusOptions {
+    fn default() -> Self {
+        OpusOptions {
+            bitrate_kbps: 64,
+            voice: false,
+        }
+    }
+}
+
+/// Encodes interleaved f32 audio into 20 ms Opus packets. Input can arrive
+/// in any block size; samples are buffered until a full frame is available.
+/// Usable on its own wherever raw packets are wanted, such as streaming.
+pub struct OpusFrameEncoder {
+    encoder: OpusEncoder,
+    channels: usize,
+    frame_size: usize,
+    pending: Vec<f32>,
+}
+
+impl OpusFrameEncoder {
+    pub fn new(sample_rate: u32, channels: u16, options: &OpusOptions) -> Result<Self, String> {
+        if !OPUS_SAMPLE_RATES.contains(&sample_rate) {
+            return Err(format!(
+                "Opus needs a sample rate of 8, 12, 16, 24 or 48 kHz, got {} Hz",
+                sample_rate
+            ));
+        }
+        if !matches!(channels, 1 | 2) {
+            return Err(format!("Opus supports mono or stereo only, got {} channels", channels));
+        }
+
+        let application = if options.voice { Application::Voip } else { Application::Audio };
+        let mut encoder = OpusEncoder::new(sample_rate as i32, channels as usize, application)
+            .map_err(|e| format!("Failed to create Opus encoder: {}", e))?;
+        encoder.bitrate_bps = (options.bitrate_kbps * 1000) as i32;
+
+        Ok(OpusFrameEncoder {
+            encoder,
+            channels: channels as usize,
+            frame_size: (sample_rate / FRAMES_PER_SECOND) as usize,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Buffers `samples` and returns every packet that could be completed.
+    pub fn push(&mut self, samples: &[f32]) -> Result<Vec<Vec<u8>>, String> {
+        self.pending.extend_from_slice(samples);
+
+        let frame_samples = self.frame_size * self.channels;
+        let mut packets = Vec::new();
+        let mut output = [0u8; MAX_PACKET_BYTES];
+        let mut consumed = 0;
+
+        while self.pending.len() - consumed >= frame_samples {
+            let frame = &self.pending[consumed..consumed + frame_samples];
+            let bytes = self.encoder.encode(frame, self.frame_size, &mut output)
+                .map_err(|e| format!("Failed to encode Opus frame: {}", e))?;
+            packets.push(output[..bytes].to_vec());
+            consumed += frame_samples;
+        }
+
+        self.pending.drain(..consumed);
+        Ok(packets)
+    }
+
+    /// Pads whatever is left to a full frame and encodes it.
+    pub fn flush(&mut self) -> Result<Vec<Vec<u8>>, String> {
+        if self.pending.is_empty() {
+            return Ok(Vec::new());
+        }
+        let frame_samples = self.frame_size * self.channels;
+        let padding = frame_samples - self.pending.len();
+        self.push(&vec![0.0; padding])
+    }
+}
+
+fn ogg_crc(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ 0x04c1_1db7 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+/// Writes Ogg pages, one logical stream per file.
+struct OggWriter<W: Write> {
+    out: W,
+    serial: u32,
+    sequence: u32,
+    bytes_written: u64,
+}
+
+impl<W: Write> OggWriter<W> {
+    const BEGIN: u8 = 0x02;
+    const END: u8 = 0x04;
+
+    fn write_page(&mut self, packets: &[Vec<u8>], granule: u64, flags: u8) -> Result<(), String> {
+        let mut lacing = Vec::new();
+        for packet in packets {
+            lacing.extend(std::iter::repeat_n(255u8, packet.len() / 255));
+            lacing.push((packet.len() % 255) as u8);
+        }
+
+        let mut page = Vec::with_capacity(27 + lacing.len() + packets.iter().map(Vec::len).sum::<usize>());
+        page.extend_from_slice(b"OggS");
+        page.push(0);
+        page.push(flags);
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&self.serial.to_le_bytes());
+        page.extend_from_slice(&self.sequence.to_le_bytes());
+        page.extend_from_slice(&[0; 4]);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        for packet in packets {
+            page.extend_from_slice(packet);
+        }
+
+        let crc = ogg_crc(&page);
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        self.sequence += 1;
+        self.bytes_written += page.len() as u64;
+
+        self.out.write_all(&page).map_err(|e| format!("Failed to write Opus file: {}", e))
+    }
+}
+
+/// Streams Opus packets into an Ogg Opus file as audio arrives, so long
+/// recordings never need to be held in memory.
+pub struct OpusFileWriter {
+    encoder: OpusFrameEncoder,
+    ogg: OggWriter<BufWriter<File>>,
+    page: Vec<Vec<u8>>,
+    granule: u64,
+    input_frames: u64,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl OpusFileWriter {
+    pub fn create(path: &Path, sample_rate: u32, channels: u16, options: &OpusOptions) -> Result<Self, String> {
+        let encoder = OpusFrameEncoder::new(sample_rate, channels, options)?;
+        let file = File::create(path).map_err(|e| format!("Failed to create Opus file: {}", e))?;
+        let mut ogg = OggWriter {
+            out: BufWriter::new(file),
+            serial: std::process::id() ^ sample_rate,
+            sequence: 0,
+            bytes_written: 0,
+        };
+
+        let mut head = b"OpusHead".to_vec();
+        head.push(1);
+        head.push(channels as u8);
+        head.extend_from_slice(&PRE_SKIP.to_le_bytes());
+        head.extend_from_slice(&sample_rate.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        ogg.write_page(&[head], 0, OggWriter::<BufWriter<File>>::BEGIN)?;
+
+        let vendor = concat!("toolbox ", env!("CARGO_PKG_VERSION"));
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        ogg.write_page(&[tags], 0, 0)?;
+
+        Ok(OpusFileWriter {
+            encoder,
+            ogg,
+            page: Vec::new(),
+            granule: PRE_SKIP as u64,
+            input_frames: 0,
+            sample_rate,
+            channels,
+        })
+    }
+
+    fn add_packets(&mut self, packets: Vec<Vec<u8>>) -> Result<(), String> {
+        for packet in packets {
+            // Granule positions always count 48 kHz samples
+            self.granule += (48000 / FRAMES_PER_SECOND) as u64;
+            self.page.push(packet);
+            if self.page.len() >= PACKETS_PER_PAGE {
+                let page = std::mem::take(&mut self.page);
+                self.ogg.write_page(&page, self.granule, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        self.input_frames += (samples.len() / self.channels as usize) as u64;
+        let packets = self.encoder.push(samples)?;
+        self.add_packets(packets)
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.ogg.bytes_written
+    }
+
+    pub fn finalize(mut self) -> Result<(), String> {
+        // Feed silence through the encoder delay so the tail is not lost
+        let delay = PRE_SKIP as usize * self.sample_rate as usize / 48000;
+        let mut packets = self.encoder.push(&vec![0.0; delay * self.channels as usize])?;
+        packets.extend(self.encoder.flush()?);
+        self.add_packets(packets)?;
+
+        // The last granule marks where real audio ends, trimming the padding
+        let end = PRE_SKIP as u64 + self.input_frames * 48000 / self.sample_rate as u64;
+        let page = std::mem::take(&mut self.page);
+        self.ogg.write_page(&page, end.min(self.granule), OggWriter::<BufWriter<File>>::END)?;
+        self.ogg.out.flush().map_err(|e| format!("Failed to write Opus file: {}", e))
+    }
+}
+
+pub fn write_opus(path: &Path, audio: &AudioBuffer, options: &OpusOptions) -> Result<(), String> {
+    let mut writer = OpusFileWriter::create(path, audio.sample_rate, audio.channels, options)?;
+    writer.write(&audio.samples)?;
+    writer.finalize()
+}
+
+/// Splits an Ogg stream into its packets, joining those that span pages,
+/// and returns them with the final granule position.
+fn ogg_packets(bytes: &[u8]) -> Result<(Vec<Vec<u8>>, u64), String> {
+    let mut packets = Vec::new();
+    let mut current = Vec::new();
+    let mut granule = 0;
+    let mut offset = 0;
+
+    while offset + 27 <= bytes.len() {
+        if &bytes[offset..offset + 4] != b"OggS" {
+            return Err("Corrupt Ogg page".to_string());
+        }
+        let page_granule = u64::from_le_bytes(bytes[offset + 6..offset + 14].try_into().unwrap());
+        let segments = bytes[offset + 26] as usize;
+        let lacing = bytes.get(offset + 27..offset + 27 + segments).ok_or("Truncated Ogg page")?;
+        let mut data = offset + 27 + segments;
+
+        for &length in lacing {
+            let end = (data + length as usize).min(bytes.len());
+            current.extend_from_slice(&bytes[data..end]);
+            data = end;
+            if length < 255 {
+                packets.push(std::mem::take(&mut current));
+            }
+        }
+
+        if page_granule != u64::MAX {
+            granule = page_granule;
+        }
+        offset = data;
+    }
+
+    Ok((packets, granule))
+}
+
+pub fn read_opus(path: &Path) -> Result<AudioBuffer, String> {
+    let stream = stream_opus(path)?;
+    let mut samples = Vec::with_capacity(reserved_samples(path, &stream));
+    for block in stream.blocks {
+        samples.extend(block?);
+    }
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate: stream.sample_rate,
+        channels: stream.channels,
+    })
+}
+
+/// Frames at 48 kHz that `packet` decodes to, from its TOC byte (RFC 6716,
+/// section 3.1).
+fn packet_frames(packet: &[u8]) -> usize {
+    let Some(&toc) = packet.first() else {
+        return 0;
+    };
+    let config = (toc >> 3) as usize;
+    let frame = match config {
+        0..=11 => [480, 960, 1920, 2880][config % 4],
+        12..=15 => [480, 960][config % 2],
+        _ => [120, 240, 480, 960][config % 4],
+    };
+    let count = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map_or(0, |count| (count & 0x3F) as usize),
+    };
+    frame * count
+}
+
+/// An Ogg Opus file's audio packets and where each starts, so decoding can
+/// begin at any of them.
+struct OpusPackets {
+    packets: Vec<Vec<u8>>,
+    /// Position of each packet at 48 kHz, counting the pre-skip.
+    starts: Vec<usize>,
+    channels: usize,
+    pre_skip: usize,
+    /// Final granule position, after which the last packet is padding.
+    end: usize,
+}
+
+impl OpusPackets {
+    /// Decodes from packet `first` on, keeping only what comes after the
+    /// pre-skip and `keep_from`.
+    fn blocks(self: &Arc<Self>, first: usize, keep_from: usize) -> Result<Blocks, String> {
+        let mut decoder = OpusDecoder::new(48000, self.channels)
+            .map_err(|e| format!("Failed to create Opus decoder: {}", e))?;
+        let opus = Arc::clone(self);
+        let channels = self.channels;
+        let mut frame = vec![0.0f32; MAX_DECODED_FRAMES * channels];
+        let keep_from = keep_from.max(self.pre_skip);
+        let mut position = self.starts.get(first).copied().unwrap_or(0);
+
+        Ok(Box::new((first..self.packets.len()).map(move |index| {
+            let decoded = decoder.decode(&opus.packets[index], MAX_DECODED_FRAMES, &mut frame)
+                .map_err(|e| format!("Failed to decode Opus packet: {}", e))?;
+
+            // The part of this packet between the start of what is kept and the end
+            let first = keep_from.clamp(position, position + decoded) - position;
+            let last = opus.end.clamp(position, position + decoded) - position;
+            position += decoded;
+            Ok(frame[first * channels..last.max(first) * channels].to_vec())
+        })))
+    }
+
+    /// Restarts at the packet holding `target` (a frame after the
+    /// pre-skip). Decoding starts `SEEK_PREROLL` earlier, and what it
+    /// produces before that packet is dropped.
+    fn seek(self: &Arc<Self>, target: usize) -> Result<(usize, Blocks), String> {
+        let packet = self.starts.partition_point(|&start| start <= target + self.pre_skip).saturating_sub(1);
+        let keep_from = self.starts.get(packet).copied().unwrap_or(0);
+        let preroll_from = keep_from.saturating_sub(SEEK_PREROLL);
+        let first = self.starts.partition_point(|&start| start <= preroll_from).saturating_sub(1);
+        let blocks = self.blocks(first, keep_from)?;
+        Ok((keep_from.saturating_sub(self.pre_skip), blocks))
+    }
+}
+
+/// Decodes packet by packet, trimming the pre-skip and the padding after
+/// the final granule position. Packet durations are read from their TOC
+/// bytes up front, so seeking goes straight to the right packet.
+pub fn stream_opus(path: &Path) -> Result<DecodeStream, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open Opus file: {}", e))?;
+    let (packets, granule) = ogg_packets(&bytes)?;
+
+    let head = packets.first().filter(|p| p.starts_with(b"OpusHead") && p.len() >= 19)
+        .ok_or_else(|| "Not an Ogg Opus file".to_string())?;
+    let channels = head[9] as usize;
+    let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+    if !matches!(channels, 1 | 2) {
+        return Err(format!("Unsupported Opus channel count: {}", channels));
+    }
+
+    // Skip the OpusHead and OpusTags packets
+    let packets: Vec<Vec<u8>> = packets.into_iter().skip(2).collect();
+    let starts = packets.iter()
+        .scan(0, |position, packet| {
+            let start = *position;
+            *position += packet_frames(packet);
+            Some(start)
+        })
+        .collect();
+    let end = granule as usize;
+    let opus = Arc::new(OpusPackets { packets, starts, channels, pre_skip, end });
+
+    Ok(DecodeStream {
+        sample_rate: 48000,
+        channels: channels as u16,
+        frames: end.saturating_sub(pre_skip),
+        blocks: opus.blocks(0, 0)?,
+        seek: Some(Box::new(move |target| opus.seek(target))),
+    })
+}
@@ -0,0 +1,168 @@
+use super::WavEncoding;
+
+/// Layout of raw interleaved PCM sample data.
+#[derive(Debug, Clone, Copy)]
+pub struct PcmLayout {
+    pub bits: u16,
+    pub float: bool,
+    pub little_endian: bool,
+}
+
+impl PcmLayout {
+    pub fn from_encoding(encoding: WavEncoding, little_endian: bool) -> Self {
+        let (bits, float) = match encoding {
+            WavEncoding::Pcm16 => (16, false),
+            WavEncoding::Pcm24 => (24, false),
+            WavEncoding::Float32 => (32, true),
+        };
+        PcmLayout { bits, float, little_endian }
+    }
+
+    pub fn bytes_per_sample(&self) -> usize {
+        self.bits.div_ceil(8) as usize
+    }
+}
+
+/// Decodes interleaved samples to f32. Integer samples are signed and may
+/// be 8 to 32 bits wide; float samples 32 or 64.
+pub fn decode(data: &[u8], layout: PcmLayout) -> Result<Vec<f32>, String> {
+    let width = layout.bytes_per_sample();
+
+    let samples = match (layout.float, width) {
+        (true, 4) => data
+            .chunks_exact(4)
+            .map(|b| {
+                let b: [u8; 4] = b.try_into().unwrap();
+                if layout.little_endian { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+            })
+            .collect(),
+        (true, 8) => data
+            .chunks_exact(8)
+            .map(|b| {
+                let b: [u8; 8] = b.try_into().unwrap();
+                (if layout.little_endian { f64::from_le_bytes(b) } else { f64::from_be_bytes(b) }) as f32
+            })
+            .collect(),
+        (false, 1..=4) => {
+            let scale = 2f32.powi(width as i32 * 8 - 1);
+
+            data.chunks_exact(width)
+                .map(|b| {
+                    // Left-align into an i32 so the sign bit lands in place
+                    let mut value = [0u8; 4];
+                    for (i, &byte) in b.iter().enumerate() {
+                        let index = if layout.little_endian { width - 1 - i } else { i };
+                        value[index] = byte;
+                    }
+                    (i32::from_be_bytes(value) >> (32 - width * 8)) as f32 / scale
+                })
+                .collect()
+        }
+        _ => return Err(format!("Unsupported bit depth: {}", layout.bits)),
+    };
+
+    Ok(samples)
+}
+
+pub fn encode(samples: &[f32], layout: PcmLayout) -> Vec<u8> {
+    let width = layout.bytes_per_sample();
+    let mut data = Vec::with_capacity(samples.len() * width);
+
+    for &sample in samples {
+        if layout.float {
+            let bytes = if layout.little_endian { sample.to_le_bytes() } else { sample.to_be_bytes() };
+            data.extend_from_slice(&bytes);
+            continue;
+        }
+
+        let max = 2f32.powi(width as i32 * 8 - 1) - 1.0;
+        let value = (sample.clamp(-1.0, 1.0) * max) as i32;
+        let bytes = value.to_be_bytes();
+        let bytes = &bytes[4 - width..];
+        if layout.little_endian {
+            data.extend(bytes.iter().rev());
+        } else {
+            data.extend_from_slice(bytes);
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [f32; 6] = [0.0, 0.5, -0.5, 0.25, -1.0, 0.999];
+
+    fn layout(bits: u16, float: bool, little_endian: bool) -> PcmLayout {
+        PcmLayout { bits, float, little_endian }
+    }
+
+    fn assert_close(decoded: &[f32], expected: &[f32], tolerance: f32) {
+        assert_eq!(decoded.len(), expected.len());
+        for (a, b) in decoded.iter().zip(expected) {
+            assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+        }
+    }
+
+    #[test]
+    fn integer_round_trips_in_both_byte_orders() {
+        for bits in [8, 16, 24, 32] {
+            for little_endian in [false, true] {
+                let layout = layout(bits, false, little_endian);
+                let data = encode(&SAMPLES, layout);
+                assert_eq!(data.len(), SAMPLES.len() * bits as usize / 8);
+                // Encoding truncates against a full scale one step short
+                // of the decoder's, so allow two steps
+                let tolerance = 2f32.powi(2 - bits as i32);
+                assert_close(&decode(&data, layout).unwrap(), &SAMPLES, tolerance);
+            }
+        }
+    }
+
+    #[test]
+    fn byte_order_follows_layout() {
+        // 0.5 at 16 bits is 0x3FFF
+        assert_eq!(encode(&[0.5], layout(16, false, false)), [0x3F, 0xFF]);
+        assert_eq!(encode(&[0.5], layout(16, false, true)), [0xFF, 0x3F]);
+        assert_eq!(encode(&[-1.0], layout(24, false, false)), [0x80, 0x00, 0x01]);
+        assert_eq!(encode(&[-1.0], layout(24, false, true)), [0x01, 0x00, 0x80]);
+
+        assert_eq!(decode(&[0x40, 0x00], layout(16, false, false)).unwrap(), [0.5]);
+        assert_eq!(decode(&[0x00, 0x40], layout(16, false, true)).unwrap(), [0.5]);
+        assert_eq!(decode(&[0x80, 0x00, 0x00], layout(24, false, true)).unwrap(), [2f32.powi(-16)]);
+    }
+
+    #[test]
+    fn float_round_trips() {
+        for little_endian in [false, true] {
+            let layout = layout(32, true, little_endian);
+            assert_eq!(decode(&encode(&SAMPLES, layout), layout).unwrap(), SAMPLES);
+        }
+
+        let data: Vec<u8> = SAMPLES.iter().flat_map(|&s| (s as f64).to_be_bytes()).collect();
+        assert_eq!(decode(&data, layout(64, true, false)).unwrap(), SAMPLES);
+    }
+
+    #[test]
+    fn odd_widths_use_whole_bytes() {
+        // 12- and 20-bit samples are stored left-justified in 2 and 3 bytes
+        assert_eq!(layout(12, false, false).bytes_per_sample(), 2);
+        assert_eq!(layout(20, false, false).bytes_per_sample(), 3);
+        assert_eq!(decode(&[0x40, 0x00, 0xC0, 0x00], layout(12, false, false)).unwrap(), [0.5, -0.5]);
+        assert_eq!(decode(&[0x00, 0x00, 0x40], layout(20, false, true)).unwrap(), [0.5]);
+
+        // 8-bit samples are signed here, as in AIFF and CAF
+        assert_eq!(decode(&[0x80, 0x7F], layout(8, false, false)).unwrap(), [-1.0, 127.0 / 128.0]);
+
+        // A trailing partial sample is dropped
+        assert_eq!(decode(&[0x40, 0x00, 0x12], layout(16, false, false)).unwrap(), [0.5]);
+    }
+
+    #[test]
+    fn rejects_unsupported_depths() {
+        assert!(decode(&[0; 4], layout(16, true, false)).is_err());
+        assert!(decode(&[0; 5], layout(40, false, false)).is_err());
+    }
+}
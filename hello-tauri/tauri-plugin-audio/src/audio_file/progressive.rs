@@ -0,0 +1,244 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+
+use super::flac::stream_flac;
+use super::opus::stream_opus;
+use super::{extension, read_audio_file, AudioBuffer};
+use crate::validate;
+
+/// Upper bound on how many samples a compressed file is assumed to hold
+/// per byte when reserving space. Well above what real FLAC or Opus files
+/// reach, it only stops a header that overstates the length from asking
+/// for more memory than the file could ever fill.
+const MAX_RESERVED_SAMPLES_PER_BYTE: usize = 32;
+
+/// Interleaved blocks in file order.
+pub type Blocks = Box<dyn Iterator<Item = Result<Vec<f32>, String>> + Send>;
+
+/// Restarts decoding near a frame, returning the frame the new blocks start
+/// at (at or before the one asked for).
+pub type Seek = Box<dyn FnMut(usize) -> Result<(usize, Blocks), String> + Send>;
+
+/// A decoder handing out interleaved blocks in file order.
+pub struct DecodeStream {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Length stated by the file's header.
+    pub frames: usize,
+    pub blocks: Blocks,
+    /// Set for formats that can start decoding mid-file.
+    pub seek: Option<Seek>,
+}
+
+/// Audio that may still be decoding on a background thread. The file is
+/// decoded in order, except that seeking ahead of the decoder starts a
+/// second pass at the new position, so `decoded` can hold several ranges
+/// until the in-order pass catches up.
+pub struct ProgressiveAudio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Interleaved samples, indexed by frame. Frames outside `decoded` are
+    /// silence waiting for the decoder. Capacity is reserved for the whole
+    /// file up front (within what the file's size allows) so decoding
+    /// doesn't reallocate.
+    pub samples: RwLock<Vec<f32>>,
+    /// Sorted, non-overlapping frame ranges holding final samples.
+    decoded: Mutex<Vec<Range<usize>>>,
+    /// A frame playback wants next that hasn't been decoded yet.
+    priority: Mutex<Option<usize>>,
+    /// Expected length; replaced by the decoded length if decoding stops
+    /// early.
+    frames: Mutex<usize>,
+    complete: Mutex<bool>,
+    error: Mutex<Option<String>>,
+}
+
+impl ProgressiveAudio {
+    pub fn complete(audio: AudioBuffer) -> Self {
+        let decoded = 0..audio.frames();
+        ProgressiveAudio {
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+            frames: Mutex::new(audio.frames()),
+            decoded: Mutex::new(vec![decoded]),
+            priority: Mutex::new(None),
+            samples: RwLock::new(audio.samples),
+            complete: Mutex::new(true),
+            error: Mutex::new(None),
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        *self.frames.lock().unwrap()
+    }
+
+    /// How far the file is decoded without gaps from its start.
+    pub fn decoded_frames(&self) -> usize {
+        self.decoded.lock().unwrap().first().filter(|range| range.start == 0).map_or(0, |range| range.end)
+    }
+
+    /// End of the decoded run `frame` is in, or `frame` itself when it is
+    /// still waiting for the decoder.
+    pub fn ready_until(&self, frame: usize) -> usize {
+        self.decoded.lock().unwrap().iter().find(|range| range.contains(&frame)).map_or(frame, |range| range.end)
+    }
+
+    /// Asks the decoder to move on to `frame` next if it isn't decoded yet,
+    /// as after a seek. The in-order pass fills in what was skipped later.
+    pub fn prioritize(&self, frame: usize) {
+        if self.is_complete() || self.ready_until(frame) > frame || frame >= self.frames() {
+            return;
+        }
+        *self.priority.lock().unwrap() = Some(frame);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        *self.complete.lock().unwrap()
+    }
+
+    /// Why decoding stopped early, if it did.
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Copies `block` in at `start` and marks it decoded. Blocks decoded
+    /// ahead are dropped when they fall outside the reserved space, so a file
+    /// that overstates its length can't make a seek allocate for it.
+    fn write(&self, start: usize, block: &[f32], ahead: bool) -> bool {
+        let channels = self.channels.max(1) as usize;
+        let (from, to) = (start * channels, start * channels + block.len());
+        if block.is_empty() {
+            return true;
+        }
+        {
+            let mut samples = self.samples.write().unwrap();
+            if ahead && to > samples.capacity() {
+                return false;
+            }
+            if samples.len() < to {
+                samples.resize(to, 0.0);
+            }
+            samples[from..to].copy_from_slice(block);
+        }
+
+        let mut range = start..start + block.len() / channels;
+        let mut decoded = self.decoded.lock().unwrap();
+        decoded.retain(|other| {
+            let touches = other.start <= range.end && range.start <= other.end;
+            if touches {
+                range = range.start.min(other.start)..range.end.max(other.end);
+            }
+            !touches
+        });
+        let index = decoded.partition_point(|other| other.start < range.start);
+        decoded.insert(index, range);
+        true
+    }
+
+    fn decode(&self, stream: DecodeStream) {
+        let channels = self.channels.max(1) as usize;
+        let DecodeStream { mut blocks, mut seek, .. } = stream;
+        let mut position = 0;
+        // The furthest the in-order pass has jumped to skip decoded audio
+        let mut skipped_to = 0;
+        // A pass started by a seek, and where it has got to
+        let mut ahead: Option<(usize, Blocks)> = None;
+
+        loop {
+            let priority = self.priority.lock().unwrap().take();
+            if let (Some(frame), Some(seek)) = (priority, seek.as_mut()) {
+                // If the seek fails, the in-order pass gets there eventually
+                ahead = seek(frame).ok();
+            }
+
+            if let Some((start, pass)) = &mut ahead {
+                match pass.next() {
+                    Some(Ok(block)) if self.write(*start, &block, true) => {
+                        *start += block.len() / channels;
+                        // It stops once it runs into audio that is already there
+                        if self.ready_until(*start) > *start || *start >= self.frames() {
+                            ahead = None;
+                        }
+                    }
+                    _ => ahead = None,
+                }
+                continue;
+            }
+
+            let ready = self.ready_until(position);
+            if ready > skipped_to {
+                skipped_to = ready;
+                match seek.as_mut().map(|seek| seek(ready)) {
+                    Some(Ok((start, pass))) if start > position => {
+                        position = start;
+                        blocks = pass;
+                    }
+                    // Otherwise it decodes through the run again
+                    _ => {}
+                }
+            }
+
+            match blocks.next() {
+                Some(Ok(block)) => {
+                    self.write(position, &block, false);
+                    position += block.len() / channels;
+                }
+                Some(Err(e)) => {
+                    *self.error.lock().unwrap() = Some(e);
+                    break;
+                }
+                None => break,
+            }
+        }
+
+        *self.frames.lock().unwrap() = position;
+        *self.complete.lock().unwrap() = true;
+    }
+}
+
+/// Opens `path` for playback. FLAC and Opus files are decoded on a
+/// background thread and can be played from the start straight away;
+/// other formats are quick to read and are loaded in full first.
+///
+/// A seek past what has been decoded so far (see `prioritize`) restarts
+/// the decoder at the new position: FLAC finds the frame there through its
+/// seek table, or by bisecting the file for frame headers, and Opus from its
+/// packet durations. Decoding always runs flat out.
+pub fn open_progressive(path: &Path) -> Result<Arc<ProgressiveAudio>, String> {
+    validate::input_file(path)?;
+    let stream = match extension(path).as_str() {
+        "flac" => stream_flac(path),
+        "opus" => stream_opus(path),
+        _ => return Ok(Arc::new(ProgressiveAudio::complete(read_audio_file(path)?))),
+    };
+    let stream = match stream {
+        Ok(stream) => stream,
+        // A FLAC stream without a stated length can still be read whole
+        Err(_) => return Ok(Arc::new(ProgressiveAudio::complete(read_audio_file(path)?))),
+    };
+
+    let audio = Arc::new(ProgressiveAudio {
+        sample_rate: stream.sample_rate,
+        channels: stream.channels,
+        samples: RwLock::new(Vec::with_capacity(reserved_samples(path, &stream))),
+        decoded: Mutex::new(Vec::new()),
+        priority: Mutex::new(None),
+        frames: Mutex::new(stream.frames),
+        complete: Mutex::new(false),
+        error: Mutex::new(None),
+    });
+
+    let decoding = Arc::clone(&audio);
+    thread::spawn(move || decoding.decode(stream));
+    Ok(audio)
+}
+
+/// Space to reserve for a stream's samples: its stated length, unless that
+/// is more than a file of this size could hold.
+pub fn reserved_samples(path: &Path, stream: &DecodeStream) -> usize {
+    let file_len = fs::metadata(path).map_or(0, |metadata| metadata.len() as usize);
+    (stream.frames.saturating_mul(stream.channels as usize)).min(file_len.saturating_mul(MAX_RESERVED_SAMPLES_PER_BYTE))
+}
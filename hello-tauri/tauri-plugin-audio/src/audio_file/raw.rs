@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use specta::Type;
+use std::fs;
+use std::path::Path;
+
+use super::pcm::{self, PcmLayout};
+use super::AudioBuffer;
+use crate::validate;
+
+#[derive(Debug, Clone, Copy, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum RawSampleFormat {
+    U8,
+    S8,
+    S16,
+    S24,
+    S32,
+    F32,
+    F64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    #[default]
+    Little,
+    Big,
+}
+
+/// Reads headerless interleaved PCM. `offset` bytes are skipped first, for
+/// captures that carry a proprietary header; a trailing partial frame is
+/// dropped.
+pub fn read_raw(
+    path: &Path,
+    sample_rate: u32,
+    channels: u16,
+    format: RawSampleFormat,
+    endianness: Endianness,
+    offset: usize,
+) -> Result<AudioBuffer, String> {
+    validate::sample_rate(sample_rate)?;
+    validate::channel_count(channels)?;
+    validate::input_file(path)?;
+
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open raw PCM file: {}", e))?;
+    let data = bytes.get(offset..).ok_or_else(|| "Offset is past the end of the file".to_string())?;
+
+    let (bits, float) = match format {
+        RawSampleFormat::U8 | RawSampleFormat::S8 => (8, false),
+        RawSampleFormat::S16 => (16, false),
+        RawSampleFormat::S24 => (24, false),
+        RawSampleFormat::S32 => (32, false),
+        RawSampleFormat::F32 => (32, true),
+        RawSampleFormat::F64 => (64, true),
+    };
+
+    let mut samples = match format {
+        // Unsigned 8-bit is centred on 128, as written by most SDR tools
+        RawSampleFormat::U8 => data.iter().map(|&b| (b as f32 - 128.0) / 128.0).collect(),
+        _ => pcm::decode(data, PcmLayout {
+            bits,
+            float,
+            little_endian: matches!(endianness, Endianness::Little),
+        })?,
+    };
+    samples.truncate(samples.len() - samples.len() % channels as usize);
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
@@ -0,0 +1,25 @@
+/// iOS routes audio through a shared session whose category decides whether
+/// the app may record at all; the default one is playback only. Elsewhere
+/// there is nothing to set up.
+#[cfg(target_os = "ios")]
+pub fn configure() -> Result<(), String> {
+    use objc2_avf_audio::{AVAudioSession, AVAudioSessionCategoryOptions, AVAudioSessionCategoryPlayAndRecord};
+
+    let category = unsafe { AVAudioSessionCategoryPlayAndRecord }
+        .ok_or_else(|| "The play and record audio session is unavailable".to_string())?;
+    // Play through the speaker rather than the earpiece, and keep headsets usable
+    let options = AVAudioSessionCategoryOptions::DefaultToSpeaker
+        | AVAudioSessionCategoryOptions::AllowBluetoothHFP
+        | AVAudioSessionCategoryOptions::AllowBluetoothA2DP;
+
+    let session = unsafe { AVAudioSession::sharedInstance() };
+    unsafe { session.setCategory_withOptions_error(category, options) }
+        .map_err(|e| format!("Failed to configure the audio session: {}", e.localizedDescription()))?;
+    unsafe { session.setActive_error(true) }
+        .map_err(|e| format!("Failed to activate the audio session: {}", e.localizedDescription()))
+}
+
+#[cfg(not(target_os = "ios"))]
+pub fn configure() -> Result<(), String> {
+    Ok(())
+}
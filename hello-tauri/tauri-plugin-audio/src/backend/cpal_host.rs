@@ -0,0 +1,169 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig, SupportedStreamConfig, SupportedStreamConfigRange};
+use std::sync::Arc;
+
+use super::{
+    AudioBackend, BackendDevice, BackendStream, Device, ErrorCallback, InputCallback, InputTimestamp, OutputCallback,
+    Stream, StreamError,
+};
+
+/// The system's sound hardware through cpal's default host.
+pub struct CpalBackend;
+
+impl AudioBackend for CpalBackend {
+    fn input_devices(&self) -> Result<Vec<Device>, String> {
+        let devices = cpal::default_host().input_devices().map_err(|e| e.to_string())?;
+        Ok(devices.map(wrap).collect())
+    }
+
+    fn output_devices(&self) -> Result<Vec<Device>, String> {
+        let devices = cpal::default_host().output_devices().map_err(|e| e.to_string())?;
+        Ok(devices.map(wrap).collect())
+    }
+
+    fn default_input_device(&self) -> Option<Device> {
+        cpal::default_host().default_input_device().map(wrap)
+    }
+
+    fn default_output_device(&self) -> Option<Device> {
+        cpal::default_host().default_output_device().map(wrap)
+    }
+}
+
+fn wrap(device: cpal::Device) -> Device {
+    Arc::new(CpalDevice(device))
+}
+
+struct CpalDevice(cpal::Device);
+
+impl BackendDevice for CpalDevice {
+    fn name(&self) -> Result<String, String> {
+        self.0.name().map_err(|e| e.to_string())
+    }
+
+    fn default_input_config(&self) -> Result<SupportedStreamConfig, String> {
+        self.0.default_input_config().map_err(|e| e.to_string())
+    }
+
+    fn default_output_config(&self) -> Result<SupportedStreamConfig, String> {
+        self.0.default_output_config().map_err(|e| e.to_string())
+    }
+
+    fn supported_input_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String> {
+        Ok(self.0.supported_input_configs().map_err(|e| e.to_string())?.collect())
+    }
+
+    fn supported_output_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String> {
+        Ok(self.0.supported_output_configs().map_err(|e| e.to_string())?.collect())
+    }
+
+    fn build_input_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data: InputCallback,
+        error: ErrorCallback,
+    ) -> Result<Stream, String> {
+        match sample_format {
+            SampleFormat::F32 => build_typed_input_stream::<f32>(&self.0, config, data, error),
+            SampleFormat::I16 => build_typed_input_stream::<i16>(&self.0, config, data, error),
+            SampleFormat::U16 => build_typed_input_stream::<u16>(&self.0, config, data, error),
+            _ => Err("Unsupported sample format".to_string()),
+        }
+    }
+
+    fn build_output_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data: OutputCallback,
+    ) -> Result<Stream, String> {
+        match sample_format {
+            SampleFormat::F32 => build_typed_output_stream::<f32>(&self.0, config, data),
+            SampleFormat::I16 => build_typed_output_stream::<i16>(&self.0, config, data),
+            SampleFormat::U16 => build_typed_output_stream::<u16>(&self.0, config, data),
+            _ => Err("Unsupported sample format".to_string()),
+        }
+    }
+}
+
+fn build_typed_input_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut data: InputCallback,
+    mut error: ErrorCallback,
+) -> Result<Stream, String>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let err_fn = move |err| {
+        eprintln!("an error occurred on stream: {}", err);
+        error(match err {
+            cpal::StreamError::DeviceNotAvailable => StreamError::DeviceNotAvailable,
+            cpal::StreamError::BackendSpecific { .. } => StreamError::Driver,
+        });
+    };
+
+    // Host timestamps count from an arbitrary point, so they are passed on
+    // relative to the first capture
+    let mut epoch = None;
+    let mut buffer = Vec::new();
+
+    let stream = device.build_input_stream(
+        config,
+        move |samples: &[T], info: &cpal::InputCallbackInfo| {
+            let timestamp = info.timestamp();
+            let epoch = *epoch.get_or_insert(timestamp.capture);
+            let since_epoch = |instant: cpal::StreamInstant| instant.duration_since(&epoch).unwrap_or_default();
+
+            buffer.clear();
+            buffer.extend(samples.iter().map(|&s| s.to_sample::<f32>()));
+            data(&buffer, InputTimestamp {
+                callback: since_epoch(timestamp.callback),
+                capture: since_epoch(timestamp.capture),
+            });
+        },
+        err_fn,
+        None,
+    ).map_err(|e| format!("Failed to build input stream: {}", e))?;
+
+    Ok(Box::new(CpalStream(stream)))
+}
+
+fn build_typed_output_stream<T>(device: &cpal::Device, config: &StreamConfig, mut data: OutputCallback) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let err_fn = |err| eprintln!("an error occurred on output stream: {}", err);
+    let mut buffer = Vec::new();
+
+    let stream = device.build_output_stream(
+        config,
+        move |samples: &mut [T], _: &_| {
+            buffer.clear();
+            buffer.resize(samples.len(), 0.0);
+            data(&mut buffer);
+
+            for (out, &sample) in samples.iter_mut().zip(&buffer) {
+                *out = T::from_sample(sample);
+            }
+        },
+        err_fn,
+        None,
+    ).map_err(|e| format!("Failed to build output stream: {}", e))?;
+
+    Ok(Box::new(CpalStream(stream)))
+}
+
+struct CpalStream(cpal::Stream);
+
+impl BackendStream for CpalStream {
+    fn play(&self) -> Result<(), String> {
+        self.0.play().map_err(|e| e.to_string())
+    }
+
+    fn pause(&self) -> Result<(), String> {
+        self.0.pause().map_err(|e| e.to_string())
+    }
+}
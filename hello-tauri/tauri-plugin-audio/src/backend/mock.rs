@@ -0,0 +1,311 @@
+use cpal::{
+    BufferSize, SampleFormat, SampleRate, StreamConfig, SupportedBufferSize, SupportedStreamConfig,
+    SupportedStreamConfigRange,
+};
+use std::f64::consts::TAU;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::{
+    AudioBackend, BackendDevice, BackendStream, Device, ErrorCallback, InputCallback, InputTimestamp, OutputCallback,
+    Stream,
+};
+
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+const SAMPLE_RATE_RANGE: (u32, u32) = (8000, 192000);
+const BUFFER_SIZE_RANGE: (u32, u32) = (64, 4096);
+/// Buffer length used when the caller leaves the size to the device.
+const DEFAULT_BUFFER_MS: u32 = 10;
+/// Tone on the first input channel; channel n plays n times this.
+const TONE_HZ: f64 = 440.0;
+/// Tone level, -6 dBFS.
+const TONE_AMPLITUDE: f64 = 0.5;
+
+/// Devices that need no sound hardware. Inputs produce a fixed tone per
+/// channel (or silence) with timestamps counted from the frames delivered,
+/// so levels, recordings and timing come out the same on every run; output
+/// streams render into nothing at the device rate.
+pub struct MockBackend {
+    inputs: Vec<Device>,
+    outputs: Vec<Device>,
+}
+
+impl MockBackend {
+    fn new(clock: Option<Arc<MockClock>>) -> Self {
+        let device = |name: &'static str, input: bool, channels: u16, tone: bool| -> Device {
+            Arc::new(MockDevice { name, input, channels, tone, clock: clock.clone() })
+        };
+
+        MockBackend {
+            inputs: vec![device("Mock Input", true, 2, true), device("Mock Silence", true, 1, false)],
+            outputs: vec![device("Mock Output", false, 2, false)],
+        }
+    }
+
+    /// Devices whose streams only deliver buffers when `clock` ticks.
+    #[cfg(test)]
+    pub fn with_clock(clock: Arc<MockClock>) -> Self {
+        MockBackend::new(Some(clock))
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        MockBackend::new(None)
+    }
+}
+
+/// Drives mock streams by hand instead of from wall time, so a test decides
+/// exactly how many buffers each stream has delivered.
+#[derive(Default)]
+pub struct MockClock {
+    streams: Mutex<Vec<ClockedStream>>,
+}
+
+// Only tests tick a clock
+#[cfg_attr(not(test), allow(dead_code))]
+struct ClockedStream {
+    playing: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    buffer_index: u64,
+    tick: Box<dyn FnMut(u64) + Send>,
+}
+
+impl MockClock {
+    /// Delivers `buffers` buffers on every playing stream, on the calling
+    /// thread.
+    #[cfg(test)]
+    pub fn tick(&self, buffers: usize) {
+        let mut streams = self.streams.lock().unwrap();
+        streams.retain(|stream| !stream.stopped.load(Ordering::Relaxed));
+        for _ in 0..buffers {
+            for stream in streams.iter_mut() {
+                if stream.playing.load(Ordering::Relaxed) {
+                    (stream.tick)(stream.buffer_index);
+                    stream.buffer_index += 1;
+                }
+            }
+        }
+    }
+}
+
+impl AudioBackend for MockBackend {
+    fn input_devices(&self) -> Result<Vec<Device>, String> {
+        Ok(self.inputs.clone())
+    }
+
+    fn output_devices(&self) -> Result<Vec<Device>, String> {
+        Ok(self.outputs.clone())
+    }
+
+    fn default_input_device(&self) -> Option<Device> {
+        self.inputs.first().cloned()
+    }
+
+    fn default_output_device(&self) -> Option<Device> {
+        self.outputs.first().cloned()
+    }
+}
+
+struct MockDevice {
+    name: &'static str,
+    input: bool,
+    channels: u16,
+    tone: bool,
+    /// Set when streams are ticked by hand rather than from a thread.
+    clock: Option<Arc<MockClock>>,
+}
+
+impl MockDevice {
+    fn default_config(&self, input: bool) -> Result<SupportedStreamConfig, String> {
+        self.check_direction(input)?;
+        let (min, max) = BUFFER_SIZE_RANGE;
+        Ok(SupportedStreamConfig::new(
+            self.channels,
+            SampleRate(DEFAULT_SAMPLE_RATE),
+            SupportedBufferSize::Range { min, max },
+            SampleFormat::F32,
+        ))
+    }
+
+    fn supported_configs(&self, input: bool) -> Result<Vec<SupportedStreamConfigRange>, String> {
+        self.check_direction(input)?;
+        let (min_rate, max_rate) = SAMPLE_RATE_RANGE;
+        let (min, max) = BUFFER_SIZE_RANGE;
+        Ok((1..=self.channels)
+            .map(|channels| {
+                SupportedStreamConfigRange::new(
+                    channels,
+                    SampleRate(min_rate),
+                    SampleRate(max_rate),
+                    SupportedBufferSize::Range { min, max },
+                    SampleFormat::F32,
+                )
+            })
+            .collect())
+    }
+
+    fn check_direction(&self, input: bool) -> Result<(), String> {
+        match (self.input, input) {
+            (true, false) => Err(format!("{} has no outputs", self.name)),
+            (false, true) => Err(format!("{} has no inputs", self.name)),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl BackendDevice for MockDevice {
+    fn name(&self) -> Result<String, String> {
+        Ok(self.name.to_string())
+    }
+
+    fn default_input_config(&self) -> Result<SupportedStreamConfig, String> {
+        self.default_config(true)
+    }
+
+    fn default_output_config(&self) -> Result<SupportedStreamConfig, String> {
+        self.default_config(false)
+    }
+
+    fn supported_input_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String> {
+        self.supported_configs(true)
+    }
+
+    fn supported_output_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String> {
+        self.supported_configs(false)
+    }
+
+    fn build_input_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data: InputCallback,
+        _error: ErrorCallback,
+    ) -> Result<Stream, String> {
+        self.check_direction(true)?;
+        check_format(sample_format)?;
+
+        let sample_rate = config.sample_rate.0;
+        let channels = config.channels as usize;
+        let frames = buffer_frames(config);
+        let buffer_duration = Duration::from_secs_f64(frames as f64 / sample_rate as f64);
+        let tone = self.tone;
+        let mut buffer = vec![0.0; frames * channels];
+
+        Ok(Box::new(MockStream::start(buffer_duration, self.clock.as_deref(), move |buffer_index| {
+            let position = buffer_index * frames as u64;
+            if tone {
+                for (i, frame) in buffer.chunks_exact_mut(channels).enumerate() {
+                    let t = (position + i as u64) as f64 / sample_rate as f64;
+                    for (channel, sample) in frame.iter_mut().enumerate() {
+                        *sample = (TONE_AMPLITUDE * (TAU * TONE_HZ * (channel + 1) as f64 * t).sin()) as f32;
+                    }
+                }
+            }
+
+            let capture = Duration::from_secs_f64(position as f64 / sample_rate as f64);
+            data(&buffer, InputTimestamp { callback: capture + buffer_duration, capture });
+        })))
+    }
+
+    fn build_output_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        mut data: OutputCallback,
+    ) -> Result<Stream, String> {
+        self.check_direction(false)?;
+        check_format(sample_format)?;
+
+        let frames = buffer_frames(config);
+        let buffer_duration = Duration::from_secs_f64(frames as f64 / config.sample_rate.0 as f64);
+        let mut buffer = vec![0.0; frames * config.channels as usize];
+
+        Ok(Box::new(MockStream::start(buffer_duration, self.clock.as_deref(), move |_| {
+            buffer.fill(0.0);
+            data(&mut buffer);
+        })))
+    }
+}
+
+fn check_format(sample_format: SampleFormat) -> Result<(), String> {
+    match sample_format {
+        SampleFormat::F32 => Ok(()),
+        _ => Err("Unsupported sample format".to_string()),
+    }
+}
+
+fn buffer_frames(config: &StreamConfig) -> usize {
+    match config.buffer_size {
+        BufferSize::Fixed(frames) => frames as usize,
+        BufferSize::Default => (config.sample_rate.0 * DEFAULT_BUFFER_MS / 1000) as usize,
+    }
+}
+
+/// Calls `tick` with the buffer index once per buffer duration of wall
+/// time while playing, from a thread that lives as long as the stream, or
+/// from `MockClock::tick` when there is a clock.
+struct MockStream {
+    playing: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockStream {
+    fn start(buffer_duration: Duration, clock: Option<&MockClock>, mut tick: impl FnMut(u64) + Send + 'static) -> Self {
+        let playing = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        if let Some(clock) = clock {
+            clock.streams.lock().unwrap().push(ClockedStream {
+                playing: Arc::clone(&playing),
+                stopped: Arc::clone(&stopped),
+                buffer_index: 0,
+                tick: Box::new(tick),
+            });
+            return MockStream { playing, stopped, thread: None };
+        }
+
+        let thread_playing = Arc::clone(&playing);
+        let thread_stopped = Arc::clone(&stopped);
+        let thread = thread::spawn(move || {
+            let mut buffer_index = 0;
+            let mut next = Instant::now();
+            while !thread_stopped.load(Ordering::Relaxed) {
+                if thread_playing.load(Ordering::Relaxed) {
+                    tick(buffer_index);
+                    buffer_index += 1;
+                    next += buffer_duration;
+                } else {
+                    next = Instant::now() + buffer_duration;
+                }
+                thread::sleep(next.saturating_duration_since(Instant::now()));
+            }
+        });
+
+        MockStream { playing, stopped, thread: Some(thread) }
+    }
+}
+
+impl BackendStream for MockStream {
+    fn play(&self) -> Result<(), String> {
+        self.playing.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), String> {
+        self.playing.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Drop for MockStream {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
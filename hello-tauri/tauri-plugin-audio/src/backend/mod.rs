@@ -0,0 +1,92 @@
+mod cpal_host;
+mod mock;
+
+use cpal::{SampleFormat, StreamConfig, SupportedStreamConfig, SupportedStreamConfigRange};
+use std::env;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use cpal_host::CpalBackend;
+pub use mock::MockBackend;
+#[cfg(test)]
+pub use mock::MockClock;
+
+/// Environment variable that selects the backend; `mock` runs every stream
+/// on the deterministic mock devices instead of sound hardware.
+const BACKEND_VAR: &str = "AUDIO_BACKEND";
+
+/// Where devices come from. Everything that enumerates, opens or plays a
+/// device goes through `host()` rather than cpal directly.
+pub trait AudioBackend: Send + Sync {
+    fn input_devices(&self) -> Result<Vec<Device>, String>;
+    fn output_devices(&self) -> Result<Vec<Device>, String>;
+    fn default_input_device(&self) -> Option<Device>;
+    fn default_output_device(&self) -> Option<Device>;
+}
+
+/// One audio device. Configs use cpal's types, which are plain data.
+pub trait BackendDevice: Send + Sync {
+    fn name(&self) -> Result<String, String>;
+    fn default_input_config(&self) -> Result<SupportedStreamConfig, String>;
+    fn default_output_config(&self) -> Result<SupportedStreamConfig, String>;
+    fn supported_input_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String>;
+    fn supported_output_configs(&self) -> Result<Vec<SupportedStreamConfigRange>, String>;
+
+    /// Opens a paused input stream delivering interleaved f32 buffers.
+    fn build_input_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data: InputCallback,
+        error: ErrorCallback,
+    ) -> Result<Stream, String>;
+
+    /// Opens a paused output stream that asks `data` to fill interleaved
+    /// f32 buffers.
+    fn build_output_stream(
+        &self,
+        config: &StreamConfig,
+        sample_format: SampleFormat,
+        data: OutputCallback,
+    ) -> Result<Stream, String>;
+}
+
+/// A built stream. Dropping it stops the stream.
+pub trait BackendStream {
+    fn play(&self) -> Result<(), String>;
+    fn pause(&self) -> Result<(), String>;
+}
+
+pub type Device = Arc<dyn BackendDevice>;
+pub type Stream = Box<dyn BackendStream>;
+
+pub type InputCallback = Box<dyn FnMut(&[f32], InputTimestamp) + Send>;
+pub type OutputCallback = Box<dyn FnMut(&mut [f32]) + Send>;
+pub type ErrorCallback = Box<dyn FnMut(StreamError) + Send>;
+
+/// When an input buffer was handed over and when its first frame was
+/// captured, both measured from the capture of the stream's first buffer.
+#[derive(Debug, Clone, Copy)]
+pub struct InputTimestamp {
+    pub callback: Duration,
+    pub capture: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamError {
+    DeviceNotAvailable,
+    /// Any other driver error; the stream may keep running.
+    Driver,
+}
+
+static BACKEND: OnceLock<Box<dyn AudioBackend>> = OnceLock::new();
+
+/// The backend in use, chosen from `AUDIO_BACKEND` on first use.
+pub fn host() -> &'static dyn AudioBackend {
+    BACKEND
+        .get_or_init(|| match env::var(BACKEND_VAR).as_deref() {
+            Ok("mock") => Box::new(MockBackend::default()),
+            _ => Box::new(CpalBackend),
+        })
+        .as_ref()
+}
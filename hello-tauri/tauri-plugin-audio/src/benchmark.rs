@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audio_file::{encode_flac, AudioBuffer, FlacOptions, OpusFrameEncoder, OpusOptions};
+use crate::calculate_rms;
+use crate::dsp::dynamics::{CompressorSettings, Dynamics, DynamicsProcessor};
+use crate::dsp::eq::{EqBand, EqBandKind, Equalizer};
+use crate::dsp::fft::SpectrumAnalyzer;
+use crate::dsp::resample::StreamResampler;
+use crate::stream::ANALYSIS_WINDOW;
+use crate::validate;
+
+/// Block length the per-block timings are quoted for, matching a typical
+/// device buffer.
+const BLOCK_MS: u32 = 10;
+
+/// Time spent on each test unless the caller asks otherwise.
+const DEFAULT_DURATION_MS: u64 = 500;
+const DURATION_RANGE_MS: (u64, u64) = (50, 10_000);
+
+/// Opus only runs at a few rates and at most two channels, so it is always
+/// measured at 48 kHz on up to two channels.
+const OPUS_SAMPLE_RATE: u32 = 48000;
+
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct BenchmarkOptions {
+    /// Defaults to 48 kHz.
+    pub sample_rate: Option<u32>,
+    /// Defaults to stereo.
+    pub channels: Option<u16>,
+    /// Time spent on each test, 500 ms by default.
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct BenchmarkResult {
+    /// `rms`, `fft`, `resample`, `eq`, `compressor`, `flac` or `opus`.
+    pub name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Seconds of audio processed per second, on one thread. Below 1.0 the
+    /// operation cannot keep up in real time.
+    pub realtime_factor: f64,
+    /// Processing time for one 10 ms block.
+    pub block_us: f64,
+    /// Channels of this operation one thread could keep up with.
+    pub max_realtime_channels: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct DspBenchmark {
+    pub results: Vec<BenchmarkResult>,
+    /// Threads the machine can run at once; streams and outputs each run
+    /// their callbacks on their own.
+    pub threads: usize,
+}
+
+/// Deterministic white noise at about -12 dBFS, so encoders and dynamics
+/// do real work instead of coasting on silence.
+fn test_signal(frames: usize, channels: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491_u32;
+    (0..frames * channels)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            (state as f32 / u32::MAX as f32 * 2.0 - 1.0) * 0.25
+        })
+        .collect()
+}
+
+/// Repeats `run`, which processes `chunk_frames` frames per call, for at
+/// least `duration` after one untimed warm-up call.
+fn measure(
+    name: &str,
+    (sample_rate, channels): (u32, u16),
+    duration: Duration,
+    chunk_frames: usize,
+    mut run: impl FnMut() -> Result<(), String>,
+) -> Result<BenchmarkResult, String> {
+    run()?;
+
+    let start = Instant::now();
+    let mut frames = 0;
+    while start.elapsed() < duration {
+        run()?;
+        frames += chunk_frames;
+    }
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let realtime_factor = frames as f64 / sample_rate as f64 / elapsed;
+    let block_frames = (sample_rate * BLOCK_MS / 1000) as f64;
+
+    Ok(BenchmarkResult {
+        name: name.to_string(),
+        sample_rate,
+        channels,
+        realtime_factor,
+        block_us: elapsed / frames as f64 * block_frames * 1e6,
+        max_realtime_channels: (realtime_factor * channels as f64).floor() as u32,
+    })
+}
+
+fn run_benchmarks(sample_rate: u32, channels: u16, duration: Duration) -> Result<Vec<BenchmarkResult>, String> {
+    let format = (sample_rate, channels);
+    let channel_count = channels as usize;
+    let block_frames = (sample_rate * BLOCK_MS / 1000) as usize;
+    let block = test_signal(block_frames, channel_count);
+    let mut results = Vec::new();
+
+    results.push(measure("rms", format, duration, block_frames, || {
+        calculate_rms(&block);
+        Ok(())
+    })?);
+
+    // One spectrum per channel over a window of the live analyzers' size
+    let window = test_signal(ANALYSIS_WINDOW, 1);
+    let mut analyzer = SpectrumAnalyzer::new(ANALYSIS_WINDOW);
+    results.push(measure("fft", format, duration, ANALYSIS_WINDOW, || {
+        for _ in 0..channels {
+            analyzer.amplitudes(&window);
+        }
+        Ok(())
+    })?);
+
+    let from = if sample_rate == 44100 { 48000 } else { 44100 };
+    let from_block = test_signal((from * BLOCK_MS / 1000) as usize, channel_count);
+    let mut resampler = StreamResampler::new(from, sample_rate, channel_count)?;
+    results.push(measure("resample", format, duration, block_frames, || {
+        resampler.process(&from_block).map(|_| ())
+    })?);
+
+    let bands = [
+        EqBand { kind: EqBandKind::LowShelf, frequency_hz: 120.0, gain_db: 3.0, q: 0.7 },
+        EqBand { kind: EqBandKind::Peak, frequency_hz: 1000.0, gain_db: -2.0, q: 1.0 },
+        EqBand { kind: EqBandKind::Peak, frequency_hz: 3500.0, gain_db: 2.0, q: 1.4 },
+        EqBand { kind: EqBandKind::HighShelf, frequency_hz: 8000.0, gain_db: -3.0, q: 0.7 },
+    ];
+    let mut equalizer = Equalizer::new(&bands, sample_rate, channel_count)?;
+    let mut eq_block = block.clone();
+    results.push(measure("eq", format, duration, block_frames, || {
+        equalizer.process(&mut eq_block);
+        Ok(())
+    })?);
+
+    let compressor = Dynamics::Compressor(CompressorSettings::default());
+    let mut dynamics = DynamicsProcessor::new(sample_rate);
+    let mut dynamics_block = block.clone();
+    results.push(measure("compressor", format, duration, block_frames, || {
+        dynamics.process(&mut dynamics_block, channel_count, &compressor, calculate_rms(&block));
+        Ok(())
+    })?);
+
+    // FLAC encodes whole files, so it gets a second of audio per call
+    let second = AudioBuffer {
+        samples: test_signal(sample_rate as usize, channel_count),
+        sample_rate,
+        channels,
+    };
+    let flac = FlacOptions::default();
+    results.push(measure("flac", format, duration, sample_rate as usize, || {
+        encode_flac(&second, &flac).map(|_| ())
+    })?);
+
+    let opus_channels = channels.min(2);
+    let opus_frames = (OPUS_SAMPLE_RATE * BLOCK_MS / 1000) as usize;
+    let opus_block = test_signal(opus_frames, opus_channels as usize);
+    let mut encoder = OpusFrameEncoder::new(OPUS_SAMPLE_RATE, opus_channels, &OpusOptions::default())?;
+    results.push(measure("opus", (OPUS_SAMPLE_RATE, opus_channels), duration, opus_frames, || {
+        encoder.push(&opus_block).map(|_| ())
+    })?);
+
+    Ok(results)
+}
+
+/// Times the main processing steps on this machine for `options`, so the
+/// frontend can tell whether an effect chain or channel count will keep up
+/// before going live. Each test runs on one thread for the given duration,
+/// about 3.5 s in total by default.
+#[tauri::command]
+#[specta::specta]
+pub async fn run_dsp_benchmark(options: Option<BenchmarkOptions>) -> Result<DspBenchmark, String> {
+    let options = options.unwrap_or_default();
+    let sample_rate = validate::sample_rate(options.sample_rate.unwrap_or(48000))?;
+    let channels = validate::channel_count(options.channels.unwrap_or(2))?;
+    let duration_ms = options.duration_ms.unwrap_or(DEFAULT_DURATION_MS);
+    validate::range("Duration", duration_ms, DURATION_RANGE_MS, "ms")?;
+
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        run_benchmarks(sample_rate, channels, Duration::from_millis(duration_ms))
+    })
+    .await
+    .map_err(|e| format!("Benchmark task failed: {}", e))??;
+
+    Ok(DspBenchmark {
+        results,
+        threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    })
+}
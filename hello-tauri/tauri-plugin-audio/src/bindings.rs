@@ -0,0 +1,238 @@
+use specta_typescript::Typescript;
+use std::fs;
+use std::path::Path;
+use tauri::Wry;
+use tauri_specta::{collect_commands, collect_events, Builder, ErrorHandlingMode};
+
+use crate::validate::ErrorCode;
+
+/// Every command, with the desktop-only ones appended where they exist.
+macro_rules! audio_commands {
+    ($($extra:tt)*) => {
+        collect_commands![
+            crate::get_audio_devices,
+            crate::start_monitoring,
+            crate::stop_monitoring,
+            crate::pause_monitoring,
+            crate::get_stream_state,
+            crate::list_streams,
+            crate::get_volume,
+            crate::get_stream_info,
+            crate::get_stream_stats,
+            crate::set_stream_auto_restart,
+            crate::set_input_gain,
+            crate::set_mid_side,
+            crate::set_stream_mute,
+            crate::set_stream_solo,
+            crate::set_dynamics,
+            crate::insert::set_input_chain,
+            crate::master::set_master_chain,
+            crate::set_ducking,
+            crate::read_wav_file,
+            crate::read_raw_pcm,
+            crate::convert::convert_audio_file,
+            crate::edit::crossfade_files,
+            crate::edit::mix_files,
+            crate::metadata::get_wav_metadata,
+            crate::metadata::set_wav_metadata,
+            crate::output::test_output_device,
+            crate::passthrough::start_passthrough,
+            crate::passthrough::stop_passthrough,
+            crate::passthrough::set_passthrough_buffer,
+            crate::passthrough::get_passthrough_status,
+            crate::passthrough::set_passthrough_convolution,
+            crate::plugins::scan_plugins,
+            crate::plugins::insert_passthrough_plugin,
+            crate::plugins::remove_passthrough_plugin,
+            crate::plugins::get_passthrough_plugins,
+            crate::plugins::get_plugin_parameters,
+            crate::plugins::set_plugin_parameter,
+            crate::plugins::process_file_with_plugins,
+            crate::convolution::convolve_file,
+            crate::measure::capture_impulse_response,
+            crate::measure::measure_frequency_response,
+            crate::measure::analyze_rt60,
+            crate::measure::measure_snr,
+            crate::transcription::models::list_whisper_models,
+            crate::transcription::models::download_whisper_model,
+            crate::transcription::models::delete_whisper_model,
+            crate::transcription::transcribe_file,
+            crate::transcription::language::detect_language,
+            crate::transcription::subtitles::export_subtitles,
+            crate::transcription::live::start_live_transcription,
+            crate::transcription::live::stop_live_transcription,
+            crate::transcription::live::set_live_keywords,
+            crate::mixer::start_mixer,
+            crate::mixer::stop_mixer,
+            crate::mixer::add_to_mix,
+            crate::mixer::remove_from_mix,
+            crate::mixer::set_mix_gain,
+            crate::mixer::get_mix_levels,
+            crate::mixer::reset_mix_loudness,
+            crate::mixer::start_mix_recording,
+            crate::mixer::stop_mix_recording,
+            crate::analysis::get_spectrum,
+            crate::analysis::get_band_levels,
+            crate::analysis::start_goniometer,
+            crate::analysis::stop_goniometer,
+            crate::analysis::start_oscilloscope,
+            crate::analysis::stop_oscilloscope,
+            crate::analysis::get_phase_relationship,
+            crate::analysis::get_loudness,
+            crate::analysis::reset_loudness,
+            crate::analysis::analyze_loudness,
+            crate::scan::scan_folder_levels,
+            crate::waveform::get_waveform_overview,
+            crate::spectral::analyze_spectrum,
+            crate::render::render_spectrogram,
+            crate::render::render_waveform,
+            crate::qc::detect_clipping,
+            crate::qc::detect_dropouts,
+            crate::qc::detect_silence,
+            crate::split::split_on_silence,
+            crate::split::export_regions,
+            crate::markers::get_markers,
+            crate::markers::set_markers,
+            crate::cue::read_cue_sheet,
+            crate::cue::write_cue_sheet,
+            crate::recording::start_recording,
+            crate::recording::stop_recording,
+            crate::recording::save_recording,
+            crate::recording::discard_recording,
+            crate::player::play_file,
+            crate::player::stop_playback,
+            crate::player::pause_playback,
+            crate::player::seek_playback,
+            crate::player::set_ab_loop,
+            crate::player::set_playback_eq,
+            crate::player::set_playback_convolution,
+            crate::player::scrub_to,
+            crate::player::end_scrub,
+            crate::player::set_position_interval,
+            crate::player::set_playback_fades,
+            crate::player::get_playback_status,
+            crate::clips::open_clip_output,
+            crate::clips::preload_clip,
+            crate::clips::trigger_clip,
+            crate::clips::stop_clips,
+            crate::clips::unload_clip,
+            crate::clips::list_clips,
+            crate::soundboard::assign_pad,
+            crate::soundboard::clear_pad,
+            crate::soundboard::load_soundboard,
+            crate::soundboard::trigger_pad,
+            crate::soundboard::stop_pad,
+            crate::soundboard::get_soundboard,
+            crate::dual::start_dual_recording,
+            crate::dual::stop_dual_recording,
+            crate::dual::get_dual_recording_status,
+            crate::recording::get_recording_presets,
+            crate::recording::save_recording_preset,
+            crate::recording::delete_recording_preset,
+            crate::recovery::recover_recording,
+            crate::overdub::start_overdub,
+            crate::overdub::stop_overdub,
+            crate::overdub::start_punch,
+            crate::overdub::stop_punch,
+            crate::settings::set_device_alias,
+            crate::settings::get_device_aliases,
+            crate::events::get_event_rates,
+            crate::events::set_event_rates,
+            crate::lifecycle::get_lifecycle_states,
+            crate::subscriptions::subscribe,
+            crate::permissions::check_audio_permission,
+            crate::latency::get_output_latency,
+            crate::latency::set_output_latency,
+            crate::permissions::request_audio_permission,
+            crate::subscriptions::unsubscribe,
+            crate::sync::get_sync_status,
+            crate::benchmark::run_dsp_benchmark,
+            crate::analysis::get_metering_settings,
+            crate::analysis::set_metering_settings,
+            crate::recent::get_recent_audio,
+            crate::recent::save_recent_audio,
+            crate::recent::capture_snapshot,
+            crate::recent::get_recent_audio_settings,
+            crate::recent::set_recent_audio_settings,
+            $($extra)*
+        ]
+    };
+}
+
+/// The plugin's commands, for both the invoke handler and the bindings.
+/// Failed commands throw their error message, as `invoke` does; messages
+/// from input validation start with an `ErrorCode`.
+pub fn commands() -> Builder<Wry> {
+    #[cfg(desktop)]
+    let commands = audio_commands![
+        crate::hotkeys::set_hotkey,
+        crate::hotkeys::remove_hotkey,
+        crate::hotkeys::get_hotkeys
+    ];
+    #[cfg(mobile)]
+    let commands = audio_commands![];
+
+    Builder::new()
+        .plugin_name("audio")
+        .commands(commands)
+        .typ::<ErrorCode>()
+        .error_handling(ErrorHandlingMode::Throw)
+        .dangerously_cast_bigints_to_number()
+}
+
+/// Every event, with the desktop-only ones appended where they exist.
+macro_rules! audio_events {
+    ($($extra:tt)*) => {
+        collect_events![
+            crate::analysis::SpectrumFrame,
+            crate::analysis::GoniometerFrame,
+            crate::analysis::OscilloscopeFrame,
+            crate::recording::RecordStatus,
+            crate::recording::DiskSpaceEvent,
+            crate::recording::RecordFinished,
+            crate::measure::SnrPhaseEvent,
+            crate::player::PlaybackPosition,
+            crate::player::PlaybackEnded,
+            crate::lifecycle::StateChanged,
+            crate::transcription::models::ModelDownloadProgress,
+            crate::transcription::TranscriptionProgress,
+            crate::transcription::live::LiveTranscriptEvent,
+            crate::transcription::live::KeywordAlert,
+            crate::scan::ScanProgress,
+            crate::stream::DeviceLostEvent,
+            crate::stream::XrunEvent,
+            crate::stream::StreamStalledEvent,
+            crate::stream::StreamRestartedEvent,
+            $($extra)*
+        ]
+    };
+}
+
+/// Events with a payload of their own. Their names aren't prefixed with the
+/// plugin's like the commands are, so they are exported separately.
+/// `transcript://partial` carries a `LiveTranscriptEvent` like
+/// `transcript://final`, and `dual-record://finished` and
+/// `overdub://finished` a `Result` of the command's return type.
+fn events() -> Builder<Wry> {
+    #[cfg(desktop)]
+    let events = audio_events![crate::hotkeys::HotkeyFailed];
+    #[cfg(mobile)]
+    let events = audio_events![];
+
+    Builder::new().events(events).dangerously_cast_bigints_to_number()
+}
+
+
+/// Writes `audio.ts` (the commands) and `audio-events.ts` (the events)
+/// into `dir`, typically from the app's debug builds so the frontend's
+/// types can't drift from the Rust side.
+pub fn export(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create bindings directory: {}", e))?;
+    let typescript = || Typescript::default().header("// Generated by tauri-plugin-audio. Do not edit.");
+    commands()
+        .export(typescript(), dir.join("audio.ts"))
+        .map_err(|e| format!("Failed to export command bindings: {}", e))?;
+    events()
+        .export(typescript(), dir.join("audio-events.ts"))
+        .map_err(|e| format!("Failed to export event bindings: {}", e))
+}
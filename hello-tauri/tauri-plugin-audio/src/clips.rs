@@ -0,0 +1,378 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::State;
+
+use crate::audio_file::{read_audio_file, AudioBuffer};
+use crate::backend::{self, Device};
+use crate::config::{negotiate_output_config, ConfigRequest, NegotiatedConfig};
+use crate::dsp::fade::FadeRamp;
+use crate::dsp::gain::db_to_linear;
+use crate::master::MasterChain;
+use crate::output::{build_output_stream, find_output_device};
+use crate::playback::PlaybackFades;
+use crate::validate;
+
+/// A file held in memory, ready to play on the clip output.
+struct Clip {
+    path: String,
+    /// The decoded file, kept so the clip can be converted again when the
+    /// output changes.
+    source: AudioBuffer,
+    /// `source` at the output's rate and channel layout.
+    samples: Arc<Vec<f32>>,
+    /// `(sample_rate, channels)` that `samples` was converted for.
+    format: (u32, u16),
+}
+
+/// One triggered clip sounding on the output.
+struct Voice {
+    /// What started it: the clip id for direct triggers, or e.g. a
+    /// soundboard pad, so it can be stopped by whoever started it.
+    key: String,
+    samples: Arc<Vec<f32>>,
+    position: usize,
+    gain: f32,
+    /// Stopping a voice fades it out rather than cutting it off.
+    fade: FadeRamp,
+}
+
+/// A stream kept open on an output device so triggered clips start at the
+/// next callback instead of waiting for a device to open.
+struct ClipOutput {
+    config: NegotiatedConfig,
+    voices: Arc<Mutex<Vec<Voice>>>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl ClipOutput {
+    fn start(device: Device, request: &ConfigRequest, master: &MasterChain) -> Result<Self, String> {
+        let (config, negotiated) = negotiate_output_config(&device, request)?;
+        let sample_format = config.sample_format();
+        let config = negotiated.stream_config();
+
+        let voices = Arc::new(Mutex::new(Vec::<Voice>::new()));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let render_voices = Arc::clone(&voices);
+        let channels = config.channels as usize;
+        let mut master = master.processor(negotiated.sample_rate, channels);
+        thread::spawn(move || {
+            let render = move |out: &mut [f32]| {
+                let mut voices = render_voices.lock().unwrap();
+                for voice in voices.iter_mut() {
+                    for frame in out.chunks_exact_mut(channels) {
+                        let Some(samples) = voice.samples.get(voice.position..voice.position + channels) else {
+                            break;
+                        };
+                        let gain = voice.gain * voice.fade.next_gain();
+                        for (out, &sample) in frame.iter_mut().zip(samples) {
+                            *out += sample * gain;
+                        }
+                        voice.position += channels;
+                    }
+                }
+                voices.retain(|voice| voice.position < voice.samples.len() && !voice.fade.is_closed());
+                drop(voices);
+                master.process(out);
+            };
+
+            let stream = match build_output_stream(&device, &config, sample_format, render) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            if let Err(e) = stream.play() {
+                let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+                return;
+            }
+
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+        });
+
+        ready_rx.recv()
+            .map_err(|_| "Clip output thread exited unexpectedly".to_string())??;
+
+        Ok(ClipOutput { config: negotiated, voices, stop_tx })
+    }
+
+    fn format(&self) -> (u32, u16) {
+        (self.config.sample_rate, self.config.channels)
+    }
+
+    fn fade_frames(&self, ms: f32) -> usize {
+        PlaybackFades::frames(ms, self.config.sample_rate)
+    }
+}
+
+impl Drop for ClipOutput {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+    }
+}
+
+/// Lays `audio` out for the output: resampled to its rate, a mono clip
+/// sent to every channel, a multichannel clip folded to mono for a mono
+/// output, and channels the output doesn't have dropped.
+fn convert_clip(audio: &AudioBuffer, (sample_rate, channels): (u32, u16)) -> Result<Vec<f32>, String> {
+    let audio = audio.resampled(sample_rate)?;
+    let channels = channels.max(1) as usize;
+    let source_channels = audio.channels.max(1) as usize;
+
+    if channels == 1 {
+        return Ok(audio.to_mono());
+    }
+    let mut samples = vec![0.0; audio.frames() * channels];
+    for (out, frame) in samples.chunks_exact_mut(channels).zip(audio.samples.chunks_exact(source_channels)) {
+        if source_channels == 1 {
+            out.fill(frame[0]);
+        } else {
+            for (out, &sample) in out.iter_mut().zip(frame) {
+                *out = sample;
+            }
+        }
+    }
+    Ok(samples)
+}
+
+#[derive(Default)]
+pub struct ClipCache {
+    clips: Mutex<HashMap<String, Clip>>,
+    next_id: Mutex<u64>,
+    output: Mutex<Option<ClipOutput>>,
+    fades: Mutex<PlaybackFades>,
+    master: MasterChain,
+}
+
+impl ClipCache {
+    pub fn new(master: MasterChain) -> Self {
+        ClipCache { master, ..ClipCache::default() }
+    }
+
+    /// Format of the clip output, opening the default output in low-latency
+    /// mode if none is open yet.
+    fn output_format(&self) -> Result<(u32, u16), String> {
+        let mut output = self.output.lock().unwrap();
+        if let Some(output) = output.as_ref() {
+            return Ok(output.format());
+        }
+
+        let device = backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string())?;
+        let request = ConfigRequest {
+            low_latency: true,
+            ..ConfigRequest::default()
+        };
+        let opened = ClipOutput::start(device, &request, &self.master)?;
+        let format = opened.format();
+        *output = Some(opened);
+        Ok(format)
+    }
+
+    pub fn set_fades(&self, fades: PlaybackFades) {
+        *self.fades.lock().unwrap() = fades;
+    }
+
+    pub fn info(&self, id: &str) -> Option<ClipInfo> {
+        self.clips.lock().unwrap().get(id).map(|clip| clip_info(id, clip))
+    }
+
+    pub fn unload(&self, id: &str) -> bool {
+        self.clips.lock().unwrap().remove(id).is_some()
+    }
+
+    /// Starts clip `id` as a voice tagged `key`, first fading out voices
+    /// whose key matches `stop`. With `skip_if_playing` nothing happens
+    /// while a voice tagged `key` is still sounding. Returns whether it
+    /// started.
+    pub fn trigger(
+        &self,
+        id: &str,
+        key: &str,
+        gain_db: f32,
+        stop: impl Fn(&str) -> bool,
+        skip_if_playing: bool,
+    ) -> Result<bool, String> {
+        let samples = {
+            let clips = self.clips.lock().unwrap();
+            let clip = clips.get(id).ok_or_else(|| format!("Clip '{}' is not loaded", id))?;
+            Arc::clone(&clip.samples)
+        };
+
+        let output = self.output.lock().unwrap();
+        let output = output.as_ref().ok_or_else(|| "The clip output is not open".to_string())?;
+        let fades = *self.fades.lock().unwrap();
+        let mut voices = output.voices.lock().unwrap();
+        if skip_if_playing && voices.iter().any(|voice| voice.key == key && voice.fade.is_open()) {
+            return Ok(false);
+        }
+        release(&mut voices, stop, output.fade_frames(fades.fade_out_ms));
+
+        let mut fade = FadeRamp::new(false);
+        fade.ramp_to(true, output.fade_frames(fades.fade_in_ms));
+        voices.push(Voice {
+            key: key.to_string(),
+            samples,
+            position: 0,
+            gain: db_to_linear(gain_db),
+            fade,
+        });
+        Ok(true)
+    }
+
+    /// Fades out every voice whose key matches `stop`.
+    pub fn stop(&self, stop: impl Fn(&str) -> bool) {
+        let fade_out_ms = self.fades.lock().unwrap().fade_out_ms;
+        if let Some(output) = self.output.lock().unwrap().as_ref() {
+            release(&mut output.voices.lock().unwrap(), stop, output.fade_frames(fade_out_ms));
+        }
+    }
+}
+
+fn release(voices: &mut [Voice], stop: impl Fn(&str) -> bool, fade_frames: usize) {
+    for voice in voices.iter_mut().filter(|voice| stop(&voice.key)) {
+        voice.fade.ramp_to(false, fade_frames);
+    }
+}
+
+fn clip_info(id: &str, clip: &Clip) -> ClipInfo {
+    ClipInfo {
+        id: id.to_string(),
+        path: clip.path.clone(),
+        duration_ms: clip.source.frames() as f64 * 1000.0 / clip.source.sample_rate as f64,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ClipInfo {
+    pub id: String,
+    pub path: String,
+    pub duration_ms: f64,
+}
+
+/// Opens the output clips play on, `output_device` (the default output
+/// when omitted) with `config` (a low-latency buffer when omitted).
+/// Loaded clips are converted for the new output and anything playing on
+/// the old one stops. Clips open the default output on their own if this
+/// is never called.
+#[tauri::command]
+#[specta::specta]
+pub async fn open_clip_output(
+    output_device: Option<String>,
+    config: Option<ConfigRequest>,
+    cache: State<'_, ClipCache>,
+) -> Result<NegotiatedConfig, String> {
+    let device = match output_device {
+        Some(id) => find_output_device(&id)?,
+        None => backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string())?,
+    };
+    let request = config.unwrap_or(ConfigRequest {
+        low_latency: true,
+        ..ConfigRequest::default()
+    });
+
+    let mut output = cache.output.lock().unwrap();
+    // Close the previous output before opening the device again
+    output.take();
+    let opened = ClipOutput::start(device, &request, &cache.master)?;
+    let format = opened.format();
+    let negotiated = opened.config.clone();
+    *output = Some(opened);
+    drop(output);
+
+    for clip in cache.clips.lock().unwrap().values_mut() {
+        if clip.format != format {
+            clip.samples = Arc::new(convert_clip(&clip.source, format)?);
+            clip.format = format;
+        }
+    }
+    Ok(negotiated)
+}
+
+/// Decodes `path` in full and converts it for the clip output, so that
+/// `trigger_clip` has nothing left to do but start it. Returns the id to
+/// trigger it by.
+#[tauri::command]
+#[specta::specta]
+pub async fn preload_clip(path: String, cache: State<'_, ClipCache>) -> Result<ClipInfo, String> {
+    preload(&cache, path).await
+}
+
+pub async fn preload(cache: &ClipCache, path: String) -> Result<ClipInfo, String> {
+    let format = cache.output_format()?;
+    let file_path = path.clone();
+    let (source, samples) = tauri::async_runtime::spawn_blocking(move || {
+        let source = read_audio_file(Path::new(&file_path))?;
+        let samples = convert_clip(&source, format)?;
+        Ok::<_, String>((source, samples))
+    })
+    .await
+    .map_err(|e| format!("Load task failed: {}", e))??;
+
+    let mut clip = Clip { path, source, samples: Arc::new(samples), format };
+    // The output may have been reopened while the file was loading
+    let format = cache.output_format()?;
+    if clip.format != format {
+        clip.samples = Arc::new(convert_clip(&clip.source, format)?);
+        clip.format = format;
+    }
+
+    let id = {
+        let mut next_id = cache.next_id.lock().unwrap();
+        *next_id += 1;
+        format!("clip_{}", *next_id)
+    };
+    let info = clip_info(&id, &clip);
+    cache.clips.lock().unwrap().insert(id, clip);
+    Ok(info)
+}
+
+/// Starts clip `id` from the top at `gain_db` (unity when omitted). It
+/// sounds from the output's next buffer, mixed over anything already
+/// playing, including earlier triggers of the same clip.
+#[tauri::command]
+#[specta::specta]
+pub fn trigger_clip(id: String, gain_db: Option<f32>, cache: State<ClipCache>) -> Result<(), String> {
+    let gain_db = validate::gain_db(gain_db.unwrap_or(0.0))?;
+    cache.trigger(&id, &id, gain_db, |_| false, false)?;
+    Ok(())
+}
+
+/// Silences every playing instance of clip `id`, or all clips when omitted.
+#[tauri::command]
+#[specta::specta]
+pub fn stop_clips(id: Option<String>, cache: State<ClipCache>) -> Result<(), String> {
+    cache.stop(|key| id.as_ref().is_none_or(|id| id == key));
+    Ok(())
+}
+
+/// Frees the memory held by clip `id`. Instances already playing finish.
+#[tauri::command]
+#[specta::specta]
+pub fn unload_clip(id: String, cache: State<ClipCache>) -> Result<(), String> {
+    if !cache.unload(&id) {
+        return Err(format!("Clip '{}' is not loaded", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn list_clips(cache: State<ClipCache>) -> Result<Vec<ClipInfo>, String> {
+    let clips = cache.clips.lock().unwrap();
+    let mut infos: Vec<ClipInfo> = clips.iter().map(|(id, clip)| clip_info(id, clip)).collect();
+    infos.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(infos)
+}
@@ -0,0 +1,193 @@
+use cpal::{
+    BufferSize, SampleFormat, SampleRate, SupportedBufferSize, SupportedStreamConfig, SupportedStreamConfigRange,
+};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::backend::Device;
+use crate::validate;
+
+/// Sample formats the stream callbacks know how to convert.
+const SUPPORTED_FORMATS: [SampleFormat; 3] = [SampleFormat::F32, SampleFormat::I16, SampleFormat::U16];
+
+/// Rates tried, in order, when the caller has no preference and the
+/// device's default rate is not available in a candidate range.
+const PREFERRED_SAMPLE_RATES: [u32; 2] = [48000, 44100];
+
+/// Buffer size asked for in low-latency mode (about 2.7 ms at 48 kHz).
+const LOW_LATENCY_BUFFER_FRAMES: u32 = 128;
+
+/// What the caller would like the stream to run at. Every field is optional;
+/// anything left out falls back to the device default.
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+pub struct ConfigRequest {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub sample_format: Option<String>,
+    /// Ask the driver for a small fixed buffer instead of its default. cpal
+    /// opens WASAPI in shared mode only, so on Windows this shortens the
+    /// shared-mode period rather than taking exclusive control of the device.
+    #[serde(default)]
+    pub low_latency: bool,
+    /// Explicit buffer size in frames; takes precedence over `low_latency`.
+    pub buffer_size: Option<u32>,
+}
+
+/// The configuration a stream actually ended up with.
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct NegotiatedConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub sample_format: String,
+    /// Fixed buffer size in frames, or `None` when the driver default is used.
+    pub buffer_size: Option<u32>,
+    /// False when one or more requested values had to be substituted.
+    pub exact_match: bool,
+}
+
+impl NegotiatedConfig {
+    fn new(config: &SupportedStreamConfig, request: &ConfigRequest) -> Self {
+        let exact_match = request.sample_rate.is_none_or(|rate| rate == config.sample_rate().0)
+            && request.channels.is_none_or(|channels| channels == config.channels())
+            && request
+                .sample_format
+                .as_deref()
+                .is_none_or(|format| format == config.sample_format().to_string());
+
+        NegotiatedConfig {
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+            sample_format: config.sample_format().to_string(),
+            buffer_size: buffer_frames(config.buffer_size(), request),
+            exact_match,
+        }
+    }
+
+    /// Builds the cpal stream config, applying the negotiated buffer size.
+    pub fn stream_config(&self) -> cpal::StreamConfig {
+        cpal::StreamConfig {
+            channels: self.channels,
+            sample_rate: SampleRate(self.sample_rate),
+            buffer_size: self.buffer_size.map_or(BufferSize::Default, BufferSize::Fixed),
+        }
+    }
+}
+
+/// Resolves the requested buffer size against what the device supports.
+fn buffer_frames(supported: &SupportedBufferSize, request: &ConfigRequest) -> Option<u32> {
+    let frames = match (request.buffer_size, request.low_latency) {
+        (Some(frames), _) => frames,
+        (None, true) => LOW_LATENCY_BUFFER_FRAMES,
+        (None, false) => return None,
+    };
+
+    match supported {
+        SupportedBufferSize::Range { min, max } => Some(frames.clamp(*min, *max)),
+        SupportedBufferSize::Unknown => Some(frames),
+    }
+}
+
+fn parse_sample_format(format: &str) -> Result<SampleFormat, String> {
+    SUPPORTED_FORMATS
+        .into_iter()
+        .find(|f| f.to_string() == format)
+        .ok_or_else(|| format!("Unsupported sample format: {}", format))
+}
+
+/// Picks the supported input config closest to `request`. Channel count is
+/// matched first (more channels than asked for beats fewer), then sample rate,
+/// then sample format, preferring f32 when nothing was requested.
+pub fn negotiate_input_config(
+    device: &Device,
+    request: &ConfigRequest,
+) -> Result<(SupportedStreamConfig, NegotiatedConfig), String> {
+    let default_config = device.default_input_config()
+        .map_err(|e| format!("Failed to get default input config: {}", e))?;
+    let ranges = || device.supported_input_configs()
+        .map_err(|e| format!("Failed to query supported input configs: {}", e));
+
+    negotiate_config("input", default_config, ranges, request)
+}
+
+/// Same as `negotiate_input_config`, for output devices.
+pub fn negotiate_output_config(
+    device: &Device,
+    request: &ConfigRequest,
+) -> Result<(SupportedStreamConfig, NegotiatedConfig), String> {
+    let default_config = device.default_output_config()
+        .map_err(|e| format!("Failed to get default output config: {}", e))?;
+    let ranges = || device.supported_output_configs()
+        .map_err(|e| format!("Failed to query supported output configs: {}", e));
+
+    negotiate_config("output", default_config, ranges, request)
+}
+
+fn negotiate_config(
+    direction: &str,
+    default_config: SupportedStreamConfig,
+    ranges: impl FnOnce() -> Result<Vec<SupportedStreamConfigRange>, String>,
+    request: &ConfigRequest,
+) -> Result<(SupportedStreamConfig, NegotiatedConfig), String> {
+    request.sample_rate.map(validate::sample_rate).transpose()?;
+    request.channels.map(validate::channel_count).transpose()?;
+    let wanted_format = request.sample_format.as_deref().map(parse_sample_format).transpose()?;
+
+    // With no format preferences the device default is kept as-is
+    if request.sample_rate.is_none() && request.channels.is_none() && wanted_format.is_none() {
+        let negotiated = NegotiatedConfig::new(&default_config, request);
+        return Ok((default_config, negotiated));
+    }
+
+    let wanted_rate = request.sample_rate.unwrap_or(default_config.sample_rate().0);
+    let wanted_channels = request.channels.unwrap_or(default_config.channels());
+
+    let best = ranges()?
+        .into_iter()
+        .filter(|range| SUPPORTED_FORMATS.contains(&range.sample_format()))
+        .map(|range| {
+            let rate = pick_sample_rate(&range, wanted_rate, request.sample_rate.is_some());
+            let score = (
+                channel_penalty(range.channels(), wanted_channels),
+                rate.abs_diff(wanted_rate),
+                format_penalty(range.sample_format(), wanted_format),
+            );
+            (score, range.with_sample_rate(SampleRate(rate)))
+        })
+        .min_by_key(|(score, _)| *score)
+        .map(|(_, config)| config)
+        .ok_or_else(|| format!("Device has no {} configs with a supported sample format", direction))?;
+
+    let negotiated = NegotiatedConfig::new(&best, request);
+    Ok((best, negotiated))
+}
+
+fn pick_sample_rate(range: &SupportedStreamConfigRange, wanted: u32, explicit: bool) -> u32 {
+    let (min, max) = (range.min_sample_rate().0, range.max_sample_rate().0);
+
+    if (min..=max).contains(&wanted) {
+        return wanted;
+    }
+
+    if !explicit {
+        if let Some(rate) = PREFERRED_SAMPLE_RATES.into_iter().find(|rate| (min..=max).contains(rate)) {
+            return rate;
+        }
+    }
+
+    wanted.clamp(min, max)
+}
+
+fn channel_penalty(available: u16, wanted: u16) -> u32 {
+    if available >= wanted {
+        (available - wanted) as u32
+    } else {
+        1000 + (wanted - available) as u32
+    }
+}
+
+fn format_penalty(format: SampleFormat, wanted: Option<SampleFormat>) -> usize {
+    match wanted {
+        Some(wanted) if wanted == format => 0,
+        _ => 1 + SUPPORTED_FORMATS.iter().position(|f| *f == format).unwrap_or(SUPPORTED_FORMATS.len()),
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::audio_file::{
+    read_audio_file, write_audio_file, write_flac, write_mp3, write_opus, FlacOptions, Mp3Options, OpusOptions, WavEncoding,
+};
+use crate::dsp::mix::{Downmix, Upmix};
+use crate::dsp::stereo::{mid_side, MidSide, WidthProcessor};
+use crate::lifecycle::Operation;
+
+/// Encode settings for a conversion. Which fields apply depends on the
+/// extension of the destination file.
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct EncodeOptions {
+    /// Sample encoding for WAV, AIFF and CAF output.
+    pub encoding: Option<WavEncoding>,
+    pub flac: Option<FlacOptions>,
+    pub mp3: Option<Mp3Options>,
+    pub opus: Option<OpusOptions>,
+    /// Fold the source through this matrix before encoding.
+    pub downmix: Option<Downmix>,
+    /// Spread a mono source (after any downmix) to stereo.
+    pub upmix: Option<Upmix>,
+    /// Convert the first two channels between L/R and M/S.
+    pub mid_side: Option<MidSide>,
+    /// Stereo width as a multiple of the original: 0.0 is mono, 1.0
+    /// unchanged, up to 2.0 for widening.
+    pub width: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ConversionResult {
+    pub output_path: String,
+    pub original_bytes: u64,
+    pub output_bytes: u64,
+    /// Output size as a fraction of the original.
+    pub size_ratio: f64,
+}
+
+fn file_size(path: &Path) -> Result<u64, String> {
+    fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read file size of {}: {}", path.display(), e))
+}
+
+/// Converts `src` to the format implied by the extension of `dst`.
+#[tauri::command]
+#[specta::specta]
+pub async fn convert_audio_file(
+    src: String,
+    dst: String,
+    options: Option<EncodeOptions>,
+    app: AppHandle,
+) -> Result<ConversionResult, String> {
+    let operation = Operation::start(&app, "conversion", &dst);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let options = options.unwrap_or_default();
+        let (src, dst) = (Path::new(&src), Path::new(&dst));
+        let mut audio = read_audio_file(src)?;
+        if let Some(downmix) = &options.downmix {
+            audio = audio.downmix(downmix)?;
+        }
+        if let Some(upmix) = &options.upmix {
+            audio = audio.upmix(upmix)?;
+        }
+        if let Some(direction) = options.mid_side {
+            if audio.channels < 2 {
+                return Err("Mid/side conversion needs a stereo source".to_string());
+            }
+            mid_side(&mut audio.samples, audio.channels as usize, direction);
+        }
+        if let Some(width) = options.width {
+            if audio.channels < 2 {
+                return Err("Width adjustment needs a stereo source".to_string());
+            }
+            WidthProcessor::new(audio.sample_rate, width).process(&mut audio.samples, audio.channels as usize);
+        }
+
+        let extension = dst.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_ascii_lowercase();
+        match (extension.as_str(), &options) {
+            ("flac", EncodeOptions { flac: Some(flac), .. }) => write_flac(dst, &audio, flac)?,
+            ("mp3", EncodeOptions { mp3: Some(mp3), .. }) => write_mp3(dst, &audio, mp3)?,
+            ("opus", EncodeOptions { opus: Some(opus), .. }) => write_opus(dst, &audio, opus)?,
+            _ => write_audio_file(dst, &audio, options.encoding.unwrap_or_default())?,
+        }
+
+        let original_bytes = file_size(src)?;
+        let output_bytes = file_size(dst)?;
+        Ok(ConversionResult {
+            output_path: dst.display().to_string(),
+            original_bytes,
+            output_bytes,
+            size_ratio: output_bytes as f64 / original_bytes.max(1) as f64,
+        })
+    })
+    .await
+    .map_err(|e| format!("Conversion task failed: {}", e))
+    .and_then(|result| result);
+    operation.finish(result)
+}
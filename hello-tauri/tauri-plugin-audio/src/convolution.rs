@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+
+use crate::audio_file::{read_audio_file, write_audio_file, AudioBuffer, WavEncoding};
+use crate::dsp::convolution::Convolver;
+use crate::dsp::gain::db_to_linear;
+use crate::dsp::resample::resample;
+
+/// Block size for live convolution: the added delay on the wet signal.
+const LIVE_BLOCK_FRAMES: usize = 512;
+
+/// Offline processing isn't latency bound, so bigger blocks save work.
+const OFFLINE_BLOCK_FRAMES: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct ConvolutionSettings {
+    /// Impulse response file, in any format the app reads.
+    pub ir_path: String,
+    /// Wet share of the output, 0 (dry) to 1 (wet only).
+    #[serde(default = "default_mix")]
+    pub mix: f32,
+    /// Applied to the IR after it is normalized to unit energy.
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+fn default_mix() -> f32 {
+    0.3
+}
+
+/// Reads the settings' IR as planar channels at `sample_rate`, scaled so
+/// its loudest channel has unit energy and a broadband signal comes out at
+/// about the level it went in.
+fn load_impulse_response(settings: &ConvolutionSettings, sample_rate: u32) -> Result<Vec<Vec<f32>>, String> {
+    let ir = read_audio_file(Path::new(&settings.ir_path))?;
+    let channels = ir.channels.max(1) as usize;
+    let samples = if ir.sample_rate == sample_rate {
+        ir.samples
+    } else {
+        resample(&ir.samples, channels, ir.sample_rate, sample_rate)?
+    };
+
+    let mut planar: Vec<Vec<f32>> = (0..channels)
+        .map(|channel| samples.iter().skip(channel).step_by(channels).copied().collect())
+        .collect();
+    let energy = planar
+        .iter()
+        .map(|channel| channel.iter().map(|s| s * s).sum::<f32>())
+        .fold(0.0f32, f32::max);
+    if energy <= 0.0 {
+        return Err(format!("{} is silent", settings.ir_path));
+    }
+
+    let scale = db_to_linear(settings.gain_db) / energy.sqrt();
+    planar.iter_mut().flatten().for_each(|s| *s *= scale);
+    Ok(planar)
+}
+
+/// A convolver for a live output at `sample_rate` with `channels`.
+pub fn live_convolver(settings: &ConvolutionSettings, sample_rate: u32, channels: usize) -> Result<Convolver, String> {
+    let ir = load_impulse_response(settings, sample_rate)?;
+    Convolver::new(&ir, channels, LIVE_BLOCK_FRAMES, settings.mix)
+}
+
+/// Convolves `src` with the settings' IR into `dst`. The output runs on
+/// past the end of the source for the length of the reverb tail, and the
+/// wet signal is lined up with the dry one.
+#[tauri::command]
+#[specta::specta]
+pub async fn convolve_file(
+    src: String,
+    dst: String,
+    settings: ConvolutionSettings,
+    encoding: Option<WavEncoding>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&src))?;
+        let channels = audio.channels.max(1) as usize;
+        let ir = load_impulse_response(&settings, audio.sample_rate)?;
+        let tail = ir.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut convolver = Convolver::new(&ir, channels, OFFLINE_BLOCK_FRAMES, 1.0)?;
+        let latency = convolver.latency_frames();
+        let mut wet = audio.samples.clone();
+        wet.resize(audio.samples.len() + (tail + latency) * channels, 0.0);
+        convolver.process(&mut wet);
+        wet.drain(..latency * channels);
+
+        let mix = settings.mix.clamp(0.0, 1.0);
+        for (wet, dry) in wet.iter_mut().zip(audio.samples.iter().chain(std::iter::repeat(&0.0))) {
+            *wet = dry * (1.0 - mix) + *wet * mix;
+        }
+
+        let output = AudioBuffer {
+            samples: wet,
+            sample_rate: audio.sample_rate,
+            channels: audio.channels,
+        };
+        write_audio_file(Path::new(&dst), &output, encoding.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Convolution task failed: {}", e))?
+}
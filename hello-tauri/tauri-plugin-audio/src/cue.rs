@@ -0,0 +1,353 @@
+use serde::Serialize;
+use specta::Type;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::markers::{load_markers, save_markers, Marker, MarkerSet, Region};
+use crate::validate;
+
+/// CUE positions are counted in CD frames, 75 per second.
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub start_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct CueSheet {
+    /// Audio file named by the sheet, resolved relative to the sheet.
+    pub file: Option<String>,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+fn parse_cue_time(time: &str) -> Result<f64, String> {
+    let parts: Vec<u32> = time
+        .split(':')
+        .map(|p| p.parse().map_err(|_| format!("Invalid CUE time: {}", time)))
+        .collect::<Result<_, _>>()?;
+
+    match parts[..] {
+        [minutes, seconds, frames] => {
+            Ok((minutes as f64 * 60.0 + seconds as f64 + frames as f64 / CUE_FRAMES_PER_SECOND) * 1000.0)
+        }
+        _ => Err(format!("Invalid CUE time: {}", time)),
+    }
+}
+
+fn format_cue_time(ms: f64) -> String {
+    let total_frames = (ms / 1000.0 * CUE_FRAMES_PER_SECOND).round() as u64;
+    let frames = total_frames % 75;
+    let seconds = (total_frames / 75) % 60;
+    let minutes = total_frames / 75 / 60;
+    format!("{:02}:{:02}:{:02}", minutes, seconds, frames)
+}
+
+/// Returns the rest of a CUE line, unquoting it if it is quoted.
+fn cue_value(rest: &str) -> String {
+    let rest = rest.trim();
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next().unwrap_or_default().to_string(),
+        None => rest.split_whitespace().next().unwrap_or_default().to_string(),
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "'"))
+}
+
+fn parse_cue_sheet(contents: &str, base_dir: &Path) -> Result<CueSheet, String> {
+    let mut sheet = CueSheet {
+        file: None,
+        title: None,
+        performer: None,
+        tracks: Vec::new(),
+    };
+    let mut current: Option<CueTrack> = None;
+
+    for line in contents.lines() {
+        let line = line.trim();
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        match keyword.to_ascii_uppercase().as_str() {
+            "FILE" if sheet.file.is_none() => {
+                sheet.file = Some(base_dir.join(cue_value(rest)).display().to_string());
+            }
+            "TRACK" => {
+                if let Some(track) = current.take() {
+                    sheet.tracks.push(track);
+                }
+                let number = cue_value(rest).parse()
+                    .map_err(|_| format!("Invalid track number: {}", rest))?;
+                current = Some(CueTrack {
+                    number,
+                    title: None,
+                    performer: None,
+                    start_ms: 0.0,
+                });
+            }
+            "TITLE" => match current.as_mut() {
+                Some(track) => track.title = Some(cue_value(rest)),
+                None => sheet.title = Some(cue_value(rest)),
+            },
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = Some(cue_value(rest)),
+                None => sheet.performer = Some(cue_value(rest)),
+            },
+            "INDEX" => {
+                // INDEX 01 is where the track starts; 00 marks the pregap
+                let mut fields = rest.split_whitespace();
+                if let (Some(track), Some("01"), Some(time)) = (current.as_mut(), fields.next(), fields.next()) {
+                    track.start_ms = parse_cue_time(time)?;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(track) = current.take() {
+        sheet.tracks.push(track);
+    }
+
+    Ok(sheet)
+}
+
+fn track_name(track: &CueTrack) -> String {
+    track.title.clone().unwrap_or_else(|| format!("Track {:02}", track.number))
+}
+
+fn duration_ms(file_path: &Path) -> Result<f64, String> {
+    validate::input_file(file_path)?;
+    let reader = hound::WavReader::open(file_path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    Ok(reader.duration() as f64 * 1000.0 / reader.spec().sample_rate as f64)
+}
+
+/// Turns CUE tracks into a marker at each track start and a region that
+/// runs to the next track, or to the end of the file for the last one.
+fn tracks_to_markers(tracks: &[CueTrack], end_ms: f64) -> MarkerSet {
+    let markers = tracks
+        .iter()
+        .map(|track| Marker {
+            name: track_name(track),
+            position_ms: track.start_ms,
+        })
+        .collect();
+
+    let regions = tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| Region {
+            name: track_name(track),
+            start_ms: track.start_ms,
+            end_ms: tracks.get(i + 1).map_or(end_ms, |next| next.start_ms),
+        })
+        .filter(|region| region.end_ms > region.start_ms)
+        .collect();
+
+    MarkerSet { markers, regions }
+}
+
+/// Parses a CUE sheet. With `import` set, its tracks replace the markers
+/// and regions of the audio file it references.
+#[tauri::command]
+#[specta::specta]
+pub fn read_cue_sheet(cue_path: String, import: Option<bool>) -> Result<CueSheet, String> {
+    let cue_path = Path::new(&cue_path);
+    validate::input_file(cue_path)?;
+    let contents = fs::read_to_string(cue_path)
+        .map_err(|e| format!("Failed to read CUE sheet: {}", e))?;
+    let sheet = parse_cue_sheet(&contents, cue_path.parent().unwrap_or(Path::new("")))?;
+
+    if import.unwrap_or(false) {
+        let file = sheet.file.as_deref()
+            .ok_or_else(|| "CUE sheet does not reference an audio file".to_string())?;
+        let markers = tracks_to_markers(&sheet.tracks, duration_ms(Path::new(file))?);
+        save_markers(Path::new(file), &markers)?;
+    }
+
+    Ok(sheet)
+}
+
+/// Writes a CUE sheet for `file_path` with one track per region, or per
+/// marker when the file has no regions. Defaults to `<file>.cue` alongside
+/// the audio and returns the path written.
+#[tauri::command]
+#[specta::specta]
+pub fn write_cue_sheet(file_path: String, cue_path: Option<String>, title: Option<String>) -> Result<String, String> {
+    let audio_path = Path::new(&file_path);
+    let markers = load_markers(audio_path)?;
+
+    let mut starts: Vec<(String, f64)> = if markers.regions.is_empty() {
+        markers.markers.iter().map(|m| (m.name.clone(), m.position_ms)).collect()
+    } else {
+        markers.regions.iter().map(|r| (r.name.clone(), r.start_ms)).collect()
+    };
+    if starts.is_empty() {
+        return Err("File has no markers or regions to write".to_string());
+    }
+    starts.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let cue_path = cue_path.map(PathBuf::from).unwrap_or_else(|| audio_path.with_extension("cue"));
+    let file_name = audio_path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+
+    let mut contents = String::new();
+    if let Some(title) = &title {
+        let _ = writeln!(contents, "TITLE {}", quote(title));
+    }
+    let _ = writeln!(contents, "FILE {} WAVE", quote(file_name));
+    for (i, (name, start_ms)) in starts.iter().enumerate() {
+        let _ = writeln!(contents, "  TRACK {:02} AUDIO", i + 1);
+        let _ = writeln!(contents, "    TITLE {}", quote(name));
+        let _ = writeln!(contents, "    INDEX 01 {}", format_cue_time(*start_ms));
+    }
+
+    fs::write(&cue_path, contents)
+        .map_err(|e| format!("Failed to write CUE sheet: {}", e))?;
+    Ok(cue_path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    /// A scratch directory for one test, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("cue-{}-{}", name, process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Ten seconds of silent mono WAV.
+    fn write_wav(path: &Path) {
+        let spec = hound::WavSpec { channels: 1, sample_rate: 8000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for _ in 0..80000 {
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn region(name: &str, start_ms: f64, end_ms: f64) -> Region {
+        Region { name: name.to_string(), start_ms, end_ms }
+    }
+
+    #[test]
+    fn cue_times_are_cd_frames() {
+        assert_eq!(format_cue_time(0.0), "00:00:00");
+        assert_eq!(format_cue_time(61_000.0 + 1000.0 / 75.0 * 74.0), "01:01:74");
+        assert_eq!(format_cue_time(3_600_000.0), "60:00:00");
+        assert_eq!(parse_cue_time("01:01:74").unwrap(), (61.0 + 74.0 / 75.0) * 1000.0);
+        assert!(parse_cue_time("01:02").is_err());
+        assert!(parse_cue_time("aa:00:00").is_err());
+    }
+
+    #[test]
+    fn parses_sheet_fields() {
+        let contents = "REM GENRE Test\n\
+            PERFORMER \"Someone\"\n\
+            TITLE \"An Album\"\n\
+            FILE \"side a.wav\" WAVE\n\
+            \x20 TRACK 01 AUDIO\n\
+            \x20   TITLE \"Intro\"\n\
+            \x20   INDEX 01 00:00:00\n\
+            \x20 track 02 audio\n\
+            \x20   performer Guest\n\
+            \x20   INDEX 00 00:04:00\n\
+            \x20   INDEX 01 00:05:30\n";
+        let sheet = parse_cue_sheet(contents, Path::new("music")).unwrap();
+
+        assert_eq!(sheet.file.as_deref(), Some(Path::new("music").join("side a.wav").to_str().unwrap()));
+        assert_eq!(sheet.title.as_deref(), Some("An Album"));
+        assert_eq!(sheet.performer.as_deref(), Some("Someone"));
+        assert_eq!(sheet.tracks.len(), 2);
+        assert_eq!(sheet.tracks[0].title.as_deref(), Some("Intro"));
+        assert_eq!(sheet.tracks[1].number, 2);
+        assert_eq!(sheet.tracks[1].title, None);
+        assert_eq!(sheet.tracks[1].performer.as_deref(), Some("Guest"));
+        // The pregap is not the start
+        assert_eq!(sheet.tracks[1].start_ms, 5400.0);
+    }
+
+    #[test]
+    fn written_sheet_reads_back_as_markers() {
+        let dir = TempDir::new("round-trip");
+        let audio = dir.0.join("take.wav");
+        write_wav(&audio);
+        let regions = vec![
+            region("Verse \"one\"", 1234.0, 4000.0),
+            region("Intro", 0.0, 1234.0),
+            region("Outro", 4000.0, 10000.0),
+        ];
+        save_markers(&audio, &MarkerSet { markers: Vec::new(), regions }).unwrap();
+
+        let cue_path = write_cue_sheet(audio.display().to_string(), None, Some("Take".to_string())).unwrap();
+        assert_eq!(Path::new(&cue_path), audio.with_extension("cue"));
+
+        // Importing replaces the markers with ones rebuilt from the sheet
+        save_markers(&audio, &MarkerSet::default()).unwrap();
+        let sheet = read_cue_sheet(cue_path, Some(true)).unwrap();
+        assert_eq!(sheet.title.as_deref(), Some("Take"));
+        assert_eq!(sheet.file.as_deref(), Some(audio.to_str().unwrap()));
+
+        let names: Vec<_> = sheet.tracks.iter().map(|t| t.title.clone().unwrap()).collect();
+        assert_eq!(names, ["Intro", "Verse 'one'", "Outro"]);
+        let numbers: Vec<_> = sheet.tracks.iter().map(|t| t.number).collect();
+        assert_eq!(numbers, [1, 2, 3]);
+
+        let markers = load_markers(&audio).unwrap();
+        let spans: Vec<_> = markers.regions.iter().map(|r| (r.start_ms, r.end_ms)).collect();
+        // Starts are rounded to the nearest CD frame; the last region runs
+        // to the end of the file
+        let frame_ms = 1000.0 / CUE_FRAMES_PER_SECOND;
+        let expected = [(0.0, 1240.0), (1240.0, 4000.0), (4000.0, 10000.0)];
+        assert_eq!(spans.len(), expected.len());
+        for ((start, end), (expected_start, expected_end)) in spans.iter().zip(expected) {
+            assert!((start - expected_start).abs() < frame_ms / 2.0, "{} != {}", start, expected_start);
+            assert!((end - expected_end).abs() < frame_ms / 2.0, "{} != {}", end, expected_end);
+        }
+        assert_eq!(markers.markers.len(), 3);
+    }
+
+    #[test]
+    fn writes_markers_when_there_are_no_regions() {
+        let dir = TempDir::new("markers");
+        let audio = dir.0.join("talk.wav");
+        let markers = vec![
+            Marker { name: "Second".to_string(), position_ms: 2000.0 },
+            Marker { name: "First".to_string(), position_ms: 0.0 },
+        ];
+        save_markers(&audio, &MarkerSet { markers, regions: Vec::new() }).unwrap();
+
+        let cue_path = dir.0.join("sheet.cue");
+        write_cue_sheet(audio.display().to_string(), Some(cue_path.display().to_string()), None).unwrap();
+        let sheet = read_cue_sheet(cue_path.display().to_string(), None).unwrap();
+        assert_eq!(sheet.title, None);
+        let tracks: Vec<_> = sheet.tracks.iter().map(|t| (t.title.clone().unwrap(), t.start_ms)).collect();
+        assert_eq!(tracks, [("First".to_string(), 0.0), ("Second".to_string(), 2000.0)]);
+    }
+
+    #[test]
+    fn refuses_to_write_an_empty_sheet() {
+        let dir = TempDir::new("empty");
+        let audio = dir.0.join("blank.wav");
+        assert!(write_cue_sheet(audio.display().to_string(), None, None).is_err());
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::fft::{Spectrum, HANN_ENBW};
+
+/// Nominal ISO 266 third-octave centre frequencies, 20 Hz - 20 kHz (31 bands).
+const THIRD_OCTAVE_CENTERS: [f32; 31] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0,
+    500.0, 630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0,
+    8000.0, 10000.0, 12500.0, 16000.0, 20000.0,
+];
+
+/// Nominal octave centre frequencies, 31.5 Hz - 16 kHz.
+const OCTAVE_CENTERS: [f32; 10] = [31.5, 63.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0];
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum BandResolution {
+    Octave,
+    #[default]
+    ThirdOctave,
+}
+
+impl BandResolution {
+    pub fn centers(self) -> &'static [f32] {
+        match self {
+            BandResolution::Octave => &OCTAVE_CENTERS,
+            BandResolution::ThirdOctave => &THIRD_OCTAVE_CENTERS,
+        }
+    }
+
+    /// Ratio between a band's upper edge and its centre.
+    fn half_width(self) -> f32 {
+        match self {
+            BandResolution::Octave => 2f32.powf(1.0 / 2.0),
+            BandResolution::ThirdOctave => 2f32.powf(1.0 / 6.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct BandLevel {
+    pub center_hz: f32,
+    pub level_db: f32,
+}
+
+/// Integrates spectrum power into fractional-octave bands. Bands narrower
+/// than one FFT bin fall back to the bin nearest their centre; bands above
+/// Nyquist read as silence.
+pub fn band_levels(spectrum: &Spectrum, resolution: BandResolution) -> Vec<BandLevel> {
+    let half_width = resolution.half_width();
+    let last_bin = spectrum.amplitudes.len().saturating_sub(1);
+
+    resolution
+        .centers()
+        .iter()
+        .map(|&center_hz| {
+            let low_bin = ((center_hz / half_width) / spectrum.bin_hz).ceil() as usize;
+            let high_bin = (((center_hz * half_width) / spectrum.bin_hz).floor() as usize).min(last_bin);
+
+            let power: f32 = if low_bin > last_bin {
+                0.0
+            } else if low_bin <= high_bin {
+                spectrum.amplitudes[low_bin..=high_bin].iter().map(|a| a * a).sum::<f32>() / HANN_ENBW
+            } else {
+                let nearest = ((center_hz / spectrum.bin_hz).round() as usize).min(last_bin);
+                spectrum.amplitudes.get(nearest).map_or(0.0, |a| a * a)
+            };
+
+            BandLevel {
+                center_hz,
+                level_db: 10.0 * power.max(1e-20).log10(),
+            }
+        })
+        .collect()
+}
+
+/// Power average of `spectrum` over a band `fraction` of an octave wide
+/// around each of `frequencies`, in dB. Bands narrower than one bin use
+/// the nearest bin.
+pub fn smoothed_levels(spectrum: &Spectrum, fraction: f32, frequencies: &[f32]) -> Vec<f32> {
+    let half_width = 2f32.powf(0.5 / fraction);
+    let last_bin = spectrum.amplitudes.len().saturating_sub(1);
+
+    frequencies
+        .iter()
+        .map(|&frequency| {
+            let low_bin = ((frequency / half_width) / spectrum.bin_hz).ceil() as usize;
+            let high_bin = (((frequency * half_width) / spectrum.bin_hz).floor() as usize).min(last_bin);
+
+            let power = if low_bin <= high_bin {
+                let bins = &spectrum.amplitudes[low_bin..=high_bin];
+                bins.iter().map(|a| a * a).sum::<f32>() / bins.len() as f32
+            } else {
+                let nearest = ((frequency / spectrum.bin_hz).round() as usize).min(last_bin);
+                spectrum.amplitudes.get(nearest).map_or(0.0, |a| a * a)
+            };
+            10.0 * power.max(1e-20).log10()
+        })
+        .collect()
+}
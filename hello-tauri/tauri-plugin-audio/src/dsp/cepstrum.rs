@@ -0,0 +1,88 @@
+use std::f32::consts::PI;
+
+use crate::dsp::fft::SpectrumAnalyzer;
+
+/// Mel filters span this range, where voices carry their character.
+const LOWEST_HZ: f32 = 80.0;
+const HIGHEST_HZ: f32 = 7600.0;
+
+fn hz_to_mel(hz: f32) -> f32 {
+    2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+    700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Mel-frequency cepstral coefficients per frame: the shape of the
+/// spectral envelope, independent of level when the 0th coefficient is
+/// left out.
+pub struct MfccAnalyzer {
+    analyzer: SpectrumAnalyzer,
+    /// Bin weights of each triangular mel filter.
+    filters: Vec<Vec<(usize, f32)>>,
+    coefficients: usize,
+}
+
+impl MfccAnalyzer {
+    /// Analyzes frames of `length` samples through `bands` mel filters,
+    /// keeping coefficients 1 to `coefficients`.
+    pub fn new(length: usize, sample_rate: u32, bands: usize, coefficients: usize) -> Self {
+        let analyzer = SpectrumAnalyzer::new(length);
+        let bin_hz = sample_rate as f32 / length as f32;
+        let highest = HIGHEST_HZ.min(sample_rate as f32 / 2.0);
+
+        let (low, high) = (hz_to_mel(LOWEST_HZ), hz_to_mel(highest));
+        let edges: Vec<f32> = (0..bands + 2)
+            .map(|i| mel_to_hz(low + (high - low) * i as f32 / (bands + 1) as f32) / bin_hz)
+            .collect();
+        let filters = edges
+            .windows(3)
+            .map(|edge| {
+                let (start, centre, end) = (edge[0], edge[1], edge[2]);
+                (start.ceil() as usize..=end.floor() as usize)
+                    .filter(|&bin| bin < analyzer.bins())
+                    .map(|bin| {
+                        let position = bin as f32;
+                        let weight = if position <= centre {
+                            (position - start) / (centre - start).max(f32::EPSILON)
+                        } else {
+                            (end - position) / (end - centre).max(f32::EPSILON)
+                        };
+                        (bin, weight.max(0.0))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        MfccAnalyzer { analyzer, filters, coefficients }
+    }
+
+    /// Coefficients of `samples`, which must be exactly the frame length,
+    /// along with the frame's energy.
+    pub fn frame(&mut self, samples: &[f32]) -> (Vec<f32>, f32) {
+        let amplitudes = self.analyzer.amplitudes(samples);
+        let energy: f32 = amplitudes.iter().map(|a| a * a).sum();
+        let bands: Vec<f32> = self
+            .filters
+            .iter()
+            .map(|filter| {
+                let power: f32 = filter.iter().map(|&(bin, weight)| amplitudes[bin] * amplitudes[bin] * weight).sum();
+                (power + 1e-10).ln()
+            })
+            .collect();
+
+        // DCT-II of the log mel energies
+        let count = bands.len() as f32;
+        let coefficients = (1..=self.coefficients)
+            .map(|k| {
+                bands
+                    .iter()
+                    .enumerate()
+                    .map(|(m, &band)| band * (PI * k as f32 * (m as f32 + 0.5) / count).cos())
+                    .sum()
+            })
+            .collect();
+        (coefficients, energy)
+    }
+}
@@ -0,0 +1,31 @@
+/// Picks the given zero-based channel positions out of interleaved samples,
+/// returning a new interleaved buffer with `picks.len()` channels.
+pub fn select_channels(samples: &[f32], channels: usize, picks: &[usize]) -> Vec<f32> {
+    let frames = samples.len() / channels.max(1);
+    let mut selected = Vec::with_capacity(frames * picks.len());
+
+    for frame in samples.chunks_exact(channels.max(1)) {
+        selected.extend(picks.iter().map(|&channel| frame[channel]));
+    }
+
+    selected
+}
+
+/// Converts 1-based channel numbers (as printed on an interface) into
+/// zero-based positions, checking them against the available channel count.
+pub fn parse_channel_numbers(numbers: &[u16], available: u16) -> Result<Vec<usize>, String> {
+    if numbers.is_empty() {
+        return Err("At least one channel must be selected".to_string());
+    }
+
+    numbers
+        .iter()
+        .map(|&number| {
+            if number == 0 || number > available {
+                Err(format!("Channel {} is out of range (1-{})", number, available))
+            } else {
+                Ok(number as usize - 1)
+            }
+        })
+        .collect()
+}
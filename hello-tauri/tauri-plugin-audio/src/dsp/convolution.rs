@@ -0,0 +1,149 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+/// Running state for one channel of the signal.
+struct ChannelState {
+    /// Impulse response channel it is convolved with.
+    ir: usize,
+    /// The previous block followed by the one being filled.
+    input: Vec<f32>,
+    /// Wet output of the last finished block, played while the next fills.
+    output: Vec<f32>,
+    /// Spectra of the most recent input blocks, one per IR partition.
+    history: Vec<Vec<Complex<f32>>>,
+    newest: usize,
+}
+
+/// Uniformly partitioned overlap-save convolution. Each IR channel is cut
+/// into blocks that are convolved with the matching input blocks in the
+/// frequency domain, so long reverbs stay cheap. The wet signal comes out
+/// one block late.
+pub struct Convolver {
+    block: usize,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+    /// Partition spectra of each IR channel.
+    partitions: Vec<Vec<Vec<Complex<f32>>>>,
+    channels: Vec<ChannelState>,
+    /// Position inside the current block, shared by all channels.
+    fill: usize,
+    /// Wet share of the output, 0 (dry) to 1 (wet only).
+    mix: f32,
+    buffer: Vec<Complex<f32>>,
+    sum: Vec<Complex<f32>>,
+}
+
+impl Convolver {
+    /// `ir` is planar. A mono IR is used on every channel; otherwise
+    /// channel `n` uses IR channel `n`, the last one standing in for
+    /// channels the IR doesn't have.
+    pub fn new(ir: &[Vec<f32>], channels: usize, block: usize, mix: f32) -> Result<Self, String> {
+        let length = ir.iter().map(Vec::len).max().unwrap_or(0);
+        if length == 0 {
+            return Err("Impulse response is empty".to_string());
+        }
+        let block = block.max(1);
+        let size = block * 2;
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(size);
+        let ifft = planner.plan_fft_inverse(size);
+
+        let count = length.div_ceil(block);
+        let partitions = ir
+            .iter()
+            .map(|channel| {
+                (0..count)
+                    .map(|index| {
+                        let mut spectrum = vec![Complex::new(0.0, 0.0); size];
+                        let start = (index * block).min(channel.len());
+                        let end = (start + block).min(channel.len());
+                        for (bin, &sample) in spectrum.iter_mut().zip(&channel[start..end]) {
+                            bin.re = sample;
+                        }
+                        fft.process(&mut spectrum);
+                        spectrum
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let channels = (0..channels.max(1))
+            .map(|channel| ChannelState {
+                ir: channel.min(ir.len() - 1),
+                input: vec![0.0; size],
+                output: vec![0.0; block],
+                history: vec![vec![Complex::new(0.0, 0.0); size]; count],
+                newest: 0,
+            })
+            .collect();
+
+        Ok(Convolver {
+            block,
+            fft,
+            ifft,
+            partitions,
+            channels,
+            fill: 0,
+            mix: mix.clamp(0.0, 1.0),
+            buffer: vec![Complex::new(0.0, 0.0); size],
+            sum: vec![Complex::new(0.0, 0.0); size],
+        })
+    }
+
+    /// How far the wet signal lags the input.
+    pub fn latency_frames(&self) -> usize {
+        self.block
+    }
+
+    /// Mixes the wet signal into interleaved `samples` in place. The dry
+    /// part is not delayed to match, so live monitoring stays immediate.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let count = self.channels.len();
+        for frame in samples.chunks_exact_mut(count) {
+            for (sample, channel) in frame.iter_mut().zip(&mut self.channels) {
+                let wet = channel.output[self.fill];
+                channel.input[self.block + self.fill] = *sample;
+                *sample = *sample * (1.0 - self.mix) + wet * self.mix;
+            }
+
+            self.fill += 1;
+            if self.fill == self.block {
+                self.fill = 0;
+                for index in 0..count {
+                    self.run_block(index);
+                }
+            }
+        }
+    }
+
+    fn run_block(&mut self, index: usize) {
+        let size = self.block * 2;
+        let channel = &mut self.channels[index];
+        let partitions = &self.partitions[channel.ir];
+
+        for (bin, &sample) in self.buffer.iter_mut().zip(&channel.input) {
+            *bin = Complex::new(sample, 0.0);
+        }
+        self.fft.process(&mut self.buffer);
+        channel.history[channel.newest].copy_from_slice(&self.buffer);
+
+        self.sum.fill(Complex::new(0.0, 0.0));
+        let count = partitions.len();
+        for (age, partition) in partitions.iter().enumerate() {
+            let spectrum = &channel.history[(channel.newest + count - age) % count];
+            for ((sum, &x), &h) in self.sum.iter_mut().zip(spectrum).zip(partition) {
+                *sum += x * h;
+            }
+        }
+        self.ifft.process(&mut self.sum);
+
+        // Overlap-save: only the second half is free of circular wrap
+        let scale = 1.0 / size as f32;
+        for (out, bin) in channel.output.iter_mut().zip(&self.sum[self.block..]) {
+            *out = bin.re * scale;
+        }
+        channel.input.copy_within(self.block.., 0);
+        channel.newest = (channel.newest + 1) % count;
+    }
+}
@@ -0,0 +1,46 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+
+/// Normalized cross-correlation of `a` and `b` for lags `-max_lag..=max_lag`.
+/// Index `max_lag` is zero lag; a peak at a positive lag means `b` arrives
+/// later than `a`. Values lie in -1..=1, negative meaning inverted polarity.
+pub fn cross_correlation(a: &[f32], b: &[f32], max_lag: usize) -> Vec<f32> {
+    let length = a.len().min(b.len());
+    let fft_length = (length * 2).next_power_of_two();
+
+    let to_complex = |samples: &[f32]| {
+        let mut buffer: Vec<Complex<f32>> = samples[..length].iter().map(|&s| Complex::new(s, 0.0)).collect();
+        buffer.resize(fft_length, Complex::new(0.0, 0.0));
+        buffer
+    };
+
+    let mut planner = FftPlanner::new();
+    let forward = planner.plan_fft_forward(fft_length);
+    let inverse = planner.plan_fft_inverse(fft_length);
+
+    let mut spectrum_a = to_complex(a);
+    let mut spectrum_b = to_complex(b);
+    forward.process(&mut spectrum_a);
+    forward.process(&mut spectrum_b);
+
+    // conj(A) * B correlates so that a delayed `b` peaks at a positive lag
+    let mut product: Vec<Complex<f32>> = spectrum_a
+        .iter()
+        .zip(&spectrum_b)
+        .map(|(x, y)| x.conj() * y)
+        .collect();
+    inverse.process(&mut product);
+
+    let energy_a: f32 = a[..length].iter().map(|s| s * s).sum();
+    let energy_b: f32 = b[..length].iter().map(|s| s * s).sum();
+    let norm = (energy_a * energy_b).sqrt().max(f32::EPSILON) * fft_length as f32;
+
+    let max_lag = max_lag.min(length.saturating_sub(1));
+    (0..=2 * max_lag)
+        .map(|i| {
+            let lag = i as isize - max_lag as isize;
+            let index = lag.rem_euclid(fft_length as isize) as usize;
+            product[index].re / norm
+        })
+        .collect()
+}
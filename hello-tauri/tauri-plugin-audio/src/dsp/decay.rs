@@ -0,0 +1,99 @@
+use serde::Serialize;
+use specta::Type;
+
+/// Moving average used to find where the decay meets the noise floor.
+const SMOOTHING_SECONDS: f64 = 0.01;
+
+/// Share of the recording at its end taken as background noise.
+const NOISE_SHARE: usize = 10;
+
+/// Decay times from the Schroeder energy decay curve, each extrapolated to
+/// a 60 dB drop. A time is missing when the decay doesn't clear the noise
+/// floor by enough to measure it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Type)]
+pub struct DecayTimes {
+    /// Early decay time, from the first 10 dB.
+    pub edt_s: Option<f32>,
+    /// From the -5 to -25 dB span.
+    pub t20_s: Option<f32>,
+    /// From the -5 to -35 dB span.
+    pub t30_s: Option<f32>,
+}
+
+impl DecayTimes {
+    /// T30 where it could be measured, otherwise T20.
+    pub fn rt60_s(&self) -> Option<f32> {
+        self.t30_s.or(self.t20_s)
+    }
+}
+
+/// Energy decay curve of mono `samples` from their peak, in dB relative to
+/// the total energy. The noise floor is subtracted and integration starts
+/// where the decay sinks into it, so the curve isn't flattened by noise.
+pub fn decay_curve(samples: &[f32], sample_rate: u32) -> Vec<f64> {
+    let start = samples
+        .iter()
+        .enumerate()
+        .fold((0, 0.0f32), |(best, peak), (i, s)| if s.abs() > peak { (i, s.abs()) } else { (best, peak) })
+        .0;
+    let energy: Vec<f64> = samples[start..].iter().map(|&s| s as f64 * s as f64).collect();
+    if energy.is_empty() {
+        return Vec::new();
+    }
+
+    let tail = &energy[energy.len() - (energy.len() / NOISE_SHARE).max(1)..];
+    let noise = tail.iter().sum::<f64>() / tail.len() as f64;
+
+    // The first point where the smoothed energy is within 3 dB of the noise
+    let window = ((SMOOTHING_SECONDS * sample_rate as f64) as usize).max(1);
+    let mut sum: f64 = energy.iter().take(window).sum();
+    let mut end = energy.len();
+    for i in window..energy.len() {
+        if sum / window as f64 <= noise * 2.0 {
+            end = i;
+            break;
+        }
+        sum += energy[i] - energy[i - window];
+    }
+
+    let mut curve = vec![0.0; end];
+    let mut remaining = 0.0;
+    for i in (0..end).rev() {
+        remaining += (energy[i] - noise).max(0.0);
+        curve[i] = remaining;
+    }
+    let total = curve.first().copied().unwrap_or(0.0).max(f64::MIN_POSITIVE);
+    curve.iter().map(|&e| 10.0 * (e / total).max(1e-30).log10()).collect()
+}
+
+/// Time for a 60 dB drop, from a least-squares line through the part of
+/// `curve` between `from_db` and `to_db`.
+fn fit_decay(curve: &[f64], sample_rate: u32, from_db: f64, to_db: f64) -> Option<f32> {
+    let first = curve.iter().position(|&db| db <= from_db)?;
+    let last = first + curve[first..].iter().position(|&db| db <= to_db)?;
+    let points = &curve[first..=last];
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_t = (first + last) as f64 / 2.0 / sample_rate as f64;
+    let mean_db = points.iter().sum::<f64>() / n;
+    let (mut covariance, mut variance) = (0.0, 0.0);
+    for (i, &db) in points.iter().enumerate() {
+        let t = (first + i) as f64 / sample_rate as f64 - mean_t;
+        covariance += t * (db - mean_db);
+        variance += t * t;
+    }
+    let slope = covariance / variance;
+    (slope < 0.0).then(|| (-60.0 / slope) as f32)
+}
+
+pub fn decay_times(samples: &[f32], sample_rate: u32) -> DecayTimes {
+    let curve = decay_curve(samples, sample_rate);
+    DecayTimes {
+        edt_s: fit_decay(&curve, sample_rate, 0.0, -10.0),
+        t20_s: fit_decay(&curve, sample_rate, -5.0, -25.0),
+        t30_s: fit_decay(&curve, sample_rate, -5.0, -35.0),
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+/// Largest rate correction applied. Real clocks differ by tens of ppm;
+/// anything beyond this is a glitch rather than drift.
+pub const MAX_ADJUSTMENT: f64 = 0.001;
+
+/// Time constant of the smoothing applied to the backlog, which hides the
+/// jitter from the two sides working in different block sizes.
+const SMOOTHING_S: f64 = 1.0;
+
+/// Seconds after a start or reset before the smoothed backlog is taken as
+/// the level to hold.
+const SETTLE_S: f64 = 2.0;
+
+/// Seconds over which a backlog that has moved from that level is worked
+/// off. Shorter reacts faster but bends the pitch more.
+const CORRECTION_TIME_S: f64 = 10.0;
+
+/// Follows the backlog between a producer and a consumer on independent
+/// clocks and works out how much faster the consumer has to run to keep it
+/// level.
+#[derive(Debug, Clone)]
+pub struct DriftEstimator {
+    sample_rate: f64,
+    /// Smoothed backlog, in frames.
+    backlog: f64,
+    /// Backlog to hold, once settled.
+    reference: Option<f64>,
+    settle_frames: usize,
+    adjustment: f64,
+}
+
+impl DriftEstimator {
+    pub fn new(sample_rate: u32) -> Self {
+        DriftEstimator {
+            sample_rate: sample_rate.max(1) as f64,
+            backlog: 0.0,
+            reference: None,
+            settle_frames: 0,
+            adjustment: 0.0,
+        }
+    }
+
+    /// Starts over, e.g. after the backlog was changed on purpose.
+    pub fn reset(&mut self) {
+        *self = DriftEstimator::new(self.sample_rate as u32);
+    }
+
+    /// Takes the backlog in frames, observed `elapsed_frames` after the last
+    /// update, and returns the adjustment: positive when the producer runs
+    /// fast and the consumer should take frames that much quicker.
+    pub fn update(&mut self, backlog: f64, elapsed_frames: usize) -> f64 {
+        if self.settle_frames == 0 {
+            self.backlog = backlog;
+        }
+        let weight = (elapsed_frames as f64 / (SMOOTHING_S * self.sample_rate)).min(1.0);
+        self.backlog += (backlog - self.backlog) * weight;
+        self.settle_frames += elapsed_frames;
+
+        let reference = match self.reference {
+            Some(reference) => reference,
+            None if self.settle_frames as f64 >= SETTLE_S * self.sample_rate => *self.reference.insert(self.backlog),
+            None => return self.adjustment,
+        };
+        self.adjustment = ((self.backlog - reference) / (CORRECTION_TIME_S * self.sample_rate))
+            .clamp(-MAX_ADJUSTMENT, MAX_ADJUSTMENT);
+        self.adjustment
+    }
+
+    /// How much faster the producer's clock runs than the consumer's, as
+    /// currently corrected for.
+    pub fn drift_ppm(&self) -> f64 {
+        self.adjustment * 1e6
+    }
+}
+
+/// Changes the rate of a stream by a few ppm with cubic interpolation,
+/// one frame at a time. Unlike a block resampler it holds back only a
+/// couple of frames, so it can sit in a low latency path.
+#[derive(Debug, Clone)]
+pub struct MicroResampler {
+    channels: usize,
+    /// The last four input frames, interleaved, oldest first.
+    window: VecDeque<f32>,
+    /// Position of the next output frame between the second and third
+    /// frame of the window.
+    phase: f64,
+    /// Input frames consumed per output frame.
+    step: f64,
+}
+
+impl MicroResampler {
+    pub fn new(channels: usize) -> Self {
+        let channels = channels.max(1);
+        // One frame of silence ahead so the first output is the first input
+        MicroResampler {
+            channels,
+            window: VecDeque::from(vec![0.0; channels]),
+            phase: 0.0,
+            step: 1.0,
+        }
+    }
+
+    /// Consumes input `1 + adjustment` times as fast as it produces output.
+    pub fn set_adjustment(&mut self, adjustment: f64) {
+        self.step = 1.0 + adjustment.clamp(-MAX_ADJUSTMENT, MAX_ADJUSTMENT);
+    }
+
+    /// Takes interleaved input and returns the frames that are ready.
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let channels = self.channels;
+        let mut output = Vec::with_capacity(samples.len() + channels);
+
+        for frame in samples.chunks_exact(channels) {
+            self.window.extend(frame);
+            if self.window.len() < 4 * channels {
+                continue;
+            }
+
+            while self.phase < 1.0 {
+                let t = self.phase as f32;
+                for channel in 0..channels {
+                    let at = |i: usize| self.window[i * channels + channel];
+                    output.push(hermite(at(0), at(1), at(2), at(3), t));
+                }
+                self.phase += self.step;
+            }
+            self.phase -= 1.0;
+            self.window.drain(..channels);
+        }
+        output
+    }
+}
+
+/// Catmull-Rom interpolation between `y1` and `y2`.
+fn hermite(y0: f32, y1: f32, y2: f32, y3: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (y2 - y0);
+    let c2 = y0 - 2.5 * y1 + 2.0 * y2 - 0.5 * y3;
+    let c3 = 0.5 * (y3 - y0) + 1.5 * (y1 - y2);
+    ((c3 * t + c2) * t + c1) * t + y1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+    /// Both sides exchange 10 ms blocks.
+    const BLOCK: usize = 480;
+
+    /// Runs a producer `ppm` fast against a consumer corrected by the
+    /// estimator, returning the backlog after every block and the estimator.
+    fn simulate(ppm: f64, seconds: usize, start_backlog: f64) -> (Vec<f64>, DriftEstimator) {
+        let mut estimator = DriftEstimator::new(SAMPLE_RATE);
+        let mut backlog = start_backlog;
+        let mut adjustment = 0.0;
+        let mut history = Vec::new();
+        for _ in 0..seconds * 100 {
+            backlog += BLOCK as f64 * (1.0 + ppm * 1e-6);
+            backlog -= BLOCK as f64 * (1.0 + adjustment);
+            adjustment = estimator.update(backlog, BLOCK);
+            history.push(backlog);
+        }
+        (history, estimator)
+    }
+
+    #[test]
+    fn holds_the_backlog_against_drift() {
+        for ppm in [-80.0, -15.0, 15.0, 80.0] {
+            let (history, estimator) = simulate(ppm, 600, 960.0);
+            // The correction settles on the drift...
+            assert!((estimator.drift_ppm() - ppm).abs() < 1.0, "{} ppm estimated as {}", ppm, estimator.drift_ppm());
+            // ...with the backlog never more than a few ms from where it
+            // started, instead of running away by tens of ms
+            let worst = history.iter().map(|backlog| (backlog - 960.0).abs()).fold(0.0, f64::max);
+            assert!(worst < 0.1 * SAMPLE_RATE as f64 / 1000.0 * ppm.abs(), "{} ppm strayed {} frames", ppm, worst);
+            let last = history.last().unwrap();
+            assert!((last - 960.0).abs() < 48.0, "{} ppm ended {} frames off", ppm, last - 960.0);
+        }
+    }
+
+    #[test]
+    fn adjustment_waits_to_settle_and_is_bounded() {
+        let mut estimator = DriftEstimator::new(SAMPLE_RATE);
+        for _ in 0..150 {
+            assert_eq!(estimator.update(1000.0, BLOCK), 0.0);
+        }
+        for _ in 0..100 {
+            estimator.update(1000.0, BLOCK);
+        }
+        assert_eq!(estimator.update(1000.0, BLOCK), 0.0);
+
+        // A jump far beyond any real drift is clamped
+        for _ in 0..100 {
+            estimator.update(1_000_000.0, BLOCK);
+        }
+        assert_eq!(estimator.update(1_000_000.0, BLOCK), MAX_ADJUSTMENT);
+
+        estimator.reset();
+        assert_eq!(estimator.update(1_000_000.0, BLOCK), 0.0);
+        assert_eq!(estimator.drift_ppm(), 0.0);
+    }
+
+    #[test]
+    fn resampler_passes_audio_through_unadjusted() {
+        let input: Vec<f32> = (0..2000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let mut resampler = MicroResampler::new(2);
+        let output: Vec<f32> = input.chunks(2 * 37).flat_map(|chunk| resampler.process(chunk)).collect();
+        // Held back by the two frames of lookahead
+        assert_eq!(output, input[..input.len() - 2 * 2]);
+    }
+
+    #[test]
+    fn resampler_follows_the_adjustment() {
+        let adjustment = 500e-6;
+        let frequency = 0.02;
+        let input: Vec<f32> = (0..200_000).map(|i| (i as f32 * frequency).sin()).collect();
+        let mut resampler = MicroResampler::new(1);
+        resampler.set_adjustment(adjustment);
+        let output: Vec<f32> = input.chunks(BLOCK).flat_map(|chunk| resampler.process(chunk)).collect();
+
+        let expected_len = (input.len() - 2) as f64 / (1.0 + adjustment);
+        assert!((output.len() as f64 - expected_len).abs() <= 1.0, "{} frames, expected {}", output.len(), expected_len);
+        // Each output frame is the input read at its stretched position
+        for (i, &sample) in output.iter().enumerate() {
+            let ideal = (i as f64 * (1.0 + adjustment) * frequency as f64).sin() as f32;
+            assert!((sample - ideal).abs() < 1e-3, "frame {}: {} != {}", i, sample, ideal);
+        }
+
+        // Settings beyond the limit are clamped
+        resampler.set_adjustment(1.0);
+        assert_eq!(resampler.step, 1.0 + MAX_ADJUSTMENT);
+    }
+}
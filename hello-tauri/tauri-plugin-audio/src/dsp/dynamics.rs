@@ -0,0 +1,187 @@
+use serde::Deserialize;
+use specta::Type;
+
+use super::fft::amplitude_to_db;
+use super::gain::db_to_linear;
+
+fn time_coefficient(ms: f32, sample_rate: u32) -> f32 {
+    1.0 - (-1.0 / (ms.max(0.1) / 1000.0 * sample_rate as f32)).exp()
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct DuckingSettings {
+    /// Key level above which the target is turned down.
+    pub threshold_db: f32,
+    /// How far the target is turned down while the key is active.
+    pub depth_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for DuckingSettings {
+    fn default() -> Self {
+        DuckingSettings {
+            threshold_db: -30.0,
+            depth_db: -12.0,
+            attack_ms: 20.0,
+            release_ms: 500.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct GateSettings {
+    /// Key level above which the gate opens.
+    pub threshold_db: f32,
+    /// Attenuation while closed.
+    pub range_db: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for GateSettings {
+    fn default() -> Self {
+        GateSettings {
+            threshold_db: -45.0,
+            range_db: -80.0,
+            attack_ms: 1.0,
+            release_ms: 150.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct CompressorSettings {
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+    pub makeup_db: f32,
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        CompressorSettings {
+            threshold_db: -20.0,
+            ratio: 4.0,
+            attack_ms: 10.0,
+            release_ms: 100.0,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct LimiterSettings {
+    /// Peak level the output never goes over.
+    pub ceiling_db: f32,
+    pub release_ms: f32,
+}
+
+impl Default for LimiterSettings {
+    fn default() -> Self {
+        LimiterSettings {
+            ceiling_db: -1.0,
+            release_ms: 50.0,
+        }
+    }
+}
+
+/// A gain computer driven by a key level, which is either the processed
+/// signal itself or another stream (sidechain).
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Dynamics {
+    /// Turns the signal down while the key is above the threshold.
+    Duck(DuckingSettings),
+    /// Lets the signal through only while the key is above the threshold.
+    Gate(GateSettings),
+    /// Reduces gain by the ratio as the key goes over the threshold.
+    Compressor(CompressorSettings),
+}
+
+impl Dynamics {
+    /// Gain to glide towards for a key at `level_db`, and the time to get
+    /// there from `current`.
+    fn target(&self, level_db: f32, current: f32) -> (f32, f32) {
+        match self {
+            Dynamics::Duck(s) if level_db > s.threshold_db => (db_to_linear(s.depth_db.min(0.0)), s.attack_ms),
+            Dynamics::Duck(s) => (1.0, s.release_ms),
+            Dynamics::Gate(s) if level_db > s.threshold_db => (1.0, s.attack_ms),
+            Dynamics::Gate(s) => (db_to_linear(s.range_db.min(0.0)), s.release_ms),
+            Dynamics::Compressor(s) => {
+                let over = (level_db - s.threshold_db).max(0.0);
+                let reduction_db = over * (1.0 - 1.0 / s.ratio.max(1.0));
+                let target = db_to_linear(-reduction_db);
+                (target, if target < current { s.attack_ms } else { s.release_ms })
+            }
+        }
+    }
+
+    fn makeup(&self) -> f32 {
+        match self {
+            Dynamics::Compressor(s) => db_to_linear(s.makeup_db),
+            _ => 1.0,
+        }
+    }
+}
+
+/// Running gain of a `Dynamics` processor.
+pub struct DynamicsProcessor {
+    sample_rate: u32,
+    gain: f32,
+}
+
+impl DynamicsProcessor {
+    pub fn new(sample_rate: u32) -> Self {
+        DynamicsProcessor { sample_rate, gain: 1.0 }
+    }
+
+    /// Applies `dynamics` to interleaved `samples` given the key's current
+    /// RMS level.
+    pub fn process(&mut self, samples: &mut [f32], channels: usize, dynamics: &Dynamics, key_level: f32) {
+        let (target, ms) = dynamics.target(amplitude_to_db(key_level), self.gain);
+        let coefficient = time_coefficient(ms, self.sample_rate);
+        let makeup = dynamics.makeup();
+
+        for frame in samples.chunks_mut(channels.max(1)) {
+            self.gain += (target - self.gain) * coefficient;
+            frame.iter_mut().for_each(|s| *s *= self.gain * makeup);
+        }
+    }
+}
+
+/// Peak limiter without lookahead: gain drops instantly on a frame that
+/// would go over the ceiling and recovers over the release time.
+pub struct Limiter {
+    ceiling: f32,
+    release: f32,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(settings: &LimiterSettings, sample_rate: u32) -> Self {
+        Limiter {
+            ceiling: db_to_linear(settings.ceiling_db.min(0.0)),
+            release: time_coefficient(settings.release_ms, sample_rate),
+            gain: 1.0,
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        for frame in samples.chunks_mut(channels.max(1)) {
+            let peak = frame.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+            let target = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+            if target < self.gain {
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release;
+            }
+            frame.iter_mut().for_each(|s| *s *= self.gain);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::f64::consts::PI;
+
+use super::biquad::Biquad;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum EqBandKind {
+    Peak,
+    /// Bass tone control style shelf below the frequency.
+    LowShelf,
+    /// Treble tone control style shelf above the frequency.
+    HighShelf,
+    LowPass,
+    HighPass,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct EqBand {
+    pub kind: EqBandKind,
+    pub frequency_hz: f64,
+    /// Ignored by the pass filters.
+    #[serde(default)]
+    pub gain_db: f64,
+    #[serde(default = "default_q")]
+    pub q: f64,
+}
+
+fn default_q() -> f64 {
+    std::f64::consts::FRAC_1_SQRT_2
+}
+
+impl EqBand {
+    /// Coefficients from the RBJ audio EQ cookbook.
+    fn biquad(&self, sample_rate: u32) -> Result<Biquad, String> {
+        let nyquist = sample_rate as f64 / 2.0;
+        if !(self.frequency_hz > 0.0 && self.frequency_hz < nyquist) {
+            return Err(format!("Band frequency must be between 0 and {} Hz", nyquist));
+        }
+        if self.q <= 0.0 {
+            return Err("Band Q must be positive".to_string());
+        }
+
+        let w0 = 2.0 * PI * self.frequency_hz / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * self.q);
+        let a = 10f64.powf(self.gain_db / 40.0);
+        let shelf = 2.0 * a.sqrt() * alpha;
+
+        let (b, a) = match self.kind {
+            EqBandKind::Peak => (
+                [1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a],
+                [1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a],
+            ),
+            EqBandKind::LowShelf => (
+                [
+                    a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) + (a - 1.0) * cos + shelf,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - shelf,
+                ],
+            ),
+            EqBandKind::HighShelf => (
+                [
+                    a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                ],
+                [
+                    (a + 1.0) - (a - 1.0) * cos + shelf,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - shelf,
+                ],
+            ),
+            EqBandKind::LowPass => (
+                [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+            EqBandKind::HighPass => (
+                [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+                [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+            ),
+        };
+
+        Ok(Biquad::new(
+            [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            [a[1] / a[0], a[2] / a[0]],
+        ))
+    }
+}
+
+/// A chain of EQ bands applied to every channel of interleaved audio.
+pub struct Equalizer {
+    /// One filter chain per channel.
+    filters: Vec<Vec<Biquad>>,
+}
+
+impl Equalizer {
+    pub fn new(bands: &[EqBand], sample_rate: u32, channels: usize) -> Result<Self, String> {
+        let chain = bands
+            .iter()
+            .map(|band| band.biquad(sample_rate))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Equalizer {
+            filters: vec![chain; channels.max(1)],
+        })
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.filters.len();
+        for frame in samples.chunks_mut(channels) {
+            for (sample, chain) in frame.iter_mut().zip(&mut self.filters) {
+                let mut value = *sample as f64;
+                for filter in chain.iter_mut() {
+                    value = filter.process(value);
+                }
+                *sample = value as f32;
+            }
+        }
+    }
+}
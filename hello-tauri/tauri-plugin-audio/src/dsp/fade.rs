@@ -0,0 +1,72 @@
+use serde::Deserialize;
+use specta::Type;
+use std::f32::consts::FRAC_PI_2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum FadeCurve {
+    Linear,
+    /// Sine/cosine pair whose powers sum to one, so uncorrelated material
+    /// keeps a steady level through a crossfade.
+    #[default]
+    EqualPower,
+}
+
+impl FadeCurve {
+    /// Gain of a fade-in at position `t` from 0.0 to 1.0. The matching
+    /// fade-out is `gain(1.0 - t)`.
+    pub fn gain(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            FadeCurve::Linear => t,
+            FadeCurve::EqualPower => (t * FRAC_PI_2).sin(),
+        }
+    }
+}
+
+/// A gain that ramps linearly between silence and full level, for starting
+/// and stopping audio part way through without a click.
+#[derive(Debug, Clone)]
+pub struct FadeRamp {
+    gain: f32,
+    target: f32,
+    step: f32,
+}
+
+impl FadeRamp {
+    /// Starts silent when `open` is false, ready to fade in.
+    pub fn new(open: bool) -> Self {
+        let gain = if open { 1.0 } else { 0.0 };
+        FadeRamp { gain, target: gain, step: 1.0 }
+    }
+
+    /// Heads to full level (`open`) or to silence over `frames`. Asking for
+    /// the direction it is already heading leaves the ramp running as is.
+    pub fn ramp_to(&mut self, open: bool, frames: usize) {
+        let target = if open { 1.0 } else { 0.0 };
+        if target != self.target {
+            self.target = target;
+            self.step = 1.0 / frames.max(1) as f32;
+        }
+    }
+
+    /// Gain for the next frame.
+    pub fn next_gain(&mut self) -> f32 {
+        self.gain = if self.gain < self.target {
+            (self.gain + self.step).min(self.target)
+        } else {
+            (self.gain - self.step).max(self.target)
+        };
+        self.gain
+    }
+
+    /// True unless fading out or silent after a fade out.
+    pub fn is_open(&self) -> bool {
+        self.target == 1.0
+    }
+
+    /// True once a fade out has reached silence.
+    pub fn is_closed(&self) -> bool {
+        self.target == 0.0 && self.gain == 0.0
+    }
+}
@@ -0,0 +1,79 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+/// Hann window equivalent noise bandwidth, in bins. Dividing summed bin
+/// powers by this makes a full-scale sine read 0 dB regardless of leakage.
+pub const HANN_ENBW: f32 = 1.5;
+
+/// Single-sided amplitude spectrum: a full-scale sine centred on a bin has
+/// an amplitude of 1.0 there.
+#[derive(Debug, Clone)]
+pub struct Spectrum {
+    pub bin_hz: f32,
+    pub amplitudes: Vec<f32>,
+}
+
+pub fn hann_window(length: usize) -> Vec<f32> {
+    (0..length)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / length as f32).cos())
+        .collect()
+}
+
+/// A planned Hann-windowed FFT of fixed size, reusable across frames.
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    scale: f32,
+    buffer: Vec<Complex<f32>>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(length: usize) -> Self {
+        let window = hann_window(length);
+        let window_sum: f32 = window.iter().sum();
+
+        SpectrumAnalyzer {
+            fft: FftPlanner::new().plan_fft_forward(length),
+            window,
+            scale: 2.0 / window_sum.max(f32::EPSILON),
+            buffer: Vec::with_capacity(length),
+        }
+    }
+
+    pub fn fft_size(&self) -> usize {
+        self.window.len()
+    }
+
+    pub fn bins(&self) -> usize {
+        self.window.len() / 2 + 1
+    }
+
+    /// Amplitudes of `samples`, which must be exactly the analyzer length.
+    pub fn amplitudes(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.buffer.clear();
+        self.buffer.extend(samples.iter().zip(&self.window).map(|(&s, &w)| Complex::new(s * w, 0.0)));
+        self.fft.process(&mut self.buffer);
+
+        self.buffer[..self.bins()].iter().map(|c| c.norm() * self.scale).collect()
+    }
+}
+
+pub fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-10).log10()
+}
+
+/// Magnitude of an impulse response's transfer function, unwindowed so the
+/// whole response counts: a unit impulse reads 1.0 in every bin.
+pub fn transfer_magnitude(ir: &[f32], sample_rate: u32) -> Spectrum {
+    let length = ir.len().max(1);
+    let mut buffer: Vec<Complex<f32>> = ir.iter().map(|&s| Complex::new(s, 0.0)).collect();
+    buffer.resize(length, Complex::new(0.0, 0.0));
+    FftPlanner::new().plan_fft_forward(length).process(&mut buffer);
+
+    Spectrum {
+        bin_hz: sample_rate as f32 / length as f32,
+        amplitudes: buffer[..length / 2 + 1].iter().map(|c| c.norm()).collect(),
+    }
+}
@@ -0,0 +1,42 @@
+/// Time constant of the gain smoothing. Short enough to feel instant,
+/// long enough to avoid zipper noise when the gain jumps.
+const SMOOTHING_SECONDS: f32 = 0.01;
+
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// Applies a gain that glides towards its target instead of jumping.
+pub struct GainRamp {
+    current: f32,
+    coefficient: f32,
+}
+
+impl GainRamp {
+    pub fn new(sample_rate: u32) -> Self {
+        GainRamp {
+            current: 1.0,
+            coefficient: 1.0 - (-1.0 / (SMOOTHING_SECONDS * sample_rate as f32)).exp(),
+        }
+    }
+
+    /// Scales interleaved samples in place, advancing the ramp once per frame
+    /// so every channel in a frame gets the same gain.
+    pub fn apply(&mut self, samples: &mut [f32], channels: usize, target: f32) {
+        if self.current == target {
+            if target != 1.0 {
+                samples.iter_mut().for_each(|s| *s *= target);
+            }
+            return;
+        }
+
+        for frame in samples.chunks_mut(channels.max(1)) {
+            self.current += (target - self.current) * self.coefficient;
+            if (target - self.current).abs() < 1e-5 {
+                self.current = target;
+            }
+
+            frame.iter_mut().for_each(|s| *s *= self.current);
+        }
+    }
+}
@@ -0,0 +1,11 @@
+/// Magnitude at or above which a sample counts as clipped: the largest
+/// positive 16-bit value, so clipped integer files are caught as well.
+pub const CLIP_THRESHOLD: f32 = 1.0 - 1.0 / 32768.0;
+
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0, |peak, &s| peak.max(s.abs()))
+}
+
+pub fn is_clipped(sample: f32) -> bool {
+    sample.abs() >= CLIP_THRESHOLD
+}
@@ -0,0 +1,399 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use super::biquad::Biquad;
+
+/// Loudness is accumulated in 100 ms blocks; momentary and short-term
+/// windows (400 ms / 3 s) and the gating blocks are built from these.
+const BLOCK_SECONDS: f64 = 0.1;
+const MOMENTARY_BLOCKS: usize = 4;
+const SHORT_TERM_BLOCKS: usize = 30;
+
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// BS.1770 relative gate for integrated loudness.
+const INTEGRATED_RELATIVE_GATE_LU: f64 = -10.0;
+/// EBU Tech 3342 relative gate for loudness range.
+const LRA_RELATIVE_GATE_LU: f64 = -20.0;
+const HISTOGRAM_BIN_LU: f64 = 0.1;
+/// Louder windows are counted in the top bin.
+const HISTOGRAM_TOP_LUFS: f64 = 30.0;
+
+/// Per-channel stage 1 (high shelf) + stage 2 (high pass) K-weighting filter
+/// from ITU-R BS.1770, with coefficients derived for any sample rate.
+#[derive(Debug, Clone)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    fn new(sample_rate: u32) -> Self {
+        let fs = sample_rate as f64;
+
+        let f0 = 1681.974450955533;
+        let gain_db = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / fs).tan();
+        let vh = 10f64.powf(gain_db / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / fs).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        KWeighting { shelf, high_pass }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+}
+
+/// BS.1770 channel weights: surround channels count +1.5 dB, LFE is ignored.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    if channels == 6 {
+        // L, R, C, LFE, Ls, Rs
+        vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41]
+    } else {
+        vec![1.0; channels]
+    }
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+/// Summary of a loudness measurement. Values are `None` until enough audio
+/// has been measured (or when everything was gated out as silence).
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct LoudnessReport {
+    pub momentary_lufs: Option<f64>,
+    pub short_term_lufs: Option<f64>,
+    pub integrated_lufs: Option<f64>,
+    /// EBU Tech 3342 loudness range in LU.
+    pub loudness_range_lu: Option<f64>,
+    pub max_momentary_lufs: Option<f64>,
+    pub max_short_term_lufs: Option<f64>,
+}
+
+/// Histogram of window loudness above the absolute gate, in 0.1 LU bins as
+/// in libebur128. Integrated loudness and loudness range are read from it,
+/// so a report costs the same however long the measurement has run.
+#[derive(Debug, Clone)]
+struct GatingHistogram {
+    counts: Vec<u64>,
+    /// Sum of the window powers in each bin.
+    powers: Vec<f64>,
+}
+
+impl GatingHistogram {
+    fn new() -> Self {
+        let bins = ((HISTOGRAM_TOP_LUFS - ABSOLUTE_GATE_LUFS) / HISTOGRAM_BIN_LU).round() as usize;
+        GatingHistogram { counts: vec![0; bins], powers: vec![0.0; bins] }
+    }
+
+    /// Adds one window; windows at or below the absolute gate are dropped.
+    fn add(&mut self, power: f64) {
+        let lufs = power_to_lufs(power);
+        if lufs <= ABSOLUTE_GATE_LUFS {
+            return;
+        }
+        let bin = (((lufs - ABSOLUTE_GATE_LUFS) / HISTOGRAM_BIN_LU) as usize).min(self.counts.len() - 1);
+        self.counts[bin] += 1;
+        self.powers[bin] += power;
+    }
+
+    fn bin_lufs(bin: usize) -> f64 {
+        ABSOLUTE_GATE_LUFS + (bin as f64 + 0.5) * HISTOGRAM_BIN_LU
+    }
+
+    /// The bins above a relative gate `relative_lu` below the loudness of
+    /// everything that passed the absolute gate.
+    fn gated(&self, relative_lu: f64) -> Option<std::ops::Range<usize>> {
+        let count: u64 = self.counts.iter().sum();
+        if count == 0 {
+            return None;
+        }
+        let threshold = power_to_lufs(self.powers.iter().sum::<f64>() / count as f64) + relative_lu;
+        let first = (0..self.counts.len()).find(|&bin| Self::bin_lufs(bin) > threshold)?;
+        Some(first..self.counts.len())
+    }
+
+    fn gated_mean_power(&self, relative_lu: f64) -> Option<f64> {
+        let bins = self.gated(relative_lu)?;
+        let count: u64 = self.counts[bins.clone()].iter().sum();
+        (count > 0).then(|| self.powers[bins].iter().sum::<f64>() / count as f64)
+    }
+
+    /// Loudness of the gated windows at each of `percentiles`, ranked as if
+    /// they were sorted.
+    fn gated_percentiles<const N: usize>(&self, relative_lu: f64, percentiles: [f64; N]) -> Option<[f64; N]> {
+        let bins = self.gated(relative_lu)?;
+        let count: u64 = self.counts[bins.clone()].iter().sum();
+        if count == 0 {
+            return None;
+        }
+
+        Some(percentiles.map(|p| {
+            let rank = ((count - 1) as f64 * p).round() as u64;
+            let mut seen = 0;
+            for bin in bins.clone() {
+                seen += self.counts[bin];
+                if seen > rank {
+                    return Self::bin_lufs(bin);
+                }
+            }
+            Self::bin_lufs(bins.end - 1)
+        }))
+    }
+}
+
+/// Streaming EBU R128 loudness meter for interleaved audio.
+#[derive(Debug, Clone)]
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<KWeighting>,
+    weights: Vec<f64>,
+    block_frames: usize,
+    block_sums: Vec<f64>,
+    block_position: usize,
+    /// Channel-weighted mean square of the latest 100 ms blocks, enough for
+    /// a short-term window.
+    recent: VecDeque<f64>,
+    momentary: GatingHistogram,
+    short_term: GatingHistogram,
+    max_momentary: Option<f64>,
+    max_short_term: Option<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        let channels = channels.max(1);
+
+        LoudnessMeter {
+            channels,
+            filters: vec![KWeighting::new(sample_rate); channels],
+            weights: channel_weights(channels),
+            block_frames: ((sample_rate as f64 * BLOCK_SECONDS) as usize).max(1),
+            block_sums: vec![0.0; channels],
+            block_position: 0,
+            recent: VecDeque::with_capacity(SHORT_TERM_BLOCKS),
+            momentary: GatingHistogram::new(),
+            short_term: GatingHistogram::new(),
+            max_momentary: None,
+            max_short_term: None,
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (channel, &sample) in frame.iter().enumerate() {
+                let weighted = self.filters[channel].process(sample as f64);
+                self.block_sums[channel] += weighted * weighted;
+            }
+
+            self.block_position += 1;
+            if self.block_position == self.block_frames {
+                let power = self
+                    .block_sums
+                    .iter()
+                    .zip(&self.weights)
+                    .map(|(sum, weight)| weight * sum / self.block_frames as f64)
+                    .sum();
+                self.push_block(power);
+                self.block_sums.iter_mut().for_each(|sum| *sum = 0.0);
+                self.block_position = 0;
+            }
+        }
+    }
+
+    /// Adds a completed block and the momentary and short-term windows
+    /// ending with it.
+    fn push_block(&mut self, power: f64) {
+        if self.recent.len() == SHORT_TERM_BLOCKS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(power);
+
+        if let Some(power) = self.window_power(MOMENTARY_BLOCKS) {
+            self.momentary.add(power);
+            self.max_momentary = Some(self.max_momentary.map_or(power, |max| max.max(power)));
+        }
+        if let Some(power) = self.window_power(SHORT_TERM_BLOCKS) {
+            self.short_term.add(power);
+            self.max_short_term = Some(self.max_short_term.map_or(power, |max| max.max(power)));
+        }
+    }
+
+    /// Mean power of the latest `length` blocks, once there are that many.
+    fn window_power(&self, length: usize) -> Option<f64> {
+        let start = self.recent.len().checked_sub(length)?;
+        Some(self.recent.range(start..).sum::<f64>() / length as f64)
+    }
+
+    pub fn integrated(&self) -> Option<f64> {
+        self.momentary.gated_mean_power(INTEGRATED_RELATIVE_GATE_LU).map(power_to_lufs)
+    }
+
+    /// Loudness range per EBU Tech 3342: the spread between the 10th and 95th
+    /// percentiles of gated short-term loudness.
+    pub fn loudness_range(&self) -> Option<f64> {
+        let [low, high] = self.short_term.gated_percentiles(LRA_RELATIVE_GATE_LU, [0.10, 0.95])?;
+        Some(high - low)
+    }
+
+    pub fn report(&self) -> LoudnessReport {
+        LoudnessReport {
+            momentary_lufs: self.window_power(MOMENTARY_BLOCKS).map(power_to_lufs),
+            short_term_lufs: self.window_power(SHORT_TERM_BLOCKS).map(power_to_lufs),
+            integrated_lufs: self.integrated(),
+            loudness_range_lu: self.loudness_range(),
+            max_momentary_lufs: self.max_momentary.map(power_to_lufs),
+            max_short_term_lufs: self.max_short_term.map(power_to_lufs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 48000;
+
+    /// Stereo 1 kHz sine, `seconds` long at each of the `(dBFS, seconds)`
+    /// levels in turn, as in the EBU Tech 3341/3342 test signals.
+    fn sine_steps(steps: &[(f64, f64)]) -> Vec<f32> {
+        let mut samples = Vec::new();
+        let mut t = 0usize;
+        for &(dbfs, seconds) in steps {
+            let amplitude = 10f64.powf(dbfs / 20.0);
+            for _ in 0..(seconds * SAMPLE_RATE as f64).round() as usize {
+                let sample = (amplitude * (2.0 * PI * 1000.0 * t as f64 / SAMPLE_RATE as f64).sin()) as f32;
+                samples.extend([sample, sample]);
+                t += 1;
+            }
+        }
+        samples
+    }
+
+    fn measure(steps: &[(f64, f64)]) -> LoudnessReport {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        // Fed in uneven chunks so block boundaries fall mid-call
+        for chunk in sine_steps(steps).chunks(2 * 317) {
+            meter.process(chunk);
+        }
+        meter.report()
+    }
+
+    fn assert_near(value: Option<f64>, expected: f64, tolerance: f64) {
+        let value = value.expect("no measurement");
+        assert!((value - expected).abs() <= tolerance, "{} is not within {} of {}", value, tolerance, expected);
+    }
+
+    #[test]
+    fn tech_3341_stationary_levels() {
+        for level in [-23.0, -33.0] {
+            let report = measure(&[(level, 20.0)]);
+            assert_near(report.momentary_lufs, level, 0.1);
+            assert_near(report.short_term_lufs, level, 0.1);
+            assert_near(report.integrated_lufs, level, 0.1);
+            assert_near(report.max_momentary_lufs, level, 0.1);
+            assert_near(report.max_short_term_lufs, level, 0.1);
+        }
+    }
+
+    #[test]
+    fn tech_3341_gated_integrated_loudness() {
+        // Cases 3 to 5: quiet passages are gated out of the integrated value
+        assert_near(measure(&[(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)]).integrated_lufs, -23.0, 0.1);
+        let case_4 = [(-72.0, 10.0), (-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0), (-72.0, 10.0)];
+        assert_near(measure(&case_4).integrated_lufs, -23.0, 0.1);
+        assert_near(measure(&[(-26.0, 20.0), (-20.0, 20.1), (-26.0, 20.0)]).integrated_lufs, -23.0, 0.1);
+    }
+
+    #[test]
+    fn tech_3342_loudness_range() {
+        assert_near(measure(&[(-20.0, 20.0), (-30.0, 20.0)]).loudness_range_lu, 10.0, 1.0);
+        assert_near(measure(&[(-20.0, 20.0), (-15.0, 20.0)]).loudness_range_lu, 5.0, 1.0);
+        assert_near(measure(&[(-40.0, 20.0), (-20.0, 20.0)]).loudness_range_lu, 20.0, 1.0);
+        let case_4 = [(-50.0, 20.0), (-35.0, 20.0), (-20.0, 20.0), (-35.0, 20.0), (-50.0, 20.0)];
+        assert_near(measure(&case_4).loudness_range_lu, 15.0, 1.0);
+    }
+
+    #[test]
+    fn max_values_hold_the_loudest_window() {
+        let report = measure(&[(-30.0, 5.0), (-18.0, 4.0), (-30.0, 5.0)]);
+        assert_near(report.momentary_lufs, -30.0, 0.1);
+        assert_near(report.max_momentary_lufs, -18.0, 0.1);
+        assert_near(report.max_short_term_lufs, -18.0, 0.1);
+    }
+
+    #[test]
+    fn silence_is_gated_out() {
+        let mut meter = LoudnessMeter::new(SAMPLE_RATE, 2);
+        assert!(meter.report().momentary_lufs.is_none());
+
+        meter.process(&vec![0.0; 2 * SAMPLE_RATE as usize * 5]);
+        let report = meter.report();
+        // Windows are measured, but nothing passes the absolute gate
+        assert!(report.momentary_lufs.unwrap() < ABSOLUTE_GATE_LUFS);
+        assert!(report.integrated_lufs.is_none());
+        assert!(report.loudness_range_lu.is_none());
+    }
+
+    #[test]
+    fn histogram_gates_and_ranks_windows() {
+        let mut histogram = GatingHistogram::new();
+        let power = |lufs: f64| 10f64.powf((lufs + 0.691) / 10.0);
+        assert!(histogram.gated(-10.0).is_none());
+
+        // One window below the absolute gate, ten at -30 and ten at -20
+        histogram.add(power(-75.0));
+        for _ in 0..10 {
+            histogram.add(power(-30.0));
+            histogram.add(power(-20.0));
+        }
+        assert_eq!(histogram.counts.iter().sum::<u64>(), 20);
+
+        // Together they average about -22.6 LUFS, so a -5 LU gate drops
+        // the -30 windows and a -10 LU gate keeps them
+        assert_near(histogram.gated_mean_power(-5.0).map(power_to_lufs), -20.0, 0.01);
+        assert_near(histogram.gated_mean_power(-10.0).map(power_to_lufs), -22.6, 0.01);
+        let [low, high] = histogram.gated_percentiles(-20.0, [0.10, 0.95]).unwrap();
+        assert!((low - -30.0).abs() <= HISTOGRAM_BIN_LU && (high - -20.0).abs() <= HISTOGRAM_BIN_LU, "{} {}", low, high);
+        let [median] = histogram.gated_percentiles(-20.0, [0.5]).unwrap();
+        assert!((median - -20.0).abs() <= HISTOGRAM_BIN_LU, "{}", median);
+    }
+
+    #[test]
+    fn surround_channels_weigh_more_and_lfe_not_at_all() {
+        let tone = sine_steps(&[(-23.0, 5.0)]);
+        let mono: Vec<f32> = tone.iter().step_by(2).copied().collect();
+        let loudness_in = |channel: usize| {
+            let mut meter = LoudnessMeter::new(SAMPLE_RATE, 6);
+            let mut samples = vec![0.0; mono.len() * 6];
+            for (frame, &sample) in mono.iter().enumerate() {
+                samples[frame * 6 + channel] = sample;
+            }
+            meter.process(&samples);
+            meter.report().momentary_lufs.unwrap()
+        };
+
+        let front = loudness_in(0);
+        assert!((loudness_in(2) - front).abs() < 0.01);
+        assert!((loudness_in(4) - front - 1.49).abs() < 0.01);
+        assert!(loudness_in(3) < ABSOLUTE_GATE_LUFS);
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Deserialize;
+use specta::Type;
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+/// How to fold a multichannel signal down to fewer channels.
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Downmix {
+    /// Every channel at equal weight, summing to unity: (L+R)/2 for stereo.
+    #[default]
+    Average,
+    /// Every channel at -3 dB to mono, which keeps the perceived level of
+    /// uncorrelated material constant.
+    CenterLaw,
+    /// ITU-R BS.775 5.1 to stereo, with inputs in L R C LFE Ls Rs order.
+    /// The LFE channel is dropped.
+    ItuStereo,
+    /// Rows are output channels, columns input channels.
+    Custom { matrix: Vec<Vec<f32>> },
+}
+
+impl Downmix {
+    pub fn matrix(&self, channels: usize) -> Result<Vec<Vec<f32>>, String> {
+        let matrix = match self {
+            Downmix::Average => vec![vec![1.0 / channels as f32; channels]],
+            Downmix::CenterLaw => vec![vec![FRAC_1_SQRT_2; channels]],
+            Downmix::ItuStereo => {
+                if channels != 6 {
+                    return Err(format!("ITU 5.1 downmix needs 6 channels, got {}", channels));
+                }
+                let g = FRAC_1_SQRT_2;
+                vec![
+                    vec![1.0, 0.0, g, 0.0, g, 0.0],
+                    vec![0.0, 1.0, g, 0.0, 0.0, g],
+                ]
+            }
+            Downmix::Custom { matrix } => {
+                if matrix.is_empty() || matrix.iter().any(|row| row.len() != channels) {
+                    return Err(format!("Downmix matrix must have one column per input channel ({})", channels));
+                }
+                matrix.clone()
+            }
+        };
+        Ok(matrix)
+    }
+}
+
+/// How to spread a mono signal across a stereo pair.
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Upmix {
+    /// The same signal at full level on both channels.
+    Duplicate,
+    /// Constant-power pan from -1.0 (hard left) through 0.0 (centre, -3 dB
+    /// per side) to 1.0 (hard right).
+    Pan { position: f32 },
+}
+
+impl Upmix {
+    pub fn matrix(&self, channels: usize) -> Result<Vec<Vec<f32>>, String> {
+        if channels != 1 {
+            return Err(format!("Upmixing needs a mono source, got {} channels", channels));
+        }
+
+        let matrix = match self {
+            Upmix::Duplicate => vec![vec![1.0], vec![1.0]],
+            Upmix::Pan { position } => {
+                let angle = (position.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+                vec![vec![angle.cos()], vec![angle.sin()]]
+            }
+        };
+        Ok(matrix)
+    }
+}
+
+/// Mixes interleaved `samples` through `matrix`, producing one output
+/// channel per matrix row.
+pub fn apply_matrix(samples: &[f32], channels: usize, matrix: &[Vec<f32>]) -> Vec<f32> {
+    let mut output = Vec::with_capacity(samples.len() / channels.max(1) * matrix.len());
+
+    for frame in samples.chunks_exact(channels) {
+        for row in matrix {
+            output.push(row.iter().zip(frame).map(|(gain, sample)| gain * sample).sum());
+        }
+    }
+
+    output
+}
@@ -0,0 +1,20 @@
+pub mod bands;
+pub mod biquad;
+pub mod cepstrum;
+pub mod channels;
+pub mod convolution;
+pub mod decay;
+pub mod drift;
+pub mod correlation;
+pub mod dynamics;
+pub mod eq;
+pub mod fade;
+pub mod fft;
+pub mod gain;
+pub mod levels;
+pub mod loudness;
+pub mod mix;
+pub mod resample;
+pub mod ring;
+pub mod stereo;
+pub mod sweep;
@@ -0,0 +1,135 @@
+use rubato::{
+    FftFixedIn, Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
+};
+
+/// Input block size handed to the resampler.
+const CHUNK_FRAMES: usize = 1024;
+
+/// Largest speed-up or slow-down `StreamResampler::adjust` accepts.
+pub const MAX_ADJUSTMENT: f64 = 0.01;
+
+/// Resamples audio that arrives in pieces, with a ratio that can be nudged
+/// while running to follow a drifting clock.
+pub struct StreamResampler {
+    resampler: SincFixedIn<f32>,
+    /// Output rate over input rate, before adjustment.
+    ratio: f64,
+    adjustment: f64,
+    channels: usize,
+    /// Planar input waiting for a full chunk.
+    pending: Vec<Vec<f32>>,
+}
+
+impl StreamResampler {
+    pub fn new(from: u32, to: u32, channels: usize) -> Result<Self, String> {
+        let channels = channels.max(1);
+        let parameters = SincInterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            oversampling_factor: 128,
+            interpolation: SincInterpolationType::Cubic,
+            window: WindowFunction::BlackmanHarris2,
+        };
+        let ratio = to as f64 / from as f64;
+        let resampler = SincFixedIn::new(ratio, 1.0 + MAX_ADJUSTMENT, parameters, CHUNK_FRAMES, channels)
+            .map_err(|e| format!("Failed to create resampler: {}", e))?;
+
+        Ok(StreamResampler {
+            resampler,
+            ratio,
+            adjustment: 0.0,
+            channels,
+            pending: vec![Vec::new(); channels],
+        })
+    }
+
+    /// Scales the output rate by `1 + adjustment`, gliding to it over the
+    /// next chunk.
+    pub fn adjust(&mut self, adjustment: f64) -> Result<(), String> {
+        self.adjustment = adjustment.clamp(-MAX_ADJUSTMENT, MAX_ADJUSTMENT);
+        self.resampler
+            .set_resample_ratio_relative(1.0 + self.adjustment, true)
+            .map_err(|e| format!("Failed to adjust resampler: {}", e))
+    }
+
+    /// Drops held-back input and the filter's history, e.g. after a seek.
+    pub fn reset(&mut self) {
+        self.resampler.reset();
+        self.pending.iter_mut().for_each(Vec::clear);
+    }
+
+    /// Input frames held back plus the filter delay, in output frames.
+    pub fn buffered_frames(&self) -> f64 {
+        self.pending[0].len() as f64 * self.ratio * (1.0 + self.adjustment) + self.resampler.output_delay() as f64
+    }
+
+    /// Takes interleaved input and returns whatever output is ready. Input
+    /// is held back until a whole chunk has arrived.
+    pub fn process(&mut self, samples: &[f32]) -> Result<Vec<f32>, String> {
+        for (i, &sample) in samples.iter().enumerate() {
+            self.pending[i % self.channels].push(sample);
+        }
+
+        let mut output = Vec::new();
+        while self.pending[0].len() >= self.resampler.input_frames_next() {
+            let next = self.resampler.input_frames_next();
+            let block: Vec<&[f32]> = self.pending.iter().map(|c| &c[..next]).collect();
+            let resampled = self.resampler.process(&block, None).map_err(|e| format!("Resampling failed: {}", e))?;
+            self.pending.iter_mut().for_each(|c| { c.drain(..next); });
+
+            for frame in 0..resampled[0].len() {
+                output.extend(resampled.iter().map(|channel| channel[frame]));
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Converts a whole interleaved buffer from one sample rate to another
+/// with an FFT-based resampler, compensating for its delay so the output
+/// lines up with the input.
+pub fn resample(samples: &[f32], channels: usize, from: u32, to: u32) -> Result<Vec<f32>, String> {
+    if from == to || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+
+    let channels = channels.max(1);
+    let frames = samples.len() / channels;
+    let planar: Vec<Vec<f32>> = (0..channels)
+        .map(|c| samples.iter().skip(c).step_by(channels).copied().collect())
+        .collect();
+
+    let mut resampler = FftFixedIn::<f32>::new(from as usize, to as usize, CHUNK_FRAMES, 2, channels)
+        .map_err(|e| format!("Failed to create resampler: {}", e))?;
+    let delay = resampler.output_delay();
+    let expected = (frames as u64 * to as u64).div_ceil(from as u64) as usize;
+
+    let mut output: Vec<Vec<f32>> = vec![Vec::with_capacity(expected + delay); channels];
+    let append = |output: &mut Vec<Vec<f32>>, block: Vec<Vec<f32>>| {
+        for (out, block) in output.iter_mut().zip(block) {
+            out.extend(block);
+        }
+    };
+
+    let mut position = 0;
+    while frames - position >= resampler.input_frames_next() {
+        let next = resampler.input_frames_next();
+        let block: Vec<&[f32]> = planar.iter().map(|c| &c[position..position + next]).collect();
+        append(&mut output, resampler.process(&block, None).map_err(|e| format!("Resampling failed: {}", e))?);
+        position += next;
+    }
+
+    let tail: Vec<&[f32]> = planar.iter().map(|c| &c[position..]).collect();
+    append(&mut output, resampler.process_partial(Some(&tail), None).map_err(|e| format!("Resampling failed: {}", e))?);
+    while output[0].len() < expected + delay {
+        append(&mut output, resampler.process_partial::<&[f32]>(None, None).map_err(|e| format!("Resampling failed: {}", e))?);
+    }
+
+    let mut interleaved = Vec::with_capacity(expected * channels);
+    for frame in delay..delay + expected {
+        for channel in &output {
+            interleaved.push(channel[frame]);
+        }
+    }
+    Ok(interleaved)
+}
@@ -0,0 +1,41 @@
+/// Fixed-capacity buffer that keeps the most recent samples written to it.
+#[derive(Debug, Clone, Default)]
+pub struct RingBuffer {
+    data: Vec<f32>,
+    write: usize,
+    filled: usize,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            data: vec![0.0; capacity],
+            write: 0,
+            filled: 0,
+        }
+    }
+
+    pub fn push_slice(&mut self, samples: &[f32]) {
+        let capacity = self.data.len();
+        if capacity == 0 {
+            return;
+        }
+
+        // Only the tail can survive if more than a full buffer arrives at once
+        let samples = &samples[samples.len().saturating_sub(capacity)..];
+        for &sample in samples {
+            self.data[self.write] = sample;
+            self.write = (self.write + 1) % capacity;
+        }
+        self.filled = (self.filled + samples.len()).min(capacity);
+    }
+
+    /// Returns up to `count` of the newest samples, oldest first.
+    pub fn latest(&self, count: usize) -> Vec<f32> {
+        let count = count.min(self.filled);
+        let capacity = self.data.len();
+        let start = (self.write + capacity - count) % capacity.max(1);
+
+        (0..count).map(|i| self.data[(start + i) % capacity]).collect()
+    }
+}
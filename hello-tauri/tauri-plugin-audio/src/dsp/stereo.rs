@@ -0,0 +1,79 @@
+use serde::Deserialize;
+use specta::Type;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum MidSide {
+    /// L/R to M/S: M = (L + R) / 2, S = (L - R) / 2.
+    Encode,
+    /// M/S back to L/R: L = M + S, R = M - S.
+    Decode,
+}
+
+/// Converts the first two channels of interleaved `samples` in place. The
+/// pair round-trips exactly, so encode followed by decode is lossless.
+pub fn mid_side(samples: &mut [f32], channels: usize, direction: MidSide) {
+    if channels < 2 {
+        return;
+    }
+
+    for frame in samples.chunks_exact_mut(channels) {
+        let (a, b) = (frame[0], frame[1]);
+        (frame[0], frame[1]) = match direction {
+            MidSide::Encode => ((a + b) * 0.5, (a - b) * 0.5),
+            MidSide::Decode => (a + b, a - b),
+        };
+    }
+}
+
+/// Widest setting accepted, as a multiple of the original side level.
+pub const MAX_STEREO_WIDTH: f32 = 2.0;
+/// Time constant of the mid/side energy tracking behind the width limit.
+const WIDTH_ENERGY_SECONDS: f32 = 0.3;
+
+/// Scales the side signal of the first two channels: 0.0 collapses to mono,
+/// 1.0 leaves the image untouched and values above 1.0 widen it. Widening
+/// is held back wherever it would push side energy above mid energy, which
+/// is where the L/R correlation would turn negative and mono playback would
+/// start cancelling.
+pub struct WidthProcessor {
+    width: f32,
+    mid_energy: f32,
+    side_energy: f32,
+    coefficient: f32,
+}
+
+impl WidthProcessor {
+    pub fn new(sample_rate: u32, width: f32) -> Self {
+        WidthProcessor {
+            width: width.clamp(0.0, MAX_STEREO_WIDTH),
+            mid_energy: 0.0,
+            side_energy: 0.0,
+            coefficient: 1.0 - (-1.0 / (WIDTH_ENERGY_SECONDS * sample_rate as f32)).exp(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        if channels < 2 || self.width == 1.0 {
+            return;
+        }
+
+        for frame in samples.chunks_exact_mut(channels) {
+            let mid = (frame[0] + frame[1]) * 0.5;
+            let side = (frame[0] - frame[1]) * 0.5;
+
+            let mut gain = self.width;
+            if gain > 1.0 {
+                self.mid_energy += (mid * mid - self.mid_energy) * self.coefficient;
+                self.side_energy += (side * side - self.side_energy) * self.coefficient;
+                if self.side_energy > 0.0 {
+                    let limit = (self.mid_energy / self.side_energy).sqrt();
+                    gain = gain.min(limit.max(1.0));
+                }
+            }
+
+            frame[0] = mid + side * gain;
+            frame[1] = mid - side * gain;
+        }
+    }
+}
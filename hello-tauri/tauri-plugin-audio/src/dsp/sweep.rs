@@ -0,0 +1,95 @@
+use rustfft::num_complex::Complex;
+use rustfft::FftPlanner;
+use std::f64::consts::PI;
+
+/// Fade at each end of the sweep, so it starts and stops without a click.
+const SWEEP_FADE_SECONDS: f64 = 0.01;
+
+/// An exponential sine sweep and the filter that turns a recording of it
+/// back into an impulse response (Farina's method). Harmonic distortion
+/// ends up ahead of the linear response, where it is cut away.
+pub struct Sweep {
+    pub samples: Vec<f32>,
+    inverse: Vec<f32>,
+    /// Bin of the band's geometric centre frequency, as a fraction of the
+    /// FFT length, where the deconvolution is normalized to unity.
+    centre: f64,
+}
+
+impl Sweep {
+    /// Sweeps from `start_hz` to `end_hz` over `seconds` at `amplitude`.
+    pub fn exponential(start_hz: f64, end_hz: f64, seconds: f64, sample_rate: u32, amplitude: f32) -> Result<Self, String> {
+        let nyquist = sample_rate as f64 / 2.0;
+        if !(start_hz > 0.0 && start_hz < end_hz && end_hz < nyquist) {
+            return Err(format!("Sweep range must lie between 0 and {} Hz", nyquist));
+        }
+        if seconds <= 0.0 {
+            return Err("Sweep length must be positive".to_string());
+        }
+
+        let frames = (seconds * sample_rate as f64) as usize;
+        let rate = seconds / (end_hz / start_hz).ln();
+        let fade = (SWEEP_FADE_SECONDS * sample_rate as f64).max(1.0);
+        let samples: Vec<f32> = (0..frames)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                let edge = (i.min(frames - 1 - i) as f64 / fade).min(1.0);
+                let value = (2.0 * PI * start_hz * rate * ((t / rate).exp() - 1.0)).sin();
+                (value * edge) as f32 * amplitude
+            })
+            .collect();
+
+        // Time reversed, falling 6 dB per octave to undo the pink spectrum
+        let inverse = samples
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, &s)| s * (-(i as f64) / sample_rate as f64 / rate).exp() as f32)
+            .collect();
+
+        Ok(Sweep {
+            samples,
+            inverse,
+            centre: (start_hz * end_hz).sqrt() / sample_rate as f64,
+        })
+    }
+
+    /// Impulse response in `recorded`, a mono capture starting together
+    /// with the sweep: `length` samples from the arrival time of a direct,
+    /// undelayed path.
+    pub fn deconvolve(&self, recorded: &[f32], length: usize) -> Vec<f32> {
+        let size = (recorded.len() + self.inverse.len()).next_power_of_two();
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(size);
+        let ifft = planner.plan_fft_inverse(size);
+
+        let spectrum = |samples: &[f32]| {
+            let mut buffer = vec![Complex::new(0.0f64, 0.0); size];
+            for (bin, &s) in buffer.iter_mut().zip(samples) {
+                bin.re = s as f64;
+            }
+            fft.process(&mut buffer);
+            buffer
+        };
+        let inverse = spectrum(&self.inverse);
+        let sweep = spectrum(&self.samples);
+
+        let centre = (self.centre * size as f64).round() as usize;
+        let unity = (sweep[centre] * inverse[centre]).norm().max(f64::EPSILON);
+
+        let mut response = spectrum(recorded);
+        for (bin, &inv) in response.iter_mut().zip(&inverse) {
+            *bin *= inv / unity;
+        }
+        ifft.process(&mut response);
+
+        // The linear response starts where the sweep and its inverse line up
+        let scale = 1.0 / size as f64;
+        response
+            .iter()
+            .skip(self.inverse.len() - 1)
+            .take(length)
+            .map(|bin| (bin.re * scale) as f32)
+            .collect()
+    }
+}
@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::{HashMap, VecDeque};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+use crate::audio_file::WavEncoding;
+use crate::dsp::channels::select_channels;
+use crate::dsp::resample::StreamResampler;
+use crate::events;
+use crate::lifecycle::{self, LifecycleState, Subsystem};
+use crate::recording::{channel_positions, RecordWriter};
+use crate::stream::{InputStream, RecordTap, StreamShared};
+use crate::validate;
+use crate::AudioState;
+
+/// Seconds over which a measured offset between the two clocks is worked
+/// off. Shorter reacts faster but bends the pitch of the right side more.
+const CORRECTION_TIME_S: f64 = 10.0;
+
+/// Time constant of the smoothing applied to the measured offset, which
+/// hides the jitter from the devices delivering different buffer sizes.
+const OFFSET_SMOOTHING_S: f64 = 1.0;
+
+/// Audio one side may get ahead by before the other is treated as having
+/// dropped out and is filled with silence.
+const MAX_BACKLOG_MS: u32 = 1000;
+
+/// One channel of a monitored stream.
+#[derive(Debug, Clone, Deserialize, Type)]
+pub struct ChannelSource {
+    pub stream_id: String,
+    /// 1-based device input; the stream's first channel by default.
+    pub channel: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Type)]
+pub struct DualStatus {
+    pub elapsed_ms: f64,
+    /// How much faster the right device's clock runs than the left's, as
+    /// currently corrected for.
+    pub drift_ppm: f64,
+    /// Silence inserted because one side stopped delivering audio.
+    pub dropout_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct DualResult {
+    pub file_path: String,
+    pub duration_ms: f64,
+    pub drift_ppm: f64,
+}
+
+/// Everything the writer thread needs to know about each side.
+struct Side {
+    shared: Arc<StreamShared>,
+    sample_rate: u32,
+    channels: usize,
+    pick: usize,
+}
+
+impl Side {
+    fn new(streams: &mut HashMap<String, InputStream>, source: &ChannelSource) -> Result<Self, String> {
+        let stream = streams
+            .get_mut(&source.stream_id)
+            .ok_or_else(|| validate::stream_not_monitored(&source.stream_id))?;
+        if stream.recording.as_ref().is_some_and(|r| !r.is_finished()) {
+            return Err(format!("Stream '{}' is already recording", source.stream_id));
+        }
+        // Collect a recording that already stopped on its own, so stopping it
+        // later can't detach the dual recording's tap
+        stream.finish_recording()?;
+
+        let pick = match source.channel {
+            Some(channel) => channel_positions(&[channel], &stream.input_channels)?[0],
+            None => 0,
+        };
+        if stream.shared.record_tap.lock().unwrap().is_some() {
+            return Err(format!("Stream '{}' is already recording", source.stream_id));
+        }
+
+        Ok(Side {
+            shared: Arc::clone(&stream.shared),
+            sample_rate: stream.sample_rate,
+            channels: stream.channels as usize,
+            pick,
+        })
+    }
+
+    fn mono(&self, samples: &[f32]) -> Vec<f32> {
+        select_channels(samples, self.channels, &[self.pick])
+    }
+}
+
+/// Interleaves the left and right queues into the file. The left device is
+/// the clock the file runs on; the right side is resampled to it, with the
+/// ratio steered by how far the right queue runs ahead of or behind the
+/// left one.
+struct Interleaver {
+    left: VecDeque<f32>,
+    right: VecDeque<f32>,
+    resampler: StreamResampler,
+    sample_rate: u32,
+    /// Smoothed right-minus-left queue difference, in frames.
+    offset: f64,
+    adjustment: f64,
+    frames_written: u64,
+    dropout_frames: u64,
+}
+
+impl Interleaver {
+    fn write(&mut self, writer: &mut RecordWriter) -> Result<(), String> {
+        let limit = (self.sample_rate * MAX_BACKLOG_MS / 1000) as usize;
+        let (left, right) = (self.left.len(), self.right.len());
+        let (behind, missing) = if left > right { (&mut self.right, left - right) } else { (&mut self.left, right - left) };
+        if missing > limit {
+            behind.extend(std::iter::repeat_n(0.0, missing));
+            self.dropout_frames += missing as u64;
+        }
+
+        let frames = self.left.len().min(self.right.len());
+        let mut interleaved = Vec::with_capacity(frames * 2);
+        for (l, r) in self.left.drain(..frames).zip(self.right.drain(..frames)) {
+            interleaved.push(l);
+            interleaved.push(r);
+        }
+        writer.write(&interleaved)?;
+        self.frames_written += frames as u64;
+        Ok(())
+    }
+
+    /// Nudges the right side's resampling ratio so the queues stay level.
+    fn steer(&mut self, elapsed_frames: usize) -> Result<(), String> {
+        let right = self.right.len() as f64 + self.resampler.buffered_frames();
+        let difference = right - self.left.len() as f64;
+        let weight = (elapsed_frames as f64 / (OFFSET_SMOOTHING_S * self.sample_rate as f64)).min(1.0);
+        self.offset += (difference - self.offset) * weight;
+
+        // Running ahead means the right clock is fast, so slow it down
+        self.adjustment = -self.offset / (CORRECTION_TIME_S * self.sample_rate as f64);
+        self.resampler.adjust(self.adjustment)
+    }
+
+    fn status(&self) -> DualStatus {
+        let to_ms = |frames: u64| frames as f64 * 1000.0 / self.sample_rate as f64;
+        DualStatus {
+            elapsed_ms: to_ms(self.frames_written),
+            drift_ppm: -self.adjustment * 1e6,
+            dropout_ms: to_ms(self.dropout_frames),
+        }
+    }
+}
+
+pub struct DualRecording {
+    shared: [Arc<StreamShared>; 2],
+    status: Arc<Mutex<DualStatus>>,
+    writer_thread: JoinHandle<Result<DualResult, String>>,
+}
+
+impl DualRecording {
+    fn start(app: AppHandle, left: Side, right: Side, file_path: String, encoding: Option<WavEncoding>) -> Result<Self, String> {
+        let mut writer = RecordWriter::create(&file_path, left.sample_rate, 2, encoding, None, None)?;
+        let mut interleaver = Interleaver {
+            left: VecDeque::new(),
+            right: VecDeque::new(),
+            resampler: StreamResampler::new(right.sample_rate, left.sample_rate, 1)?,
+            sample_rate: left.sample_rate,
+            offset: 0.0,
+            adjustment: 0.0,
+            frames_written: 0,
+            dropout_frames: 0,
+        };
+
+        let (left_tx, left_rx) = mpsc::channel::<Vec<f32>>();
+        let (right_tx, right_rx) = mpsc::channel::<Vec<f32>>();
+        let status = Arc::new(Mutex::new(DualStatus::default()));
+
+        let shared = [Arc::clone(&left.shared), Arc::clone(&right.shared)];
+        let thread_status = Arc::clone(&status);
+        let path = file_path.clone();
+        lifecycle::transition(&app, Subsystem::Recording, "dual", LifecycleState::Running, None);
+        let writer_thread = thread::spawn(move || {
+            let mut started = false;
+            let result = loop {
+                let left_samples = match left_rx.recv_timeout(Duration::from_millis(100)) {
+                    Ok(samples) => left.mono(&samples),
+                    Err(RecvTimeoutError::Timeout) => Vec::new(),
+                    Err(RecvTimeoutError::Disconnected) => break Ok(()),
+                };
+                let mut right_samples = Vec::new();
+                let right_open = loop {
+                    match right_rx.try_recv() {
+                        Ok(samples) => right_samples.extend(right.mono(&samples)),
+                        Err(mpsc::TryRecvError::Empty) => break true,
+                        Err(mpsc::TryRecvError::Disconnected) => break false,
+                    }
+                };
+                if !right_open {
+                    break Ok(());
+                }
+
+                // Both sides start from the first moment both are delivering
+                if !started {
+                    started = !left_samples.is_empty() && !right_samples.is_empty();
+                    continue;
+                }
+
+                let elapsed = left_samples.len();
+                interleaver.left.extend(left_samples);
+                match interleaver.resampler.process(&right_samples) {
+                    Ok(resampled) => interleaver.right.extend(resampled),
+                    Err(e) => break Err(e),
+                }
+
+                if let Err(e) = interleaver.steer(elapsed).and_then(|_| interleaver.write(&mut writer)) {
+                    break Err(e);
+                }
+                *thread_status.lock().unwrap() = interleaver.status();
+            };
+
+            let status = interleaver.status();
+            let result = result.and_then(|_| writer.finalize()).map(|_| DualResult {
+                file_path: path,
+                duration_ms: status.elapsed_ms,
+                drift_ppm: status.drift_ppm,
+            });
+            events::emit(&app, "dual-record://finished", "dual", &result);
+            let (state, reason) = match &result {
+                Ok(_) => (LifecycleState::Completed, None),
+                Err(e) => (LifecycleState::Failed, Some(e.clone())),
+            };
+            lifecycle::update(&app, Subsystem::Recording, "dual", state, reason);
+            result
+        });
+
+        *shared[0].record_tap.lock().unwrap() = Some(RecordTap { file_path: Some(file_path.clone()), tx: left_tx });
+        *shared[1].record_tap.lock().unwrap() = Some(RecordTap { file_path: Some(file_path), tx: right_tx });
+
+        Ok(DualRecording { shared, status, writer_thread })
+    }
+
+    fn finish(self) -> Result<DualResult, String> {
+        // Detaching either tap ends the writer thread. Once it has ended on
+        // its own the taps may already belong to another recording.
+        if !self.writer_thread.is_finished() {
+            for shared in &self.shared {
+                shared.record_tap.lock().unwrap().take();
+            }
+        }
+        self.writer_thread
+            .join()
+            .map_err(|_| "Dual recording thread panicked".to_string())?
+    }
+}
+
+#[derive(Default)]
+pub struct DualRecordingState {
+    recording: Mutex<Option<DualRecording>>,
+}
+
+impl DualRecordingState {
+    /// Stops and finalizes a running dual recording.
+    pub fn close(&self) -> Result<(), String> {
+        match self.recording.lock().unwrap().take() {
+            Some(recording) => recording.finish().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Drift the running dual recording is correcting for.
+    pub fn drift_ppm(&self) -> Option<f64> {
+        let recording = self.recording.lock().unwrap();
+        recording
+            .as_ref()
+            .filter(|recording| !recording.writer_thread.is_finished())
+            .map(|recording| recording.status.lock().unwrap().drift_ppm)
+    }
+}
+
+/// Records one channel from each of two monitored streams into a stereo
+/// file, `left` on the left channel and `right` on the right. The file runs
+/// at the left stream's rate and the right side is resampled to follow the
+/// left device's clock, so the two stay in sync however long it runs. The
+/// devices' own input latencies are not matched; a constant offset between
+/// them remains.
+#[tauri::command]
+#[specta::specta]
+pub fn start_dual_recording(
+    left: ChannelSource,
+    right: ChannelSource,
+    file_path: String,
+    encoding: Option<WavEncoding>,
+    app: AppHandle,
+    state: State<AudioState>,
+    dual: State<DualRecordingState>,
+) -> Result<(), String> {
+    if left.stream_id == right.stream_id {
+        return Err("Both sides must come from different streams".to_string());
+    }
+
+    let mut recording = dual.recording.lock().unwrap();
+    if recording.as_ref().is_some_and(|r| !r.writer_thread.is_finished()) {
+        return Err("A dual recording is already running".to_string());
+    }
+
+    let mut streams = state.streams.lock().unwrap();
+    let left_side = Side::new(&mut streams, &left)?;
+    let right_side = Side::new(&mut streams, &right)?;
+
+    // Collect a recording that already stopped on its own
+    if let Some(finished) = recording.take() {
+        let _ = finished.finish();
+    }
+
+    *recording = Some(DualRecording::start(app, left_side, right_side, file_path, encoding)?);
+    Ok(())
+}
+
+/// Stops the dual recording, or collects the result of one that ended
+/// because a device went away.
+#[tauri::command]
+#[specta::specta]
+pub fn stop_dual_recording(dual: State<DualRecordingState>) -> Result<DualResult, String> {
+    let recording = dual.recording.lock().unwrap().take()
+        .ok_or_else(|| "No dual recording is running".to_string())?;
+    recording.finish()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_dual_recording_status(dual: State<DualRecordingState>) -> Result<DualStatus, String> {
+    let recording = dual.recording.lock().unwrap();
+    let recording = recording.as_ref().ok_or_else(|| "No dual recording is running".to_string())?;
+    let status = recording.status.lock().unwrap().clone();
+    Ok(status)
+}
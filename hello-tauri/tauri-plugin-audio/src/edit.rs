@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+
+use crate::audio_file::{read_audio_file, write_audio_file, AudioBuffer, WavEncoding};
+use crate::dsp::fade::FadeCurve;
+use crate::dsp::fft::amplitude_to_db;
+use crate::dsp::gain::db_to_linear;
+use crate::dsp::levels::peak;
+use crate::dsp::mix::Upmix;
+
+fn ms_to_frames(ms: f64, sample_rate: u32) -> usize {
+    (ms.max(0.0) / 1000.0 * sample_rate as f64) as usize
+}
+
+/// Overlaps the last `overlap` frames of `a` with the first of `b`.
+fn crossfade(a: &AudioBuffer, b: &AudioBuffer, overlap: usize, curve: FadeCurve) -> AudioBuffer {
+    let channels = a.channels.max(1) as usize;
+    let overlap = overlap.min(a.frames()).min(b.frames());
+    let a_body = (a.frames() - overlap) * channels;
+
+    let mut samples = Vec::with_capacity(a.samples.len() + b.samples.len() - overlap * channels);
+    samples.extend_from_slice(&a.samples[..a_body]);
+
+    for frame in 0..overlap {
+        let t = (frame as f32 + 0.5) / overlap as f32;
+        let (fade_out, fade_in) = (curve.gain(1.0 - t), curve.gain(t));
+        for channel in 0..channels {
+            let index = frame * channels + channel;
+            samples.push(a.samples[a_body + index] * fade_out + b.samples[index] * fade_in);
+        }
+    }
+
+    samples.extend_from_slice(&b.samples[overlap * channels..]);
+
+    AudioBuffer {
+        samples,
+        sample_rate: a.sample_rate,
+        channels: a.channels,
+    }
+}
+
+/// Replaces the frames of `target` from `at` onwards with `insert`, fading
+/// from old to new over the first and last `fade` frames of the insert so
+/// the edit lands exactly where asked without clicking. Both buffers must
+/// share sample rate and channel count.
+pub fn splice(target: &AudioBuffer, insert: &AudioBuffer, at: usize, fade: usize, curve: FadeCurve) -> AudioBuffer {
+    let channels = target.channels.max(1) as usize;
+    let at = at.min(target.frames());
+    let end = at + insert.frames();
+    let fade = fade.min(insert.frames() / 2);
+
+    let mut samples = target.samples.clone();
+    samples.resize(samples.len().max(end * channels), 0.0);
+
+    for frame in 0..insert.frames() {
+        let edge = frame.min(insert.frames() - 1 - frame);
+        let t = if edge < fade { (edge as f32 + 0.5) / fade as f32 } else { 1.0 };
+        // Past the end of the old take there is nothing to fade out
+        let fade_out = if at + frame < target.frames() { curve.gain(1.0 - t) } else { 0.0 };
+        let fade_in = curve.gain(t);
+        for channel in 0..channels {
+            let index = (at + frame) * channels + channel;
+            samples[index] = samples[index] * fade_out + insert.samples[frame * channels + channel] * fade_in;
+        }
+    }
+
+    AudioBuffer {
+        samples,
+        sample_rate: target.sample_rate,
+        channels: target.channels,
+    }
+}
+
+/// Joins `a` and `b` into `dst`, fading A out over its last `overlap_ms`
+/// while B fades in. Both files must share sample rate and channel count.
+#[tauri::command]
+#[specta::specta]
+pub async fn crossfade_files(
+    a: String,
+    b: String,
+    dst: String,
+    overlap_ms: f64,
+    curve: Option<FadeCurve>,
+    encoding: Option<WavEncoding>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let first = read_audio_file(Path::new(&a))?;
+        let second = read_audio_file(Path::new(&b))?;
+
+        if first.sample_rate != second.sample_rate {
+            return Err(format!(
+                "Sample rates differ ({} Hz and {} Hz)",
+                first.sample_rate, second.sample_rate
+            ));
+        }
+        if first.channels != second.channels {
+            return Err(format!(
+                "Channel counts differ ({} and {})",
+                first.channels, second.channels
+            ));
+        }
+
+        let overlap = ms_to_frames(overlap_ms, first.sample_rate);
+        let joined = crossfade(&first, &second, overlap, curve.unwrap_or_default());
+        write_audio_file(Path::new(&dst), &joined, encoding.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Crossfade task failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+pub struct MixInput {
+    pub path: String,
+    #[serde(default)]
+    pub gain_db: f32,
+    /// Where the input starts in the mix.
+    #[serde(default)]
+    pub offset_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct MixResult {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_ms: f64,
+    /// Above 0 dB means the mix clips unless written as float.
+    pub peak_db: f32,
+}
+
+/// Sums `inputs` into `dst`. The mix runs at the first input's sample rate
+/// (unless `sample_rate` is given) and the widest input's channel count;
+/// other inputs are resampled and mono inputs are duplicated into a stereo
+/// mix.
+#[tauri::command]
+#[specta::specta]
+pub async fn mix_files(
+    inputs: Vec<MixInput>,
+    dst: String,
+    sample_rate: Option<u32>,
+    encoding: Option<WavEncoding>,
+) -> Result<MixResult, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        if inputs.is_empty() {
+            return Err("At least one input is required".to_string());
+        }
+
+        let sources = inputs
+            .iter()
+            .map(|input| read_audio_file(Path::new(&input.path)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let sample_rate = sample_rate.unwrap_or(sources[0].sample_rate);
+        let channels = sources.iter().map(|s| s.channels).max().unwrap_or(1);
+        let mut mix: Vec<f32> = Vec::new();
+
+        for (input, source) in inputs.iter().zip(sources) {
+            let mut source = source.resampled(sample_rate)?;
+            if source.channels == 1 && channels == 2 {
+                source = source.upmix(&Upmix::Duplicate)?;
+            } else if source.channels != channels {
+                return Err(format!(
+                    "{} has {} channels but the mix has {}",
+                    input.path, source.channels, channels
+                ));
+            }
+
+            let gain = db_to_linear(input.gain_db);
+            let start = ms_to_frames(input.offset_ms, sample_rate) * channels as usize;
+            if mix.len() < start + source.samples.len() {
+                mix.resize(start + source.samples.len(), 0.0);
+            }
+            for (out, sample) in mix[start..].iter_mut().zip(&source.samples) {
+                *out += sample * gain;
+            }
+        }
+
+        let mixed = AudioBuffer {
+            samples: mix,
+            sample_rate,
+            channels,
+        };
+        write_audio_file(Path::new(&dst), &mixed, encoding.unwrap_or_default())?;
+
+        Ok(MixResult {
+            sample_rate,
+            channels,
+            duration_ms: mixed.frames() as f64 * 1000.0 / sample_rate as f64,
+            peak_db: amplitude_to_db(peak(&mixed.samples)),
+        })
+    })
+    .await
+    .map_err(|e| format!("Mix task failed: {}", e))?
+}
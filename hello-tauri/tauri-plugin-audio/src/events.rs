@@ -0,0 +1,220 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::settings::SettingsStore;
+use crate::subscriptions::Subscriptions;
+use crate::ticker::Ticker;
+use crate::validate;
+
+/// How often held-back events are checked for being due.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Topics quiet for this long are forgotten.
+const IDLE_TOPIC: Duration = Duration::from_secs(10);
+
+/// Allowed range for a topic's rate, in events per second.
+const RATE_RANGE_HZ: (f32, f32) = (0.1, 1000.0);
+
+/// Rate limits by default, for events that report a changing state. Events
+/// marking something that happened, like a recording finishing, are never
+/// limited unless configured.
+const DEFAULT_RATES_HZ: [(&str, f32); 8] = [
+    ("audio://goniometer", 30.0),
+    ("audio://oscilloscope", 30.0),
+    ("audio://spectrum", 30.0),
+    ("playback://position", 30.0),
+    ("record://status", 10.0),
+    ("transcript://partial", 4.0),
+    ("transcription://progress", 10.0),
+    ("transcription://download-progress", 10.0),
+];
+
+/// Most events per second emitted on each topic. Events coming in faster
+/// are coalesced: only the newest is sent once the topic is due again.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+#[serde(transparent)]
+pub struct EventRates(pub HashMap<String, f32>);
+
+impl Default for EventRates {
+    fn default() -> Self {
+        EventRates(DEFAULT_RATES_HZ.iter().map(|&(topic, rate)| (topic.to_string(), rate)).collect())
+    }
+}
+
+impl EventRates {
+    /// None for topics that aren't limited, or whose rate is unusable.
+    fn interval(&self, event: &str) -> Option<Duration> {
+        self.0.get(event).and_then(|rate| Duration::try_from_secs_f32(1.0 / rate).ok())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for (topic, &rate) in &self.0 {
+            validate::range(&format!("Rate for '{}'", topic), rate, RATE_RANGE_HZ, "per second")?;
+        }
+        Ok(())
+    }
+
+    /// Drops out-of-range rates, e.g. from a hand-edited settings file, so
+    /// those topics go unlimited instead of failing.
+    pub fn sanitized(mut self) -> Self {
+        self.0.retain(|topic, &mut rate| match validate::range("Rate", rate, RATE_RANGE_HZ, "per second") {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Ignoring event rate for {}: {}", topic, e);
+                false
+            }
+        });
+        self
+    }
+}
+
+struct Topic {
+    interval: Duration,
+    last_sent: Instant,
+    /// Newest event waiting for the topic to be due.
+    pending: Option<serde_json::Value>,
+}
+
+type TopicKey = (String, String);
+
+/// Sends events to the webview, rate limiting the topics configured in
+/// `EventRates`. Each topic is limited per key, e.g. per stream, so one
+/// busy stream doesn't hold back another's events.
+pub struct EventDispatcher {
+    app: AppHandle,
+    rates: Mutex<EventRates>,
+    topics: Arc<Mutex<HashMap<TopicKey, Topic>>>,
+    _flusher: Ticker,
+}
+
+impl EventDispatcher {
+    pub fn new(app: AppHandle, rates: EventRates) -> Self {
+        let topics: Arc<Mutex<HashMap<TopicKey, Topic>>> = Arc::default();
+        let flush_topics = Arc::clone(&topics);
+        let flush_app = app.clone();
+        let flusher = Ticker::spawn(FLUSH_INTERVAL, move || {
+            let now = Instant::now();
+            let mut due = Vec::new();
+            {
+                let mut topics = flush_topics.lock().unwrap();
+                for ((event, key), topic) in topics.iter_mut() {
+                    if now.duration_since(topic.last_sent) >= topic.interval {
+                        if let Some(payload) = topic.pending.take() {
+                            topic.last_sent = now;
+                            due.push((event.clone(), key.clone(), payload));
+                        }
+                    }
+                }
+                topics.retain(|_, topic| topic.pending.is_some() || now.duration_since(topic.last_sent) < IDLE_TOPIC);
+            }
+            for (event, key, payload) in due {
+                deliver(&flush_app, &event, &key, payload);
+            }
+        });
+
+        EventDispatcher {
+            app,
+            rates: Mutex::new(rates),
+            topics,
+            _flusher: flusher,
+        }
+    }
+
+    /// Emits `payload` now if `event` isn't rate limited or is due for
+    /// `key`, otherwise holds it back in place of any event still waiting.
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, key: &str, payload: S) {
+        let Some(interval) = self.rates.lock().unwrap().interval(event) else {
+            deliver(&self.app, event, key, payload);
+            return;
+        };
+        let payload = match serde_json::to_value(payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                eprintln!("Failed to serialize {} event: {}", event, e);
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        let mut topics = self.topics.lock().unwrap();
+        let topic_key = (event.to_string(), key.to_string());
+        match topics.get_mut(&topic_key) {
+            Some(topic) if now.duration_since(topic.last_sent) < interval => {
+                topic.interval = interval;
+                topic.pending = Some(payload);
+            }
+            _ => {
+                topics.insert(topic_key, Topic { interval, last_sent: now, pending: None });
+                drop(topics);
+                deliver(&self.app, event, key, payload);
+            }
+        }
+    }
+
+    /// Drops a held-back `event` for `key`, so a stale update can't arrive
+    /// after the event that ends it.
+    pub fn discard(&self, event: &str, key: &str) {
+        if let Some(topic) = self.topics.lock().unwrap().get_mut(&(event.to_string(), key.to_string())) {
+            topic.pending = None;
+        }
+    }
+
+    fn set_rates(&self, rates: EventRates) {
+        *self.rates.lock().unwrap() = rates;
+    }
+}
+
+/// Sends to the windows subscribed to `event` for `key`, or to every
+/// window when nobody has subscribed.
+fn deliver<S: Serialize + Clone>(app: &AppHandle, event: &str, key: &str, payload: S) {
+    match app.try_state::<Subscriptions>().and_then(|subscriptions| subscriptions.targets(event, key)) {
+        Some(labels) => {
+            for label in labels {
+                let _ = app.emit_to(label.as_str(), event, payload.clone());
+            }
+        }
+        None => {
+            let _ = app.emit(event, payload);
+        }
+    }
+}
+
+/// Emits through the app's `EventDispatcher`, or directly before it's set up.
+pub fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, key: &str, payload: S) {
+    match app.try_state::<EventDispatcher>() {
+        Some(dispatcher) => dispatcher.emit(event, key, payload),
+        None => deliver(app, event, key, payload),
+    }
+}
+
+/// See `EventDispatcher::discard`.
+pub fn discard(app: &AppHandle, event: &str, key: &str) {
+    if let Some(dispatcher) = app.try_state::<EventDispatcher>() {
+        dispatcher.discard(event, key);
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_event_rates(settings: State<SettingsStore>) -> EventRates {
+    settings.get().event_rates
+}
+
+/// Replaces the per-topic rate limits; topics left out are sent as fast as
+/// they come. The setting is saved and takes effect straight away.
+#[tauri::command]
+#[specta::specta]
+pub fn set_event_rates(
+    rates: EventRates,
+    dispatcher: State<EventDispatcher>,
+    settings: State<SettingsStore>,
+) -> Result<(), String> {
+    rates.validate()?;
+    settings.update(|s| s.event_rates = rates.clone())?;
+    dispatcher.set_rates(rates);
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::str::FromStr;
+use std::thread;
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState};
+
+use crate::clips::ClipCache;
+use crate::events;
+use crate::recording::{self, RecordOptions};
+use crate::settings::SettingsStore;
+use crate::{player, soundboard, AudioState};
+
+/// What a global hotkey does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum HotkeyAction {
+    /// Starts recording `stream_id`, or stops it when it is recording.
+    /// `file_path` may contain the usual placeholders, and a take goes to
+    /// memory without one.
+    ToggleRecording {
+        stream_id: String,
+        #[serde(default)]
+        file_path: Option<String>,
+        #[serde(default)]
+        preset: Option<String>,
+    },
+    /// Pauses or resumes the player.
+    TogglePlayback,
+    StopPlayback,
+    TriggerPad { number: u32 },
+    /// Silences every clip and pad.
+    StopAllClips,
+}
+
+/// A shortcut in the keymap, saved in the settings.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Hotkey {
+    /// Accelerator such as `"CommandOrControl+Shift+R"`, stored in the
+    /// plugin's normalized spelling.
+    pub shortcut: String,
+    #[serde(flatten)]
+    pub action: HotkeyAction,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "hotkey://failed")]
+pub(crate) struct HotkeyFailed {
+    shortcut: String,
+    error: String,
+}
+
+fn parse_shortcut(shortcut: &str) -> Result<Shortcut, String> {
+    Shortcut::from_str(shortcut).map_err(|e| format!("Invalid shortcut '{}': {}", shortcut, e))
+}
+
+fn run_action(app: &AppHandle, action: &HotkeyAction) -> Result<(), String> {
+    match action {
+        HotkeyAction::ToggleRecording { stream_id, file_path, preset } => {
+            let state = app.state::<AudioState>();
+            let recording = state.streams.lock().unwrap().get(stream_id)
+                .is_some_and(|stream| stream.recording.as_ref().is_some_and(|r| !r.is_finished()));
+            if recording {
+                let recording = recording::detach_recording(stream_id, &state)?;
+                // Finalizing can take a while; the outcome arrives as record://finished
+                thread::spawn(move || recording.finish());
+                return Ok(());
+            }
+
+            let options = RecordOptions {
+                preset: preset.clone(),
+                to_memory: file_path.is_none(),
+                ..RecordOptions::default()
+            };
+            recording::start_recording(stream_id.clone(), file_path.clone(), Some(options), app.clone(), state, app.state())
+                .map(|_| ())
+        }
+        HotkeyAction::TogglePlayback => player::toggle_pause(&app.state()).map(|_| ()),
+        HotkeyAction::StopPlayback => player::stop_playback(app.state()),
+        HotkeyAction::TriggerPad { number } => {
+            soundboard::trigger_pad(*number, app.state(), app.state(), app.state()).map(|_| ())
+        }
+        HotkeyAction::StopAllClips => {
+            app.state::<ClipCache>().stop(|_| true);
+            Ok(())
+        }
+    }
+}
+
+/// Handler for every registered shortcut. Failures are reported through
+/// `hotkey://failed`, since the app may not be in front to show them.
+pub fn handle_shortcut(app: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    if event.state != ShortcutState::Pressed {
+        return;
+    }
+    let Some(settings) = app.try_state::<SettingsStore>() else {
+        return;
+    };
+    let Some(hotkey) = settings.get().hotkeys.into_iter().find(|h| h.shortcut == shortcut.into_string()) else {
+        return;
+    };
+
+    if let Err(error) = run_action(app, &hotkey.action) {
+        let key = hotkey.shortcut.clone();
+        events::emit(app, "hotkey://failed", &key, HotkeyFailed { shortcut: hotkey.shortcut, error });
+    }
+}
+
+/// Registers the saved keymap at startup. A shortcut another application
+/// has taken in the meantime is skipped and reported on stderr.
+pub fn register_saved(app: &AppHandle, settings: &SettingsStore) {
+    for hotkey in settings.get().hotkeys {
+        if let Err(e) = app.global_shortcut().register(hotkey.shortcut.as_str()) {
+            eprintln!("Failed to register hotkey {}: {}", hotkey.shortcut, e);
+        }
+    }
+}
+
+/// Binds `shortcut` to `action` system-wide, so it works while the app is
+/// in the background, and saves it in the keymap. Fails without changing
+/// anything if the shortcut is already bound, whether in the keymap or by
+/// another application.
+#[tauri::command]
+#[specta::specta]
+pub fn set_hotkey(
+    shortcut: String,
+    action: HotkeyAction,
+    app: AppHandle,
+    settings: State<SettingsStore>,
+) -> Result<Hotkey, String> {
+    let normalized = parse_shortcut(&shortcut)?.into_string();
+    if let Some(existing) = settings.get().hotkeys.iter().find(|h| h.shortcut == normalized) {
+        return Err(format!("{} is already bound to {:?}", normalized, existing.action));
+    }
+
+    app.global_shortcut().register(normalized.as_str())
+        .map_err(|e| format!("{} is already in use by another application or the system: {}", normalized, e))?;
+
+    let hotkey = Hotkey { shortcut: normalized, action };
+    let saved = hotkey.clone();
+    if let Err(e) = settings.update(|s| s.hotkeys.push(saved)) {
+        let _ = app.global_shortcut().unregister(hotkey.shortcut.as_str());
+        return Err(e);
+    }
+    Ok(hotkey)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_hotkey(shortcut: String, app: AppHandle, settings: State<SettingsStore>) -> Result<(), String> {
+    let normalized = parse_shortcut(&shortcut)?.into_string();
+    if !settings.get().hotkeys.iter().any(|h| h.shortcut == normalized) {
+        return Err(format!("{} is not bound", normalized));
+    }
+
+    app.global_shortcut().unregister(normalized.as_str())
+        .map_err(|e| format!("Failed to unregister {}: {}", normalized, e))?;
+    settings.update(|s| s.hotkeys.retain(|h| h.shortcut != normalized))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_hotkeys(settings: State<SettingsStore>) -> Vec<Hotkey> {
+    settings.get().hotkeys
+}
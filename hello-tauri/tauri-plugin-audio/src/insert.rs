@@ -0,0 +1,121 @@
+use serde::Deserialize;
+use specta::Type;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::calculate_rms;
+use crate::dsp::dynamics::{CompressorSettings, Dynamics, DynamicsProcessor, GateSettings, Limiter, LimiterSettings};
+use crate::dsp::eq::{EqBand, Equalizer};
+use crate::plugins::{ChainPlugin, PluginHost, PluginInstance, PluginInstanceInfo};
+use crate::validate;
+use crate::AudioState;
+
+/// One step of an input chain, as configured by the frontend.
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InsertEffect {
+    Gate(GateSettings),
+    Eq { bands: Vec<EqBand> },
+    Compressor(CompressorSettings),
+    Limiter(LimiterSettings),
+    Plugin(ChainPlugin),
+}
+
+enum InsertProcessor {
+    /// A gate or compressor keyed by the signal itself.
+    Dynamics(Dynamics, DynamicsProcessor),
+    Eq(Equalizer),
+    Limiter(Limiter),
+    Plugin(Arc<Mutex<PluginInstance>>),
+}
+
+/// Effects run in order on a stream's input, after its gain and before
+/// metering and recording.
+pub struct InsertChain {
+    effects: Vec<InsertProcessor>,
+}
+
+impl InsertChain {
+    fn new(
+        effects: Vec<InsertEffect>,
+        sample_rate: u32,
+        channels: usize,
+        host: &PluginHost,
+    ) -> Result<Self, String> {
+        let effects = effects
+            .into_iter()
+            .map(|effect| {
+                Ok(match effect {
+                    InsertEffect::Gate(settings) => {
+                        InsertProcessor::Dynamics(Dynamics::Gate(settings), DynamicsProcessor::new(sample_rate))
+                    }
+                    InsertEffect::Compressor(settings) => {
+                        InsertProcessor::Dynamics(Dynamics::Compressor(settings), DynamicsProcessor::new(sample_rate))
+                    }
+                    InsertEffect::Eq { bands } => InsertProcessor::Eq(Equalizer::new(&bands, sample_rate, channels)?),
+                    InsertEffect::Limiter(settings) => InsertProcessor::Limiter(Limiter::new(&settings, sample_rate)),
+                    InsertEffect::Plugin(plugin) => InsertProcessor::Plugin(host.load_live(&plugin, sample_rate, channels)?),
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(InsertChain { effects })
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        for effect in &mut self.effects {
+            match effect {
+                InsertProcessor::Dynamics(dynamics, processor) => {
+                    let level = calculate_rms(samples);
+                    processor.process(samples, channels, dynamics, level);
+                }
+                InsertProcessor::Eq(eq) => eq.process(samples),
+                InsertProcessor::Limiter(limiter) => limiter.process(samples, channels),
+                InsertProcessor::Plugin(plugin) => plugin.lock().unwrap().process(samples, channels),
+            }
+        }
+    }
+
+    fn plugins(&self) -> Vec<PluginInstanceInfo> {
+        self.effects
+            .iter()
+            .filter_map(|effect| match effect {
+                InsertProcessor::Plugin(plugin) => Some(plugin.lock().unwrap().info()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Replaces the insert chain on a monitored stream; an empty chain removes
+/// it. Returns the loaded plugins in chain order, so their parameters can be
+/// changed afterwards.
+#[tauri::command]
+#[specta::specta]
+pub fn set_input_chain(
+    stream_id: String,
+    chain: Vec<InsertEffect>,
+    state: State<AudioState>,
+    host: State<PluginHost>,
+) -> Result<Vec<PluginInstanceInfo>, String> {
+    let (shared, sample_rate, channels) = {
+        let streams = state.streams.lock().unwrap();
+        let stream = streams
+            .get(&stream_id)
+            .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+        (Arc::clone(&stream.shared), stream.sample_rate, stream.channels as usize)
+    };
+
+    // Plugins can take a while to load, so the chain is built before the
+    // callback is made to wait for it
+    let chain = if chain.is_empty() {
+        None
+    } else {
+        Some(InsertChain::new(chain, sample_rate, channels, &host)?)
+    };
+    let plugins = chain.as_ref().map(InsertChain::plugins).unwrap_or_default();
+
+    // The old chain's plugins are torn down outside the lock
+    let previous = std::mem::replace(&mut *shared.insert_chain.lock().unwrap(), chain);
+    drop(previous);
+    Ok(plugins)
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use specta::Type;
+use tauri::State;
+
+use crate::backend::{self, Device};
+use crate::output::find_output_device;
+use crate::player::PlayerState;
+use crate::settings::SettingsStore;
+use crate::validate;
+
+/// Typical delay of a Bluetooth (A2DP) output: the codec buffers well
+/// beyond what the driver reports.
+const BLUETOOTH_LATENCY_MS: f64 = 200.0;
+
+/// Allowed range for a configured output offset, in ms.
+const OFFSET_RANGE_MS: (f64, f64) = (0.0, 1000.0);
+
+/// Name fragments of outputs that are usually Bluetooth, lowercase.
+/// Generic words like "headset" or "wireless" are left out, as wired USB
+/// headsets and 2.4 GHz dongles use them too.
+const BLUETOOTH_NAME_HINTS: [&str; 6] = [
+    "bluetooth",
+    "airpods",
+    "beats",
+    "buds",
+    "hands-free",
+    "a2dp",
+];
+
+/// Hands-free profile outputs run at telephone rates or below.
+const HANDS_FREE_MAX_RATE: u32 = 16_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencySource {
+    /// Set for the device with `set_output_latency`.
+    Configured,
+    /// Guessed from the device looking like Bluetooth.
+    Estimated,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct OutputLatency {
+    pub device_name: String,
+    pub bluetooth: bool,
+    /// How far behind rendering the device is heard, on top of the
+    /// driver's buffering.
+    pub offset_ms: f64,
+    pub source: LatencySource,
+}
+
+fn looks_bluetooth(device_name: &str, sample_rate: Option<u32>) -> bool {
+    let name = device_name.to_lowercase();
+    BLUETOOTH_NAME_HINTS.iter().any(|hint| name.contains(hint))
+        || sample_rate.is_some_and(|rate| rate <= HANDS_FREE_MAX_RATE)
+}
+
+/// The offset to apply for `device`: the configured one, or an estimate
+/// for devices that look like Bluetooth.
+pub fn output_latency(device: &Device, settings: &SettingsStore) -> OutputLatency {
+    let device_name = device.name().unwrap_or_default();
+    let sample_rate = device.default_output_config().ok().map(|config| config.sample_rate().0);
+    let bluetooth = looks_bluetooth(&device_name, sample_rate);
+
+    let (offset_ms, source) = match settings.get().output_latency_ms.get(&device_name) {
+        Some(&offset_ms) => (offset_ms, LatencySource::Configured),
+        None if bluetooth => (BLUETOOTH_LATENCY_MS, LatencySource::Estimated),
+        None => (0.0, LatencySource::None),
+    };
+    OutputLatency { device_name, bluetooth, offset_ms, source }
+}
+
+fn output_device(device_id: Option<&str>) -> Result<Device, String> {
+    match device_id {
+        Some(id) => find_output_device(id),
+        None => backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string()),
+    }
+}
+
+/// `device_id` defaults to the default output.
+#[tauri::command]
+#[specta::specta]
+pub fn get_output_latency(device_id: Option<String>, settings: State<SettingsStore>) -> Result<OutputLatency, String> {
+    Ok(output_latency(&output_device(device_id.as_deref())?, &settings))
+}
+
+/// Sets how late `device_id` (the default output when omitted) is heard,
+/// so playback positions line up with what is audible. Leaving out
+/// `offset_ms` goes back to the estimate. The player picks it up straight
+/// away when playing on that device.
+#[tauri::command]
+#[specta::specta]
+pub fn set_output_latency(
+    device_id: Option<String>,
+    offset_ms: Option<f64>,
+    player: State<PlayerState>,
+    settings: State<SettingsStore>,
+) -> Result<OutputLatency, String> {
+    if let Some(ms) = offset_ms {
+        validate::range("Latency offset", ms, OFFSET_RANGE_MS, "ms")?;
+    }
+
+    let device = output_device(device_id.as_deref())?;
+    let device_name = device.name().map_err(|e| format!("Failed to get device name: {}", e))?;
+    settings.update(|s| match offset_ms {
+        Some(ms) => {
+            s.output_latency_ms.insert(device_name.clone(), ms);
+        }
+        None => {
+            s.output_latency_ms.remove(&device_name);
+        }
+    })?;
+
+    let latency = output_latency(&device, &settings);
+    player.set_output_latency(&latency.device_name, latency.offset_ms);
+    Ok(latency)
+}
@@ -0,0 +1,669 @@
+mod analysis;
+mod audio_file;
+mod audio_session;
+mod backend;
+mod benchmark;
+mod bindings;
+mod clips;
+mod config;
+mod convert;
+mod convolution;
+mod cue;
+mod dsp;
+mod dual;
+mod edit;
+mod events;
+#[cfg(desktop)]
+mod hotkeys;
+mod insert;
+mod latency;
+mod lifecycle;
+mod markers;
+mod master;
+mod measure;
+mod metadata;
+mod mixer;
+mod naming;
+mod output;
+mod overdub;
+mod passthrough;
+mod permissions;
+mod playback;
+mod player;
+mod plugins;
+mod qc;
+mod recent;
+mod recording;
+mod recovery;
+mod render;
+mod scan;
+mod settings;
+mod soundboard;
+mod spectral;
+mod split;
+mod stream;
+mod subscriptions;
+mod sync;
+mod ticker;
+mod transcription;
+mod validate;
+mod waveform;
+
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tauri::plugin::{Builder, TauriPlugin};
+use tauri::{AppHandle, Manager, RunEvent, State, WindowEvent, Wry};
+use std::path::Path;
+
+use backend::Device;
+use config::{ConfigRequest, NegotiatedConfig};
+use dsp::dynamics::{DuckingSettings, Dynamics};
+use dsp::mix::Downmix;
+use settings::SettingsStore;
+use stream::{InputStream, Sidechain, StreamState, StreamStates, StreamStats, StreamStatus, XrunCounts};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+enum DeviceDirection {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+struct AudioDevice {
+    name: String,
+    /// User-assigned label from the settings file, if any.
+    alias: Option<String>,
+    id: String,
+    direction: DeviceDirection,
+    is_default: bool,
+    max_channels: u16,
+    default_sample_rate: Option<u32>,
+}
+
+#[derive(Default)]
+struct AudioState {
+    /// Running input streams keyed by stream id ("primary" / "secondary").
+    streams: Mutex<HashMap<String, InputStream>>,
+}
+
+fn stream_id(is_primary: bool) -> &'static str {
+    if is_primary {
+        "primary"
+    } else {
+        "secondary"
+    }
+}
+
+fn describe_device(
+    device: &Device,
+    index: usize,
+    direction: DeviceDirection,
+    default_name: Option<&str>,
+) -> Option<AudioDevice> {
+    let name = device.name().ok()?;
+
+    let (max_channels, default_config) = match direction {
+        DeviceDirection::Input => (
+            device.supported_input_configs().ok().and_then(|c| c.iter().map(|c| c.channels()).max()),
+            device.default_input_config().ok(),
+        ),
+        DeviceDirection::Output => (
+            device.supported_output_configs().ok().and_then(|c| c.iter().map(|c| c.channels()).max()),
+            device.default_output_config().ok(),
+        ),
+    };
+
+    let prefix = match direction {
+        DeviceDirection::Input => "input",
+        DeviceDirection::Output => "output",
+    };
+
+    Some(AudioDevice {
+        is_default: default_name == Some(name.as_str()),
+        alias: None,
+        name,
+        id: format!("{}_{}", prefix, index),
+        direction,
+        max_channels: max_channels.unwrap_or(0),
+        default_sample_rate: default_config.map(|c| c.sample_rate().0),
+    })
+}
+
+// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+#[tauri::command]
+#[specta::specta]
+fn get_audio_devices(settings: State<SettingsStore>) -> Result<Vec<AudioDevice>, String> {
+    let host = backend::host();
+
+    let mut devices = Vec::new();
+
+    // Get input devices
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    let input_devices = host.input_devices()
+        .map_err(|e| format!("Failed to enumerate input devices: {}", e))?;
+
+    for (index, device) in input_devices.iter().enumerate() {
+        devices.extend(describe_device(device, index, DeviceDirection::Input, default_input.as_deref()));
+    }
+
+    // Get output devices
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+    let output_devices = host.output_devices()
+        .map_err(|e| format!("Failed to enumerate output devices: {}", e))?;
+
+    for (index, device) in output_devices.iter().enumerate() {
+        devices.extend(describe_device(device, index, DeviceDirection::Output, default_output.as_deref()));
+    }
+
+    let aliases = settings.get().device_aliases;
+    for device in &mut devices {
+        device.alias = aliases.get(&device.name).cloned();
+    }
+
+    Ok(devices)
+}
+
+fn find_input_device(device_id: &str) -> Result<Device, String> {
+    let host = backend::host();
+
+    let device_index = validate::device_index(device_id, "input_")?;
+
+    host.input_devices()
+        .map_err(|e| format!("Failed to enumerate devices: {}", e))?
+        .into_iter()
+        .nth(device_index)
+        .ok_or_else(|| validate::device_not_found(device_id))
+}
+
+/// `channels` optionally restricts the stream to specific 1-based device inputs.
+/// `config` requests a sample rate/channel count/format; the closest supported
+/// config is used and returned. `low_latency`/`buffer_size` only shrink the
+/// driver buffer: cpal 0.15 opens WASAPI in shared mode, and exclusive mode
+/// is not available through it.
+#[tauri::command]
+#[specta::specta]
+fn start_monitoring(
+    device_id: String,
+    is_primary: bool,
+    channels: Option<Vec<u16>>,
+    config: Option<ConfigRequest>,
+    app: AppHandle,
+    state: State<AudioState>,
+    stream_states: State<StreamStates>,
+) -> Result<NegotiatedConfig, String> {
+    permissions::require_audio_permission()?;
+    let device = find_input_device(&device_id)?;
+
+    let id = stream_id(is_primary);
+    stream_states.set(&app, id, StreamState::Starting, None)?;
+    let stream = match InputStream::open(app.clone(), id, device, channels, config.unwrap_or_default()) {
+        Ok(stream) => stream,
+        Err(e) => {
+            stream_states.set(&app, id, StreamState::Error, Some(e.clone()))?;
+            return Err(e);
+        }
+    };
+    let negotiated = stream.config.clone();
+
+    // Replacing an existing entry drops it, which stops the previous stream
+    let mut streams = state.streams.lock().unwrap();
+    streams.insert(id.to_string(), stream);
+    update_audibility(&streams);
+    stream_states.set(&app, id, StreamState::Running, None)?;
+
+    Ok(negotiated)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn stop_monitoring(
+    is_primary: bool,
+    app: AppHandle,
+    state: State<AudioState>,
+    stream_states: State<StreamStates>,
+) -> Result<(), String> {
+    let id = stream_id(is_primary);
+    let stream = {
+        let mut streams = state.streams.lock().unwrap();
+        let stream = streams.remove(id);
+        update_audibility(&streams);
+        stream
+    };
+
+    // Also clears the error left by a stream that failed to start
+    if matches!(stream_states.get(id).state, StreamState::Running | StreamState::Paused | StreamState::Error) {
+        stream_states.set(&app, id, StreamState::Stopped, None)?;
+    }
+    if let Some(mut stream) = stream {
+        stream.finish_recording()?;
+    }
+    Ok(())
+}
+
+/// Stops every stream and player and finalizes anything still being
+/// written, so nothing is left half-written when the app exits.
+fn shutdown(app: &AppHandle) {
+    let report = |what: &str, result: Result<(), String>| {
+        if let Err(e) = result {
+            eprintln!("Failed to finish {} on exit: {}", what, e);
+        }
+    };
+    report("overdub", app.state::<overdub::OverdubState>().close());
+    report("dual recording", app.state::<dual::DualRecordingState>().close());
+    report("mix recording", app.state::<mixer::MixerState>().close());
+
+    let streams: Vec<_> = app.state::<AudioState>().streams.lock().unwrap().drain().collect();
+    let stream_states = app.state::<StreamStates>();
+    for (id, mut stream) in streams {
+        let _ = stream_states.set(app, &id, StreamState::Stopped, Some("Shutting down".to_string()));
+        report("recording", stream.finish_recording());
+    }
+    app.state::<player::PlayerState>().close();
+
+    if let Some(settings) = app.try_state::<SettingsStore>() {
+        report("saving settings", settings.flush());
+    }
+}
+
+/// Stops the device delivering audio without closing the stream, or
+/// carries on after a pause. Not possible while recording, which would
+/// otherwise leave a silent gap.
+#[tauri::command]
+#[specta::specta]
+fn pause_monitoring(
+    stream_id: String,
+    paused: bool,
+    app: AppHandle,
+    state: State<AudioState>,
+    stream_states: State<StreamStates>,
+) -> Result<StreamStatus, String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    let recording = stream.recording.as_ref().is_some_and(|r| !r.is_finished())
+        || stream.shared.record_tap.lock().unwrap().is_some();
+    if paused && recording {
+        return Err(format!("Stream '{}' is recording", stream_id));
+    }
+
+    let next = if paused { StreamState::Paused } else { StreamState::Running };
+    stream_states.set(&app, &stream_id, next, None)?;
+    stream.set_paused(paused)?;
+    Ok(stream_states.get(&stream_id))
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_stream_state(stream_id: String, stream_states: State<StreamStates>) -> StreamStatus {
+    stream_states.get(&stream_id)
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+struct StreamListing {
+    stream_id: String,
+    #[serde(flatten)]
+    status: StreamStatus,
+    /// Set while the stream is open.
+    device_name: Option<String>,
+    config: Option<NegotiatedConfig>,
+    recording: bool,
+}
+
+/// Every stream opened since launch, including stopped ones, so the UI can
+/// rebuild its view after a reload.
+#[tauri::command]
+#[specta::specta]
+fn list_streams(state: State<AudioState>, stream_states: State<StreamStates>) -> Vec<StreamListing> {
+    let mut tracked = stream_states.all();
+    tracked.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let streams = state.streams.lock().unwrap();
+    tracked
+        .into_iter()
+        .map(|(stream_id, status)| {
+            let stream = streams.get(&stream_id);
+            StreamListing {
+                device_name: stream.map(|s| s.device_name.clone()),
+                config: stream.map(|s| s.config.clone()),
+                recording: stream.is_some_and(|s| s.recording.as_ref().is_some_and(|r| !r.is_finished())),
+                stream_id,
+                status,
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_volume(is_primary: bool, state: State<AudioState>) -> Result<f32, String> {
+    let vol = match state.streams.lock().unwrap().get(stream_id(is_primary)) {
+        Some(stream) => *stream.shared.volume.lock().unwrap(),
+        None => 0.0,
+    };
+
+    // Convert to percentage (0-100) and apply some scaling
+    let percentage = (vol * 100.0).min(100.0);
+    Ok(percentage)
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+struct StreamInfo {
+    stream_id: String,
+    config: NegotiatedConfig,
+    input_channels: Vec<u16>,
+    gain_db: f32,
+    muted: bool,
+    soloed: bool,
+    /// False when muted or silenced by another stream's solo.
+    audible: bool,
+    recording: bool,
+    /// Length of the recording to memory waiting to be saved, if any.
+    unsaved_take_ms: Option<f64>,
+    xruns: XrunCounts,
+    device_lost: bool,
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_stream_info(stream_id: String, state: State<AudioState>) -> Result<StreamInfo, String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    let info = StreamInfo {
+        config: stream.config.clone(),
+        input_channels: stream.input_channels.clone(),
+        gain_db: *stream.shared.gain_db.lock().unwrap(),
+        muted: *stream.shared.muted.lock().unwrap(),
+        soloed: *stream.shared.soloed.lock().unwrap(),
+        audible: *stream.shared.audible.lock().unwrap(),
+        recording: stream.recording.as_ref().is_some_and(|r| !r.is_finished()),
+        unsaved_take_ms: stream.shared.scratch_take.lock().unwrap().as_ref()
+            .map(|take| take.frames() as f64 * 1000.0 / take.sample_rate as f64),
+        xruns: *stream.shared.xruns.lock().unwrap(),
+        device_lost: *stream.shared.device_lost.lock().unwrap(),
+        stream_id,
+    };
+    Ok(info)
+}
+
+#[tauri::command]
+#[specta::specta]
+fn get_stream_stats(stream_id: String, state: State<AudioState>) -> Result<StreamStats, String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    Ok(stream.stats())
+}
+
+/// Enables or disables automatic reopening of a stream that stops
+/// delivering audio. Stalls are reported as events either way.
+#[tauri::command]
+#[specta::specta]
+fn set_stream_auto_restart(stream_id: String, enabled: bool, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    *stream.shared.auto_restart.lock().unwrap() = enabled;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn set_input_gain(stream_id: String, gain_db: f32, state: State<AudioState>) -> Result<(), String> {
+    validate::gain_db(gain_db)?;
+
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    *stream.shared.gain_db.lock().unwrap() = gain_db;
+    Ok(())
+}
+
+/// Inserts an L/R to M/S conversion on the first two channels of a stream,
+/// so meters, analyzers and recordings see mid and side instead.
+#[tauri::command]
+#[specta::specta]
+fn set_mid_side(stream_id: String, enabled: bool, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    if stream.channels < 2 {
+        return Err(format!("Stream '{}' is not stereo", stream_id));
+    }
+
+    *stream.shared.mid_side.lock().unwrap() = enabled;
+    Ok(())
+}
+
+/// Applies mute and solo across all streams: with any stream soloed, only
+/// soloed streams that aren't muted are heard.
+fn update_audibility(streams: &HashMap<String, InputStream>) {
+    let any_solo = streams.values().any(|s| *s.shared.soloed.lock().unwrap());
+
+    for stream in streams.values() {
+        let muted = *stream.shared.muted.lock().unwrap();
+        let soloed = *stream.shared.soloed.lock().unwrap();
+        *stream.shared.audible.lock().unwrap() = !muted && (soloed || !any_solo);
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+fn set_stream_mute(stream_id: String, muted: bool, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    *stream.shared.muted.lock().unwrap() = muted;
+    update_audibility(&streams);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+fn set_stream_solo(stream_id: String, soloed: bool, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    *stream.shared.soloed.lock().unwrap() = soloed;
+    update_audibility(&streams);
+    Ok(())
+}
+
+/// Puts a gate, compressor or ducker on `stream_id`, keyed by its own
+/// level or, with `key_stream_id`, by another monitored stream (sidechain).
+/// Passing no dynamics removes it.
+#[tauri::command]
+#[specta::specta]
+fn set_dynamics(
+    stream_id: String,
+    key_stream_id: Option<String>,
+    dynamics: Option<Dynamics>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    if key_stream_id.as_ref() == Some(&stream_id) {
+        return Err("A stream cannot be its own sidechain key".to_string());
+    }
+
+    let streams = state.streams.lock().unwrap();
+    let find = |id: &String| streams
+        .get(id)
+        .ok_or_else(|| validate::stream_not_monitored(id));
+    let stream = find(&stream_id)?;
+    let key = key_stream_id.as_ref().map(find).transpose()?;
+
+    *stream.shared.dynamics.lock().unwrap() = dynamics.map(|dynamics| Sidechain {
+        key: key.map(|key| Arc::downgrade(&key.shared)),
+        dynamics,
+    });
+    Ok(())
+}
+
+/// Ducks `target_stream_id` (the secondary source by default) whenever
+/// `key_stream_id` (the primary) is above the threshold. Passing no
+/// settings turns ducking off.
+#[tauri::command]
+#[specta::specta]
+fn set_ducking(
+    target_stream_id: Option<String>,
+    key_stream_id: Option<String>,
+    settings: Option<DuckingSettings>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    let target_stream_id = target_stream_id.unwrap_or_else(|| stream_id(false).to_string());
+    let key_stream_id = key_stream_id.unwrap_or_else(|| stream_id(true).to_string());
+    set_dynamics(target_stream_id, Some(key_stream_id), settings.map(Dynamics::Duck), state)
+}
+
+fn calculate_rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+
+    let sum_of_squares: f32 = samples.iter().map(|&s| s * s).sum();
+    (sum_of_squares / samples.len() as f32).sqrt()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+struct WavData {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    duration_ms: f32,
+    /// Channel count of the source file; `samples` is always a single channel.
+    channels: u16,
+}
+
+/// Returns one zero-based channel of `audio`, or all channels folded to
+/// mono through `downmix` (a plain average by default) when `channel` is
+/// `None`.
+fn to_wav_data(audio: audio_file::AudioBuffer, channel: Option<u16>, downmix: Option<Downmix>) -> Result<WavData, String> {
+    let samples = match channel {
+        Some(channel) if channel >= audio.channels => {
+            return Err(format!("Channel {} is out of range for a {}-channel file", channel, audio.channels))
+        }
+        Some(channel) => audio.channel(channel as usize),
+        None if audio.channels == 1 && downmix.is_none() => audio.samples.clone(),
+        None => {
+            let mixed = audio.downmix(&downmix.unwrap_or_default())?;
+            if mixed.channels != 1 {
+                return Err("Downmix for reading must produce a single channel".to_string());
+            }
+            mixed.samples
+        }
+    };
+
+    Ok(WavData {
+        duration_ms: (samples.len() as f32 / audio.sample_rate as f32) * 1000.0,
+        samples,
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+fn read_wav_file(file_path: String, channel: Option<u16>, downmix: Option<Downmix>) -> Result<WavData, String> {
+    let audio = audio_file::read_audio_file(Path::new(&file_path))?;
+    to_wav_data(audio, channel, downmix)
+}
+
+/// Loads a headerless PCM capture using caller-supplied parameters.
+#[tauri::command]
+#[specta::specta]
+fn read_raw_pcm(
+    file_path: String,
+    sample_rate: u32,
+    channels: u16,
+    format: audio_file::RawSampleFormat,
+    endianness: Option<audio_file::Endianness>,
+    offset: Option<usize>,
+) -> Result<WavData, String> {
+    let audio = audio_file::read_raw(
+        Path::new(&file_path),
+        sample_rate,
+        channels,
+        format,
+        endianness.unwrap_or_default(),
+        offset.unwrap_or(0),
+    )?;
+    to_wav_data(audio, None, None)
+}
+
+/// Writes the TypeScript bindings for the plugin's commands and events
+/// into `dir`.
+pub fn export_bindings(dir: &Path) -> Result<(), String> {
+    bindings::export(dir)
+}
+
+/// The audio plugin: devices, streams, recording, playback and analysis,
+/// with their state and events. Apps register it with
+/// `.plugin(tauri_plugin_audio::init())` and call its commands as
+/// `plugin:audio|<command>`.
+pub fn init() -> TauriPlugin<Wry> {
+    let commands = bindings::commands();
+    Builder::new("audio")
+        .invoke_handler(commands.invoke_handler())
+        .setup(|app, _api| {
+            // Shared with the clip cache, whose output runs through it too
+            let master = master::MasterChain::default();
+            app.manage(AudioState::default());
+            app.manage(overdub::OverdubState::default());
+            app.manage(mixer::MixerState::default());
+            app.manage(dual::DualRecordingState::default());
+            app.manage(player::PlayerState::default());
+            app.manage(clips::ClipCache::new(master.clone()));
+            app.manage(master);
+            app.manage(soundboard::SoundboardState::default());
+            app.manage(plugins::PluginHost::default());
+            app.manage(transcription::Transcriber::default());
+            app.manage(lifecycle::Lifecycle::default());
+            app.manage(StreamStates::default());
+            app.manage(subscriptions::Subscriptions::default());
+
+            let config_dir = app.path().app_config_dir()?;
+            let settings = SettingsStore::load(config_dir.join("settings.json"));
+            #[cfg(desktop)]
+            {
+                app.plugin(
+                    tauri_plugin_global_shortcut::Builder::new()
+                        .with_handler(hotkeys::handle_shortcut)
+                        .build(),
+                )?;
+                hotkeys::register_saved(app, &settings);
+            }
+            if let Err(e) = audio_session::configure() {
+                eprintln!("{}", e);
+            }
+            app.state::<clips::ClipCache>().set_fades(settings.get().playback_fades);
+            app.manage(events::EventDispatcher::new(app.clone(), settings.get().event_rates.sanitized()));
+            app.manage(analysis::SpectrumFeed::start(app.clone(), settings.get().metering));
+            app.manage(settings);
+            Ok(())
+        })
+        .on_event(|app, event| match event {
+            RunEvent::WindowEvent { label, event: WindowEvent::Destroyed, .. } => {
+                subscriptions::window_closed(app, label);
+            }
+            RunEvent::ExitRequested { .. } => shutdown(app),
+            _ => {}
+        })
+        .build()
+}
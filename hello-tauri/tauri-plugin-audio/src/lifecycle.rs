@@ -0,0 +1,170 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+
+use crate::events;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Stream,
+    Recording,
+    Playback,
+    /// Long running commands such as transcriptions and conversions.
+    Operation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleState {
+    Starting,
+    Running,
+    Paused,
+    /// A problem that may still clear up, like a stalled or lost device.
+    Error,
+    /// Playback reached the end of the file; seeking back resumes it.
+    Ended,
+    Stopped,
+    Completed,
+    Failed,
+}
+
+impl LifecycleState {
+    /// Nothing follows these, so they aren't kept once reported.
+    fn is_terminal(self) -> bool {
+        matches!(self, LifecycleState::Stopped | LifecycleState::Completed | LifecycleState::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "lifecycle://state-changed")]
+pub(crate) struct StateChanged {
+    subsystem: Subsystem,
+    id: String,
+    /// None when the subsystem wasn't active before.
+    old_state: Option<LifecycleState>,
+    new_state: LifecycleState,
+    reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LifecycleEntry {
+    subsystem: Subsystem,
+    id: String,
+    state: LifecycleState,
+}
+
+/// Current state of everything that is active, reported to the webview as
+/// `lifecycle://state-changed` whenever it moves.
+#[derive(Default)]
+pub struct Lifecycle {
+    states: Mutex<HashMap<(Subsystem, String), LifecycleState>>,
+}
+
+struct Change {
+    subsystem: Subsystem,
+    state: LifecycleState,
+    reason: Option<String>,
+    /// Only move something already being tracked.
+    tracked_only: bool,
+}
+
+impl Lifecycle {
+    fn apply(&self, app: &AppHandle, id: &str, change: Change) {
+        let Change { subsystem, state, reason, tracked_only } = change;
+        let key = (subsystem, id.to_string());
+        let old_state = {
+            let mut states = self.states.lock().unwrap();
+            if tracked_only && !states.contains_key(&key) {
+                return;
+            }
+            let old_state = if state.is_terminal() {
+                states.remove(&key)
+            } else {
+                states.insert(key, state)
+            };
+            if old_state == Some(state) {
+                return;
+            }
+            old_state
+        };
+
+        events::emit(app, "lifecycle://state-changed", id, StateChanged {
+            subsystem,
+            id: id.to_string(),
+            old_state,
+            new_state: state,
+            reason,
+        });
+    }
+}
+
+/// Moves `id` of `subsystem` to `state`, emitting the change. Moving to the
+/// state it is already in does nothing.
+pub fn transition(app: &AppHandle, subsystem: Subsystem, id: &str, state: LifecycleState, reason: Option<String>) {
+    if let Some(lifecycle) = app.try_state::<Lifecycle>() {
+        lifecycle.apply(app, id, Change { subsystem, state, reason, tracked_only: false });
+    }
+}
+
+/// Like `transition`, but ignored unless `id` is already active. For
+/// reports from threads that can outlive what they belong to, or that also
+/// run for internal uses which aren't tracked.
+pub fn update(app: &AppHandle, subsystem: Subsystem, id: &str, state: LifecycleState, reason: Option<String>) {
+    if let Some(lifecycle) = app.try_state::<Lifecycle>() {
+        lifecycle.apply(app, id, Change { subsystem, state, reason, tracked_only: true });
+    }
+}
+
+/// A long running command, reported as running until `finish` gives its
+/// result. Its id is `kind:target`, e.g. `transcription:C:\take.wav`.
+pub struct Operation {
+    app: AppHandle,
+    id: String,
+    finished: bool,
+}
+
+impl Operation {
+    pub fn start(app: &AppHandle, kind: &str, target: &str) -> Self {
+        let id = format!("{}:{}", kind, target);
+        transition(app, Subsystem::Operation, &id, LifecycleState::Running, None);
+        Operation { app: app.clone(), id, finished: false }
+    }
+
+    /// Reports the operation completed, or failed with the error as reason,
+    /// and passes `result` through.
+    pub fn finish<T>(mut self, result: Result<T, String>) -> Result<T, String> {
+        let (state, reason) = match &result {
+            Ok(_) => (LifecycleState::Completed, None),
+            Err(e) => (LifecycleState::Failed, Some(e.clone())),
+        };
+        transition(&self.app, Subsystem::Operation, &self.id, state, reason);
+        self.finished = true;
+        result
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if !self.finished {
+            let reason = Some("Operation was interrupted".to_string());
+            transition(&self.app, Subsystem::Operation, &self.id, LifecycleState::Failed, reason);
+        }
+    }
+}
+
+/// Everything currently active, for seeding the UI's state after a reload.
+#[tauri::command]
+#[specta::specta]
+pub fn get_lifecycle_states(lifecycle: State<Lifecycle>) -> Vec<LifecycleEntry> {
+    lifecycle
+        .states
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|((subsystem, id), state)| LifecycleEntry { subsystem: *subsystem, id: id.clone(), state: *state })
+        .collect()
+}
@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A named point in a file, such as a chapter start.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Marker {
+    pub name: String,
+    pub position_ms: f64,
+}
+
+/// A named span of a file, such as a track or take.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Region {
+    pub name: String,
+    pub start_ms: f64,
+    pub end_ms: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct MarkerSet {
+    pub markers: Vec<Marker>,
+    pub regions: Vec<Region>,
+}
+
+/// Markers live next to the audio as `<file>.markers.json` so they travel
+/// with it when the folder is moved.
+fn sidecar_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.as_os_str().to_owned();
+    name.push(".markers.json");
+    PathBuf::from(name)
+}
+
+pub fn load_markers(file_path: &Path) -> Result<MarkerSet, String> {
+    match fs::read_to_string(sidecar_path(file_path)) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse markers: {}", e)),
+        Err(_) => Ok(MarkerSet::default()),
+    }
+}
+
+pub fn save_markers(file_path: &Path, markers: &MarkerSet) -> Result<(), String> {
+    let contents = serde_json::to_string_pretty(markers)
+        .map_err(|e| format!("Failed to serialize markers: {}", e))?;
+    fs::write(sidecar_path(file_path), contents)
+        .map_err(|e| format!("Failed to write markers: {}", e))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_markers(file_path: String) -> Result<MarkerSet, String> {
+    load_markers(Path::new(&file_path))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_markers(file_path: String, markers: MarkerSet) -> Result<(), String> {
+    for region in &markers.regions {
+        if region.end_ms <= region.start_ms {
+            return Err(format!("Region '{}' ends before it starts", region.name));
+        }
+    }
+
+    save_markers(Path::new(&file_path), &markers)
+}
@@ -0,0 +1,134 @@
+use serde::Deserialize;
+use specta::Type;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+use crate::dsp::dynamics::{Limiter, LimiterSettings};
+use crate::dsp::eq::{EqBand, Equalizer};
+use crate::dsp::gain::db_to_linear;
+use crate::dsp::stereo::{WidthProcessor, MAX_STEREO_WIDTH};
+use crate::validate;
+
+/// EQ bands are checked against this rate when the chain is set, as the
+/// lowest rate outputs commonly run at.
+const VALIDATION_RATE: u32 = 44_100;
+
+/// One step of the master chain.
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MasterEffect {
+    Eq { bands: Vec<EqBand> },
+    Limiter(LimiterSettings),
+    /// Fixed gain, e.g. to bring playback to a listening loudness.
+    Trim { gain_db: f32 },
+    /// Stereo width of the first two channels: 0.0 is mono, 1.0 unchanged,
+    /// up to 2.0 wider.
+    Width { width: f32 },
+}
+
+#[derive(Default)]
+struct MasterSettings {
+    /// Bumped on every change so running outputs rebuild their processors.
+    generation: u64,
+    effects: Vec<MasterEffect>,
+}
+
+/// Listening corrections run on everything the playback, mixer and clip
+/// outputs send to their devices. Files and mixdowns never go through it.
+#[derive(Clone, Default)]
+pub struct MasterChain {
+    settings: Arc<Mutex<MasterSettings>>,
+}
+
+impl MasterChain {
+    /// A processor for one output, following changes to the chain.
+    pub fn processor(&self, sample_rate: u32, channels: usize) -> MasterProcessor {
+        MasterProcessor {
+            chain: self.clone(),
+            sample_rate,
+            channels,
+            generation: 0,
+            effects: Vec::new(),
+        }
+    }
+}
+
+enum MasterStage {
+    Eq(Equalizer),
+    Limiter(Limiter),
+    Trim(f32),
+    Width(WidthProcessor),
+}
+
+/// The master chain as set up for one output's rate and channels.
+pub struct MasterProcessor {
+    chain: MasterChain,
+    sample_rate: u32,
+    channels: usize,
+    generation: u64,
+    effects: Vec<MasterStage>,
+}
+
+impl MasterProcessor {
+    fn rebuild(&mut self, effects: &[MasterEffect]) {
+        self.effects = effects
+            .iter()
+            .filter_map(|effect| match effect {
+                MasterEffect::Eq { bands } => match Equalizer::new(bands, self.sample_rate, self.channels) {
+                    Ok(eq) => Some(MasterStage::Eq(eq)),
+                    Err(e) => {
+                        eprintln!("Master EQ skipped at {} Hz: {}", self.sample_rate, e);
+                        None
+                    }
+                },
+                MasterEffect::Limiter(settings) => Some(MasterStage::Limiter(Limiter::new(settings, self.sample_rate))),
+                MasterEffect::Trim { gain_db } => Some(MasterStage::Trim(db_to_linear(*gain_db))),
+                MasterEffect::Width { width } => Some(MasterStage::Width(WidthProcessor::new(self.sample_rate, *width))),
+            })
+            .collect();
+    }
+
+    /// Runs interleaved output `samples` through the chain in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        {
+            let shared = Arc::clone(&self.chain.settings);
+            let settings = shared.lock().unwrap();
+            if settings.generation != self.generation {
+                self.generation = settings.generation;
+                self.rebuild(&settings.effects);
+            }
+        }
+
+        for effect in &mut self.effects {
+            match effect {
+                MasterStage::Eq(eq) => eq.process(samples),
+                MasterStage::Limiter(limiter) => limiter.process(samples, self.channels),
+                MasterStage::Trim(gain) => samples.iter_mut().for_each(|s| *s *= *gain),
+                MasterStage::Width(width) => width.process(samples, self.channels),
+            }
+        }
+    }
+}
+
+/// Replaces the master chain; outputs already playing pick it up at their
+/// next buffer. An empty chain turns it off.
+#[tauri::command]
+#[specta::specta]
+pub fn set_master_chain(chain: Vec<MasterEffect>, master: State<MasterChain>) -> Result<(), String> {
+    for effect in &chain {
+        match effect {
+            MasterEffect::Eq { bands } => {
+                Equalizer::new(bands, VALIDATION_RATE, 1)?;
+            }
+            MasterEffect::Width { width } => {
+                validate::range("Width", *width, (0.0, MAX_STEREO_WIDTH), "")?;
+            }
+            _ => {}
+        }
+    }
+
+    let mut settings = master.settings.lock().unwrap();
+    settings.generation += 1;
+    settings.effects = chain;
+    Ok(())
+}
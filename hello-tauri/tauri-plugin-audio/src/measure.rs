@@ -0,0 +1,546 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::audio_file::{read_audio_file, write_audio_file, AudioBuffer, WavEncoding};
+use crate::config::{negotiate_output_config, ConfigRequest};
+use crate::dsp::bands::{smoothed_levels, BandResolution};
+use crate::dsp::decay::{decay_times, DecayTimes};
+use crate::dsp::eq::{EqBand, EqBandKind, Equalizer};
+use crate::dsp::fft::{amplitude_to_db, transfer_magnitude};
+use crate::dsp::gain::db_to_linear;
+use crate::dsp::sweep::Sweep;
+use crate::events;
+use crate::find_input_device;
+use crate::output::{build_output_stream, find_output_device};
+use crate::stream::{InputStream, RecordTap};
+
+/// How long the input may go quiet before a capture is given up.
+const INPUT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Kept ahead of the direct sound when the latency is trimmed off.
+const PRE_DELAY_SECONDS: f64 = 0.001;
+
+/// Width of the frequency response smoothing, as a fraction of an octave.
+const RESPONSE_SMOOTHING: f32 = 12.0;
+
+/// Frequency response points per octave.
+const RESPONSE_POINTS_PER_OCTAVE: f32 = 24.0;
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct SweepSettings {
+    pub start_hz: f64,
+    pub end_hz: f64,
+    pub duration_s: f64,
+    pub level_db: f32,
+    /// Recorded after the sweep ends, and the length of the saved IR.
+    pub tail_s: f64,
+    /// 1-based input channel the response is recorded from.
+    pub input_channel: u16,
+    /// Starts the IR just before the direct sound instead of including
+    /// the interfaces' round-trip latency.
+    pub trim_latency: bool,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        SweepSettings {
+            start_hz: 20.0,
+            end_hz: 20_000.0,
+            duration_s: 5.0,
+            level_db: -12.0,
+            tail_s: 2.0,
+            input_channel: 1,
+            trim_latency: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ImpulseResponseCapture {
+    pub path: String,
+    pub sample_rate: u32,
+    pub length_ms: f64,
+    /// Arrival time of the direct sound after the sweep started playing.
+    pub latency_ms: f64,
+    pub peak_db: f32,
+}
+
+/// A test signal playing on an output device. Dropping it stops the
+/// stream.
+struct SignalPlayer {
+    /// When the first buffer was rendered.
+    started_at: Arc<Mutex<Option<Instant>>>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl Drop for SignalPlayer {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+    }
+}
+
+/// Plays `samples` (mono) on every channel of `output_id`, then silence.
+fn play_signal(output_id: &str, sample_rate: u32, samples: Vec<f32>) -> Result<SignalPlayer, String> {
+    let device = find_output_device(output_id)?;
+    let request = ConfigRequest {
+        sample_rate: Some(sample_rate),
+        ..ConfigRequest::default()
+    };
+    let (config, negotiated) = negotiate_output_config(&device, &request)?;
+    if negotiated.sample_rate != sample_rate {
+        return Err(format!("Output device does not support {} Hz", sample_rate));
+    }
+    let sample_format = config.sample_format();
+    let config = negotiated.stream_config();
+    let channels = config.channels as usize;
+
+    let started_at = Arc::new(Mutex::new(None));
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+    let render_started = Arc::clone(&started_at);
+    thread::spawn(move || {
+        let mut position = 0;
+        let render = move |out: &mut [f32]| {
+            render_started.lock().unwrap().get_or_insert_with(Instant::now);
+            for frame in out.chunks_exact_mut(channels) {
+                frame.fill(samples.get(position).copied().unwrap_or(0.0));
+                position += 1;
+            }
+        };
+
+        let stream = match build_output_stream(&device, &config, sample_format, render) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+
+        if let Err(e) = stream.play() {
+            let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+            return;
+        }
+
+        let _ = ready_tx.send(Ok(()));
+        let _ = stop_rx.recv();
+    });
+
+    ready_rx.recv()
+        .map_err(|_| "Signal output thread exited unexpectedly".to_string())??;
+    Ok(SignalPlayer { started_at, stop_tx })
+}
+
+/// Collects the next `frames` of mono input.
+fn record_input(rx: &mpsc::Receiver<Vec<f32>>, frames: usize) -> Result<Vec<f32>, String> {
+    let mut recorded = Vec::with_capacity(frames);
+    while recorded.len() < frames {
+        match rx.recv_timeout(INPUT_TIMEOUT) {
+            Ok(samples) => recorded.extend_from_slice(&samples),
+            Err(RecvTimeoutError::Timeout) => return Err("Input stopped delivering audio".to_string()),
+            Err(RecvTimeoutError::Disconnected) => return Err("Input closed during the capture".to_string()),
+        }
+    }
+    recorded.truncate(frames);
+    Ok(recorded)
+}
+
+/// Collects `frames` of mono input captured from the moment playback
+/// started.
+fn record_response(
+    rx: &mpsc::Receiver<Vec<f32>>,
+    started_at: &Mutex<Option<Instant>>,
+    sample_rate: u32,
+    frames: usize,
+) -> Result<Vec<f32>, String> {
+    let mut recorded = Vec::with_capacity(frames);
+    while recorded.len() < frames {
+        let samples = match rx.recv_timeout(INPUT_TIMEOUT) {
+            Ok(samples) => samples,
+            Err(RecvTimeoutError::Timeout) => return Err("Input stopped delivering audio".to_string()),
+            Err(RecvTimeoutError::Disconnected) => return Err("Input closed during the capture".to_string()),
+        };
+        let Some(started_at) = *started_at.lock().unwrap() else {
+            continue;
+        };
+
+        if recorded.is_empty() {
+            // Like an overdub take: skip what came in before the first
+            // output callback
+            let late = (started_at.elapsed().as_secs_f64() * sample_rate as f64) as usize;
+            let lead = samples.len().saturating_sub(late);
+            recorded.extend_from_slice(&samples[lead..]);
+        } else {
+            recorded.extend_from_slice(&samples);
+        }
+    }
+    recorded.truncate(frames);
+    Ok(recorded)
+}
+
+/// An impulse response measured with a sweep, untrimmed.
+struct MeasuredResponse {
+    ir: Vec<f32>,
+    sample_rate: u32,
+    /// Sample of the direct sound, the IR's peak.
+    arrival: usize,
+    peak: f32,
+}
+
+impl MeasuredResponse {
+    /// The IR from just before the direct sound.
+    fn trimmed(&self) -> &[f32] {
+        let pre_delay = (PRE_DELAY_SECONDS * self.sample_rate as f64) as usize;
+        &self.ir[self.arrival.saturating_sub(pre_delay)..]
+    }
+}
+
+/// Plays a sweep on `output_id` and deconvolves what `input_id` picks up.
+fn measure_response(
+    app: AppHandle,
+    output_id: &str,
+    input_id: &str,
+    settings: &SweepSettings,
+) -> Result<MeasuredResponse, String> {
+    let request = ConfigRequest::default();
+    let input = InputStream::open(app, "impulse", find_input_device(input_id)?, Some(vec![settings.input_channel]), request)?;
+    let sample_rate = input.sample_rate;
+
+    let sweep = Sweep::exponential(
+        settings.start_hz,
+        settings.end_hz,
+        settings.duration_s,
+        sample_rate,
+        db_to_linear(settings.level_db.min(0.0)),
+    )?;
+    let tail = (settings.tail_s.max(0.0) * sample_rate as f64) as usize;
+    let frames = sweep.samples.len() + tail;
+
+    let (tx, rx) = mpsc::channel();
+    *input.shared.record_tap.lock().unwrap() = Some(RecordTap { file_path: None, tx });
+    let player = play_signal(output_id, sample_rate, sweep.samples.clone())?;
+    let recorded = record_response(&rx, &player.started_at, sample_rate, frames)?;
+    // Free both devices before the slow part
+    drop(input);
+    drop(player);
+
+    let ir = sweep.deconvolve(&recorded, tail.max(1));
+    let (arrival, peak) = ir
+        .iter()
+        .enumerate()
+        .fold((0, 0.0f32), |(best, peak), (i, s)| if s.abs() > peak { (i, s.abs()) } else { (best, peak) });
+    Ok(MeasuredResponse { ir, sample_rate, arrival, peak })
+}
+
+/// Measures the impulse response from `output_id` to `input_id`: plays an
+/// exponential sweep, records what comes back, deconvolves it and saves
+/// the result to `path` as a mono 32-bit float WAV, ready to load into the
+/// convolution reverb.
+#[tauri::command]
+#[specta::specta]
+pub async fn capture_impulse_response(
+    output_id: String,
+    input_id: String,
+    path: String,
+    settings: Option<SweepSettings>,
+    app: AppHandle,
+) -> Result<ImpulseResponseCapture, String> {
+    let settings = settings.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = measure_response(app, &output_id, &input_id, &settings)?;
+        let sample_rate = response.sample_rate;
+        let samples = if settings.trim_latency { response.trimmed() } else { &response.ir[..] };
+
+        let audio = AudioBuffer {
+            samples: samples.to_vec(),
+            sample_rate,
+            channels: 1,
+        };
+        write_audio_file(Path::new(&path), &audio, WavEncoding::Float32)?;
+
+        Ok(ImpulseResponseCapture {
+            path,
+            sample_rate,
+            length_ms: audio.frames() as f64 * 1000.0 / sample_rate as f64,
+            latency_ms: response.arrival as f64 * 1000.0 / sample_rate as f64,
+            peak_db: amplitude_to_db(response.peak),
+        })
+    })
+    .await
+    .map_err(|e| format!("Impulse response capture failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ResponsePoint {
+    pub frequency_hz: f32,
+    pub magnitude_db: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct FrequencyResponse {
+    pub sample_rate: u32,
+    /// Log-spaced across the sweep range, 1/12 octave smoothed. 0 dB is the
+    /// level the sweep was sent at.
+    pub points: Vec<ResponsePoint>,
+    pub latency_ms: f64,
+}
+
+/// Measures the magnitude response from `output_id` to `input_id` with a
+/// sweep, for checking speakers and rooms. The room's reverb is part of
+/// the result, so it is less smooth than an anechoic speaker measurement.
+#[tauri::command]
+#[specta::specta]
+pub async fn measure_frequency_response(
+    output_id: String,
+    input_id: String,
+    settings: Option<SweepSettings>,
+    app: AppHandle,
+) -> Result<FrequencyResponse, String> {
+    let settings = settings.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = measure_response(app, &output_id, &input_id, &settings)?;
+        let spectrum = transfer_magnitude(response.trimmed(), response.sample_rate);
+
+        let octaves = (settings.end_hz / settings.start_hz).log2() as f32;
+        let count = (octaves * RESPONSE_POINTS_PER_OCTAVE).ceil().max(1.0) as usize;
+        let frequencies: Vec<f32> = (0..=count)
+            .map(|i| settings.start_hz as f32 * 2f32.powf(i as f32 / RESPONSE_POINTS_PER_OCTAVE).min(2f32.powf(octaves)))
+            .collect();
+        let levels = smoothed_levels(&spectrum, RESPONSE_SMOOTHING, &frequencies);
+
+        Ok(FrequencyResponse {
+            sample_rate: response.sample_rate,
+            points: frequencies
+                .into_iter()
+                .zip(levels)
+                .map(|(frequency_hz, magnitude_db)| ResponsePoint { frequency_hz, magnitude_db })
+                .collect(),
+            latency_ms: response.arrival as f64 * 1000.0 / response.sample_rate as f64,
+        })
+    })
+    .await
+    .map_err(|e| format!("Frequency response measurement failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct BandDecay {
+    /// `None` for the unfiltered signal.
+    pub center_hz: Option<f32>,
+    #[serde(flatten)]
+    pub times: DecayTimes,
+    pub rt60_s: Option<f32>,
+}
+
+impl BandDecay {
+    fn new(center_hz: Option<f32>, samples: &[f32], sample_rate: u32) -> Self {
+        let times = decay_times(samples, sample_rate);
+        BandDecay { center_hz, times, rt60_s: times.rt60_s() }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct Rt60Analysis {
+    pub broadband: BandDecay,
+    pub bands: Vec<BandDecay>,
+}
+
+/// `samples` through a fourth-order band-pass around `center_hz`.
+fn band_filtered(samples: &[f32], sample_rate: u32, center_hz: f32, resolution: BandResolution) -> Result<Vec<f32>, String> {
+    let half_width = match resolution {
+        BandResolution::Octave => 2f64.sqrt(),
+        BandResolution::ThirdOctave => 2f64.powf(1.0 / 6.0),
+    };
+    let edge = |kind, frequency_hz| EqBand { kind, frequency_hz, gain_db: 0.0, q: std::f64::consts::FRAC_1_SQRT_2 };
+    let (low, high) = (center_hz as f64 / half_width, center_hz as f64 * half_width);
+    let bands = [
+        edge(EqBandKind::HighPass, low),
+        edge(EqBandKind::HighPass, low),
+        edge(EqBandKind::LowPass, high),
+        edge(EqBandKind::LowPass, high),
+    ];
+
+    let mut filtered = samples.to_vec();
+    Equalizer::new(&bands, sample_rate, 1)?.process(&mut filtered);
+    Ok(filtered)
+}
+
+/// Reverberation times of the impulse response or recorded clap in `path`,
+/// overall and per band (octaves by default). Bands that reach past
+/// Nyquist are left out. Multichannel files are mixed to mono first.
+#[tauri::command]
+#[specta::specta]
+pub async fn analyze_rt60(path: String, resolution: Option<BandResolution>) -> Result<Rt60Analysis, String> {
+    let resolution = resolution.unwrap_or(BandResolution::Octave);
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&path))?;
+        let channels = audio.channels.max(1) as usize;
+        let mono: Vec<f32> = audio
+            .samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        let nyquist = audio.sample_rate as f32 / 2.0;
+
+        let bands = resolution
+            .centers()
+            .iter()
+            .filter(|&&center| center * 2f32.sqrt() < nyquist)
+            .map(|&center| {
+                let filtered = band_filtered(&mono, audio.sample_rate, center, resolution)?;
+                Ok(BandDecay::new(Some(center), &filtered, audio.sample_rate))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Rt60Analysis {
+            broadband: BandDecay::new(None, &mono, audio.sample_rate),
+            bands,
+        })
+    })
+    .await
+    .map_err(|e| format!("RT60 analysis failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct SnrSettings {
+    /// 1-based input channel that is measured.
+    pub input_channel: u16,
+    pub silence_s: f64,
+    /// Time between the two captures to start the reference signal.
+    pub prepare_s: f64,
+    pub signal_s: f64,
+    /// Plays the reference tone on this output, e.g. into a loopback cable
+    /// or a speaker in front of the mic. Without it the user supplies the
+    /// signal, such as a calibrator or a sustained note.
+    pub tone_output_id: Option<String>,
+    pub tone_hz: f64,
+    pub tone_level_db: f32,
+}
+
+impl Default for SnrSettings {
+    fn default() -> Self {
+        SnrSettings {
+            input_channel: 1,
+            silence_s: 3.0,
+            prepare_s: 3.0,
+            signal_s: 3.0,
+            tone_output_id: None,
+            tone_hz: 1000.0,
+            tone_level_db: -12.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SnrPhase {
+    /// Keep the room and the source quiet.
+    Silence,
+    /// Start the reference signal.
+    Prepare,
+    /// Keep the reference signal steady.
+    Signal,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "measure://snr-phase")]
+pub(crate) struct SnrPhaseEvent {
+    phase: SnrPhase,
+    duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SnrMeasurement {
+    pub sample_rate: u32,
+    /// RMS level of the silent capture, in dBFS.
+    pub noise_floor_db: f32,
+    /// RMS level of the reference capture, in dBFS.
+    pub signal_db: f32,
+    pub signal_peak_db: f32,
+    pub snr_db: f32,
+    /// The reference reached full scale, so the SNR reads low.
+    pub clipped: bool,
+}
+
+/// RMS of `samples` with their DC offset removed, which would otherwise
+/// count as noise.
+fn ac_rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+    let power = samples.iter().map(|&s| (s - mean) * (s - mean)).sum::<f32>() / samples.len() as f32;
+    power.sqrt()
+}
+
+/// Measures the signal-to-noise ratio of `input_id`: records the noise
+/// floor while everything is quiet, then the level of a reference tone.
+/// Each step is announced with a `measure://snr-phase` event so the
+/// frontend can tell the user what to do.
+#[tauri::command]
+#[specta::specta]
+pub async fn measure_snr(input_id: String, settings: Option<SnrSettings>, app: AppHandle) -> Result<SnrMeasurement, String> {
+    let settings = settings.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let request = ConfigRequest::default();
+        let device = find_input_device(&input_id)?;
+        let input = InputStream::open(app.clone(), "snr", device, Some(vec![settings.input_channel]), request)?;
+        let sample_rate = input.sample_rate;
+        let frames = |seconds: f64| (seconds.max(0.0) * sample_rate as f64) as usize;
+        let announce = |phase, seconds: f64| {
+            events::emit(&app, "measure://snr-phase", "snr", SnrPhaseEvent { phase, duration_ms: seconds.max(0.0) * 1000.0 });
+        };
+
+        let (tx, rx) = mpsc::channel();
+        *input.shared.record_tap.lock().unwrap() = Some(RecordTap { file_path: None, tx });
+
+        announce(SnrPhase::Silence, settings.silence_s);
+        let silence = record_input(&rx, frames(settings.silence_s).max(1))?;
+
+        // The tone runs through the preparation time, so it has settled
+        // by the time the signal is captured
+        let player = match &settings.tone_output_id {
+            Some(output_id) => {
+                let amplitude = db_to_linear(settings.tone_level_db.min(0.0));
+                let length = frames(settings.prepare_s) + frames(settings.signal_s) + frames(1.0);
+                let step = 2.0 * std::f64::consts::PI * settings.tone_hz / sample_rate as f64;
+                let tone = (0..length).map(|i| (i as f64 * step).sin() as f32 * amplitude).collect();
+                Some(play_signal(output_id, sample_rate, tone)?)
+            }
+            None => None,
+        };
+        announce(SnrPhase::Prepare, settings.prepare_s);
+        record_input(&rx, frames(settings.prepare_s))?;
+
+        announce(SnrPhase::Signal, settings.signal_s);
+        let signal = record_input(&rx, frames(settings.signal_s).max(1))?;
+        drop(player);
+        drop(input);
+
+        let noise_floor_db = amplitude_to_db(ac_rms(&silence));
+        let signal_db = amplitude_to_db(ac_rms(&signal));
+        let peak = signal.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+        if signal_db - noise_floor_db < 3.0 {
+            return Err("No reference signal was picked up above the noise floor".to_string());
+        }
+
+        Ok(SnrMeasurement {
+            sample_rate,
+            noise_floor_db,
+            signal_db,
+            signal_peak_db: amplitude_to_db(peak),
+            snr_db: signal_db - noise_floor_db,
+            clipped: peak >= 0.999,
+        })
+    })
+    .await
+    .map_err(|e| format!("SNR measurement failed: {}", e))?
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::Path;
+
+use crate::validate;
+
+/// Text fields stored in a WAV file's `LIST`/`INFO` chunk.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct WavMetadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub comment: Option<String>,
+    /// Creation date, conventionally `YYYY-MM-DD`.
+    pub date: Option<String>,
+}
+
+const INFO_FIELDS: [&[u8; 4]; 4] = [b"INAM", b"IART", b"ICMT", b"ICRD"];
+
+impl WavMetadata {
+    fn field_mut(&mut self, id: &[u8]) -> Option<&mut Option<String>> {
+        match id {
+            b"INAM" => Some(&mut self.title),
+            b"IART" => Some(&mut self.artist),
+            b"ICMT" => Some(&mut self.comment),
+            b"ICRD" => Some(&mut self.date),
+            _ => None,
+        }
+    }
+}
+
+struct Chunk<'a> {
+    id: &'a [u8],
+    body: &'a [u8],
+}
+
+/// Splits a RIFF/WAVE file into its top-level chunks.
+fn riff_chunks(bytes: &[u8]) -> Result<Vec<Chunk<'_>>, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a WAV file".to_string());
+    }
+
+    let mut chunks = Vec::new();
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let end = (offset + 8 + size).min(bytes.len());
+        chunks.push(Chunk {
+            id: &bytes[offset..offset + 4],
+            body: &bytes[offset + 8..end],
+        });
+        // Chunks are padded to an even length
+        offset = end + (size & 1);
+    }
+
+    Ok(chunks)
+}
+
+fn is_info_list(chunk: &Chunk) -> bool {
+    chunk.id == b"LIST" && chunk.body.starts_with(b"INFO")
+}
+
+fn parse_info(body: &[u8], metadata: &mut WavMetadata) {
+    let mut offset = 4;
+    while offset + 8 <= body.len() {
+        let id = &body[offset..offset + 4];
+        let size = u32::from_le_bytes(body[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let value = &body[offset + 8..(offset + 8 + size).min(body.len())];
+
+        if let Some(field) = metadata.field_mut(id) {
+            let text = String::from_utf8_lossy(value).trim_end_matches('\0').to_string();
+            *field = Some(text);
+        }
+
+        offset += 8 + size + (size & 1);
+    }
+}
+
+pub fn read_wav_metadata(path: &Path) -> Result<WavMetadata, String> {
+    validate::input_file(path)?;
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    let mut metadata = WavMetadata::default();
+
+    for chunk in riff_chunks(&bytes)?.iter().filter(|c| is_info_list(c)) {
+        parse_info(chunk.body, &mut metadata);
+    }
+
+    Ok(metadata)
+}
+
+fn push_chunk(out: &mut Vec<u8>, id: &[u8], body: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Replaces the file's INFO list with `metadata`, keeping every other
+/// chunk intact. Unknown INFO fields from the old list are preserved.
+pub fn write_wav_metadata(path: &Path, metadata: &WavMetadata) -> Result<(), String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    let chunks = riff_chunks(&bytes)?;
+
+    let mut info = b"INFO".to_vec();
+    for chunk in chunks.iter().filter(|c| is_info_list(c)) {
+        let mut offset = 4;
+        while offset + 8 <= chunk.body.len() {
+            let id = &chunk.body[offset..offset + 4];
+            let size = u32::from_le_bytes(chunk.body[offset + 4..offset + 8].try_into().unwrap()) as usize;
+            let end = (offset + 8 + size).min(chunk.body.len());
+            if !INFO_FIELDS.iter().any(|f| f.as_slice() == id) {
+                push_chunk(&mut info, id, &chunk.body[offset + 8..end]);
+            }
+            offset += 8 + size + (size & 1);
+        }
+    }
+
+    let mut metadata = metadata.clone();
+    for id in INFO_FIELDS {
+        if let Some(Some(text)) = metadata.field_mut(id).map(Option::take) {
+            if !text.is_empty() {
+                let mut value = text.into_bytes();
+                value.push(0);
+                push_chunk(&mut info, id, &value);
+            }
+        }
+    }
+
+    let mut body = b"WAVE".to_vec();
+    for chunk in chunks.iter().filter(|c| !is_info_list(c)) {
+        push_chunk(&mut body, chunk.id, chunk.body);
+    }
+    if info.len() > 4 {
+        push_chunk(&mut body, b"LIST", &info);
+    }
+
+    let mut file = Vec::with_capacity(body.len() + 8);
+    push_chunk(&mut file, b"RIFF", &body);
+
+    // Write alongside and rename so a failure never leaves a truncated file
+    let temp_path = path.with_extension("wav.tmp");
+    fs::write(&temp_path, file).map_err(|e| format!("Failed to write WAV file: {}", e))?;
+    fs::rename(&temp_path, path).map_err(|e| format!("Failed to replace WAV file: {}", e))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_wav_metadata(file_path: String) -> Result<WavMetadata, String> {
+    read_wav_metadata(Path::new(&file_path))
+}
+
+/// Writes title, artist, comment and date into the file. Empty or missing
+/// fields are removed.
+#[tauri::command]
+#[specta::specta]
+pub async fn set_wav_metadata(file_path: String, metadata: WavMetadata) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || write_wav_metadata(Path::new(&file_path), &metadata))
+        .await
+        .map_err(|e| format!("Metadata task failed: {}", e))?
+}
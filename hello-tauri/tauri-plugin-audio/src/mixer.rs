@@ -0,0 +1,436 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+use std::f32::consts::FRAC_PI_4;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::State;
+
+use crate::audio_file::WavEncoding;
+use crate::backend::{self, Device};
+use crate::calculate_rms;
+use crate::config::{negotiate_output_config, ConfigRequest};
+use crate::dsp::gain::db_to_linear;
+use crate::dsp::levels::peak;
+use crate::dsp::loudness::{LoudnessMeter, LoudnessReport};
+use crate::master::MasterChain;
+use crate::output::{build_output_stream, find_output_device};
+use crate::passthrough::LatencyBuffer;
+use crate::recording::RecordWriter;
+use crate::validate;
+use crate::AudioState;
+
+/// Queue held per mix input when the caller doesn't choose.
+const DEFAULT_BUFFER_MS: f32 = 20.0;
+const BUFFER_RANGE_MS: (f32, f32) = (1.0, 500.0);
+
+/// An input stream's strip on the mix bus.
+struct MixChannel {
+    buffer: Arc<Mutex<LatencyBuffer>>,
+    gain_db: f32,
+    /// -1.0 (left) to 1.0 (right).
+    pan: f32,
+}
+
+impl MixChannel {
+    /// Left/right gains. Mono sources use a constant-power pan (-3 dB per
+    /// side at centre); stereo sources a balance that only turns the
+    /// opposite side down.
+    fn gains(&self, stereo_source: bool) -> (f32, f32) {
+        let gain = db_to_linear(self.gain_db);
+        let pan = self.pan.clamp(-1.0, 1.0);
+
+        if stereo_source {
+            (gain * (1.0 - pan).min(1.0), gain * (1.0 + pan).min(1.0))
+        } else {
+            let angle = (pan + 1.0) * FRAC_PI_4;
+            (gain * angle.cos(), gain * angle.sin())
+        }
+    }
+}
+
+/// Meters and recording tap on the summed output.
+struct MasterBus {
+    rms: f32,
+    /// Highest sample since the levels were last read.
+    peak: f32,
+    loudness: LoudnessMeter,
+    record_tx: Option<mpsc::Sender<Vec<f32>>>,
+}
+
+impl MasterBus {
+    fn process(&mut self, mix: &[f32]) {
+        self.rms = calculate_rms(mix);
+        self.peak = self.peak.max(peak(mix));
+        self.loudness.process(mix);
+
+        if let Some(tx) = &self.record_tx {
+            let _ = tx.send(mix.to_vec());
+        }
+    }
+}
+
+/// Mix inputs keyed by stream id, shared with the output callback.
+struct MixBus {
+    channels: Mutex<HashMap<String, MixChannel>>,
+    master: Mutex<MasterBus>,
+}
+
+impl MixBus {
+    fn new(sample_rate: u32) -> Self {
+        MixBus {
+            channels: Mutex::default(),
+            master: Mutex::new(MasterBus {
+                rms: 0.0,
+                peak: 0.0,
+                loudness: LoudnessMeter::new(sample_rate, 2),
+                record_tx: None,
+            }),
+        }
+    }
+
+    /// Sums every input into interleaved stereo `out`.
+    fn render(&self, out: &mut [f32], scratch: &mut Vec<f32>) {
+        out.fill(0.0);
+        scratch.resize(out.len(), 0.0);
+
+        for channel in self.channels.lock().unwrap().values() {
+            let mut buffer = channel.buffer.lock().unwrap();
+            let (left, right) = channel.gains(buffer.channels() >= 2);
+            buffer.pull(scratch, 2);
+            drop(buffer);
+
+            for (out, frame) in out.chunks_exact_mut(2).zip(scratch.chunks_exact(2)) {
+                out[0] += frame[0] * left;
+                out[1] += frame[1] * right;
+            }
+        }
+
+        self.master.lock().unwrap().process(out);
+    }
+}
+
+/// The stereo mix bus playing on an output device.
+pub struct Mixer {
+    sample_rate: u32,
+    buffer_ms: f32,
+    bus: Arc<MixBus>,
+    /// Writer thread of the mixdown being recorded, if any.
+    recording: Option<JoinHandle<Result<(), String>>>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl Mixer {
+    fn start(device: Device, buffer_ms: f32, master: &MasterChain) -> Result<Self, String> {
+        let request = ConfigRequest {
+            channels: Some(2),
+            ..ConfigRequest::default()
+        };
+        let (config, negotiated) = negotiate_output_config(&device, &request)?;
+        let sample_format = config.sample_format();
+        let config = negotiated.stream_config();
+
+        let bus = Arc::new(MixBus::new(negotiated.sample_rate));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let render_bus = Arc::clone(&bus);
+        let mut master = master.processor(negotiated.sample_rate, 2);
+        thread::spawn(move || {
+            let device_channels = config.channels as usize;
+            let mut mix = Vec::new();
+            let mut scratch = Vec::new();
+
+            let render = move |out: &mut [f32]| {
+                let frames = out.len() / device_channels;
+                mix.resize(frames * 2, 0.0);
+                render_bus.render(&mut mix, &mut scratch);
+                // After the mixdown tap, so recordings stay uncorrected
+                master.process(&mut mix);
+
+                // Mono devices get both sides; channels past the first pair stay silent
+                for (out, lr) in out.chunks_exact_mut(device_channels).zip(mix.chunks_exact(2)) {
+                    match out {
+                        [mono] => *mono = (lr[0] + lr[1]) / 2.0,
+                        [left, right, rest @ ..] => {
+                            *left = lr[0];
+                            *right = lr[1];
+                            rest.fill(0.0);
+                        }
+                        [] => {}
+                    }
+                }
+            };
+
+            let stream = match build_output_stream(&device, &config, sample_format, render) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            if let Err(e) = stream.play() {
+                let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+                return;
+            }
+
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+        });
+
+        ready_rx.recv()
+            .map_err(|_| "Mixer thread exited unexpectedly".to_string())??;
+
+        Ok(Mixer {
+            sample_rate: negotiated.sample_rate,
+            buffer_ms,
+            bus,
+            recording: None,
+            stop_tx,
+        })
+    }
+
+    fn start_recording(&mut self, file_path: &str, encoding: Option<WavEncoding>) -> Result<(), String> {
+        let mut writer = RecordWriter::create(file_path, self.sample_rate, 2, encoding, None, None)?;
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+
+        self.recording = Some(thread::spawn(move || {
+            for samples in rx {
+                writer.write(&samples)?;
+            }
+            writer.finalize()
+        }));
+        self.bus.master.lock().unwrap().record_tx = Some(tx);
+        Ok(())
+    }
+
+    /// Detaches the recording tap and waits for the file to be finalized.
+    fn finish_recording(&mut self) -> Result<(), String> {
+        self.bus.master.lock().unwrap().record_tx.take();
+
+        match self.recording.take() {
+            Some(thread) => thread.join().map_err(|_| "Mix recording thread panicked".to_string())?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        let _ = self.finish_recording();
+        let _ = self.stop_tx.send(());
+    }
+}
+
+#[derive(Default)]
+pub struct MixerState {
+    mixer: Mutex<Option<Mixer>>,
+}
+
+impl MixerState {
+    /// Stops the mix bus, finalizing its recording if there is one.
+    pub fn close(&self) -> Result<(), String> {
+        match self.mixer.lock().unwrap().take() {
+            Some(mut mixer) => mixer.finish_recording(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct MixerInfo {
+    pub sample_rate: u32,
+    pub inputs: Vec<String>,
+}
+
+/// Opens the stereo mix bus on `output_device` (the default output when
+/// omitted). Inputs are added with `add_to_mix`; `buffer_ms` is the queue
+/// kept per input.
+#[tauri::command]
+#[specta::specta]
+pub fn start_mixer(
+    output_device: Option<String>,
+    buffer_ms: Option<f32>,
+    mixer: State<MixerState>,
+    master: State<MasterChain>,
+) -> Result<MixerInfo, String> {
+    let device = match output_device {
+        Some(id) => find_output_device(&id)?,
+        None => backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string())?,
+    };
+
+    let buffer_ms = validate::range("Buffer", buffer_ms.unwrap_or(DEFAULT_BUFFER_MS), BUFFER_RANGE_MS, "ms")?;
+
+    // Replacing a running mixer drops it, which stops its output
+    let started = Mixer::start(device, buffer_ms, &master)?;
+    let info = MixerInfo {
+        sample_rate: started.sample_rate,
+        inputs: Vec::new(),
+    };
+    *mixer.mixer.lock().unwrap() = Some(started);
+    Ok(info)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_mixer(state: State<AudioState>, mixer: State<MixerState>) -> Result<(), String> {
+    for stream in state.streams.lock().unwrap().values() {
+        stream.shared.mix_send.lock().unwrap().take();
+    }
+    mixer.mixer.lock().unwrap().take();
+    Ok(())
+}
+
+/// Routes a monitored stream onto the mix bus. The stream must run at the
+/// bus's sample rate; restarting it means adding it again.
+#[tauri::command]
+#[specta::specta]
+pub fn add_to_mix(
+    stream_id: String,
+    gain_db: Option<f32>,
+    pan: Option<f32>,
+    state: State<AudioState>,
+    mixer: State<MixerState>,
+) -> Result<MixerInfo, String> {
+    let gain_db = validate::gain_db(gain_db.unwrap_or(0.0))?;
+    let pan = validate::pan(pan.unwrap_or(0.0))?;
+
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    let mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_ref().ok_or_else(|| "The mixer is not running".to_string())?;
+    if stream.sample_rate != mixer.sample_rate {
+        return Err(format!(
+            "Stream '{}' runs at {} Hz but the mix bus at {} Hz",
+            stream_id, stream.sample_rate, mixer.sample_rate
+        ));
+    }
+
+    let target = (mixer.buffer_ms / 1000.0 * mixer.sample_rate as f32) as usize;
+    let buffer = Arc::new(Mutex::new(LatencyBuffer::new(stream.channels as usize, target, mixer.sample_rate)));
+    *stream.shared.mix_send.lock().unwrap() = Some(Arc::clone(&buffer));
+
+    let mut channels = mixer.bus.channels.lock().unwrap();
+    channels.insert(stream_id, MixChannel {
+        buffer,
+        gain_db,
+        pan,
+    });
+
+    Ok(MixerInfo {
+        sample_rate: mixer.sample_rate,
+        inputs: channels.keys().cloned().collect(),
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_from_mix(stream_id: String, state: State<AudioState>, mixer: State<MixerState>) -> Result<(), String> {
+    if let Some(stream) = state.streams.lock().unwrap().get(&stream_id) {
+        stream.shared.mix_send.lock().unwrap().take();
+    }
+
+    let mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_ref().ok_or_else(|| "The mixer is not running".to_string())?;
+    let removed = mixer.bus.channels.lock().unwrap().remove(&stream_id);
+    removed
+        .map(|_| ())
+        .ok_or_else(|| format!("Stream '{}' is not in the mix", stream_id))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_mix_gain(stream_id: String, gain_db: f32, pan: Option<f32>, mixer: State<MixerState>) -> Result<(), String> {
+    validate::gain_db(gain_db)?;
+    pan.map(validate::pan).transpose()?;
+
+    let mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_ref().ok_or_else(|| "The mixer is not running".to_string())?;
+
+    let mut channels = mixer.bus.channels.lock().unwrap();
+    let channel = channels
+        .get_mut(&stream_id)
+        .ok_or_else(|| format!("Stream '{}' is not in the mix", stream_id))?;
+
+    channel.gain_db = gain_db;
+    if let Some(pan) = pan {
+        channel.pan = pan;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct MasterLevels {
+    pub rms: f32,
+    /// Highest sample since the previous call.
+    pub peak: f32,
+    pub loudness: LoudnessReport,
+    pub recording: bool,
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_mix_levels(mixer: State<MixerState>) -> Result<MasterLevels, String> {
+    let mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_ref().ok_or_else(|| "The mixer is not running".to_string())?;
+
+    // The output callback takes this lock too. The loudness report reads the
+    // meter's running totals and histograms, so it costs the same however
+    // long the mixer has run
+    let mut master = mixer.bus.master.lock().unwrap();
+    let levels = MasterLevels {
+        rms: master.rms,
+        peak: master.peak,
+        loudness: master.loudness.report(),
+        recording: master.record_tx.is_some(),
+    };
+    master.peak = 0.0;
+    Ok(levels)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn reset_mix_loudness(mixer: State<MixerState>) -> Result<(), String> {
+    let mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_ref().ok_or_else(|| "The mixer is not running".to_string())?;
+
+    mixer.bus.master.lock().unwrap().loudness = LoudnessMeter::new(mixer.sample_rate, 2);
+    Ok(())
+}
+
+/// Records the stereo mixdown to `file_path` (16-bit WAV by default, or Ogg
+/// Opus for `.opus` paths).
+#[tauri::command]
+#[specta::specta]
+pub fn start_mix_recording(
+    file_path: String,
+    encoding: Option<WavEncoding>,
+    mixer: State<MixerState>,
+) -> Result<(), String> {
+    let mut mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_mut().ok_or_else(|| "The mixer is not running".to_string())?;
+
+    if mixer.recording.is_some() {
+        return Err("The mix is already being recorded".to_string());
+    }
+    mixer.start_recording(&file_path, encoding)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_mix_recording(mixer: State<MixerState>) -> Result<(), String> {
+    let mut mixer = mixer.mixer.lock().unwrap();
+    let mixer = mixer.as_mut().ok_or_else(|| "The mixer is not running".to_string())?;
+
+    if mixer.recording.is_none() {
+        return Err("The mix is not being recorded".to_string());
+    }
+    mixer.finish_recording()
+}
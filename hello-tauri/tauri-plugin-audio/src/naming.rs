@@ -0,0 +1,55 @@
+use chrono::Local;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Replaces characters that are not allowed in file names.
+pub fn sanitize_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') { '_' } else { c })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Fills `{date}`, `{time}`, `{device}`, `{session}` and `{counter}` in a
+/// recording path and creates its directory. `{counter}` takes the first
+/// number that gives a new file; without it, an existing file gets a
+/// " (2)", " (3)", ... suffix instead of being overwritten.
+pub fn resolve_recording_path(template: &str, device: &str, session: &str) -> Result<PathBuf, String> {
+    let now = Local::now();
+    let resolved = template
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H-%M-%S").to_string())
+        .replace("{device}", &sanitize_file_name(device))
+        .replace("{session}", &sanitize_file_name(session));
+
+    let path = if resolved.contains("{counter}") {
+        (1..)
+            .map(|n| PathBuf::from(resolved.replace("{counter}", &format!("{:03}", n))))
+            .find(|path| !path.exists())
+            .unwrap()
+    } else {
+        unique_path(Path::new(&resolved))
+    };
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create recording directory: {}", e))?;
+    }
+    Ok(path)
+}
+
+fn unique_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("recording");
+    let ext = path.extension().and_then(|s| s.to_str());
+    (2..)
+        .map(|n| match ext {
+            Some(ext) => path.with_file_name(format!("{} ({}).{}", stem, n, ext)),
+            None => path.with_file_name(format!("{} ({})", stem, n)),
+        })
+        .find(|path| !path.exists())
+        .unwrap()
+}
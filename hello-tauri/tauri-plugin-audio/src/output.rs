@@ -0,0 +1,110 @@
+use std::f32::consts::PI;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::{self, Device, Stream};
+use crate::validate;
+
+/// Length of each identification ping in the output test.
+const PING_SECONDS: f32 = 0.25;
+/// Silence between the left and right pings.
+const PING_GAP_SECONDS: f32 = 0.15;
+
+pub fn find_output_device(device_id: &str) -> Result<Device, String> {
+    let host = backend::host();
+
+    let device_index = validate::device_index(device_id, "output_")?;
+
+    host.output_devices()
+        .map_err(|e| format!("Failed to enumerate devices: {}", e))?
+        .into_iter()
+        .nth(device_index)
+        .ok_or_else(|| validate::device_not_found(device_id))
+}
+
+/// Builds an output stream that asks `render` to fill interleaved f32
+/// buffers, converting to the device's native sample format.
+pub fn build_output_stream<R>(
+    device: &Device,
+    config: &cpal::StreamConfig,
+    sample_format: cpal::SampleFormat,
+    render: R,
+) -> Result<Stream, String>
+where
+    R: FnMut(&mut [f32]) + Send + 'static,
+{
+    device.build_output_stream(config, sample_format, Box::new(render))
+}
+
+/// A short ping on the left channel followed by a higher one on the right,
+/// so routing and channel order can be confirmed by ear. Mono devices get
+/// both pings; channels beyond the first two stay silent.
+fn identification_chime(sample_rate: u32, channels: usize) -> Vec<f32> {
+    let ping_frames = (PING_SECONDS * sample_rate as f32) as usize;
+    let gap_frames = (PING_GAP_SECONDS * sample_rate as f32) as usize;
+    let total_frames = ping_frames * 2 + gap_frames;
+    let mut samples = vec![0.0; total_frames * channels];
+
+    let pings = [(0, 880.0, 0), (ping_frames + gap_frames, 1320.0, 1)];
+    for (start, frequency, channel) in pings {
+        let channel = channel.min(channels - 1);
+
+        for i in 0..ping_frames {
+            let t = i as f32 / sample_rate as f32;
+            // Fast attack, exponential decay to sound like a chime rather than a beep
+            let envelope = (t / 0.005).min(1.0) * (-t * 12.0).exp();
+            let value = 0.5 * envelope * (2.0 * PI * frequency * t).sin();
+            samples[(start + i) * channels + channel] = value;
+        }
+    }
+
+    samples
+}
+
+/// Plays a left/right identification chime on the given output device.
+/// Returns once playback has started; the stream closes itself when done.
+#[tauri::command]
+#[specta::specta]
+pub fn test_output_device(device_id: String) -> Result<(), String> {
+    let device = find_output_device(&device_id)?;
+
+    let config = device.default_output_config()
+        .map_err(|e| format!("Failed to get default output config: {}", e))?;
+    let sample_format = config.sample_format();
+    let config: cpal::StreamConfig = config.into();
+
+    let chime = identification_chime(config.sample_rate.0, config.channels as usize);
+    let duration = Duration::from_secs_f32(chime.len() as f32 / (config.sample_rate.0 * config.channels as u32) as f32);
+
+    let (ready_tx, ready_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut position = 0;
+        let render = move |buffer: &mut [f32]| {
+            for sample in buffer.iter_mut() {
+                *sample = chime.get(position).copied().unwrap_or(0.0);
+                position += 1;
+            }
+        };
+
+        let stream = match build_output_stream(&device, &config, sample_format, render) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = ready_tx.send(Err(e));
+                return;
+            }
+        };
+
+        if let Err(e) = stream.play() {
+            let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+            return;
+        }
+
+        let _ = ready_tx.send(Ok(()));
+        // Leave room for the device buffer to drain before closing
+        thread::sleep(duration + Duration::from_millis(200));
+    });
+
+    ready_rx.recv()
+        .map_err(|_| "Output thread exited unexpectedly".to_string())?
+}
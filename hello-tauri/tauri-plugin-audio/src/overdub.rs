@@ -0,0 +1,367 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::{AppHandle, Manager, State};
+
+use crate::audio_file::{read_audio_file, write_audio_file, AudioBuffer, ProgressiveAudio, WavEncoding};
+use crate::backend::{self, Device};
+use crate::config::ConfigRequest;
+use crate::dsp::drift::{DriftEstimator, MicroResampler};
+use crate::dsp::fade::FadeCurve;
+use crate::dsp::mix::{Downmix, Upmix};
+use crate::edit::splice;
+use crate::events;
+use crate::find_input_device;
+use crate::master::MasterChain;
+use crate::output::find_output_device;
+use crate::playback::{Playback, PlaybackFades, PlaybackShared};
+use crate::recording::RecordWriter;
+use crate::stream::{InputStream, RecordTap};
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct TakeResult {
+    pub record_path: String,
+    pub duration_ms: f64,
+    /// How much faster the input device's clock ran than the output's, as
+    /// corrected for at the end.
+    pub drift_ppm: f64,
+}
+
+/// Where playback starts in the backing file, and which part of the input,
+/// measured from the start of playback, ends up in the take.
+struct TakeWindow {
+    playback_start_ms: f64,
+    skip_ms: f64,
+    length_ms: f64,
+}
+
+fn ms_to_frames(ms: f64, sample_rate: u32) -> usize {
+    (ms.max(0.0) / 1000.0 * sample_rate as f64) as usize
+}
+
+/// An input being recorded against a file playing on an output device.
+struct Take {
+    input: InputStream,
+    playback: Playback,
+    writer_thread: JoinHandle<Result<TakeResult, String>>,
+}
+
+impl Take {
+    fn start(
+        app: AppHandle,
+        input_device: &str,
+        output_device: Option<&str>,
+        backing: Arc<ProgressiveAudio>,
+        record_path: String,
+        window: TakeWindow,
+        encoding: Option<WavEncoding>,
+    ) -> Result<Self, String> {
+        let output = match output_device {
+            Some(id) => find_output_device(id)?,
+            None => default_output_device()?,
+        };
+
+        // Playback starts first so the input can be opened at the same rate
+        let start_frame = ms_to_frames(window.playback_start_ms, backing.sample_rate);
+        let playback = Playback::start(output, backing, start_frame, PlaybackFades::NONE, &app.state::<MasterChain>())?;
+        let request = ConfigRequest {
+            sample_rate: Some(playback.sample_rate),
+            ..ConfigRequest::default()
+        };
+        let input = InputStream::open(app.clone(), "overdub", find_input_device(input_device)?, None, request)?;
+
+        let writer = RecordWriter::create(&record_path, input.sample_rate, input.channels, encoding, None, None)?;
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+
+        let playback_shared = Arc::clone(&playback.shared);
+        let sample_rate = input.sample_rate;
+        let channels = input.channels as usize;
+        let skip = ms_to_frames(window.skip_ms, sample_rate);
+        let length = ms_to_frames(window.length_ms, sample_rate);
+        let path = record_path.clone();
+        let writer_thread = thread::spawn(move || {
+            let result = write_take(rx, writer, &playback_shared, sample_rate, channels, skip, length)
+                .map(|(frames, drift_ppm)| TakeResult {
+                    record_path: path,
+                    duration_ms: frames as f64 * 1000.0 / sample_rate as f64,
+                    drift_ppm,
+                });
+            events::emit(&app, "overdub://finished", "overdub", &result);
+            result
+        });
+
+        *input.shared.record_tap.lock().unwrap() = Some(RecordTap { file_path: Some(record_path), tx });
+
+        Ok(Take { input, playback, writer_thread })
+    }
+
+    fn finish(self) -> Result<TakeResult, String> {
+        // Closing the input detaches the tap, which ends the writer thread
+        drop(self.input);
+        drop(self.playback);
+        self.writer_thread
+            .join()
+            .map_err(|_| "Overdub thread panicked".to_string())?
+    }
+}
+
+fn default_output_device() -> Result<Device, String> {
+    backend::host()
+        .default_output_device()
+        .ok_or_else(|| "No default output device".to_string())
+}
+
+/// Writes `length` frames of input captured after playback started, minus
+/// the first `skip`, stopping early if the input goes away. The input is
+/// resampled to follow the output device's clock, so a long take stays
+/// in time with the backing. Returns the frames written and the drift.
+fn write_take(
+    rx: mpsc::Receiver<Vec<f32>>,
+    mut writer: RecordWriter,
+    playback: &PlaybackShared,
+    sample_rate: u32,
+    channels: usize,
+    mut skip: usize,
+    length: usize,
+) -> Result<(usize, f64), String> {
+    let mut remaining = length;
+    let mut aligned = false;
+
+    let mut resampler = MicroResampler::new(channels);
+    let mut drift = DriftEstimator::new(sample_rate);
+    // Output frames per backing frame; the input runs at the output's rate
+    let scale = sample_rate as f64 / playback.audio.sample_rate as f64;
+    let mut rendered_from = 0;
+    let mut recorded = 0;
+
+    while remaining > 0 {
+        let Ok(samples) = rx.recv() else { break };
+        let Some(started_at) = *playback.started_at.lock().unwrap() else {
+            continue;
+        };
+
+        let mut frames = &samples[..];
+        if !aligned {
+            // The end of this buffer was captured just now; drop whatever
+            // came in before the first output callback
+            let late = (started_at.elapsed().as_secs_f64() * sample_rate as f64) as usize;
+            let lead = (frames.len() / channels).saturating_sub(late);
+            frames = &frames[lead * channels..];
+            rendered_from = *playback.position.lock().unwrap();
+            aligned = true;
+        }
+
+        let rendered = playback.position.lock().unwrap().saturating_sub(rendered_from) as f64 * scale;
+        resampler.set_adjustment(drift.update(recorded as f64 - rendered, frames.len() / channels));
+        let resampled = resampler.process(frames);
+        recorded += resampled.len() / channels;
+        let mut frames = &resampled[..];
+
+        let dropped = skip.min(frames.len() / channels);
+        frames = &frames[dropped * channels..];
+        skip -= dropped;
+
+        let kept = remaining.min(frames.len() / channels);
+        writer.write(&frames[..kept * channels])?;
+        remaining -= kept;
+    }
+
+    writer.finalize()?;
+    Ok((length - remaining, drift.drift_ppm()))
+}
+
+/// Length of the crossfades just inside each punch point.
+const PUNCH_FADE_MS: f64 = 5.0;
+
+/// Where a punched take goes once recording stops.
+struct Splice {
+    original: AudioBuffer,
+    punch_in_ms: f64,
+    output_path: String,
+}
+
+impl Splice {
+    /// Drops the recorded audio into the original at the punch-in point,
+    /// matching its rate and channel layout first.
+    fn apply(&self, take_path: &Path) -> Result<(), String> {
+        let original = &self.original;
+        let mut take = read_audio_file(take_path)?.resampled(original.sample_rate)?;
+        take = match (take.channels, original.channels) {
+            (a, b) if a == b => take,
+            (1, 2) => take.upmix(&Upmix::Duplicate)?,
+            (_, 1) => take.downmix(&Downmix::Average)?,
+            (a, b) => return Err(format!("Cannot punch a {}-channel take into a {}-channel file", a, b)),
+        };
+
+        let at = ms_to_frames(self.punch_in_ms, original.sample_rate);
+        let fade = ms_to_frames(PUNCH_FADE_MS, original.sample_rate);
+        let spliced = splice(original, &take, at, fade, FadeCurve::EqualPower);
+        write_audio_file(Path::new(&self.output_path), &spliced, WavEncoding::default())
+    }
+}
+
+struct Session {
+    take: Take,
+    splice: Option<Splice>,
+}
+
+#[derive(Default)]
+pub struct OverdubState {
+    session: Mutex<Option<Session>>,
+}
+
+impl OverdubState {
+    /// Finalizes a running take, splicing it in for a punch recording.
+    pub fn close(&self) -> Result<(), String> {
+        match self.session.lock().unwrap().take() {
+            Some(session) if session.splice.is_some() => finish_punch(session).map(|_| ()),
+            Some(session) => session.take.finish().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Plays `playback_path` while recording `input_device` to `record_path`.
+/// The take starts with playback and runs for the length of the backing
+/// file; `latency_comp_ms` trims the interface's round-trip latency from its
+/// start so the new part lines up with the backing.
+#[tauri::command]
+#[specta::specta]
+pub fn start_overdub(
+    playback_path: String,
+    input_device: String,
+    record_path: String,
+    latency_comp_ms: Option<f64>,
+    output_device: Option<String>,
+    app: AppHandle,
+    state: State<OverdubState>,
+) -> Result<(), String> {
+    let mut session = state.session.lock().unwrap();
+    if session.is_some() {
+        return Err("An overdub is already running".to_string());
+    }
+
+    let backing = Arc::new(ProgressiveAudio::complete(read_audio_file(Path::new(&playback_path))?));
+    let window = TakeWindow {
+        playback_start_ms: 0.0,
+        skip_ms: latency_comp_ms.unwrap_or(0.0),
+        length_ms: backing.frames() as f64 * 1000.0 / backing.sample_rate as f64,
+    };
+
+    let take = Take::start(app, &input_device, output_device.as_deref(), backing, record_path, window, None)?;
+    *session = Some(Session { take, splice: None });
+    Ok(())
+}
+
+/// Stops playback and finalizes the take, which may already have ended on
+/// its own at the end of the backing file.
+#[tauri::command]
+#[specta::specta]
+pub fn stop_overdub(state: State<OverdubState>) -> Result<TakeResult, String> {
+    let session = state.session.lock().unwrap().take_if(|s| s.splice.is_none())
+        .ok_or_else(|| "No overdub is running".to_string())?;
+    session.take.finish()
+}
+
+#[derive(Debug, Clone, Deserialize, Type)]
+pub struct PunchRange {
+    pub punch_in_ms: f64,
+    pub punch_out_ms: f64,
+    /// How much of the existing take plays before punch-in.
+    #[serde(default)]
+    pub pre_roll_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PunchResult {
+    pub output_path: String,
+    /// Length of the replaced region; shorter than asked if stopped early.
+    pub duration_ms: f64,
+}
+
+/// `<stem> (punch N).<ext>` next to the original, using the first free N.
+fn punch_output_path(take_path: &Path) -> PathBuf {
+    let stem = take_path.file_stem().and_then(|s| s.to_str()).unwrap_or("take");
+    let ext = take_path.extension().and_then(|s| s.to_str()).unwrap_or("wav");
+    (1..)
+        .map(|n| take_path.with_file_name(format!("{} (punch {}).{}", stem, n, ext)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+/// Plays `take_path` from `pre_roll_ms` before the punch-in point and
+/// records `input_device` between the punch points only. Stopping splices
+/// the new audio into a copy of the take (`output_path`, or the next free
+/// "(punch N)" name); the original is left untouched.
+#[tauri::command]
+#[specta::specta]
+pub fn start_punch(
+    take_path: String,
+    input_device: String,
+    range: PunchRange,
+    latency_comp_ms: Option<f64>,
+    output_path: Option<String>,
+    app: AppHandle,
+    state: State<OverdubState>,
+) -> Result<(), String> {
+    if range.punch_out_ms <= range.punch_in_ms {
+        return Err("Punch-out must come after punch-in".to_string());
+    }
+
+    let mut session = state.session.lock().unwrap();
+    if session.is_some() {
+        return Err("An overdub is already running".to_string());
+    }
+
+    let original = read_audio_file(Path::new(&take_path))?;
+    let pre_roll = range.pre_roll_ms.clamp(0.0, range.punch_in_ms.max(0.0));
+    let window = TakeWindow {
+        playback_start_ms: range.punch_in_ms - pre_roll,
+        skip_ms: pre_roll + latency_comp_ms.unwrap_or(0.0).max(0.0),
+        length_ms: range.punch_out_ms - range.punch_in_ms,
+    };
+
+    let output_path = output_path
+        .unwrap_or_else(|| punch_output_path(Path::new(&take_path)).to_string_lossy().into_owned());
+    // Recorded as float so the splice is the only conversion
+    let record_path = format!("{}.punch.wav", output_path);
+    let take = Take::start(app, &input_device, None, Arc::new(ProgressiveAudio::complete(original.clone())), record_path, window, Some(WavEncoding::Float32))?;
+
+    let splice = Splice {
+        original,
+        punch_in_ms: range.punch_in_ms,
+        output_path,
+    };
+    *session = Some(Session { take, splice: Some(splice) });
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn stop_punch(state: State<'_, OverdubState>) -> Result<PunchResult, String> {
+    let session = state.session.lock().unwrap().take_if(|s| s.splice.is_some())
+        .ok_or_else(|| "No punch recording is running".to_string())?;
+
+    tauri::async_runtime::spawn_blocking(move || finish_punch(session))
+        .await
+        .map_err(|e| format!("Punch task failed: {}", e))?
+}
+
+fn finish_punch(session: Session) -> Result<PunchResult, String> {
+    let take = session.take.finish()?;
+    let splice = session.splice.ok_or_else(|| "Not a punch recording".to_string())?;
+
+    let take_path = Path::new(&take.record_path);
+    let result = splice.apply(take_path);
+    let _ = fs::remove_file(take_path);
+    result?;
+
+    Ok(PunchResult {
+        output_path: splice.output_path,
+        duration_ms: take.duration_ms,
+    })
+}
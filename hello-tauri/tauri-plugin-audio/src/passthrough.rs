@@ -0,0 +1,380 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::State;
+
+use crate::backend::{self, Device};
+use crate::config::{negotiate_output_config, ConfigRequest};
+use crate::convolution::{live_convolver, ConvolutionSettings};
+use crate::dsp::drift::{DriftEstimator, MicroResampler};
+use crate::dsp::convolution::Convolver;
+use crate::output::{build_output_stream, find_output_device};
+use crate::plugins::{process_chain, PluginChain};
+use crate::stream::InputStream;
+use crate::validate;
+use crate::AudioState;
+
+/// Audio held between input and output when the caller doesn't choose.
+const DEFAULT_BUFFER_MS: f32 = 20.0;
+
+/// Allowed range for the passthrough buffer, in ms.
+const BUFFER_RANGE_MS: (f32, f32) = (1.0, 500.0);
+
+/// Queue between an input callback and an output callback. Output starts
+/// once `target_frames` are queued and, after an underrun, waits for the
+/// same amount again. While it flows, the input is resampled by a few ppm
+/// to follow the output device's clock, so the queue stays level over long
+/// sessions. Anything queued beyond twice the target is still dropped in
+/// case the clocks are further apart than that can correct.
+pub struct LatencyBuffer {
+    queue: VecDeque<f32>,
+    channels: usize,
+    resampler: MicroResampler,
+    drift: DriftEstimator,
+    target_frames: usize,
+    priming: bool,
+    underruns: u64,
+    overflows: u64,
+    /// Frames queued when the output last pulled.
+    level_frames: usize,
+    input_frames: usize,
+    output_frames: usize,
+}
+
+impl LatencyBuffer {
+    pub fn new(channels: usize, target_frames: usize, sample_rate: u32) -> Self {
+        LatencyBuffer {
+            queue: VecDeque::new(),
+            channels: channels.max(1),
+            resampler: MicroResampler::new(channels),
+            drift: DriftEstimator::new(sample_rate),
+            target_frames,
+            priming: true,
+            underruns: 0,
+            overflows: 0,
+            level_frames: 0,
+            input_frames: 0,
+            output_frames: 0,
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// How much faster the input's clock runs than the output's.
+    pub fn drift_ppm(&self) -> f64 {
+        self.drift.drift_ppm()
+    }
+
+    pub fn set_target(&mut self, frames: usize) {
+        let queued = self.queue.len() / self.channels;
+        if queued > frames {
+            self.queue.drain(..(queued - frames) * self.channels);
+        } else {
+            self.priming = true;
+        }
+        self.target_frames = frames;
+        self.drift.reset();
+    }
+
+    pub fn push(&mut self, samples: &[f32]) {
+        self.input_frames = samples.len() / self.channels;
+        // The level only says something about the clocks while it flows
+        if self.priming {
+            self.drift.reset();
+        } else {
+            let adjustment = self.drift.update((self.queue.len() / self.channels) as f64, self.input_frames);
+            self.resampler.set_adjustment(adjustment);
+        }
+        self.queue.extend(self.resampler.process(samples));
+
+        let limit = (self.target_frames * 2).max(self.input_frames * 2) * self.channels;
+        if self.queue.len() > limit {
+            let excess = self.queue.len() - self.target_frames * self.channels;
+            self.queue.drain(..excess - excess % self.channels);
+            self.overflows += 1;
+            self.drift.reset();
+        }
+    }
+
+    /// Fills `out` (interleaved, `out_channels` wide). A mono input is sent
+    /// to every output channel; otherwise channels map one to one and extra
+    /// output channels stay silent.
+    pub fn pull(&mut self, out: &mut [f32], out_channels: usize) {
+        let frames = out.len() / out_channels.max(1);
+        self.output_frames = frames;
+        self.level_frames = self.queue.len() / self.channels;
+        out.fill(0.0);
+
+        if self.priming {
+            if self.level_frames < self.target_frames.max(1) {
+                return;
+            }
+            self.priming = false;
+        }
+
+        for frame in out.chunks_exact_mut(out_channels).take(frames) {
+            if self.queue.len() < self.channels {
+                self.underruns += 1;
+                self.priming = true;
+                return;
+            }
+
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                *sample = match self.channels {
+                    1 => self.queue[0],
+                    _ if channel < self.channels => self.queue[channel],
+                    _ => 0.0,
+                };
+            }
+            self.queue.drain(..self.channels);
+        }
+    }
+}
+
+/// An input stream's audio being played out on an output device.
+pub struct Passthrough {
+    pub sample_rate: u32,
+    /// Channels of the output device.
+    pub channels: usize,
+    pub buffer: Arc<Mutex<LatencyBuffer>>,
+    /// Effect plugins applied on the way out.
+    pub plugins: PluginChain,
+    /// Convolution reverb after the plugins, set up for the output.
+    pub convolver: Arc<Mutex<Option<Convolver>>>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl Passthrough {
+    fn start(
+        device: Device,
+        stream: &InputStream,
+        buffer_ms: f32,
+        low_latency: bool,
+        buffer_size: Option<u32>,
+    ) -> Result<Self, String> {
+        // The output must run at the input's rate; there is no resampling here
+        let request = ConfigRequest {
+            sample_rate: Some(stream.sample_rate),
+            low_latency,
+            buffer_size,
+            ..ConfigRequest::default()
+        };
+        let (config, negotiated) = negotiate_output_config(&device, &request)?;
+        if negotiated.sample_rate != stream.sample_rate {
+            return Err(format!("Output device does not support {} Hz", stream.sample_rate));
+        }
+        let sample_format = config.sample_format();
+        let config = negotiated.stream_config();
+        let channels = config.channels as usize;
+
+        let target = ms_to_frames(buffer_ms, stream.sample_rate);
+        let buffer = Arc::new(Mutex::new(LatencyBuffer::new(stream.channels as usize, target, stream.sample_rate)));
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        let plugins = PluginChain::default();
+        let convolver = Arc::new(Mutex::new(None::<Convolver>));
+
+        let render_buffer = Arc::clone(&buffer);
+        let render_plugins = Arc::clone(&plugins);
+        let render_convolver = Arc::clone(&convolver);
+        thread::spawn(move || {
+            let render = move |out: &mut [f32]| {
+                render_buffer.lock().unwrap().pull(out, channels);
+                process_chain(&render_plugins, out, channels);
+                if let Some(convolver) = render_convolver.lock().unwrap().as_mut() {
+                    convolver.process(out);
+                }
+            };
+
+            let stream = match build_output_stream(&device, &config, sample_format, render) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            if let Err(e) = stream.play() {
+                let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+                return;
+            }
+
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+        });
+
+        ready_rx.recv()
+            .map_err(|_| "Passthrough thread exited unexpectedly".to_string())??;
+
+        *stream.shared.passthrough.lock().unwrap() = Some(Arc::clone(&buffer));
+
+        Ok(Passthrough {
+            sample_rate: stream.sample_rate,
+            channels,
+            buffer,
+            plugins,
+            convolver,
+            stop_tx,
+        })
+    }
+
+    fn status(&self) -> PassthroughStatus {
+        let buffer = self.buffer.lock().unwrap();
+        let to_ms = |frames: usize| frames as f32 * 1000.0 / self.sample_rate as f32;
+
+        PassthroughStatus {
+            buffer_ms: to_ms(buffer.target_frames),
+            measured_latency_ms: to_ms(buffer.input_frames + buffer.level_frames + buffer.output_frames),
+            underruns: buffer.underruns,
+            overflows: buffer.overflows,
+            drift_ppm: buffer.drift_ppm(),
+        }
+    }
+}
+
+impl Drop for Passthrough {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PassthroughStatus {
+    pub buffer_ms: f32,
+    /// Input buffer + queued audio + output buffer, as last observed. The
+    /// drivers' own converter latency comes on top.
+    pub measured_latency_ms: f32,
+    pub underruns: u64,
+    /// Times queued audio was dropped because the input ran ahead.
+    pub overflows: u64,
+    /// How much faster the input device's clock runs than the output's,
+    /// as currently corrected for.
+    pub drift_ppm: f64,
+}
+
+fn ms_to_frames(ms: f32, sample_rate: u32) -> usize {
+    (ms / 1000.0 * sample_rate as f32).round() as usize
+}
+
+fn check_buffer_ms(buffer_ms: f32) -> Result<(), String> {
+    validate::range("Buffer", buffer_ms, BUFFER_RANGE_MS, "ms")?;
+    Ok(())
+}
+
+/// Plays a monitored stream out on `output_device` (the default output when
+/// omitted). `buffer_ms` trades latency against resistance to dropouts.
+/// `low_latency` and `buffer_size` size the output device's own buffer the
+/// same way `ConfigRequest` does for inputs. Like inputs, the output is opened
+/// in WASAPI shared mode: cpal 0.15 has no exclusive-mode streams, so a small
+/// buffer shortens the shared-mode period but cannot bypass the audio engine.
+#[tauri::command]
+#[specta::specta]
+pub fn start_passthrough(
+    stream_id: String,
+    output_device: Option<String>,
+    buffer_ms: Option<f32>,
+    low_latency: Option<bool>,
+    buffer_size: Option<u32>,
+    state: State<AudioState>,
+) -> Result<PassthroughStatus, String> {
+    let buffer_ms = buffer_ms.unwrap_or(DEFAULT_BUFFER_MS);
+    check_buffer_ms(buffer_ms)?;
+
+    let device = match output_device {
+        Some(id) => find_output_device(&id)?,
+        None => backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string())?,
+    };
+
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    // Replacing an existing passthrough drops it, which stops its output
+    let passthrough = Passthrough::start(device, stream, buffer_ms, low_latency.unwrap_or(false), buffer_size)?;
+    let status = passthrough.status();
+    stream.passthrough = Some(passthrough);
+    Ok(status)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_passthrough(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    stream.shared.passthrough.lock().unwrap().take();
+    stream.passthrough = None;
+    Ok(())
+}
+
+/// Changes the passthrough buffer while it runs. Shrinking drops the excess
+/// straight away; growing leaves a short gap while the queue refills.
+#[tauri::command]
+#[specta::specta]
+pub fn set_passthrough_buffer(
+    stream_id: String,
+    buffer_ms: f32,
+    state: State<AudioState>,
+) -> Result<PassthroughStatus, String> {
+    check_buffer_ms(buffer_ms)?;
+
+    let streams = state.streams.lock().unwrap();
+    let passthrough = streams
+        .get(&stream_id)
+        .and_then(|stream| stream.passthrough.as_ref())
+        .ok_or_else(|| format!("Stream '{}' has no passthrough", stream_id))?;
+
+    let frames = ms_to_frames(buffer_ms, passthrough.sample_rate);
+    passthrough.buffer.lock().unwrap().set_target(frames);
+    Ok(passthrough.status())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_passthrough_status(stream_id: String, state: State<AudioState>) -> Result<PassthroughStatus, String> {
+    let streams = state.streams.lock().unwrap();
+    let passthrough = streams
+        .get(&stream_id)
+        .and_then(|stream| stream.passthrough.as_ref())
+        .ok_or_else(|| format!("Stream '{}' has no passthrough", stream_id))?;
+
+    Ok(passthrough.status())
+}
+
+/// Puts a convolution reverb on a stream's passthrough, or takes it off
+/// with no settings. The wet signal lags the dry one by a short block.
+#[tauri::command]
+#[specta::specta]
+pub fn set_passthrough_convolution(
+    stream_id: String,
+    settings: Option<ConvolutionSettings>,
+    state: State<AudioState>,
+) -> Result<(), String> {
+    let (sample_rate, channels, slot) = {
+        let streams = state.streams.lock().unwrap();
+        let passthrough = streams
+            .get(&stream_id)
+            .and_then(|stream| stream.passthrough.as_ref())
+            .ok_or_else(|| format!("Stream '{}' has no passthrough", stream_id))?;
+        (passthrough.sample_rate, passthrough.channels, Arc::clone(&passthrough.convolver))
+    };
+
+    // The IR is loaded before the output callback has to wait for it
+    let convolver = settings
+        .map(|settings| live_convolver(&settings, sample_rate, channels))
+        .transpose()?;
+    *slot.lock().unwrap() = convolver;
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use serde::Serialize;
+use specta::Type;
+use tauri::Webview;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioPermission {
+    Granted,
+    /// Turned off by the user; only they can change it, in the system
+    /// settings.
+    Denied,
+    /// Blocked by policy or for the whole device, e.g. parental controls.
+    Restricted,
+    /// Not asked yet; `request_audio_permission` prompts for it.
+    NotDetermined,
+}
+
+/// macOS and iOS keep microphone access in TCC, which asks the user the
+/// first time an app requests it. The prompt shows the
+/// `NSMicrophoneUsageDescription` from Info.plist (Info.ios.plist on iOS).
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+mod platform {
+    use super::AudioPermission;
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_av_foundation::{AVAuthorizationStatus, AVCaptureDevice, AVMediaTypeAudio};
+    use std::sync::mpsc;
+    use tauri::Webview;
+
+    pub fn check() -> AudioPermission {
+        let Some(audio) = (unsafe { AVMediaTypeAudio }) else { return AudioPermission::Granted };
+        match unsafe { AVCaptureDevice::authorizationStatusForMediaType(audio) } {
+            AVAuthorizationStatus::Authorized => AudioPermission::Granted,
+            AVAuthorizationStatus::Denied => AudioPermission::Denied,
+            AVAuthorizationStatus::Restricted => AudioPermission::Restricted,
+            _ => AudioPermission::NotDetermined,
+        }
+    }
+
+    /// Shows the system prompt and waits for the answer.
+    pub fn request(_webview: &Webview) -> AudioPermission {
+        let status = check();
+        let Some(audio) = (unsafe { AVMediaTypeAudio }) else { return status };
+        if status != AudioPermission::NotDetermined {
+            return status;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let handler = RcBlock::new(move |granted: Bool| {
+            let _ = tx.send(granted.as_bool());
+        });
+        unsafe { AVCaptureDevice::requestAccessForMediaType_completionHandler(audio, &handler) };
+        match rx.recv() {
+            Ok(true) => AudioPermission::Granted,
+            Ok(false) => AudioPermission::Denied,
+            Err(_) => check(),
+        }
+    }
+}
+
+/// Windows has a microphone privacy switch for the whole device and one
+/// per user, with a separate one for desktop apps. Desktop apps can't
+/// prompt for it; the user has to change it in Settings.
+#[cfg(windows)]
+mod platform {
+    use super::AudioPermission;
+    use tauri::Webview;
+    use windows_registry::{Key, CURRENT_USER, LOCAL_MACHINE};
+
+    const CONSENT_STORE: &str =
+        r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\microphone";
+
+    /// "Allow", "Deny" or "Prompt"; unset means allowed.
+    fn consent(root: &Key, path: &str) -> Option<String> {
+        root.open(path).and_then(|key| key.get_string("Value")).ok()
+    }
+
+    pub fn check() -> AudioPermission {
+        if consent(LOCAL_MACHINE, CONSENT_STORE).as_deref() == Some("Deny") {
+            return AudioPermission::Restricted;
+        }
+        let desktop_apps = format!(r"{}\NonPackaged", CONSENT_STORE);
+        let user = [consent(CURRENT_USER, CONSENT_STORE), consent(CURRENT_USER, &desktop_apps)];
+        if user.iter().any(|value| value.as_deref() == Some("Deny")) {
+            AudioPermission::Denied
+        } else if user.iter().any(|value| value.as_deref() == Some("Prompt")) {
+            AudioPermission::NotDetermined
+        } else {
+            AudioPermission::Granted
+        }
+    }
+
+    pub fn request(_webview: &Webview) -> AudioPermission {
+        check()
+    }
+}
+
+/// Android grants RECORD_AUDIO at runtime, which needs the app's manifest
+/// (gen/android, created by `tauri android init`) to declare it. The
+/// prompt is shown from the webview's activity. Android can't tell a
+/// refusal apart from not having asked, so until this process has asked,
+/// not granted reads as not determined.
+#[cfg(target_os = "android")]
+mod platform {
+    use super::AudioPermission;
+    use jni::objects::{JObject, JValue};
+    use jni::{JNIEnv, JavaVM};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use tauri::Webview;
+
+    const RECORD_AUDIO: &str = "android.permission.RECORD_AUDIO";
+    /// `PackageManager.PERMISSION_GRANTED`.
+    const PERMISSION_GRANTED: i32 = 0;
+    /// The answer goes to the activity's `onRequestPermissionsResult`,
+    /// which isn't ours, so it's read back with `checkSelfPermission`.
+    const REQUEST_CODE: i32 = 0x4155;
+    const ACTIVITY_TIMEOUT: Duration = Duration::from_secs(2);
+    /// After "don't ask again" Android answers without showing anything.
+    const PROMPT_SHOWN_TIMEOUT: Duration = Duration::from_secs(1);
+    const ANSWER_TIMEOUT: Duration = Duration::from_secs(300);
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    static ASKED: AtomicBool = AtomicBool::new(false);
+
+    fn granted() -> Result<bool, String> {
+        let context = ndk_context::android_context();
+        let vm = unsafe { JavaVM::from_raw(context.vm().cast()) }
+            .map_err(|e| format!("Failed to reach the Java VM: {}", e))?;
+        let mut env = vm
+            .attach_current_thread()
+            .map_err(|e| format!("Failed to attach to the Java VM: {}", e))?;
+        let app_context = unsafe { JObject::from_raw(context.context().cast()) };
+
+        let permission = env
+            .new_string(RECORD_AUDIO)
+            .map_err(|e| format!("Failed to check microphone permission: {}", e))?;
+        let result = env
+            .call_method(&app_context, "checkSelfPermission", "(Ljava/lang/String;)I", &[JValue::Object(&permission)])
+            .and_then(|value| value.i())
+            .map_err(|e| format!("Failed to check microphone permission: {}", e))?;
+        Ok(result == PERMISSION_GRANTED)
+    }
+
+    pub fn check() -> AudioPermission {
+        match granted() {
+            Ok(true) => AudioPermission::Granted,
+            Ok(false) if ASKED.load(Ordering::Relaxed) => AudioPermission::Denied,
+            Ok(false) => AudioPermission::NotDetermined,
+            Err(e) => {
+                eprintln!("{}", e);
+                AudioPermission::NotDetermined
+            }
+        }
+    }
+
+    /// Runs `f` with the webview's activity on its thread and waits for it.
+    fn on_activity<T, F>(webview: &Webview, f: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut JNIEnv, &JObject) -> Result<T, String> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        webview
+            .with_webview(move |webview| {
+                webview.jni_handle().exec(move |env, activity, _| {
+                    let _ = tx.send(f(env, activity));
+                })
+            })
+            .map_err(|e| format!("Failed to reach the activity: {}", e))?;
+        rx.recv_timeout(ACTIVITY_TIMEOUT)
+            .map_err(|_| "The activity didn't respond".to_string())?
+    }
+
+    fn has_focus(webview: &Webview) -> Result<bool, String> {
+        on_activity(webview, |env, activity| {
+            env.call_method(activity, "hasWindowFocus", "()Z", &[])
+                .and_then(|value| value.z())
+                .map_err(|e| format!("Failed to check the activity's focus: {}", e))
+        })
+    }
+
+    /// Waits until the activity has or hasn't got focus, returning false on
+    /// a timeout.
+    fn wait_for_focus(webview: &Webview, focused: bool, timeout: Duration) -> Result<bool, String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if has_focus(webview)? == focused {
+                return Ok(true);
+            }
+            if Instant::now() >= deadline {
+                return Ok(false);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    fn ask(webview: &Webview) -> Result<AudioPermission, String> {
+        on_activity(webview, |env, activity| {
+            let permission = env
+                .new_string(RECORD_AUDIO)
+                .map_err(|e| format!("Failed to request microphone permission: {}", e))?;
+            let permissions = env
+                .new_object_array(1, "java/lang/String", &permission)
+                .map_err(|e| format!("Failed to request microphone permission: {}", e))?;
+            env.call_method(
+                activity,
+                "requestPermissions",
+                "([Ljava/lang/String;I)V",
+                &[JValue::Object(&permissions), JValue::Int(REQUEST_CODE)],
+            )
+            .map_err(|e| format!("Failed to request microphone permission: {}", e))?;
+            Ok(())
+        })?;
+        ASKED.store(true, Ordering::Relaxed);
+
+        // The prompt takes focus from the activity and gives it back once
+        // it's answered
+        if wait_for_focus(webview, false, PROMPT_SHOWN_TIMEOUT)? {
+            wait_for_focus(webview, true, ANSWER_TIMEOUT)?;
+        }
+        Ok(if granted()? { AudioPermission::Granted } else { AudioPermission::Denied })
+    }
+
+    /// Shows the system prompt from the webview's activity and waits for
+    /// the answer.
+    pub fn request(webview: &Webview) -> AudioPermission {
+        if check() == AudioPermission::Granted {
+            return AudioPermission::Granted;
+        }
+        ask(webview).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            check()
+        })
+    }
+}
+
+/// Elsewhere, e.g. Linux, access to audio devices isn't permission gated.
+#[cfg(not(any(target_os = "macos", target_os = "ios", target_os = "android", windows)))]
+mod platform {
+    use super::AudioPermission;
+    use tauri::Webview;
+
+    pub fn check() -> AudioPermission {
+        AudioPermission::Granted
+    }
+
+    pub fn request(_webview: &Webview) -> AudioPermission {
+        AudioPermission::Granted
+    }
+}
+
+/// Fails with an explanation when the microphone can't be used, rather
+/// than letting the device fail to open.
+pub fn require_audio_permission() -> Result<(), String> {
+    match platform::check() {
+        // Opening the device doesn't prompt on Android, it just fails
+        AudioPermission::NotDetermined if cfg!(target_os = "android") => {
+            Err("Microphone access hasn't been granted yet; ask for it with request_audio_permission".to_string())
+        }
+        AudioPermission::Granted | AudioPermission::NotDetermined => Ok(()),
+        AudioPermission::Denied => {
+            Err("Microphone access is turned off for this app; allow it in the system privacy settings".to_string())
+        }
+        AudioPermission::Restricted => Err("Microphone access is blocked on this device".to_string()),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn check_audio_permission() -> AudioPermission {
+    platform::check()
+}
+
+/// Asks the user for microphone access if they haven't been asked yet and
+/// returns the outcome. Where the system can't prompt, this is the same
+/// as `check_audio_permission`.
+#[tauri::command]
+#[specta::specta]
+pub async fn request_audio_permission(webview: Webview) -> Result<AudioPermission, String> {
+    tauri::async_runtime::spawn_blocking(move || platform::request(&webview))
+        .await
+        .map_err(|e| format!("Permission request failed: {}", e))
+}
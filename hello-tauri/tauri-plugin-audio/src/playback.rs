@@ -0,0 +1,375 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::f32::consts::PI;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::audio_file::ProgressiveAudio;
+use crate::backend::Device;
+use crate::dsp::convolution::Convolver;
+use crate::dsp::eq::Equalizer;
+use crate::dsp::fade::FadeRamp;
+use crate::dsp::resample::StreamResampler;
+use crate::master::MasterChain;
+use crate::output::build_output_stream;
+
+/// Length of each grain played while scrubbing.
+const GRAIN_MS: f32 = 60.0;
+
+/// How long grains keep repeating at a scrub target that has stopped
+/// moving, after which the scrub falls silent like a held playhead.
+const SCRUB_HOLD: Duration = Duration::from_millis(150);
+
+/// How long a stopped playback stays open after its fade out, so the
+/// device has played the faded audio before the stream closes.
+const STOP_MARGIN: Duration = Duration::from_millis(100);
+
+/// Ramps applied when playback starts, pauses, resumes and stops, so
+/// cutting in or out mid-waveform doesn't click.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+pub struct PlaybackFades {
+    pub fade_in_ms: f32,
+    pub fade_out_ms: f32,
+}
+
+impl PlaybackFades {
+    pub const NONE: PlaybackFades = PlaybackFades { fade_in_ms: 0.0, fade_out_ms: 0.0 };
+
+    pub fn frames(ms: f32, sample_rate: u32) -> usize {
+        (ms.max(0.0) / 1000.0 * sample_rate as f32) as usize
+    }
+}
+
+impl Default for PlaybackFades {
+    fn default() -> Self {
+        PlaybackFades { fade_in_ms: 10.0, fade_out_ms: 10.0 }
+    }
+}
+
+/// Where a scrub currently points, in source frames.
+#[derive(Debug, Clone, Copy)]
+pub struct ScrubTarget {
+    pub frame: usize,
+    pub moved_at: Instant,
+}
+
+/// Overlapping Hann-windowed grains started at the scrub target every half
+/// grain, which sum to unity gain while the target stays put.
+struct Scrubber {
+    grain_frames: usize,
+    /// Frames until the next grain starts.
+    countdown: usize,
+    /// `(start, offset)` of each sounding grain.
+    grains: Vec<(usize, usize)>,
+}
+
+impl Scrubber {
+    fn new(sample_rate: u32) -> Self {
+        Scrubber {
+            grain_frames: ((GRAIN_MS / 1000.0 * sample_rate as f32) as usize).max(2),
+            countdown: 0,
+            grains: Vec::new(),
+        }
+    }
+
+    fn render(&mut self, frame: &mut [f32], samples: &[f32], channels: usize, target: Option<ScrubTarget>) {
+        if self.countdown == 0 {
+            if let Some(target) = target.filter(|t| t.moved_at.elapsed() < SCRUB_HOLD) {
+                self.grains.push((target.frame, 0));
+            }
+            self.countdown = self.grain_frames / 2;
+        }
+        self.countdown -= 1;
+
+        let length = self.grain_frames as f32;
+        for (start, offset) in &mut self.grains {
+            let window = 0.5 - 0.5 * (2.0 * PI * *offset as f32 / length).cos();
+            for (channel, out) in frame.iter_mut().enumerate() {
+                *out += source_sample(samples, channels, *start + *offset, channel) * window;
+            }
+            *offset += 1;
+        }
+        let grain_frames = self.grain_frames;
+        self.grains.retain(|&(_, offset)| offset < grain_frames);
+    }
+}
+
+/// Sample for an output channel from interleaved `samples`: a mono source
+/// feeds every channel, while channels the source doesn't have (and frames
+/// not decoded yet) are silent.
+fn source_sample(samples: &[f32], channels: usize, frame: usize, channel: usize) -> f32 {
+    let channel = if channels == 1 { 0 } else { channel };
+    if channel >= channels {
+        return 0.0;
+    }
+    samples.get(frame * channels + channel).copied().unwrap_or(0.0)
+}
+
+/// State shared between a playing output callback and its owner.
+pub struct PlaybackShared {
+    /// May still be decoding while it plays.
+    pub audio: Arc<ProgressiveAudio>,
+    /// Next source frame to be rendered.
+    pub position: Mutex<usize>,
+    /// Fades out, then renders silence and holds the position while set.
+    pub paused: Mutex<bool>,
+    /// Set once the playback has been stopped and is fading out.
+    stopping: Mutex<bool>,
+    pub fades: Mutex<PlaybackFades>,
+    /// `(a, b)` in source frames: reaching `b` jumps back to `a`. Playing
+    /// from beyond `b` runs on to the end.
+    pub ab_loop: Mutex<Option<(usize, usize)>>,
+    /// Grains at the target replace normal playback while scrubbing.
+    pub scrub: Mutex<Option<ScrubTarget>>,
+    /// Applied to the rendered output, set up for the device's rate and
+    /// channels.
+    pub eq: Mutex<Option<Equalizer>>,
+    /// Convolution reverb after the EQ, likewise set up for the device.
+    pub convolver: Mutex<Option<Convolver>>,
+    /// When the first callback rendered audio, used to line captures up
+    /// with the start of playback.
+    pub started_at: Mutex<Option<Instant>>,
+}
+
+impl PlaybackShared {
+    /// Length of the audio in source frames.
+    pub fn frames(&self) -> usize {
+        self.audio.frames()
+    }
+
+    /// Moves playback to `frame`, having the decoder start there if it
+    /// hasn't got that far.
+    pub fn seek(&self, frame: usize) {
+        self.audio.prioritize(frame);
+        *self.position.lock().unwrap() = frame;
+    }
+}
+
+/// Produces output frames at the source rate, laid out for the device's
+/// channels.
+struct SourceRenderer {
+    shared: Arc<PlaybackShared>,
+    scrubber: Scrubber,
+    fade: FadeRamp,
+    channels: usize,
+}
+
+impl SourceRenderer {
+    fn render(&mut self, buffer: &mut [f32]) {
+        let shared = &self.shared;
+        let samples = shared.audio.samples.read().unwrap();
+        let source_channels = shared.audio.channels.max(1) as usize;
+
+        let scrub = *shared.scrub.lock().unwrap();
+        if scrub.is_some() || !self.scrubber.grains.is_empty() {
+            // Grains still sounding when the scrub ends fade out first
+            for frame in buffer.chunks_exact_mut(self.channels) {
+                self.scrubber.render(frame, &samples, source_channels, scrub);
+            }
+            return;
+        }
+        let paused = *shared.paused.lock().unwrap() || *shared.stopping.lock().unwrap();
+        let fades = *shared.fades.lock().unwrap();
+        let fade_ms = if paused { fades.fade_out_ms } else { fades.fade_in_ms };
+        self.fade.ramp_to(!paused, PlaybackFades::frames(fade_ms, shared.audio.sample_rate));
+        if self.fade.is_closed() {
+            return;
+        }
+        let ab_loop = *shared.ab_loop.lock().unwrap();
+        let mut position = shared.position.lock().unwrap();
+        let mut ready = shared.audio.ready_until(*position);
+        shared.started_at.lock().unwrap().get_or_insert_with(Instant::now);
+
+        for frame in buffer.chunks_exact_mut(self.channels) {
+            if let Some((a, _)) = ab_loop.filter(|&(_, b)| *position == b) {
+                *position = a;
+                ready = shared.audio.ready_until(a);
+            }
+            // Past the end, or waiting for the decoder to catch up
+            if *position >= ready || self.fade.is_closed() {
+                break;
+            }
+            let gain = self.fade.next_gain();
+            for (channel, out) in frame.iter_mut().enumerate() {
+                *out = source_sample(&samples, source_channels, *position, channel) * gain;
+            }
+            *position += 1;
+        }
+    }
+}
+
+/// Source frames rendered per pass through the resampler.
+const RESAMPLE_BLOCK_FRAMES: usize = 512;
+
+/// Converts rendered source frames to the device rate as they are needed,
+/// so playback can start without converting the whole file first.
+struct DeviceResampler {
+    resampler: StreamResampler,
+    block: Vec<f32>,
+    /// Device-rate samples not yet handed to the device.
+    queue: VecDeque<f32>,
+    /// Source position after the last block, to notice seeks.
+    rendered_to: usize,
+}
+
+impl DeviceResampler {
+    fn render(&mut self, buffer: &mut [f32], source: &mut SourceRenderer) {
+        // Audio queued from before a seek would be heard after it
+        if *source.shared.position.lock().unwrap() != self.rendered_to {
+            self.queue.clear();
+            self.resampler.reset();
+        }
+
+        while self.queue.len() < buffer.len() {
+            self.block.fill(0.0);
+            source.render(&mut self.block);
+            match self.resampler.process(&self.block) {
+                Ok(resampled) => self.queue.extend(resampled),
+                Err(_) => break,
+            }
+        }
+
+        let ready = buffer.len().min(self.queue.len());
+        for (out, sample) in buffer.iter_mut().zip(self.queue.drain(..ready)) {
+            *out = sample;
+        }
+        self.rendered_to = *source.shared.position.lock().unwrap();
+    }
+}
+
+/// A file playing on an output device. Dropping it stops the stream.
+pub struct Playback {
+    /// The device's rate.
+    pub sample_rate: u32,
+    /// The file's rate, which positions are counted in.
+    pub source_rate: u32,
+    pub channels: u16,
+    pub shared: Arc<PlaybackShared>,
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl Playback {
+    /// Plays `audio` on `device` from `start_frame` (in source frames) at
+    /// the device's default rate, resampling on the fly when the rates
+    /// differ. A mono source is sent to every channel, otherwise extra
+    /// device channels stay silent.
+    pub fn start(
+        device: Device,
+        audio: Arc<ProgressiveAudio>,
+        start_frame: usize,
+        fades: PlaybackFades,
+        master: &MasterChain,
+    ) -> Result<Self, String> {
+        let config = device.default_output_config()
+            .map_err(|e| format!("Failed to get default output config: {}", e))?;
+        let sample_format = config.sample_format();
+        let config: cpal::StreamConfig = config.into();
+        let sample_rate = config.sample_rate.0;
+        let channels = config.channels;
+        let source_rate = audio.sample_rate;
+
+        let shared = Arc::new(PlaybackShared {
+            audio,
+            position: Mutex::new(start_frame),
+            paused: Mutex::new(false),
+            stopping: Mutex::new(false),
+            fades: Mutex::new(fades),
+            ab_loop: Mutex::new(None),
+            scrub: Mutex::new(None),
+            eq: Mutex::new(None),
+            convolver: Mutex::new(None),
+            started_at: Mutex::new(None),
+        });
+
+        let mut resampler = if source_rate == sample_rate {
+            None
+        } else {
+            Some(DeviceResampler {
+                resampler: StreamResampler::new(source_rate, sample_rate, channels as usize)?,
+                block: vec![0.0; RESAMPLE_BLOCK_FRAMES * channels as usize],
+                queue: VecDeque::new(),
+                rendered_to: start_frame,
+            })
+        };
+        let mut source = SourceRenderer {
+            shared: Arc::clone(&shared),
+            scrubber: Scrubber::new(source_rate),
+            fade: FadeRamp::new(false),
+            channels: channels as usize,
+        };
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        // Like input streams, the device stream lives on its own thread
+        let eq_shared = Arc::clone(&shared);
+        let mut master = master.processor(sample_rate, channels as usize);
+        thread::spawn(move || {
+            let render = move |buffer: &mut [f32]| {
+                match resampler.as_mut() {
+                    Some(resampler) => resampler.render(buffer, &mut source),
+                    None => source.render(buffer),
+                }
+                if let Some(eq) = eq_shared.eq.lock().unwrap().as_mut() {
+                    eq.process(buffer);
+                }
+                if let Some(convolver) = eq_shared.convolver.lock().unwrap().as_mut() {
+                    convolver.process(buffer);
+                }
+                master.process(buffer);
+            };
+
+            let stream = match build_output_stream(&device, &config, sample_format, render) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            if let Err(e) = stream.play() {
+                let _ = ready_tx.send(Err(format!("Failed to play stream: {}", e)));
+                return;
+            }
+
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+        });
+
+        ready_rx.recv()
+            .map_err(|_| "Playback thread exited unexpectedly".to_string())??;
+
+        Ok(Playback {
+            sample_rate,
+            source_rate,
+            channels,
+            shared,
+            stop_tx,
+        })
+    }
+}
+
+impl Playback {
+    /// Fades out and closes the stream once the fade has been heard,
+    /// without waiting for it. Dropping instead cuts off straight away.
+    pub fn stop(self) {
+        let fade_out_ms = self.shared.fades.lock().unwrap().fade_out_ms;
+        if fade_out_ms <= 0.0 {
+            return;
+        }
+        *self.shared.stopping.lock().unwrap() = true;
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs_f32(fade_out_ms / 1000.0) + STOP_MARGIN);
+            drop(self);
+        });
+    }
+}
+
+impl Drop for Playback {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+    }
+}
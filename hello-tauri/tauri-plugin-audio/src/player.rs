@@ -0,0 +1,461 @@
+use serde::Serialize;
+use specta::Type;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+
+use crate::audio_file::open_progressive;
+use crate::backend;
+use crate::clips::ClipCache;
+use crate::convolution::{live_convolver, ConvolutionSettings};
+use crate::dsp::eq::{EqBand, Equalizer};
+use crate::events;
+use crate::latency::output_latency;
+use crate::lifecycle::{self, LifecycleState, Subsystem};
+use crate::master::MasterChain;
+use crate::output::find_output_device;
+use crate::playback::{Playback, PlaybackFades, ScrubTarget};
+use crate::settings::SettingsStore;
+use crate::ticker::Ticker;
+use crate::validate;
+
+/// How often `playback://position` is emitted unless the caller chooses.
+const DEFAULT_POSITION_INTERVAL_MS: u64 = 50;
+
+/// Allowed range for the position event interval, in ms.
+const POSITION_INTERVAL_RANGE_MS: (u64, u64) = (10, 1000);
+
+/// Allowed range for playback fades, in ms. Zero turns a fade off.
+const FADE_RANGE_MS: (f32, f32) = (0.0, 500.0);
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "playback://position")]
+pub(crate) struct PlaybackPosition {
+    path: String,
+    position_ms: f64,
+    duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "playback://ended")]
+pub(crate) struct PlaybackEnded {
+    path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PlaybackStatus {
+    pub path: String,
+    pub position_ms: f64,
+    pub duration_ms: f64,
+    /// How much of the file is ready to play from its start. Compressed
+    /// files keep decoding in the background after playback starts; seeking
+    /// further in decodes around the new position first.
+    pub decoded_ms: f64,
+    pub decoding: bool,
+    /// Set when decoding stopped early; the rest of the file is missing.
+    pub decode_error: Option<String>,
+    pub paused: bool,
+    pub ended: bool,
+    /// Repeat points in ms, when an A-B loop is set.
+    pub ab_loop: Option<(f64, f64)>,
+    pub scrubbing: bool,
+    /// EQ bands applied to the output, empty when bypassed.
+    pub eq: Vec<EqBand>,
+    pub convolution: Option<ConvolutionSettings>,
+}
+
+/// A file loaded into the player. It stays loaded after reaching the end so
+/// it can be sought back into.
+struct Player {
+    app: AppHandle,
+    path: String,
+    device_name: String,
+    /// How late the output is heard, taken off reported positions.
+    output_latency_ms: Arc<Mutex<f64>>,
+    playback: Playback,
+    eq: Vec<EqBand>,
+    convolution: Option<ConvolutionSettings>,
+    /// Emits position events and notices the end of the file.
+    ticker: Ticker,
+}
+
+impl Player {
+    fn frames_to_ms(&self, frames: usize) -> f64 {
+        frames as f64 * 1000.0 / self.playback.source_rate as f64
+    }
+
+    /// Positions trail the rendered one by the output latency, matching
+    /// `playback://position`.
+    fn status(&self) -> PlaybackStatus {
+        let shared = &self.playback.shared;
+        let position = *shared.position.lock().unwrap();
+        let latency_ms = *self.output_latency_ms.lock().unwrap();
+        PlaybackStatus {
+            path: self.path.clone(),
+            position_ms: (self.frames_to_ms(position.min(shared.frames())) - latency_ms).max(0.0),
+            duration_ms: self.frames_to_ms(shared.frames()),
+            decoded_ms: self.frames_to_ms(shared.audio.decoded_frames()),
+            decoding: !shared.audio.is_complete(),
+            decode_error: shared.audio.error(),
+            paused: *shared.paused.lock().unwrap(),
+            ended: position >= shared.frames(),
+            ab_loop: shared.ab_loop.lock().unwrap()
+                .map(|(a, b)| (self.frames_to_ms(a), self.frames_to_ms(b))),
+            scrubbing: shared.scrub.lock().unwrap().is_some(),
+            eq: self.eq.clone(),
+            convolution: self.convolution.clone(),
+        }
+    }
+
+    fn ms_to_frames(&self, ms: f64) -> usize {
+        ((ms.max(0.0) / 1000.0 * self.playback.source_rate as f64) as usize).min(self.playback.shared.frames())
+    }
+
+    fn set_eq(&mut self, bands: Vec<EqBand>) -> Result<(), String> {
+        let playback = &self.playback;
+        let eq = if bands.is_empty() {
+            None
+        } else {
+            Some(Equalizer::new(&bands, playback.sample_rate, playback.channels as usize)?)
+        };
+        *playback.shared.eq.lock().unwrap() = eq;
+        self.eq = bands;
+        Ok(())
+    }
+
+    fn set_convolution(&mut self, settings: Option<ConvolutionSettings>) -> Result<(), String> {
+        let playback = &self.playback;
+        let convolver = settings.as_ref()
+            .map(|settings| live_convolver(settings, playback.sample_rate, playback.channels as usize))
+            .transpose()?;
+        *playback.shared.convolver.lock().unwrap() = convolver;
+        self.convolution = settings;
+        Ok(())
+    }
+
+    /// Reports pausing or resuming, unless the file has already ended.
+    fn report_paused(&self) {
+        let shared = &self.playback.shared;
+        if *shared.position.lock().unwrap() >= shared.frames() {
+            return;
+        }
+        let state = if *shared.paused.lock().unwrap() { LifecycleState::Paused } else { LifecycleState::Running };
+        lifecycle::transition(&self.app, Subsystem::Playback, &self.path, state, None);
+    }
+
+    fn set_position_interval(&mut self, interval_ms: u64) {
+        let (app, path) = (self.app.clone(), self.path.clone());
+        self.ticker = position_ticker(app, path, &self.playback, &self.output_latency_ms, interval_ms);
+    }
+}
+
+/// Position events report what is being heard, so they trail the rendered
+/// position by the output's latency.
+fn position_ticker(
+    app: AppHandle,
+    path: String,
+    playback: &Playback,
+    output_latency_ms: &Arc<Mutex<f64>>,
+    interval_ms: u64,
+) -> Ticker {
+    let shared = Arc::clone(&playback.shared);
+    let output_latency_ms = Arc::clone(output_latency_ms);
+    let sample_rate = playback.source_rate as f64;
+    // A ticker replaced after the end doesn't report it a second time
+    let mut reported_end = *shared.position.lock().unwrap() >= shared.frames();
+
+    Ticker::spawn(Duration::from_millis(interval_ms), move || {
+        let position = *shared.position.lock().unwrap();
+        let paused = *shared.paused.lock().unwrap();
+        let ended = position >= shared.frames();
+
+        if !ended && !paused {
+            let latency_ms = *output_latency_ms.lock().unwrap();
+            events::emit(&app, "playback://position", &path, PlaybackPosition {
+                path: path.clone(),
+                position_ms: (position as f64 * 1000.0 / sample_rate - latency_ms).max(0.0),
+                duration_ms: shared.frames() as f64 * 1000.0 / sample_rate,
+            });
+        }
+
+        // Reported once per pass; seeking back arms it again
+        if ended && !reported_end {
+            events::discard(&app, "playback://position", &path);
+            events::emit(&app, "playback://ended", &path, PlaybackEnded { path: path.clone() });
+            lifecycle::update(&app, Subsystem::Playback, &path, LifecycleState::Ended, None);
+        }
+        if !ended && reported_end {
+            let state = if paused { LifecycleState::Paused } else { LifecycleState::Running };
+            lifecycle::update(&app, Subsystem::Playback, &path, state, None);
+        }
+        reported_end = ended;
+    })
+}
+
+fn check_interval(interval_ms: u64) -> Result<(), String> {
+    validate::range("Position interval", interval_ms, POSITION_INTERVAL_RANGE_MS, "ms")?;
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct PlayerState {
+    player: Mutex<Option<Player>>,
+}
+
+impl PlayerState {
+    /// Applies a changed latency offset if playing on `device_name`.
+    pub fn set_output_latency(&self, device_name: &str, offset_ms: f64) {
+        if let Some(player) = self.player.lock().unwrap().as_ref().filter(|p| p.device_name == device_name) {
+            *player.output_latency_ms.lock().unwrap() = offset_ms;
+        }
+    }
+
+    /// Stops whatever is playing.
+    pub fn close(&self) {
+        if let Some(player) = self.player.lock().unwrap().take() {
+            player.playback.stop();
+            let reason = Some("Shutting down".to_string());
+            lifecycle::transition(&player.app, Subsystem::Playback, &player.path, LifecycleState::Stopped, reason);
+        }
+    }
+}
+
+fn with_player<T>(state: &PlayerState, f: impl FnOnce(&mut Player) -> Result<T, String>) -> Result<T, String> {
+    let mut player = state.player.lock().unwrap();
+    let player = player.as_mut().ok_or_else(|| "Nothing is loaded in the player".to_string())?;
+    f(player)
+}
+
+/// Plays `path` on `output_device` (the default output when omitted) from
+/// `start_ms`, replacing whatever was playing. Progress is reported through
+/// `playback://position` every `position_interval_ms` and the end of the
+/// file through `playback://ended`.
+#[tauri::command]
+#[specta::specta]
+pub async fn play_file(
+    path: String,
+    output_device: Option<String>,
+    start_ms: Option<f64>,
+    position_interval_ms: Option<u64>,
+    app: AppHandle,
+    state: State<'_, PlayerState>,
+    settings: State<'_, SettingsStore>,
+) -> Result<PlaybackStatus, String> {
+    let interval_ms = position_interval_ms.unwrap_or(DEFAULT_POSITION_INTERVAL_MS);
+    check_interval(interval_ms)?;
+
+    let file_path = path.clone();
+    let audio = tauri::async_runtime::spawn_blocking(move || open_progressive(Path::new(&file_path)))
+        .await
+        .map_err(|e| format!("Load task failed: {}", e))??;
+
+    let device = match output_device {
+        Some(id) => find_output_device(&id)?,
+        None => backend::host()
+            .default_output_device()
+            .ok_or_else(|| "No default output device".to_string())?,
+    };
+
+    let mut player = state.player.lock().unwrap();
+    // The previous file fades out under the start of this one
+    let (eq, convolution) = match player.take() {
+        Some(previous) => {
+            previous.playback.stop();
+            let reason = Some("Replaced by another file".to_string());
+            lifecycle::transition(&app, Subsystem::Playback, &previous.path, LifecycleState::Stopped, reason);
+            (previous.eq, previous.convolution)
+        }
+        None => (Vec::new(), None),
+    };
+
+    let start_frame = (start_ms.unwrap_or(0.0).max(0.0) / 1000.0 * audio.sample_rate as f64) as usize;
+    audio.prioritize(start_frame);
+    let latency = output_latency(&device, &settings);
+    let output_latency_ms = Arc::new(Mutex::new(latency.offset_ms));
+    let master = app.state::<MasterChain>();
+    let playback = Playback::start(device, audio, start_frame, settings.get().playback_fades, &master)?;
+    lifecycle::transition(&app, Subsystem::Playback, &path, LifecycleState::Running, None);
+    let ticker = position_ticker(app.clone(), path.clone(), &playback, &output_latency_ms, interval_ms);
+
+    let mut loaded = Player {
+        app,
+        path,
+        device_name: latency.device_name,
+        output_latency_ms,
+        playback,
+        eq: Vec::new(),
+        convolution: None,
+        ticker,
+    };
+    loaded.set_eq(eq)?;
+    loaded.set_convolution(convolution)?;
+    let status = loaded.status();
+    *player = Some(loaded);
+    Ok(status)
+}
+
+/// Pauses the player if it is playing and resumes it if paused, returning
+/// whether it is now paused.
+pub fn toggle_pause(state: &PlayerState) -> Result<bool, String> {
+    with_player(state, |player| {
+        let paused = {
+            let mut paused = player.playback.shared.paused.lock().unwrap();
+            *paused = !*paused;
+            *paused
+        };
+        player.report_paused();
+        Ok(paused)
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_playback(state: State<PlayerState>) -> Result<(), String> {
+    let player = state.player.lock().unwrap().take()
+        .ok_or_else(|| "Nothing is loaded in the player".to_string())?;
+    player.playback.stop();
+    lifecycle::transition(&player.app, Subsystem::Playback, &player.path, LifecycleState::Stopped, None);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn pause_playback(paused: bool, state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        *player.playback.shared.paused.lock().unwrap() = paused;
+        player.report_paused();
+        Ok(player.status())
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn seek_playback(position_ms: f64, state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        player.playback.shared.seek(player.ms_to_frames(position_ms));
+        Ok(player.status())
+    })
+}
+
+/// Repeats the span between `a_ms` and `b_ms` until cleared by passing
+/// neither. The jump back happens on the exact frame, so the loop length
+/// doesn't drift however often it repeats. Playback already past `b_ms`
+/// carries on to the end.
+#[tauri::command]
+#[specta::specta]
+pub fn set_ab_loop(a_ms: Option<f64>, b_ms: Option<f64>, state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        let ab_loop = match (a_ms, b_ms) {
+            (Some(a_ms), Some(b_ms)) => {
+                let (a, b) = (player.ms_to_frames(a_ms), player.ms_to_frames(b_ms));
+                if b <= a {
+                    return Err("Loop end must come after its start".to_string());
+                }
+                Some((a, b))
+            }
+            (None, None) => None,
+            _ => return Err("Both loop points are needed to set a loop".to_string()),
+        };
+
+        *player.playback.shared.ab_loop.lock().unwrap() = ab_loop;
+        Ok(player.status())
+    })
+}
+
+/// Moves the scrub point, starting a scrub if none is running. Call it as
+/// the playhead is dragged: short grains at the point are played over and
+/// over, falling silent shortly after the drag stops moving.
+#[tauri::command]
+#[specta::specta]
+pub fn scrub_to(position_ms: f64, state: State<PlayerState>) -> Result<(), String> {
+    with_player(&state, |player| {
+        let frame = player.ms_to_frames(position_ms);
+        player.playback.shared.audio.prioritize(frame);
+        *player.playback.shared.scrub.lock().unwrap() = Some(ScrubTarget {
+            frame,
+            moved_at: Instant::now(),
+        });
+        Ok(())
+    })
+}
+
+/// Ends the scrub, leaving the playhead at the last scrub point. Playback
+/// carries on from there unless paused.
+#[tauri::command]
+#[specta::specta]
+pub fn end_scrub(state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        let shared = &player.playback.shared;
+        let scrub = shared.scrub.lock().unwrap().take()
+            .ok_or_else(|| "Not scrubbing".to_string())?;
+        shared.seek(scrub.frame);
+        Ok(player.status())
+    })
+}
+
+/// Runs the player's output through `bands`, in order, so a file can be
+/// auditioned with corrections before processing it offline. An empty list
+/// bypasses the EQ. Stays in place when another file is played.
+#[tauri::command]
+#[specta::specta]
+pub fn set_playback_eq(bands: Vec<EqBand>, state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        player.set_eq(bands)?;
+        Ok(player.status())
+    })
+}
+
+/// Runs the player's output through a convolution reverb, or takes it off
+/// with no settings. Like the EQ, it stays in place across files.
+#[tauri::command]
+#[specta::specta]
+pub fn set_playback_convolution(
+    settings: Option<ConvolutionSettings>,
+    state: State<PlayerState>,
+) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| {
+        player.set_convolution(settings)?;
+        Ok(player.status())
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_position_interval(interval_ms: u64, state: State<PlayerState>) -> Result<(), String> {
+    check_interval(interval_ms)?;
+    with_player(&state, |player| {
+        player.set_position_interval(interval_ms);
+        Ok(())
+    })
+}
+
+/// Sets the fades the player applies when starting, pausing, resuming and
+/// stopping, and the clip output when triggering and stopping clips. The
+/// setting is saved and takes effect straight away.
+#[tauri::command]
+#[specta::specta]
+pub fn set_playback_fades(
+    fades: PlaybackFades,
+    state: State<PlayerState>,
+    cache: State<ClipCache>,
+    settings: State<SettingsStore>,
+) -> Result<(), String> {
+    for ms in [fades.fade_in_ms, fades.fade_out_ms] {
+        validate::range("Fades", ms, FADE_RANGE_MS, "ms")?;
+    }
+
+    settings.update(|s| s.playback_fades = fades)?;
+    cache.set_fades(fades);
+    if let Some(player) = state.player.lock().unwrap().as_ref() {
+        *player.playback.shared.fades.lock().unwrap() = fades;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_playback_status(state: State<PlayerState>) -> Result<PlaybackStatus, String> {
+    with_player(&state, |player| Ok(player.status()))
+}
@@ -0,0 +1,413 @@
+use clap_sys::audio_buffer::clap_audio_buffer;
+use clap_sys::entry::clap_plugin_entry;
+use clap_sys::events::{
+    clap_event_header, clap_event_param_value, clap_input_events, clap_output_events, CLAP_CORE_EVENT_SPACE_ID,
+    CLAP_EVENT_PARAM_VALUE,
+};
+use clap_sys::ext::audio_ports::{clap_audio_port_info, clap_plugin_audio_ports, CLAP_EXT_AUDIO_PORTS};
+use clap_sys::ext::latency::{clap_plugin_latency, CLAP_EXT_LATENCY};
+use clap_sys::ext::params::{
+    clap_param_info, clap_plugin_params, CLAP_EXT_PARAMS, CLAP_PARAM_IS_HIDDEN, CLAP_PARAM_IS_READONLY,
+    CLAP_PARAM_IS_STEPPED,
+};
+use clap_sys::factory::plugin_factory::{clap_plugin_factory, CLAP_PLUGIN_FACTORY_ID};
+use clap_sys::host::clap_host;
+use clap_sys::plugin::{clap_plugin, clap_plugin_descriptor};
+use clap_sys::plugin_features::CLAP_PLUGIN_FEATURE_AUDIO_EFFECT;
+use clap_sys::process::{clap_process, CLAP_PROCESS_ERROR};
+use clap_sys::version::{clap_version_is_compatible, CLAP_VERSION};
+use libloading::Library;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::{mem, ptr};
+
+use super::{fixed_string, PluginDescriptor, PluginFormat, PluginParameter, PluginProcessor, PluginRef, PluginSetup};
+
+/// The binary inside a plugin. On macOS a `.clap` is a bundle.
+fn binary_path(path: &Path) -> PathBuf {
+    match path.file_stem() {
+        Some(stem) if path.is_dir() => path.join("Contents").join("MacOS").join(stem),
+        _ => path.to_path_buf(),
+    }
+}
+
+unsafe fn c_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(ptr).to_string_lossy().into_owned()
+}
+
+/// A loaded `.clap` file. The entry is deinitialized before the library is
+/// unloaded.
+struct Module {
+    entry: *const clap_plugin_entry,
+    _library: Library,
+}
+
+impl Module {
+    fn open(path: &Path) -> Result<Self, String> {
+        let binary = binary_path(path);
+        let library = unsafe { Library::new(&binary) }
+            .map_err(|e| format!("Failed to load {}: {}", binary.display(), e))?;
+        let entry = unsafe { library.get::<*const clap_plugin_entry>(b"clap_entry\0") }
+            .map(|symbol| *symbol)
+            .map_err(|e| format!("{} is not a CLAP plugin: {}", path.display(), e))?;
+
+        let entry_ref = unsafe { &*entry };
+        if !clap_version_is_compatible(entry_ref.clap_version) {
+            return Err(format!("{} needs a newer CLAP version", path.display()));
+        }
+        let plugin_path = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|e| format!("Invalid plugin path: {}", e))?;
+        let initialized = entry_ref.init.is_some_and(|init| unsafe { init(plugin_path.as_ptr()) });
+        if !initialized {
+            return Err(format!("{} failed to initialize", path.display()));
+        }
+        Ok(Module { entry, _library: library })
+    }
+
+    fn factory(&self) -> Result<&clap_plugin_factory, String> {
+        let entry = unsafe { &*self.entry };
+        let factory = entry.get_factory
+            .map_or(ptr::null(), |get| unsafe { get(CLAP_PLUGIN_FACTORY_ID.as_ptr()) });
+        if factory.is_null() {
+            return Err("Plugin has no plugin factory".to_string());
+        }
+        Ok(unsafe { &*(factory as *const clap_plugin_factory) })
+    }
+
+    fn descriptors(&self, path: &Path) -> Result<Vec<PluginDescriptor>, String> {
+        let factory = self.factory()?;
+        let count = factory.get_plugin_count.map_or(0, |count| unsafe { count(factory) });
+        let descriptors = (0..count)
+            .filter_map(|index| {
+                let descriptor = factory.get_plugin_descriptor
+                    .map_or(ptr::null(), |get| unsafe { get(factory, index) });
+                unsafe { descriptor.as_ref() }.map(|descriptor| unsafe { describe(path, descriptor) })
+            })
+            .collect();
+        Ok(descriptors)
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        if let Some(deinit) = unsafe { (*self.entry).deinit } {
+            unsafe { deinit() };
+        }
+    }
+}
+
+unsafe fn describe(path: &Path, descriptor: &clap_plugin_descriptor) -> PluginDescriptor {
+    let mut categories = Vec::new();
+    let mut feature = descriptor.features;
+    while !feature.is_null() && !(*feature).is_null() {
+        categories.push(c_string(*feature));
+        feature = feature.add(1);
+    }
+
+    PluginDescriptor {
+        plugin: PluginRef {
+            format: PluginFormat::Clap,
+            path: path.display().to_string(),
+            plugin_id: c_string(descriptor.id),
+        },
+        name: c_string(descriptor.name),
+        vendor: c_string(descriptor.vendor),
+        categories,
+    }
+}
+
+/// Effect plugins in a `.clap` file.
+pub fn scan(path: &Path) -> Result<Vec<PluginDescriptor>, String> {
+    let effect = CLAP_PLUGIN_FEATURE_AUDIO_EFFECT.to_string_lossy();
+    Ok(Module::open(path)?.descriptors(path)?.into_iter()
+        .filter(|descriptor| descriptor.categories.iter().any(|c| *c == effect))
+        .collect())
+}
+
+unsafe extern "C" fn host_get_extension(_host: *const clap_host, _id: *const c_char) -> *const c_void {
+    ptr::null()
+}
+
+/// Restart, process and main-thread callback requests are ignored: the
+/// plugin is kept active and processing for as long as it is loaded.
+unsafe extern "C" fn host_request(_host: *const clap_host) {}
+
+unsafe extern "C" fn events_size(list: *const clap_input_events) -> u32 {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+    events.len() as u32
+}
+
+unsafe extern "C" fn events_get(list: *const clap_input_events, index: u32) -> *const clap_event_header {
+    let events = &*((*list).ctx as *const Vec<clap_event_param_value>);
+    events.get(index as usize).map_or(ptr::null(), |event| &event.header)
+}
+
+/// Parameter changes the plugin sends back aren't needed; values are read
+/// on request instead.
+unsafe extern "C" fn discard_event(_list: *const clap_output_events, _event: *const clap_event_header) -> bool {
+    true
+}
+
+unsafe fn extension<T>(plugin: *const clap_plugin, id: &CStr) -> *const T {
+    (*plugin).get_extension.map_or(ptr::null(), |get| get(plugin, id.as_ptr())) as *const T
+}
+
+/// Channels on the plugin's first input or output port.
+unsafe fn main_port_channels(
+    plugin: *const clap_plugin,
+    ports: *const clap_plugin_audio_ports,
+    is_input: bool,
+    fallback: usize,
+) -> usize {
+    let Some(ports) = ports.as_ref() else {
+        return fallback;
+    };
+    let (Some(count), Some(get)) = (ports.count, ports.get) else {
+        return fallback;
+    };
+    if count(plugin, is_input) == 0 {
+        return 0;
+    }
+    let mut info: clap_audio_port_info = mem::zeroed();
+    if get(plugin, 0, is_input, &mut info) {
+        info.channel_count as usize
+    } else {
+        fallback
+    }
+}
+
+pub struct ClapPlugin {
+    plugin: *const clap_plugin,
+    params: *const clap_plugin_params,
+    latency: *const clap_plugin_latency,
+    channels: (usize, usize),
+    activated: bool,
+    processing: bool,
+    steady_time: i64,
+    /// Parameter changes for the next block.
+    pending: Vec<clap_event_param_value>,
+    input_ptrs: Vec<*mut f32>,
+    output_ptrs: Vec<*mut f32>,
+    /// Must outlive the plugin, which keeps a pointer to it.
+    _host: Box<clap_host>,
+    _module: Module,
+}
+
+// The plugin is only ever called from one thread at a time, through the
+// Mutex around its `PluginInstance`.
+unsafe impl Send for ClapPlugin {}
+
+pub fn load(plugin: &PluginRef, setup: &PluginSetup) -> Result<(Box<dyn PluginProcessor>, PluginDescriptor), String> {
+    let path = Path::new(&plugin.path);
+    let module = Module::open(path)?;
+    let descriptor = module.descriptors(path)?.into_iter()
+        .find(|descriptor| descriptor.plugin.plugin_id == plugin.plugin_id)
+        .ok_or_else(|| format!("{} has no plugin '{}'", path.display(), plugin.plugin_id))?;
+
+    let host = Box::new(clap_host {
+        clap_version: CLAP_VERSION,
+        host_data: ptr::null_mut(),
+        name: concat!(env!("CARGO_PKG_NAME"), "\0").as_ptr() as *const c_char,
+        vendor: ptr::null(),
+        url: ptr::null(),
+        version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        get_extension: Some(host_get_extension),
+        request_restart: Some(host_request),
+        request_process: Some(host_request),
+        request_callback: Some(host_request),
+    });
+
+    let factory = module.factory()?;
+    let plugin_id = CString::new(plugin.plugin_id.as_str()).map_err(|e| format!("Invalid plugin id: {}", e))?;
+    let instance = factory.create_plugin
+        .map_or(ptr::null(), |create| unsafe { create(factory, &*host, plugin_id.as_ptr()) });
+    if instance.is_null() {
+        return Err(format!("{} could not be created", descriptor.name));
+    }
+
+    // From here on dropping the plugin destroys the instance
+    let mut loaded = ClapPlugin {
+        plugin: instance,
+        params: ptr::null(),
+        latency: ptr::null(),
+        channels: (0, 0),
+        activated: false,
+        processing: false,
+        steady_time: 0,
+        pending: Vec::new(),
+        input_ptrs: Vec::new(),
+        output_ptrs: Vec::new(),
+        _host: host,
+        _module: module,
+    };
+
+    unsafe {
+        if !(*instance).init.is_some_and(|init| init(instance)) {
+            return Err(format!("{} failed to initialize", descriptor.name));
+        }
+        loaded.params = extension(instance, CLAP_EXT_PARAMS);
+        loaded.latency = extension(instance, CLAP_EXT_LATENCY);
+        let ports = extension::<clap_plugin_audio_ports>(instance, CLAP_EXT_AUDIO_PORTS);
+        loaded.channels = (
+            main_port_channels(instance, ports, true, setup.channels),
+            main_port_channels(instance, ports, false, setup.channels),
+        );
+
+        loaded.activated = (*instance).activate
+            .is_some_and(|activate| activate(instance, setup.sample_rate as f64, 1, setup.max_frames as u32));
+        if !loaded.activated {
+            return Err(format!("{} failed to activate", descriptor.name));
+        }
+    }
+
+    Ok((Box::new(loaded), descriptor))
+}
+
+impl PluginProcessor for ClapPlugin {
+    fn channels(&self) -> (usize, usize) {
+        self.channels
+    }
+
+    fn latency_frames(&self) -> usize {
+        unsafe { self.latency.as_ref() }
+            .and_then(|latency| latency.get)
+            .map_or(0, |get| unsafe { get(self.plugin) } as usize)
+    }
+
+    fn process(&mut self, inputs: &mut [Vec<f32>], outputs: &mut [Vec<f32>], frames: usize) -> Result<(), String> {
+        let plugin = unsafe { &*self.plugin };
+        if !self.processing {
+            // Processing starts on the thread that will keep calling process
+            if !plugin.start_processing.is_none_or(|start| unsafe { start(self.plugin) }) {
+                return Err("Plugin refused to start processing".to_string());
+            }
+            self.processing = true;
+        }
+
+        self.input_ptrs.clear();
+        self.input_ptrs.extend(inputs.iter_mut().map(|channel| channel.as_mut_ptr()));
+        self.output_ptrs.clear();
+        self.output_ptrs.extend(outputs.iter_mut().map(|channel| channel.as_mut_ptr()));
+
+        let input = clap_audio_buffer {
+            data32: self.input_ptrs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.input_ptrs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let mut output = clap_audio_buffer {
+            data32: self.output_ptrs.as_mut_ptr(),
+            data64: ptr::null_mut(),
+            channel_count: self.output_ptrs.len() as u32,
+            latency: 0,
+            constant_mask: 0,
+        };
+        let in_events = clap_input_events {
+            ctx: &mut self.pending as *mut Vec<clap_event_param_value> as *mut c_void,
+            size: Some(events_size),
+            get: Some(events_get),
+        };
+        let out_events = clap_output_events {
+            ctx: ptr::null_mut(),
+            try_push: Some(discard_event),
+        };
+        let (has_input, has_output) = (!inputs.is_empty(), !outputs.is_empty());
+        let process = clap_process {
+            steady_time: self.steady_time,
+            frames_count: frames as u32,
+            transport: ptr::null(),
+            audio_inputs: if has_input { &input } else { ptr::null() },
+            audio_outputs: if has_output { &mut output } else { ptr::null_mut() },
+            audio_inputs_count: has_input as u32,
+            audio_outputs_count: has_output as u32,
+            in_events: &in_events,
+            out_events: &out_events,
+        };
+
+        let status = plugin.process.map_or(CLAP_PROCESS_ERROR, |process_fn| unsafe { process_fn(self.plugin, &process) });
+        self.pending.clear();
+        self.steady_time += frames as i64;
+        if status == CLAP_PROCESS_ERROR {
+            return Err("Plugin reported a processing error".to_string());
+        }
+        Ok(())
+    }
+
+    fn parameters(&self) -> Vec<PluginParameter> {
+        let Some(params) = (unsafe { self.params.as_ref() }) else {
+            return Vec::new();
+        };
+        let (Some(count), Some(get_info), Some(get_value)) = (params.count, params.get_info, params.get_value) else {
+            return Vec::new();
+        };
+
+        (0..unsafe { count(self.plugin) })
+            .filter_map(|index| {
+                let mut info: clap_param_info = unsafe { mem::zeroed() };
+                if !unsafe { get_info(self.plugin, index, &mut info) } || info.flags & CLAP_PARAM_IS_HIDDEN != 0 {
+                    return None;
+                }
+                let mut value = info.default_value;
+                unsafe { get_value(self.plugin, info.id, &mut value) };
+
+                let stepped = info.flags & CLAP_PARAM_IS_STEPPED != 0;
+                Some(PluginParameter {
+                    id: info.id,
+                    name: fixed_string(&info.name),
+                    units: String::new(),
+                    min: info.min_value,
+                    max: info.max_value,
+                    default: info.default_value,
+                    value,
+                    step_count: if stepped { (info.max_value - info.min_value).round() as u32 } else { 0 },
+                    read_only: info.flags & CLAP_PARAM_IS_READONLY != 0,
+                })
+            })
+            .collect()
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String> {
+        if self.params.is_null() {
+            return Err("Plugin has no parameters".to_string());
+        }
+        self.pending.retain(|event| event.param_id != id);
+        self.pending.push(clap_event_param_value {
+            header: clap_event_header {
+                size: mem::size_of::<clap_event_param_value>() as u32,
+                time: 0,
+                space_id: CLAP_CORE_EVENT_SPACE_ID,
+                type_: CLAP_EVENT_PARAM_VALUE,
+                flags: 0,
+            },
+            param_id: id,
+            cookie: ptr::null_mut(),
+            note_id: -1,
+            port_index: -1,
+            channel: -1,
+            key: -1,
+            value,
+        });
+        Ok(())
+    }
+}
+
+impl Drop for ClapPlugin {
+    fn drop(&mut self) {
+        let plugin = unsafe { &*self.plugin };
+        unsafe {
+            if let (true, Some(stop)) = (self.processing, plugin.stop_processing) {
+                stop(self.plugin);
+            }
+            if let (true, Some(deactivate)) = (self.activated, plugin.deactivate) {
+                deactivate(self.plugin);
+            }
+            if let Some(destroy) = plugin.destroy {
+                destroy(self.plugin);
+            }
+        }
+    }
+}
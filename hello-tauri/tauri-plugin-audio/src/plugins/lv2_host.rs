@@ -0,0 +1,370 @@
+use lilv::instance::ActiveInstance;
+use lilv::node::Node;
+use lilv::plugin::Plugin;
+use lilv::World;
+use lv2_raw::core::LV2Feature;
+use lv2_raw::urid::{LV2Urid, LV2UridMap, LV2UridMapHandle};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{PluginDescriptor, PluginFormat, PluginParameter, PluginProcessor, PluginRef, PluginSetup};
+
+const LV2_CORE: &str = "http://lv2plug.in/ns/lv2core#";
+const URID_MAP: &CStr = c"http://lv2plug.in/ns/ext/urid#map";
+const ATOM_PORT: &str = "http://lv2plug.in/ns/ext/atom#AtomPort";
+const ATOM_SEQUENCE: &CStr = c"http://lv2plug.in/ns/ext/atom#Sequence";
+const ATOM_CHUNK: &CStr = c"http://lv2plug.in/ns/ext/atom#Chunk";
+const UNITS_UNIT: &str = "http://lv2plug.in/ns/extensions/units#unit";
+
+/// Space for the events a plugin may write to an atom output port.
+const ATOM_BUFFER_BYTES: usize = 8192;
+
+/// Frames run through a plugin at load so it reports its latency.
+const LATENCY_PROBE_FRAMES: usize = 64;
+
+/// Node for a term in the LV2 core vocabulary, such as `AudioPort`.
+fn core_uri(world: &World, term: &str) -> Node {
+    world.new_uri(&format!("{}{}", LV2_CORE, term))
+}
+
+fn bundle_uri(world: &World, path: &Path) -> Node {
+    let path = path.to_string_lossy();
+    world.new_file_uri(None, &format!("{}/", path.trim_end_matches('/')))
+}
+
+fn bundle_path(plugin: &Plugin) -> String {
+    plugin.bundle_uri().path()
+        .map(|(_, path)| path.trim_end_matches('/').to_string())
+        .unwrap_or_default()
+}
+
+fn node_string(node: Option<Node>) -> String {
+    node.and_then(|node| node.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+fn audio_port_counts(world: &World, plugin: &Plugin) -> (usize, usize) {
+    let audio = core_uri(world, "AudioPort");
+    let input = core_uri(world, "InputPort");
+    let output = core_uri(world, "OutputPort");
+    (
+        plugin.num_ports_of_class([&audio, &input]),
+        plugin.num_ports_of_class([&audio, &output]),
+    )
+}
+
+fn describe(plugin: &Plugin) -> PluginDescriptor {
+    let label = node_string(Some(plugin.class().label()));
+    PluginDescriptor {
+        plugin: PluginRef {
+            format: PluginFormat::Lv2,
+            path: bundle_path(plugin),
+            plugin_id: node_string(Some(plugin.uri())),
+        },
+        name: node_string(Some(plugin.name())),
+        vendor: node_string(plugin.author_name()),
+        categories: Some(label).filter(|label| !label.is_empty() && label != "Plugin").into_iter().collect(),
+    }
+}
+
+/// Effects in the given bundles. The bundles are read together so plugins
+/// can use the class and unit definitions from the LV2 specification
+/// bundles installed alongside them.
+pub fn scan(bundles: &[PathBuf]) -> Result<Vec<PluginDescriptor>, String> {
+    let world = World::new();
+    for bundle in bundles {
+        world.load_bundle(&bundle_uri(&world, bundle));
+    }
+    world.load_specifications();
+    world.load_plugin_classes();
+
+    Ok(world.plugins().iter()
+        .filter(|plugin| {
+            let (inputs, outputs) = audio_port_counts(&world, plugin);
+            inputs > 0 && outputs > 0
+        })
+        .map(|plugin| describe(&plugin))
+        .collect())
+}
+
+/// URIs mapped to integers for the plugin, as the urid:map feature.
+#[derive(Default)]
+struct UridMap {
+    uris: Mutex<HashMap<CString, LV2Urid>>,
+}
+
+impl UridMap {
+    fn map(&self, uri: &CStr) -> LV2Urid {
+        let mut uris = self.uris.lock().unwrap();
+        let next = uris.len() as LV2Urid + 1;
+        *uris.entry(uri.to_owned()).or_insert(next)
+    }
+}
+
+extern "C" fn map_uri(handle: LV2UridMapHandle, uri: *const c_char) -> LV2Urid {
+    let map = unsafe { &*(handle as *const UridMap) };
+    map.map(unsafe { CStr::from_ptr(uri) })
+}
+
+/// A control input or output port, offered as a parameter.
+struct ControlPort {
+    index: usize,
+    name: String,
+    units: String,
+    min: f32,
+    max: f32,
+    default: f32,
+    step_count: u32,
+    output: bool,
+}
+
+/// An atom port, which carries events rather than audio. Inputs are sent
+/// an empty sequence each block and outputs are discarded.
+struct AtomPort {
+    index: usize,
+    output: bool,
+    /// 64-bit words, since atoms must be 8-byte aligned.
+    buffer: Vec<u64>,
+}
+
+pub struct Lv2Plugin {
+    instance: ActiveInstance,
+    audio_inputs: Vec<usize>,
+    audio_outputs: Vec<usize>,
+    /// Value of every control port, indexed by port. Connected to the
+    /// plugin, which reads them at each block.
+    controls: Vec<f32>,
+    control_ports: Vec<ControlPort>,
+    latency_port: Option<usize>,
+    atom_ports: Vec<AtomPort>,
+    atom_sequence: LV2Urid,
+    atom_chunk: LV2Urid,
+    /// Side-chain inputs and ports of other types are connected here.
+    silence: Vec<f32>,
+    scratch: Vec<f32>,
+    /// The plugin keeps pointers to the feature data.
+    _map_feature: Box<LV2UridMap>,
+    _urid_map: Box<UridMap>,
+    _world: World,
+}
+
+// The plugin is only ever called from one thread at a time, through the
+// Mutex around its `PluginInstance`.
+unsafe impl Send for Lv2Plugin {}
+
+pub fn load(plugin_ref: &PluginRef, setup: &PluginSetup) -> Result<(Box<dyn PluginProcessor>, PluginDescriptor), String> {
+    let world = World::new();
+    world.load_bundle(&bundle_uri(&world, Path::new(&plugin_ref.path)));
+    let plugin = world.plugins().plugin(&world.new_uri(&plugin_ref.plugin_id))
+        .ok_or_else(|| format!("{} has no plugin '{}'", plugin_ref.path, plugin_ref.plugin_id))?;
+    let descriptor = describe(&plugin);
+
+    let supported = URID_MAP.to_string_lossy();
+    let missing: Vec<String> = plugin.required_features().iter()
+        .filter_map(|feature| feature.as_uri().map(str::to_string))
+        .filter(|uri| *uri != supported)
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!("{} needs unsupported host features: {}", descriptor.name, missing.join(", ")));
+    }
+
+    let urid_map = Box::<UridMap>::default();
+    let map_feature = Box::new(LV2UridMap {
+        handle: &*urid_map as *const UridMap as *mut c_void,
+        map: map_uri,
+    });
+    let feature = LV2Feature {
+        uri: URID_MAP.as_ptr(),
+        data: &*map_feature as *const LV2UridMap as *mut c_void,
+    };
+
+    let audio = core_uri(&world, "AudioPort");
+    let control = core_uri(&world, "ControlPort");
+    let output = core_uri(&world, "OutputPort");
+    let side_chain = core_uri(&world, "isSideChain");
+    let integer = core_uri(&world, "integer");
+    let toggled = core_uri(&world, "toggled");
+    let atom = world.new_uri(ATOM_PORT);
+    let unit = world.new_uri(UNITS_UNIT);
+
+    let ranges = plugin.port_ranges_float();
+    let mut controls = vec![0.0; plugin.ports_count()];
+    let mut loaded_ports = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    let mut other_ports = (Vec::new(), Vec::new());
+    for port in plugin.iter_ports() {
+        let index = port.index();
+        let is_output = port.is_a(&output);
+        if port.is_a(&audio) && !port.has_property(&side_chain) {
+            if is_output {
+                loaded_ports.1.push(index);
+            } else {
+                loaded_ports.0.push(index);
+            }
+        } else if port.is_a(&control) {
+            let range = ranges[index];
+            let finite = |value: f32, fallback: f32| if value.is_finite() { value } else { fallback };
+            let (min, max) = (finite(range.min, 0.0), finite(range.max, 1.0));
+            let default = finite(range.default, min);
+            let step_count = if port.has_property(&toggled) {
+                1
+            } else if port.has_property(&integer) {
+                (max - min).round().max(0.0) as u32
+            } else {
+                0
+            };
+            controls[index] = default;
+            loaded_ports.2.push(ControlPort {
+                index,
+                name: node_string(port.name()),
+                units: port.get(&unit)
+                    .and_then(|unit| unit.as_uri().and_then(|uri| uri.rsplit('#').next()).map(str::to_string))
+                    .unwrap_or_default(),
+                min,
+                max,
+                default,
+                step_count,
+                output: is_output,
+            });
+        } else if port.is_a(&atom) {
+            loaded_ports.3.push(AtomPort {
+                index,
+                output: is_output,
+                buffer: vec![0; ATOM_BUFFER_BYTES / 8],
+            });
+        } else if is_output {
+            other_ports.1.push(index);
+        } else {
+            other_ports.0.push(index);
+        }
+    }
+    let (audio_inputs, audio_outputs, control_ports, mut atom_ports) = loaded_ports;
+
+    let mut instance = unsafe { plugin.instantiate(setup.sample_rate as f64, [&feature]) }
+        .ok_or_else(|| format!("{} could not be created", descriptor.name))?;
+
+    let mut silence = vec![0.0; setup.max_frames.max(LATENCY_PROBE_FRAMES)];
+    let mut scratch = silence.clone();
+    unsafe {
+        for port in &control_ports {
+            instance.connect_port_mut(port.index, &mut controls[port.index]);
+        }
+        for port in &mut atom_ports {
+            instance.connect_port_mut(port.index, port.buffer.as_mut_ptr());
+        }
+        // Side-chain and CV inputs read silence; other outputs go to scratch
+        for index in other_ports.0 {
+            instance.connect_port_mut(index, silence.as_mut_ptr());
+        }
+        for index in other_ports.1 {
+            instance.connect_port_mut(index, scratch.as_mut_ptr());
+        }
+    }
+
+    let atom_sequence = urid_map.map(ATOM_SEQUENCE);
+    let atom_chunk = urid_map.map(ATOM_CHUNK);
+    let mut loaded = Lv2Plugin {
+        instance: unsafe { instance.activate() },
+        audio_inputs,
+        audio_outputs,
+        controls,
+        latency_port: plugin.latency_port_index(),
+        control_ports,
+        atom_ports,
+        atom_sequence,
+        atom_chunk,
+        silence,
+        scratch,
+        _map_feature: map_feature,
+        _urid_map: urid_map,
+        _world: world,
+    };
+    if loaded.latency_port.is_some() {
+        // The latency port is only written while the plugin runs
+        loaded.run_silence(LATENCY_PROBE_FRAMES);
+    }
+
+    Ok((Box::new(loaded), descriptor))
+}
+
+impl Lv2Plugin {
+    fn prepare_atoms(&mut self) {
+        for port in &mut self.atom_ports {
+            let words = port.buffer.as_mut_ptr() as *mut u32;
+            unsafe {
+                if port.output {
+                    *words = (ATOM_BUFFER_BYTES - 8) as u32;
+                    *words.add(1) = self.atom_chunk;
+                } else {
+                    // Header plus an empty sequence body
+                    *words = 8;
+                    *words.add(1) = self.atom_sequence;
+                    *words.add(2) = 0;
+                    *words.add(3) = 0;
+                }
+            }
+        }
+    }
+
+    fn run_silence(&mut self, frames: usize) {
+        let instance = self.instance.instance_mut();
+        unsafe {
+            for &index in &self.audio_inputs {
+                instance.connect_port_mut(index, self.silence.as_mut_ptr());
+            }
+            for &index in &self.audio_outputs {
+                instance.connect_port_mut(index, self.scratch.as_mut_ptr());
+            }
+        }
+        self.prepare_atoms();
+        unsafe { self.instance.run(frames) };
+    }
+}
+
+impl PluginProcessor for Lv2Plugin {
+    fn channels(&self) -> (usize, usize) {
+        (self.audio_inputs.len(), self.audio_outputs.len())
+    }
+
+    fn latency_frames(&self) -> usize {
+        self.latency_port.map_or(0, |index| self.controls[index].max(0.0).round() as usize)
+    }
+
+    fn process(&mut self, inputs: &mut [Vec<f32>], outputs: &mut [Vec<f32>], frames: usize) -> Result<(), String> {
+        let instance = self.instance.instance_mut();
+        unsafe {
+            for (&index, channel) in self.audio_inputs.iter().zip(inputs.iter_mut()) {
+                instance.connect_port_mut(index, channel.as_mut_ptr());
+            }
+            for (&index, channel) in self.audio_outputs.iter().zip(outputs.iter_mut()) {
+                instance.connect_port_mut(index, channel.as_mut_ptr());
+            }
+        }
+        self.prepare_atoms();
+        unsafe { self.instance.run(frames) };
+        Ok(())
+    }
+
+    fn parameters(&self) -> Vec<PluginParameter> {
+        self.control_ports.iter()
+            .filter(|port| Some(port.index) != self.latency_port)
+            .map(|port| PluginParameter {
+                id: port.index as u32,
+                name: port.name.clone(),
+                units: port.units.clone(),
+                min: port.min as f64,
+                max: port.max as f64,
+                default: port.default as f64,
+                value: self.controls[port.index] as f64,
+                step_count: port.step_count,
+                read_only: port.output,
+            })
+            .collect()
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String> {
+        let control = self.controls.get_mut(id as usize).ok_or_else(|| format!("No port {}", id))?;
+        *control = value as f32;
+        Ok(())
+    }
+}
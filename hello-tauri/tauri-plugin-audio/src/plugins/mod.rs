@@ -0,0 +1,532 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::ffi::c_char;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Weak};
+use tauri::State;
+
+use crate::audio_file::{read_audio_file, write_audio_file, WavEncoding};
+use crate::validate;
+use crate::AudioState;
+
+mod clap_host;
+#[cfg(target_os = "linux")]
+mod lv2_host;
+mod vst3_host;
+
+#[cfg(not(target_os = "linux"))]
+mod lv2_host {
+    use super::{PluginDescriptor, PluginProcessor, PluginRef, PluginSetup};
+    use std::path::PathBuf;
+
+    const UNSUPPORTED: &str = "LV2 plugins are only supported on Linux";
+
+    pub fn scan(_bundles: &[PathBuf]) -> Result<Vec<PluginDescriptor>, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn load(_plugin: &PluginRef, _setup: &PluginSetup) -> Result<(Box<dyn PluginProcessor>, PluginDescriptor), String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+/// Most frames handed to a plugin in one call; longer buffers are split.
+const MAX_BLOCK_FRAMES: usize = 4096;
+
+/// Channels run through a plugin. Beyond these, channels pass through dry.
+const MAX_PLUGIN_CHANNELS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginFormat {
+    Vst3,
+    Clap,
+    Lv2,
+}
+
+/// One plugin inside a plugin file; a file can hold several.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+pub struct PluginRef {
+    pub format: PluginFormat,
+    /// The `.vst3` or `.clap` file or bundle, or the `.lv2` bundle folder.
+    pub path: String,
+    /// VST3 class id in hex, the CLAP plugin id, or the LV2 plugin URI.
+    pub plugin_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PluginDescriptor {
+    #[serde(flatten)]
+    pub plugin: PluginRef,
+    pub name: String,
+    pub vendor: String,
+    /// CLAP features or VST3 subcategories, such as `audio-effect` or `EQ`.
+    pub categories: Vec<String>,
+}
+
+/// A plugin parameter in the plugin's own units.
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PluginParameter {
+    pub id: u32,
+    pub name: String,
+    pub units: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    pub value: f64,
+    /// Number of steps for a switch or selector, 0 when continuous.
+    pub step_count: u32,
+    /// Set for values the plugin reports, such as gain reduction.
+    pub read_only: bool,
+}
+
+/// How a plugin is activated.
+#[derive(Debug, Clone, Copy)]
+struct PluginSetup {
+    sample_rate: u32,
+    /// Channels offered to the plugin's main input and output.
+    channels: usize,
+    max_frames: usize,
+    /// Lets the plugin use more expensive processing that can't keep up in
+    /// real time.
+    offline: bool,
+}
+
+/// An activated plugin of either format. Calls are never concurrent; the
+/// owning `PluginInstance` is behind a Mutex.
+trait PluginProcessor: Send {
+    /// Channels of the main input and output, as the plugin accepted them.
+    fn channels(&self) -> (usize, usize);
+    fn latency_frames(&self) -> usize;
+    /// Processes `frames` planar samples, at most the setup's `max_frames`.
+    fn process(&mut self, inputs: &mut [Vec<f32>], outputs: &mut [Vec<f32>], frames: usize) -> Result<(), String>;
+    fn parameters(&self) -> Vec<PluginParameter>;
+    /// Takes effect from the next processed block.
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String>;
+}
+
+/// A running plugin with its scratch buffers.
+pub struct PluginInstance {
+    id: String,
+    descriptor: PluginDescriptor,
+    setup: PluginSetup,
+    processor: Box<dyn PluginProcessor>,
+    inputs: Vec<Vec<f32>>,
+    outputs: Vec<Vec<f32>>,
+    /// Why processing failed. The plugin is bypassed once this is set.
+    error: Option<String>,
+}
+
+impl PluginInstance {
+    fn load(id: String, plugin: &PluginRef, setup: PluginSetup) -> Result<Self, String> {
+        let (processor, descriptor) = match plugin.format {
+            PluginFormat::Vst3 => vst3_host::load(plugin, &setup)?,
+            PluginFormat::Clap => clap_host::load(plugin, &setup)?,
+            PluginFormat::Lv2 => lv2_host::load(plugin, &setup)?,
+        };
+        let (inputs, outputs) = processor.channels();
+        Ok(PluginInstance {
+            id,
+            descriptor,
+            setup,
+            processor,
+            inputs: vec![vec![0.0; setup.max_frames]; inputs],
+            outputs: vec![vec![0.0; setup.max_frames]; outputs],
+            error: None,
+        })
+    }
+
+    /// Runs interleaved `samples` through the plugin in place. A mono plugin
+    /// on a stereo signal gets the left channel and feeds both.
+    pub fn process(&mut self, samples: &mut [f32], channels: usize) {
+        if self.error.is_some() || channels == 0 {
+            return;
+        }
+        let used = channels.min(MAX_PLUGIN_CHANNELS);
+        let outputs = self.outputs.len();
+
+        for block in samples.chunks_mut(self.setup.max_frames * channels) {
+            let frames = block.len() / channels;
+            for (channel, input) in self.inputs.iter_mut().enumerate() {
+                let source = channel.min(used - 1);
+                for (sample, frame) in input.iter_mut().zip(block.chunks_exact(channels)) {
+                    *sample = frame[source];
+                }
+            }
+
+            if let Err(e) = self.processor.process(&mut self.inputs, &mut self.outputs, frames) {
+                eprintln!("Plugin {} failed and is bypassed: {}", self.descriptor.name, e);
+                self.error = Some(e);
+                return;
+            }
+            if outputs == 0 {
+                continue;
+            }
+
+            for (index, frame) in block.chunks_exact_mut(channels).enumerate() {
+                for (channel, sample) in frame.iter_mut().take(used).enumerate() {
+                    *sample = self.outputs[channel.min(outputs - 1)][index];
+                }
+            }
+        }
+    }
+
+    pub fn latency_frames(&self) -> usize {
+        self.processor.latency_frames()
+    }
+
+    pub fn parameters(&self) -> Vec<PluginParameter> {
+        self.processor.parameters()
+    }
+
+    pub fn set_parameter(&mut self, id: u32, value: f64) -> Result<PluginParameter, String> {
+        let mut parameter = self.parameters().into_iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| format!("{} has no parameter {}", self.descriptor.name, id))?;
+        if parameter.read_only {
+            return Err(format!("{} is read-only", parameter.name));
+        }
+        let (low, high) = (parameter.min.min(parameter.max), parameter.min.max(parameter.max));
+        validate::range(&parameter.name, value, (low, high), "")?;
+
+        self.processor.set_parameter(id, value)?;
+        parameter.value = value;
+        Ok(parameter)
+    }
+
+    pub fn info(&self) -> PluginInstanceInfo {
+        PluginInstanceInfo {
+            instance_id: self.id.clone(),
+            descriptor: self.descriptor.clone(),
+            latency_ms: self.latency_frames() as f64 * 1000.0 / self.setup.sample_rate as f64,
+            parameters: self.parameters(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PluginInstanceInfo {
+    pub instance_id: String,
+    #[serde(flatten)]
+    pub descriptor: PluginDescriptor,
+    /// Delay the plugin adds. It isn't compensated on live streams.
+    pub latency_ms: f64,
+    pub parameters: Vec<PluginParameter>,
+    pub error: Option<String>,
+}
+
+/// Plugins processing a live stream, in order.
+pub type PluginChain = Arc<Mutex<Vec<Arc<Mutex<PluginInstance>>>>>;
+
+pub fn process_chain(chain: &PluginChain, samples: &mut [f32], channels: usize) {
+    for plugin in chain.lock().unwrap().iter() {
+        plugin.lock().unwrap().process(samples, channels);
+    }
+}
+
+/// Instances loaded into live chains, so their parameters can be reached by
+/// id. An instance is dropped with the chain it belongs to.
+#[derive(Default)]
+pub struct PluginHost {
+    instances: Mutex<HashMap<String, Weak<Mutex<PluginInstance>>>>,
+    next_id: Mutex<u64>,
+}
+
+impl PluginHost {
+    fn load(&self, plugin: &PluginRef, setup: PluginSetup) -> Result<Arc<Mutex<PluginInstance>>, String> {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            format!("plugin_{}", *next_id)
+        };
+        let instance = Arc::new(Mutex::new(PluginInstance::load(id.clone(), plugin, setup)?));
+
+        let mut instances = self.instances.lock().unwrap();
+        instances.retain(|_, instance| instance.strong_count() > 0);
+        instances.insert(id, Arc::downgrade(&instance));
+        Ok(instance)
+    }
+
+    /// Loads a plugin for a live stream and sets `parameters` on it.
+    pub fn load_live(
+        &self,
+        plugin: &ChainPlugin,
+        sample_rate: u32,
+        channels: usize,
+    ) -> Result<Arc<Mutex<PluginInstance>>, String> {
+        let setup = PluginSetup {
+            sample_rate,
+            channels: channels.min(MAX_PLUGIN_CHANNELS),
+            max_frames: MAX_BLOCK_FRAMES,
+            offline: false,
+        };
+        let instance = self.load(&plugin.plugin, setup)?;
+        {
+            let mut loaded = instance.lock().unwrap();
+            for (&id, &value) in &plugin.parameters {
+                loaded.set_parameter(id, value)?;
+            }
+        }
+        Ok(instance)
+    }
+
+    pub fn find(&self, instance_id: &str) -> Result<Arc<Mutex<PluginInstance>>, String> {
+        self.instances.lock().unwrap().get(instance_id)
+            .and_then(Weak::upgrade)
+            .ok_or_else(|| format!("Plugin instance '{}' is not loaded", instance_id))
+    }
+}
+
+/// Reads a NUL-terminated string out of a fixed-size field.
+fn fixed_string(chars: &[c_char]) -> String {
+    let bytes: Vec<u8> = chars.iter().take_while(|c| **c != 0).map(|c| *c as u8).collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn default_plugin_dirs() -> Vec<PathBuf> {
+    let env_dir = |var: &str, sub: &str| std::env::var_os(var).map(|dir| Path::new(&dir).join(sub));
+
+    #[cfg(target_os = "windows")]
+    let dirs = vec![
+        env_dir("COMMONPROGRAMFILES", "VST3"),
+        env_dir("COMMONPROGRAMFILES", "CLAP"),
+        env_dir("LOCALAPPDATA", "Programs\\Common\\VST3"),
+        env_dir("LOCALAPPDATA", "Programs\\Common\\CLAP"),
+    ];
+    #[cfg(target_os = "macos")]
+    let dirs = vec![
+        Some(PathBuf::from("/Library/Audio/Plug-Ins/VST3")),
+        Some(PathBuf::from("/Library/Audio/Plug-Ins/CLAP")),
+        env_dir("HOME", "Library/Audio/Plug-Ins/VST3"),
+        env_dir("HOME", "Library/Audio/Plug-Ins/CLAP"),
+    ];
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let dirs = vec![
+        env_dir("HOME", ".vst3"),
+        Some(PathBuf::from("/usr/lib/vst3")),
+        Some(PathBuf::from("/usr/local/lib/vst3")),
+        env_dir("HOME", ".clap"),
+        Some(PathBuf::from("/usr/lib/clap")),
+        env_dir("HOME", ".lv2"),
+        Some(PathBuf::from("/usr/lib/lv2")),
+        Some(PathBuf::from("/usr/local/lib/lv2")),
+    ];
+
+    dirs.into_iter().flatten().collect()
+}
+
+fn plugin_format(path: &Path) -> Option<PluginFormat> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "vst3" => Some(PluginFormat::Vst3),
+        "clap" => Some(PluginFormat::Clap),
+        "lv2" => Some(PluginFormat::Lv2),
+        _ => None,
+    }
+}
+
+/// Collects plugin files and bundles under `dir`, without looking inside
+/// the bundles themselves.
+fn collect_plugin_files(dir: &Path, files: &mut Vec<(PathBuf, PluginFormat)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if let Some(format) = plugin_format(&path) {
+            files.push((path, format));
+        } else if path.is_dir() {
+            collect_plugin_files(&path, files);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PluginScanFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PluginScan {
+    pub plugins: Vec<PluginDescriptor>,
+    /// Files that couldn't be loaded or aren't valid plugins.
+    pub failures: Vec<PluginScanFailure>,
+}
+
+/// Lists the effect plugins in `dirs`, or in the standard VST3, CLAP and
+/// LV2 folders when omitted. Each file is loaded in-process to read what it
+/// holds, so a faulty plugin can take the app down with it.
+#[tauri::command]
+#[specta::specta]
+pub async fn scan_plugins(dirs: Option<Vec<String>>) -> Result<PluginScan, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let dirs = match dirs {
+            Some(dirs) => dirs.into_iter().map(PathBuf::from).collect(),
+            None => default_plugin_dirs(),
+        };
+        let mut files = Vec::new();
+        for dir in &dirs {
+            collect_plugin_files(dir, &mut files);
+        }
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut scan = PluginScan { plugins: Vec::new(), failures: Vec::new() };
+        let mut bundles = Vec::new();
+        for (path, format) in files {
+            let found = match format {
+                PluginFormat::Vst3 => vst3_host::scan(&path),
+                PluginFormat::Clap => clap_host::scan(&path),
+                PluginFormat::Lv2 => {
+                    bundles.push(path);
+                    continue;
+                }
+            };
+            match found {
+                Ok(plugins) => scan.plugins.extend(plugins),
+                Err(error) => scan.failures.push(PluginScanFailure { path: path.display().to_string(), error }),
+            }
+        }
+
+        // LV2 bundles are read together, since plugins can refer to
+        // definitions in other bundles
+        if !bundles.is_empty() {
+            match lv2_host::scan(&bundles) {
+                Ok(plugins) => scan.plugins.extend(plugins),
+                Err(error) => scan.failures.extend(bundles.iter().map(|path| PluginScanFailure {
+                    path: path.display().to_string(),
+                    error: error.clone(),
+                })),
+            }
+        }
+        Ok(scan)
+    })
+    .await
+    .map_err(|e| format!("Plugin scan task failed: {}", e))?
+}
+
+fn passthrough_chain(state: &AudioState, stream_id: &str) -> Result<(u32, usize, PluginChain), String> {
+    let streams = state.streams.lock().unwrap();
+    let passthrough = streams
+        .get(stream_id)
+        .and_then(|stream| stream.passthrough.as_ref())
+        .ok_or_else(|| format!("Stream '{}' has no passthrough", stream_id))?;
+    Ok((passthrough.sample_rate, passthrough.channels, Arc::clone(&passthrough.plugins)))
+}
+
+/// Loads a plugin into a stream's passthrough, at `position` in its chain
+/// or at the end. The chain runs on the output side and goes away when the
+/// passthrough is stopped or restarted.
+#[tauri::command]
+#[specta::specta]
+pub fn insert_passthrough_plugin(
+    stream_id: String,
+    plugin: PluginRef,
+    position: Option<usize>,
+    state: State<AudioState>,
+    host: State<PluginHost>,
+) -> Result<PluginInstanceInfo, String> {
+    let (sample_rate, channels, chain) = passthrough_chain(&state, &stream_id)?;
+    let plugin = ChainPlugin { plugin, parameters: HashMap::new() };
+    let instance = host.load_live(&plugin, sample_rate, channels)?;
+    let info = instance.lock().unwrap().info();
+
+    let mut chain = chain.lock().unwrap();
+    let index = position.unwrap_or(chain.len()).min(chain.len());
+    chain.insert(index, instance);
+    Ok(info)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn remove_passthrough_plugin(stream_id: String, instance_id: String, state: State<AudioState>) -> Result<(), String> {
+    let (_, _, chain) = passthrough_chain(&state, &stream_id)?;
+    let mut chain = chain.lock().unwrap();
+    let index = chain.iter()
+        .position(|plugin| plugin.lock().unwrap().id == instance_id)
+        .ok_or_else(|| format!("Plugin instance '{}' is not on this passthrough", instance_id))?;
+    let removed = chain.remove(index);
+    // Tear the plugin down once the audio callback is free to run again
+    drop(chain);
+    drop(removed);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_passthrough_plugins(stream_id: String, state: State<AudioState>) -> Result<Vec<PluginInstanceInfo>, String> {
+    let (_, _, chain) = passthrough_chain(&state, &stream_id)?;
+    let chain = chain.lock().unwrap();
+    Ok(chain.iter().map(|plugin| plugin.lock().unwrap().info()).collect())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_plugin_parameters(instance_id: String, host: State<PluginHost>) -> Result<Vec<PluginParameter>, String> {
+    Ok(host.find(&instance_id)?.lock().unwrap().parameters())
+}
+
+/// Sets a parameter in its plain units, within the range the plugin
+/// reports.
+#[tauri::command]
+#[specta::specta]
+pub fn set_plugin_parameter(
+    instance_id: String,
+    parameter_id: u32,
+    value: f64,
+    host: State<PluginHost>,
+) -> Result<PluginParameter, String> {
+    host.find(&instance_id)?.lock().unwrap().set_parameter(parameter_id, value)
+}
+
+/// A plugin in a chain, with the parameters to set before processing.
+#[derive(Debug, Clone, Deserialize, Type)]
+pub struct ChainPlugin {
+    pub plugin: PluginRef,
+    #[serde(default)]
+    pub parameters: HashMap<u32, f64>,
+}
+
+/// Renders `src` through `chain` into `dst`. Each plugin's latency is
+/// compensated so the output lines up with the source, and its tail is cut
+/// at the source's length.
+#[tauri::command]
+#[specta::specta]
+pub async fn process_file_with_plugins(
+    src: String,
+    dst: String,
+    chain: Vec<ChainPlugin>,
+    encoding: Option<WavEncoding>,
+) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut audio = read_audio_file(Path::new(&src))?;
+        let channels = audio.channels as usize;
+        let setup = PluginSetup {
+            sample_rate: audio.sample_rate,
+            channels: channels.min(MAX_PLUGIN_CHANNELS),
+            max_frames: MAX_BLOCK_FRAMES,
+            offline: true,
+        };
+
+        for entry in &chain {
+            let mut instance = PluginInstance::load(String::new(), &entry.plugin, setup)?;
+            for (&id, &value) in &entry.parameters {
+                instance.set_parameter(id, value)?;
+            }
+
+            let latency = instance.latency_frames() * channels;
+            audio.samples.extend(std::iter::repeat_n(0.0, latency));
+            instance.process(&mut audio.samples, channels);
+            if let Some(e) = &instance.error {
+                return Err(format!("{} failed: {}", instance.descriptor.name, e));
+            }
+            audio.samples.drain(..latency);
+        }
+
+        write_audio_file(Path::new(&dst), &audio, encoding.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("Plugin processing task failed: {}", e))?
+}
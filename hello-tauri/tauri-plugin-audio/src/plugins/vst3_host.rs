@@ -0,0 +1,649 @@
+use ::vst3::Steinberg::Vst::{
+    AudioBusBuffers, AudioBusBuffers__type0, BusDirections_, IAudioProcessor, IAudioProcessorTrait, IComponent,
+    IComponentHandler, IComponentHandlerTrait, IComponentTrait, IConnectionPoint, IConnectionPointTrait,
+    IEditController, IEditControllerTrait, IHostApplication, IHostApplicationTrait, IParamValueQueue,
+    IParamValueQueueTrait, IParameterChanges, IParameterChangesTrait, MediaTypes_, ParamID, ParamValue,
+    ParameterInfo, ParameterInfo_::ParameterFlags_, ProcessData, ProcessModes_, ProcessSetup, SpeakerArr,
+    SpeakerArrangement, String128, SymbolicSampleSizes_, TChar,
+};
+use ::vst3::Steinberg::{
+    kInvalidArgument, kNotImplemented, kResultFalse, kResultOk, tresult, FIDString, FUnknown, IPluginBaseTrait,
+    IPluginFactory, IPluginFactory2, IPluginFactory2Trait, IPluginFactoryTrait, PClassInfo, PClassInfo2,
+    PFactoryInfo, int32, TUID,
+};
+use ::vst3::{Class, ComPtr, ComWrapper, Interface};
+use libloading::Library;
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::{mem, ptr};
+
+use super::{fixed_string, PluginDescriptor, PluginFormat, PluginParameter, PluginProcessor, PluginRef, PluginSetup};
+
+const AUDIO_EFFECT_CLASS: &str = "Audio Module Class";
+
+type GetPluginFactory = unsafe extern "system" fn() -> *mut IPluginFactory;
+
+/// The binary inside a `.vst3` bundle, or the file itself for the older
+/// single-file layout.
+fn binary_path(path: &Path) -> Result<PathBuf, String> {
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let stem = path.file_stem()
+        .ok_or_else(|| format!("Invalid plugin path {}", path.display()))?
+        .to_string_lossy();
+
+    #[cfg(target_os = "windows")]
+    let binary = {
+        let arch = match std::env::consts::ARCH {
+            "aarch64" => "arm64",
+            arch => arch,
+        };
+        path.join("Contents").join(format!("{}-win", arch)).join(format!("{}.vst3", stem))
+    };
+    #[cfg(target_os = "linux")]
+    let binary = path.join("Contents")
+        .join(format!("{}-linux", std::env::consts::ARCH))
+        .join(format!("{}.so", stem));
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    let binary = {
+        // macOS modules are entered with a CFBundleRef, which isn't supported
+        let _ = stem;
+        return Err("VST3 plugins can only be loaded on Windows and Linux".to_string());
+    };
+
+    Ok(binary)
+}
+
+/// Calls the module's entry point, which must run before the factory is
+/// used.
+#[cfg(target_os = "windows")]
+unsafe fn enter_module(library: Library) -> Result<Library, String> {
+    if let Ok(init) = library.get::<unsafe extern "C" fn() -> bool>(b"InitDll\0") {
+        if !init() {
+            return Err("Plugin module failed to initialize".to_string());
+        }
+    }
+    Ok(library)
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn enter_module(library: Library) -> Result<Library, String> {
+    // The entry point wants the dlopen handle
+    let handle = libloading::os::unix::Library::from(library).into_raw();
+    let library: Library = libloading::os::unix::Library::from_raw(handle).into();
+    if let Ok(entry) = library.get::<unsafe extern "C" fn(*mut c_void) -> bool>(b"ModuleEntry\0") {
+        if !entry(handle) {
+            return Err("Plugin module failed to initialize".to_string());
+        }
+    }
+    Ok(library)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "linux")))]
+unsafe fn enter_module(library: Library) -> Result<Library, String> {
+    Ok(library)
+}
+
+unsafe fn exit_module(library: &Library) {
+    let name: &[u8] = if cfg!(target_os = "windows") { b"ExitDll\0" } else { b"ModuleExit\0" };
+    if let Ok(exit) = library.get::<unsafe extern "C" fn() -> bool>(name) {
+        exit();
+    }
+}
+
+struct PluginClass {
+    cid: TUID,
+    name: String,
+    vendor: String,
+    categories: Vec<String>,
+}
+
+fn class_id_hex(cid: &TUID) -> String {
+    cid.iter().map(|byte| format!("{:02X}", *byte as u8)).collect()
+}
+
+/// A loaded `.vst3` module. The factory is released and the module exited
+/// before the library is unloaded.
+struct Module {
+    factory: Option<ComPtr<IPluginFactory>>,
+    library: Library,
+}
+
+impl Module {
+    fn open(path: &Path) -> Result<Self, String> {
+        let binary = binary_path(path)?;
+        let library = unsafe { Library::new(&binary) }
+            .map_err(|e| format!("Failed to load {}: {}", binary.display(), e))?;
+        let mut module = Module {
+            factory: None,
+            library: unsafe { enter_module(library)? },
+        };
+
+        let get_factory = unsafe { module.library.get::<GetPluginFactory>(b"GetPluginFactory\0") }
+            .map_err(|e| format!("{} is not a VST3 plugin: {}", path.display(), e))?;
+        module.factory = unsafe { ComPtr::from_raw(get_factory()) };
+        if module.factory.is_none() {
+            return Err(format!("{} has no plugin factory", path.display()));
+        }
+        Ok(module)
+    }
+
+    fn factory(&self) -> &ComPtr<IPluginFactory> {
+        self.factory.as_ref().expect("factory is set once the module is open")
+    }
+
+    /// The audio effect classes in the module.
+    fn classes(&self) -> Vec<PluginClass> {
+        let factory = self.factory();
+        let factory2 = factory.cast::<IPluginFactory2>();
+        let mut factory_info: PFactoryInfo = unsafe { mem::zeroed() };
+        let factory_vendor = if unsafe { factory.getFactoryInfo(&mut factory_info) } == kResultOk {
+            fixed_string(&factory_info.vendor)
+        } else {
+            String::new()
+        };
+
+        let mut classes = Vec::new();
+        for index in 0..unsafe { factory.countClasses() } {
+            let mut info2: PClassInfo2 = unsafe { mem::zeroed() };
+            let class = match &factory2 {
+                Some(factory2) if unsafe { factory2.getClassInfo2(index, &mut info2) } == kResultOk => PluginClass {
+                    cid: info2.cid,
+                    name: fixed_string(&info2.name),
+                    vendor: Some(fixed_string(&info2.vendor))
+                        .filter(|vendor| !vendor.is_empty())
+                        .unwrap_or_else(|| factory_vendor.clone()),
+                    categories: fixed_string(&info2.subCategories).split('|')
+                        .filter(|category| !category.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                },
+                _ => {
+                    let mut info: PClassInfo = unsafe { mem::zeroed() };
+                    if unsafe { factory.getClassInfo(index, &mut info) } != kResultOk {
+                        continue;
+                    }
+                    info2.category = info.category;
+                    PluginClass {
+                        cid: info.cid,
+                        name: fixed_string(&info.name),
+                        vendor: factory_vendor.clone(),
+                        categories: Vec::new(),
+                    }
+                }
+            };
+            if fixed_string(&info2.category) == AUDIO_EFFECT_CLASS {
+                classes.push(class);
+            }
+        }
+        classes
+    }
+
+    /// Creates an object of class `cid` through interface `I`.
+    fn create<I: Interface>(&self, cid: &TUID) -> Option<ComPtr<I>> {
+        let mut object = ptr::null_mut();
+        let result = unsafe {
+            self.factory().createInstance(cid.as_ptr(), I::IID.as_ptr() as FIDString, &mut object)
+        };
+        if result != kResultOk {
+            return None;
+        }
+        unsafe { ComPtr::from_raw(object as *mut I) }
+    }
+}
+
+impl Drop for Module {
+    fn drop(&mut self) {
+        if self.factory.take().is_some() {
+            unsafe { exit_module(&self.library) };
+        }
+    }
+}
+
+fn describe(path: &Path, class: &PluginClass) -> PluginDescriptor {
+    PluginDescriptor {
+        plugin: PluginRef {
+            format: PluginFormat::Vst3,
+            path: path.display().to_string(),
+            plugin_id: class_id_hex(&class.cid),
+        },
+        name: class.name.clone(),
+        vendor: class.vendor.clone(),
+        categories: class.categories.clone(),
+    }
+}
+
+/// Audio effects in a `.vst3` module, excluding instruments.
+pub fn scan(path: &Path) -> Result<Vec<PluginDescriptor>, String> {
+    Ok(Module::open(path)?.classes().iter()
+        .filter(|class| !class.categories.iter().any(|c| c == "Instrument"))
+        .map(|class| describe(path, class))
+        .collect())
+}
+
+fn wide_string(chars: &[TChar]) -> String {
+    let len = chars.iter().position(|c| *c == 0).unwrap_or(chars.len());
+    String::from_utf16_lossy(&chars[..len])
+}
+
+struct HostApplication;
+
+impl Class for HostApplication {
+    type Interfaces = (IHostApplication,);
+}
+
+impl IHostApplicationTrait for HostApplication {
+    unsafe fn getName(&self, name: *mut String128) -> tresult {
+        let name = &mut *name;
+        let host_name = env!("CARGO_PKG_NAME").encode_utf16().chain(std::iter::once(0));
+        for (dst, src) in name.iter_mut().zip(host_name) {
+            *dst = src;
+        }
+        kResultOk
+    }
+
+    unsafe fn createInstance(&self, _cid: *mut TUID, _iid: *mut TUID, obj: *mut *mut c_void) -> tresult {
+        *obj = ptr::null_mut();
+        kNotImplemented
+    }
+}
+
+/// Receives edits made from the plugin's own editor, which isn't opened.
+struct ComponentHandler;
+
+impl Class for ComponentHandler {
+    type Interfaces = (IComponentHandler,);
+}
+
+impl IComponentHandlerTrait for ComponentHandler {
+    unsafe fn beginEdit(&self, _id: ParamID) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn performEdit(&self, _id: ParamID, _value: ParamValue) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn endEdit(&self, _id: ParamID) -> tresult {
+        kResultOk
+    }
+
+    unsafe fn restartComponent(&self, _flags: int32) -> tresult {
+        kNotImplemented
+    }
+}
+
+/// A parameter change applied at the start of a block.
+struct ParamValueQueue {
+    id: ParamID,
+    value: ParamValue,
+}
+
+impl Class for ParamValueQueue {
+    type Interfaces = (IParamValueQueue,);
+}
+
+impl IParamValueQueueTrait for ParamValueQueue {
+    unsafe fn getParameterId(&self) -> ParamID {
+        self.id
+    }
+
+    unsafe fn getPointCount(&self) -> int32 {
+        1
+    }
+
+    unsafe fn getPoint(&self, index: int32, sample_offset: *mut int32, value: *mut ParamValue) -> tresult {
+        if index != 0 {
+            return kInvalidArgument;
+        }
+        *sample_offset = 0;
+        *value = self.value;
+        kResultOk
+    }
+
+    unsafe fn addPoint(&self, _sample_offset: int32, _value: ParamValue, _index: *mut int32) -> tresult {
+        kResultFalse
+    }
+}
+
+struct ParameterChanges {
+    queues: Vec<ComWrapper<ParamValueQueue>>,
+}
+
+impl Class for ParameterChanges {
+    type Interfaces = (IParameterChanges,);
+}
+
+impl IParameterChangesTrait for ParameterChanges {
+    unsafe fn getParameterCount(&self) -> int32 {
+        self.queues.len() as int32
+    }
+
+    unsafe fn getParameterData(&self, index: int32) -> *mut IParamValueQueue {
+        usize::try_from(index).ok()
+            .and_then(|index| self.queues.get(index))
+            .and_then(|queue| queue.as_com_ref::<IParamValueQueue>())
+            .map_or(ptr::null_mut(), |queue| queue.as_ptr())
+    }
+
+    unsafe fn addParameterData(&self, _id: *const ParamID, _index: *mut int32) -> *mut IParamValueQueue {
+        ptr::null_mut()
+    }
+}
+
+pub struct Vst3Plugin {
+    component: ComPtr<IComponent>,
+    processor: ComPtr<IAudioProcessor>,
+    controller: Option<ComPtr<IEditController>>,
+    /// Set when the controller is a separate object rather than the
+    /// component itself.
+    separate_controller: bool,
+    connection: Option<(ComPtr<IConnectionPoint>, ComPtr<IConnectionPoint>)>,
+    process_mode: int32,
+    channels: (usize, usize),
+    active: bool,
+    processing: bool,
+    /// Normalized parameter changes for the next block.
+    pending: Vec<(ParamID, ParamValue)>,
+    input_ptrs: Vec<*mut f32>,
+    output_ptrs: Vec<*mut f32>,
+    _host: ComWrapper<HostApplication>,
+    handler: ComWrapper<ComponentHandler>,
+    module: Module,
+}
+
+// The plugin is only ever called from one thread at a time, through the
+// Mutex around its `PluginInstance`.
+unsafe impl Send for Vst3Plugin {}
+
+fn parse_class_id(hex: &str) -> Option<TUID> {
+    let mut cid: TUID = [0; 16];
+    if hex.len() != 32 || !hex.is_ascii() {
+        return None;
+    }
+    for (index, byte) in cid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()? as _;
+    }
+    Some(cid)
+}
+
+/// Channels in the plugin's main bus on one side, 0 without one.
+fn main_bus_channels(processor: &ComPtr<IAudioProcessor>, bus_count: int32, direction: int32) -> usize {
+    let mut arrangement: SpeakerArrangement = 0;
+    if bus_count == 0 || unsafe { processor.getBusArrangement(direction, 0, &mut arrangement) } != kResultOk {
+        return 0;
+    }
+    arrangement.count_ones() as usize
+}
+
+pub fn load(plugin: &PluginRef, setup: &PluginSetup) -> Result<(Box<dyn PluginProcessor>, PluginDescriptor), String> {
+    let path = Path::new(&plugin.path);
+    let cid = parse_class_id(&plugin.plugin_id)
+        .ok_or_else(|| format!("Invalid VST3 class id '{}'", plugin.plugin_id))?;
+    let module = Module::open(path)?;
+    let class = module.classes().into_iter()
+        .find(|class| class.cid == cid)
+        .ok_or_else(|| format!("{} has no plugin '{}'", path.display(), plugin.plugin_id))?;
+    let descriptor = describe(path, &class);
+
+    let host = ComWrapper::new(HostApplication);
+    let host_context = host.as_com_ref::<IHostApplication>()
+        .map_or(ptr::null_mut(), |host| host.as_ptr() as *mut FUnknown);
+    let handler = ComWrapper::new(ComponentHandler);
+
+    let component = module.create::<IComponent>(&cid)
+        .ok_or_else(|| format!("{} could not be created", descriptor.name))?;
+    if unsafe { component.initialize(host_context) } != kResultOk {
+        return Err(format!("{} failed to initialize", descriptor.name));
+    }
+    let Some(processor) = component.cast::<IAudioProcessor>() else {
+        unsafe { component.terminate() };
+        return Err(format!("{} is not an audio processor", descriptor.name));
+    };
+
+    let mut loaded = Vst3Plugin {
+        component,
+        processor,
+        controller: None,
+        separate_controller: false,
+        connection: None,
+        process_mode: (if setup.offline { ProcessModes_::kOffline } else { ProcessModes_::kRealtime }) as int32,
+        channels: (0, 0),
+        active: false,
+        processing: false,
+        pending: Vec::new(),
+        input_ptrs: Vec::new(),
+        output_ptrs: Vec::new(),
+        _host: host,
+        handler,
+        module,
+    };
+    loaded.attach_controller(host_context);
+
+    let component = &loaded.component;
+    let processor = &loaded.processor;
+    let audio = MediaTypes_::kAudio as int32;
+    let (input, output) = (BusDirections_::kInput as int32, BusDirections_::kOutput as int32);
+    unsafe {
+        if processor.canProcessSampleSize(SymbolicSampleSizes_::kSample32 as int32) != kResultOk {
+            return Err(format!("{} can't process 32-bit float audio", descriptor.name));
+        }
+
+        // Ask for the stream's layout on the main buses; the plugin may
+        // settle on another, which is read back below
+        let wanted = if setup.channels == 1 { SpeakerArr::kMono } else { SpeakerArr::kStereo };
+        let arrangements = |direction: int32, count: int32| -> Vec<SpeakerArrangement> {
+            (0..count)
+                .map(|index| {
+                    let mut arrangement = wanted;
+                    if index > 0 {
+                        processor.getBusArrangement(direction, index, &mut arrangement);
+                    }
+                    arrangement
+                })
+                .collect()
+        };
+        let input_buses = component.getBusCount(audio, input);
+        let output_buses = component.getBusCount(audio, output);
+        let mut inputs = arrangements(input, input_buses);
+        let mut outputs = arrangements(output, output_buses);
+        processor.setBusArrangements(inputs.as_mut_ptr(), input_buses, outputs.as_mut_ptr(), output_buses);
+        if input_buses > 0 {
+            component.activateBus(audio, input, 0, 1);
+        }
+        if output_buses > 0 {
+            component.activateBus(audio, output, 0, 1);
+        }
+        loaded.channels = (
+            main_bus_channels(processor, input_buses, input),
+            main_bus_channels(processor, output_buses, output),
+        );
+
+        let mut process_setup = ProcessSetup {
+            processMode: loaded.process_mode,
+            symbolicSampleSize: SymbolicSampleSizes_::kSample32 as int32,
+            maxSamplesPerBlock: setup.max_frames as int32,
+            sampleRate: setup.sample_rate as f64,
+        };
+        if processor.setupProcessing(&mut process_setup) != kResultOk {
+            return Err(format!("{} doesn't support {} Hz", descriptor.name, setup.sample_rate));
+        }
+        loaded.active = component.setActive(1) == kResultOk;
+        if !loaded.active {
+            return Err(format!("{} failed to activate", descriptor.name));
+        }
+    }
+
+    Ok((Box::new(loaded), descriptor))
+}
+
+impl Vst3Plugin {
+    /// Finds the edit controller, which holds the parameters, and connects
+    /// it to the component. The controller starts from its defaults since
+    /// the component's state isn't copied over.
+    fn attach_controller(&mut self, host_context: *mut FUnknown) {
+        let controller = match self.component.cast::<IEditController>() {
+            Some(controller) => controller,
+            None => {
+                let mut cid: TUID = [0; 16];
+                if unsafe { self.component.getControllerClassId(&mut cid) } != kResultOk {
+                    return;
+                }
+                let Some(controller) = self.module.create::<IEditController>(&cid) else {
+                    return;
+                };
+                if unsafe { controller.initialize(host_context) } != kResultOk {
+                    return;
+                }
+                self.separate_controller = true;
+
+                let points = (self.component.cast::<IConnectionPoint>(), controller.cast::<IConnectionPoint>());
+                if let (Some(component_point), Some(controller_point)) = points {
+                    unsafe {
+                        component_point.connect(controller_point.as_ptr());
+                        controller_point.connect(component_point.as_ptr());
+                    }
+                    self.connection = Some((component_point, controller_point));
+                }
+                controller
+            }
+        };
+
+        if let Some(handler) = self.handler.as_com_ref::<IComponentHandler>() {
+            unsafe { controller.setComponentHandler(handler.as_ptr()) };
+        }
+        self.controller = Some(controller);
+    }
+}
+
+impl PluginProcessor for Vst3Plugin {
+    fn channels(&self) -> (usize, usize) {
+        self.channels
+    }
+
+    fn latency_frames(&self) -> usize {
+        unsafe { self.processor.getLatencySamples() as usize }
+    }
+
+    fn process(&mut self, inputs: &mut [Vec<f32>], outputs: &mut [Vec<f32>], frames: usize) -> Result<(), String> {
+        if !self.processing {
+            // Processing starts on the thread that will keep calling process
+            unsafe { self.processor.setProcessing(1) };
+            self.processing = true;
+        }
+
+        self.input_ptrs.clear();
+        self.input_ptrs.extend(inputs.iter_mut().map(|channel| channel.as_mut_ptr()));
+        self.output_ptrs.clear();
+        self.output_ptrs.extend(outputs.iter_mut().map(|channel| channel.as_mut_ptr()));
+
+        let mut input = AudioBusBuffers {
+            numChannels: self.input_ptrs.len() as int32,
+            silenceFlags: 0,
+            __field0: AudioBusBuffers__type0 { channelBuffers32: self.input_ptrs.as_mut_ptr() },
+        };
+        let mut output = AudioBusBuffers {
+            numChannels: self.output_ptrs.len() as int32,
+            silenceFlags: 0,
+            __field0: AudioBusBuffers__type0 { channelBuffers32: self.output_ptrs.as_mut_ptr() },
+        };
+
+        // Only allocates when parameters have changed since the last block
+        let changes = (!self.pending.is_empty()).then(|| {
+            ComWrapper::new(ParameterChanges {
+                queues: self.pending.drain(..)
+                    .map(|(id, value)| ComWrapper::new(ParamValueQueue { id, value }))
+                    .collect(),
+            })
+        });
+        let changes_ptr = changes.as_ref()
+            .and_then(|changes| changes.as_com_ref::<IParameterChanges>())
+            .map_or(ptr::null_mut(), |changes| changes.as_ptr());
+
+        let (has_input, has_output) = (!inputs.is_empty(), !outputs.is_empty());
+        let mut data = ProcessData {
+            processMode: self.process_mode,
+            symbolicSampleSize: SymbolicSampleSizes_::kSample32 as int32,
+            numSamples: frames as int32,
+            numInputs: has_input as int32,
+            numOutputs: has_output as int32,
+            inputs: if has_input { &mut input } else { ptr::null_mut() },
+            outputs: if has_output { &mut output } else { ptr::null_mut() },
+            inputParameterChanges: changes_ptr,
+            outputParameterChanges: ptr::null_mut(),
+            inputEvents: ptr::null_mut(),
+            outputEvents: ptr::null_mut(),
+            processContext: ptr::null_mut(),
+        };
+
+        let result = unsafe { self.processor.process(&mut data) };
+        if result != kResultOk {
+            return Err(format!("Plugin process call failed ({})", result));
+        }
+        Ok(())
+    }
+
+    fn parameters(&self) -> Vec<PluginParameter> {
+        let Some(controller) = &self.controller else {
+            return Vec::new();
+        };
+
+        (0..unsafe { controller.getParameterCount() })
+            .filter_map(|index| {
+                let mut info: ParameterInfo = unsafe { mem::zeroed() };
+                if unsafe { controller.getParameterInfo(index, &mut info) } != kResultOk
+                    || info.flags & ParameterFlags_::kIsHidden != 0
+                {
+                    return None;
+                }
+                let to_plain = |normalized| unsafe { controller.normalizedParamToPlain(info.id, normalized) };
+
+                Some(PluginParameter {
+                    id: info.id,
+                    name: wide_string(&info.title),
+                    units: wide_string(&info.units),
+                    min: to_plain(0.0),
+                    max: to_plain(1.0),
+                    default: to_plain(info.defaultNormalizedValue),
+                    value: to_plain(unsafe { controller.getParamNormalized(info.id) }),
+                    step_count: info.stepCount.max(0) as u32,
+                    read_only: info.flags & ParameterFlags_::kIsReadOnly != 0,
+                })
+            })
+            .collect()
+    }
+
+    fn set_parameter(&mut self, id: u32, value: f64) -> Result<(), String> {
+        let controller = self.controller.as_ref().ok_or_else(|| "Plugin has no parameters".to_string())?;
+        let normalized = unsafe { controller.plainParamToNormalized(id, value) };
+        unsafe { controller.setParamNormalized(id, normalized) };
+
+        self.pending.retain(|(pending, _)| *pending != id);
+        self.pending.push((id, normalized));
+        Ok(())
+    }
+}
+
+impl Drop for Vst3Plugin {
+    fn drop(&mut self) {
+        unsafe {
+            if self.processing {
+                self.processor.setProcessing(0);
+            }
+            if self.active {
+                self.component.setActive(0);
+            }
+            if let Some((component_point, controller_point)) = self.connection.take() {
+                component_point.disconnect(controller_point.as_ptr());
+                controller_point.disconnect(component_point.as_ptr());
+            }
+            if let Some(controller) = self.controller.take() {
+                if self.separate_controller {
+                    controller.terminate();
+                }
+            }
+            self.component.terminate();
+        }
+    }
+}
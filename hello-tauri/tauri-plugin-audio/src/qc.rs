@@ -0,0 +1,289 @@
+use serde::Serialize;
+use specta::Type;
+use std::path::Path;
+
+use crate::audio_file::{read_audio_file, AudioBuffer};
+use crate::calculate_rms;
+use crate::dsp::fft::amplitude_to_db;
+use crate::dsp::levels::is_clipped;
+
+/// Consecutive full-scale samples needed before a run counts as clipping;
+/// a single sample touching full scale is usually legitimate.
+const DEFAULT_MIN_CLIP_RUN: usize = 3;
+
+/// Shortest run of exact digital silence, inside otherwise live audio,
+/// reported as a dropout.
+const MIN_ZERO_GAP_MS: f64 = 5.0;
+/// A sample-to-sample step must exceed this and be far larger than the
+/// local average step to count as a discontinuity.
+const JUMP_THRESHOLD: f32 = 0.5;
+const JUMP_RATIO: f32 = 20.0;
+const JUMP_CONTEXT: usize = 64;
+/// Buffer sizes checked for drivers re-delivering the previous buffer.
+const REPEAT_BUFFER_SIZES: [usize; 6] = [64, 128, 256, 480, 512, 1024];
+/// Events of the same kind closer together than this are merged.
+const GLITCH_MERGE_MS: f64 = 50.0;
+/// Window over which RMS is measured when looking for silence.
+const SILENCE_WINDOW_MS: f64 = 10.0;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ClipRegion {
+    /// Zero-based channel index.
+    pub channel: u16,
+    pub start_ms: f64,
+    pub end_ms: f64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ClippingReport {
+    pub clipped_samples: usize,
+    pub regions: Vec<ClipRegion>,
+}
+
+fn frame_to_ms(frame: usize, sample_rate: u32) -> f64 {
+    frame as f64 * 1000.0 / sample_rate as f64
+}
+
+pub fn find_clipping(audio: &AudioBuffer, min_run: usize) -> ClippingReport {
+    let channels = audio.channels.max(1) as usize;
+    let frames = audio.samples.len() / channels;
+    let mut regions = Vec::new();
+
+    for channel in 0..channels {
+        let mut run_start = None;
+
+        // One extra iteration closes a run that reaches the end of the file
+        for frame in 0..=frames {
+            let clipped = frame < frames && is_clipped(audio.samples[frame * channels + channel]);
+
+            match (clipped, run_start) {
+                (true, None) => run_start = Some(frame),
+                (false, Some(start)) => {
+                    if frame - start >= min_run {
+                        regions.push(ClipRegion {
+                            channel: channel as u16,
+                            start_ms: frame_to_ms(start, audio.sample_rate),
+                            end_ms: frame_to_ms(frame, audio.sample_rate),
+                            samples: frame - start,
+                        });
+                    }
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    regions.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+
+    ClippingReport {
+        clipped_samples: regions.iter().map(|r| r.samples).sum(),
+        regions,
+    }
+}
+
+/// Lists runs of consecutive full-scale samples so delivered recordings can
+/// be audited without listening through them.
+#[tauri::command]
+#[specta::specta]
+pub async fn detect_clipping(file_path: String, min_run: Option<usize>) -> Result<ClippingReport, String> {
+    let min_run = min_run.unwrap_or(DEFAULT_MIN_CLIP_RUN).max(1);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&file_path))?;
+        Ok(find_clipping(&audio, min_run))
+    })
+    .await
+    .map_err(|e| format!("Clipping scan failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum GlitchKind {
+    ZeroGap,
+    Discontinuity,
+    RepeatedBuffer,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct Glitch {
+    pub kind: GlitchKind,
+    pub time_ms: f64,
+    pub duration_ms: f64,
+    /// Channel the glitch was found on; `None` when it spans all channels.
+    pub channel: Option<u16>,
+}
+
+/// Exact-zero runs across all channels that sit between non-silent audio.
+fn find_zero_gaps(audio: &AudioBuffer, glitches: &mut Vec<Glitch>) {
+    let channels = audio.channels.max(1) as usize;
+    let min_frames = (MIN_ZERO_GAP_MS / 1000.0 * audio.sample_rate as f64) as usize;
+    let mut heard_audio = false;
+    let mut gap_start = None;
+
+    for (frame, samples) in audio.samples.chunks_exact(channels).enumerate() {
+        let silent = samples.iter().all(|&s| s == 0.0);
+
+        match (silent, gap_start) {
+            (true, None) if heard_audio => gap_start = Some(frame),
+            (false, Some(start)) => {
+                if frame - start >= min_frames {
+                    glitches.push(Glitch {
+                        kind: GlitchKind::ZeroGap,
+                        time_ms: frame_to_ms(start, audio.sample_rate),
+                        duration_ms: frame_to_ms(frame - start, audio.sample_rate),
+                        channel: None,
+                    });
+                }
+                gap_start = None;
+            }
+            _ => {}
+        }
+
+        heard_audio |= !silent;
+    }
+}
+
+fn find_channel_glitches(audio: &AudioBuffer, channel: usize, glitches: &mut Vec<Glitch>) {
+    let signal = audio.channel(channel);
+
+    // Sudden jumps compared to the average step over the preceding context
+    let mut step_sum = 0.0f32;
+    for n in 1..signal.len() {
+        let step = (signal[n] - signal[n - 1]).abs();
+
+        if n > JUMP_CONTEXT {
+            let average = step_sum / JUMP_CONTEXT as f32;
+            if step > JUMP_THRESHOLD && step > average * JUMP_RATIO {
+                glitches.push(Glitch {
+                    kind: GlitchKind::Discontinuity,
+                    time_ms: frame_to_ms(n, audio.sample_rate),
+                    duration_ms: 0.0,
+                    channel: Some(channel as u16),
+                });
+            }
+            step_sum -= (signal[n - JUMP_CONTEXT] - signal[n - JUMP_CONTEXT - 1]).abs();
+        }
+        step_sum += step;
+    }
+
+    // A whole buffer identical to the one before it, which real audio never is
+    for size in REPEAT_BUFFER_SIZES {
+        let mut matching = 0;
+        for n in size..signal.len() {
+            if signal[n] == signal[n - size] && signal[n] != 0.0 {
+                matching += 1;
+                if matching == size {
+                    glitches.push(Glitch {
+                        kind: GlitchKind::RepeatedBuffer,
+                        time_ms: frame_to_ms(n + 1 - size, audio.sample_rate),
+                        duration_ms: frame_to_ms(size, audio.sample_rate),
+                        channel: Some(channel as u16),
+                    });
+                    matching = 0;
+                }
+            } else {
+                matching = 0;
+            }
+        }
+    }
+}
+
+pub fn find_glitches(audio: &AudioBuffer) -> Vec<Glitch> {
+    let mut glitches = Vec::new();
+    find_zero_gaps(audio, &mut glitches);
+    for channel in 0..audio.channels.max(1) as usize {
+        find_channel_glitches(audio, channel, &mut glitches);
+    }
+
+    glitches.sort_by(|a, b| a.time_ms.total_cmp(&b.time_ms));
+
+    // Collapse bursts so one damaged spot is reported once per kind
+    let mut merged: Vec<Glitch> = Vec::new();
+    for glitch in glitches {
+        let duplicate = merged.iter().rev().take_while(|g| glitch.time_ms - g.time_ms < GLITCH_MERGE_MS).any(|g| g.kind == glitch.kind);
+        if !duplicate {
+            merged.push(glitch);
+        }
+    }
+    merged
+}
+
+/// Scans a file for dropouts: digital-silence gaps, sudden sample jumps and
+/// repeated driver buffers, returning suspect timestamps for review.
+#[tauri::command]
+#[specta::specta]
+pub async fn detect_dropouts(file_path: String) -> Result<Vec<Glitch>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&file_path))?;
+        Ok(find_glitches(&audio))
+    })
+    .await
+    .map_err(|e| format!("Dropout scan failed: {}", e))?
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SilenceRegion {
+    pub start_ms: f64,
+    pub end_ms: f64,
+}
+
+/// Finds stretches where the level of every channel stays below
+/// `threshold_db` for at least `min_silence_ms`. Returned in frames, as
+/// `(start, end)` pairs, so callers can cut on exact sample boundaries.
+pub fn find_silent_frames(audio: &AudioBuffer, threshold_db: f32, min_silence_ms: f64) -> Vec<(usize, usize)> {
+    let channels = audio.channels.max(1) as usize;
+    let frames = audio.frames();
+    let window = ((SILENCE_WINDOW_MS / 1000.0 * audio.sample_rate as f64) as usize).max(1);
+    let min_frames = (min_silence_ms / 1000.0 * audio.sample_rate as f64) as usize;
+
+    let mut silences = Vec::new();
+    let mut run_start = None;
+
+    for start in (0..frames).step_by(window) {
+        let end = (start + window).min(frames);
+        let block = &audio.samples[start * channels..end * channels];
+        let silent = (0..channels).all(|channel| {
+            let samples: Vec<f32> = block.iter().skip(channel).step_by(channels).copied().collect();
+            amplitude_to_db(calculate_rms(&samples)) < threshold_db
+        });
+
+        match (silent, run_start) {
+            (true, None) => run_start = Some(start),
+            (false, Some(run)) => {
+                if start - run >= min_frames {
+                    silences.push((run, start));
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(run) = run_start {
+        if frames - run >= min_frames {
+            silences.push((run, frames));
+        }
+    }
+
+    silences
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn detect_silence(file_path: String, threshold_db: f32, min_silence_ms: f64) -> Result<Vec<SilenceRegion>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio = read_audio_file(Path::new(&file_path))?;
+        let regions = find_silent_frames(&audio, threshold_db, min_silence_ms)
+            .into_iter()
+            .map(|(start, end)| SilenceRegion {
+                start_ms: frame_to_ms(start, audio.sample_rate),
+                end_ms: frame_to_ms(end, audio.sample_rate),
+            })
+            .collect();
+        Ok(regions)
+    })
+    .await
+    .map_err(|e| format!("Silence scan failed: {}", e))?
+}
@@ -0,0 +1,425 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::VecDeque;
+use std::env;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::audio_file::{write_audio_file, AudioBuffer, WavEncoding};
+use crate::naming::sanitize_file_name;
+use crate::settings::SettingsStore;
+use crate::stream::StreamShared;
+use crate::validate;
+use crate::AudioState;
+
+const DEFAULT_RECENT_SECONDS: f32 = 30.0;
+const RECENT_SECONDS_RANGE: (f32, f32) = (0.0, 600.0);
+/// Frames per block of recent audio. Smaller blocks mean less copying
+/// under the lock for a reader, larger ones fewer blocks to hand out.
+const RECENT_BLOCK_FRAMES: usize = 2048;
+
+const SNAPSHOT_RANGE_MS: (f32, f32) = (10.0, 1000.0);
+/// How long a snapshot waits for a stream that has only just started.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(2);
+const SNAPSHOT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How much of each monitored stream is kept in memory for
+/// `get_recent_audio`, whether or not it is being recorded.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct RecentAudioSettings {
+    /// 0 keeps nothing.
+    pub seconds: f32,
+}
+
+impl Default for RecentAudioSettings {
+    fn default() -> Self {
+        RecentAudioSettings { seconds: DEFAULT_RECENT_SECONDS }
+    }
+}
+
+/// Ring buffer length in samples holding `seconds` of interleaved audio.
+pub fn recent_capacity(seconds: f32, sample_rate: u32, channels: usize) -> usize {
+    let seconds = seconds.clamp(RECENT_SECONDS_RANGE.0, RECENT_SECONDS_RANGE.1);
+    (seconds * sample_rate as f32).round() as usize * channels
+}
+
+/// Recent interleaved audio kept as shared blocks, so a reader only clones
+/// block handles and the unfinished tail under the lock the input callback
+/// also takes, and copies the samples out afterwards.
+pub struct RecentBuffer {
+    /// Samples kept, whole frames.
+    capacity: usize,
+    block_len: usize,
+    blocks: VecDeque<Arc<Vec<f32>>>,
+    blocks_len: usize,
+    /// The block being filled.
+    current: Vec<f32>,
+}
+
+impl RecentBuffer {
+    pub fn new(capacity: usize, channels: usize) -> Self {
+        let block_len = RECENT_BLOCK_FRAMES * channels.max(1);
+        RecentBuffer {
+            capacity,
+            block_len,
+            blocks: VecDeque::new(),
+            blocks_len: 0,
+            current: Vec::with_capacity(block_len),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes how much is kept. Returns the blocks no longer needed, so
+    /// the caller can free them after releasing the lock.
+    #[must_use]
+    pub fn set_capacity(&mut self, capacity: usize) -> Vec<Arc<Vec<f32>>> {
+        self.capacity = capacity;
+        if capacity == 0 {
+            self.current.clear();
+        }
+        self.trim()
+    }
+
+    pub fn push_slice(&mut self, samples: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let room = self.block_len - self.current.len();
+            let (now, later) = rest.split_at(room.min(rest.len()));
+            self.current.extend_from_slice(now);
+            rest = later;
+
+            if self.current.len() == self.block_len {
+                let block = mem::replace(&mut self.current, Vec::with_capacity(self.block_len));
+                self.blocks_len += block.len();
+                self.blocks.push_back(Arc::new(block));
+            }
+        }
+        // Usually at most one block, freed here like any callback buffer
+        drop(self.trim());
+    }
+
+    /// Drops the oldest blocks that are no longer needed to hold `capacity`.
+    fn trim(&mut self) -> Vec<Arc<Vec<f32>>> {
+        let mut dropped = Vec::new();
+        while let Some(front) = self.blocks.front() {
+            if self.blocks_len + self.current.len() - front.len() < self.capacity {
+                break;
+            }
+            self.blocks_len -= front.len();
+            dropped.extend(self.blocks.pop_front());
+        }
+        dropped
+    }
+
+    /// The newest `count` samples, or all that are kept if fewer. Cheap
+    /// enough to take under the lock; join them with `into_samples` after.
+    pub fn snapshot(&self, count: usize) -> RecentSnapshot {
+        let count = count.min(self.capacity).min(self.blocks_len + self.current.len());
+        let tail = &self.current[self.current.len().saturating_sub(count)..];
+
+        let mut needed = count - tail.len();
+        let mut blocks = Vec::new();
+        for block in self.blocks.iter().rev() {
+            if needed == 0 {
+                break;
+            }
+            blocks.push(Arc::clone(block));
+            needed = needed.saturating_sub(block.len());
+        }
+        blocks.reverse();
+
+        RecentSnapshot { blocks, tail: tail.to_vec(), count }
+    }
+}
+
+/// Recent audio taken from a `RecentBuffer`, not yet copied together.
+pub struct RecentSnapshot {
+    blocks: Vec<Arc<Vec<f32>>>,
+    tail: Vec<f32>,
+    count: usize,
+}
+
+impl RecentSnapshot {
+    pub fn into_samples(self) -> Vec<f32> {
+        let total = self.blocks.iter().map(|block| block.len()).sum::<usize>() + self.tail.len();
+        // The oldest block usually starts before the window
+        let mut skip = total - self.count;
+        let mut samples = Vec::with_capacity(self.count);
+        for block in &self.blocks {
+            if skip >= block.len() {
+                skip -= block.len();
+                continue;
+            }
+            samples.extend_from_slice(&block[skip..]);
+            skip = 0;
+        }
+        samples.extend_from_slice(&self.tail[skip..]);
+        samples
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct RecentAudio {
+    /// Interleaved, after the stream's gain and inserts.
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// From `get_recent_audio`, shorter than asked for when the stream
+    /// hasn't run that long.
+    pub duration_ms: f32,
+}
+
+/// The shared state, sample rate and channel count of a monitored stream,
+/// so the streams lock isn't held while audio is copied.
+fn stream_info(stream_id: &str, state: &AudioState) -> Result<(Arc<StreamShared>, u32, u16), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(stream_id))?;
+    Ok((Arc::clone(&stream.shared), stream.sample_rate, stream.channels))
+}
+
+/// Copies up to the last `seconds` kept for a monitored stream.
+fn recent_audio(stream_id: &str, seconds: f32, state: &AudioState) -> Result<AudioBuffer, String> {
+    validate::range("Duration", seconds, (0.001, RECENT_SECONDS_RANGE.1), "s")?;
+
+    let (shared, sample_rate, channels) = stream_info(stream_id, state)?;
+    let count = recent_capacity(seconds, sample_rate, channels as usize);
+    let snapshot = shared.recent.lock().unwrap().snapshot(count);
+    let samples = snapshot.into_samples();
+    if samples.is_empty() {
+        return Err(format!("No recent audio kept for stream '{}'", stream_id));
+    }
+
+    Ok(AudioBuffer {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Returns the last `seconds` of a monitored stream, so "what just
+/// happened" can be grabbed without having been recording.
+#[tauri::command]
+#[specta::specta]
+pub fn get_recent_audio(stream_id: String, seconds: f32, state: State<AudioState>) -> Result<RecentAudio, String> {
+    let audio = recent_audio(&stream_id, seconds, &state)?;
+    Ok(RecentAudio {
+        duration_ms: audio.frames() as f32 / audio.sample_rate as f32 * 1000.0,
+        samples: audio.samples,
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+    })
+}
+
+/// Writes the last `seconds` of a monitored stream to `file_path`, or to a
+/// new WAV file in the temp directory, and returns the path written.
+#[tauri::command]
+#[specta::specta]
+pub async fn save_recent_audio(
+    stream_id: String,
+    seconds: f32,
+    file_path: Option<String>,
+    state: State<'_, AudioState>,
+) -> Result<String, String> {
+    let audio = recent_audio(&stream_id, seconds, &state)?;
+    let path = match file_path {
+        Some(path) => PathBuf::from(path),
+        None => env::temp_dir().join(format!(
+            "{} {}.wav",
+            sanitize_file_name(&stream_id),
+            Local::now().format("%Y-%m-%d %H-%M-%S"),
+        )),
+    };
+
+    let written = path.clone();
+    tauri::async_runtime::spawn_blocking(move || write_audio_file(Path::new(&path), &audio, WavEncoding::Float32))
+        .await
+        .map_err(|e| format!("Save task failed: {}", e))??;
+
+    Ok(written.to_string_lossy().into_owned())
+}
+
+/// Returns exactly the last `ms` of a monitored stream, for one-off pitch
+/// or spectrum checks. A stream that hasn't run that long yet is waited on
+/// briefly. Snapshots come from the recent audio, so it must keep at least
+/// `ms`.
+#[tauri::command]
+#[specta::specta]
+pub async fn capture_snapshot(stream_id: String, ms: f32, state: State<'_, AudioState>) -> Result<RecentAudio, String> {
+    validate::range("Snapshot length", ms, SNAPSHOT_RANGE_MS, "ms")?;
+
+    let (shared, sample_rate, channels) = stream_info(&stream_id, &state)?;
+
+    let count = recent_capacity(ms / 1000.0, sample_rate, channels as usize);
+    if shared.recent.lock().unwrap().capacity() < count {
+        return Err(format!("Recent audio must be kept for at least {} ms to take this snapshot", ms));
+    }
+
+    let samples = tauri::async_runtime::spawn_blocking(move || {
+        let deadline = Instant::now() + SNAPSHOT_TIMEOUT;
+        loop {
+            let snapshot = shared.recent.lock().unwrap().snapshot(count);
+            if snapshot.count == count {
+                return Ok(snapshot.into_samples());
+            }
+            if Instant::now() >= deadline {
+                return Err("Not enough audio captured yet".to_string());
+            }
+            thread::sleep(SNAPSHOT_POLL_INTERVAL);
+        }
+    })
+    .await
+    .map_err(|e| format!("Snapshot task failed: {}", e))??;
+
+    Ok(RecentAudio {
+        duration_ms: (count / channels as usize) as f32 / sample_rate as f32 * 1000.0,
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_recent_audio_settings(settings: State<SettingsStore>) -> RecentAudioSettings {
+    settings.get().recent_audio
+}
+
+/// Changes how much audio is kept per stream. Running streams keep what
+/// they already hold, up to the new length. The setting is saved.
+#[tauri::command]
+#[specta::specta]
+pub fn set_recent_audio_settings(
+    recent: RecentAudioSettings,
+    state: State<AudioState>,
+    settings: State<SettingsStore>,
+) -> Result<(), String> {
+    validate::range("Recent audio length", recent.seconds, RECENT_SECONDS_RANGE, "s")?;
+    settings.update(|s| s.recent_audio = recent)?;
+
+    let streams: Vec<(Arc<StreamShared>, u32, u16)> = state.streams.lock().unwrap()
+        .values()
+        .map(|stream| (Arc::clone(&stream.shared), stream.sample_rate, stream.channels))
+        .collect();
+    for (shared, sample_rate, channels) in streams {
+        let capacity = recent_capacity(recent.seconds, sample_rate, channels as usize);
+        let dropped = shared.recent.lock().unwrap().set_capacity(capacity);
+        // Freed outside the lock the input callback takes
+        drop(dropped);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANNELS: usize = 2;
+    const BLOCK: usize = RECENT_BLOCK_FRAMES * CHANNELS;
+
+    /// A buffer fed `total` samples counting up from 0, in uneven callbacks.
+    fn filled(capacity: usize, total: usize) -> RecentBuffer {
+        let samples: Vec<f32> = (0..total).map(|i| i as f32).collect();
+        let mut recent = RecentBuffer::new(capacity, CHANNELS);
+        for chunk in samples.chunks(2 * 733) {
+            recent.push_slice(chunk);
+        }
+        recent
+    }
+
+    fn newest(total: usize, count: usize) -> Vec<f32> {
+        (total - count..total).map(|i| i as f32).collect()
+    }
+
+    #[test]
+    fn capacity_is_whole_frames() {
+        assert_eq!(recent_capacity(1.0, 48000, 2), 96000);
+        assert_eq!(recent_capacity(0.0, 48000, 2), 0);
+        assert_eq!(recent_capacity(1000.0, 10, 1), 6000);
+    }
+
+    #[test]
+    fn keeps_capacity_and_at_most_one_block_more() {
+        let capacity = 5 * BLOCK / 2;
+        let mut recent = RecentBuffer::new(capacity, CHANNELS);
+        let samples: Vec<f32> = (0..10 * BLOCK).map(|i| i as f32).collect();
+        for (i, chunk) in samples.chunks(2 * 733).enumerate() {
+            recent.push_slice(chunk);
+            let pushed = (i * 2 * 733 + chunk.len()).min(samples.len());
+            let kept = recent.blocks_len + recent.current.len();
+            assert_eq!(recent.blocks_len, recent.blocks.iter().map(|block| block.len()).sum::<usize>());
+            assert!(kept >= capacity.min(pushed), "kept {} of {} pushed", kept, pushed);
+            assert!(kept < capacity + BLOCK, "kept {} for capacity {}", kept, capacity);
+        }
+    }
+
+    #[test]
+    fn snapshot_is_the_newest_samples() {
+        let total = 7 * BLOCK + 1000;
+        let capacity = 3 * BLOCK + 500;
+        let recent = filled(capacity, total);
+
+        // Within the unfinished block, across block edges and the whole buffer
+        for count in [0, 10, 1000, 1002, BLOCK + 1000, 2 * BLOCK + 1000, capacity] {
+            let snapshot = recent.snapshot(count);
+            assert_eq!(snapshot.count, count);
+            assert_eq!(snapshot.into_samples(), newest(total, count), "count {}", count);
+        }
+        // Never more than the capacity, even when more is held
+        let snapshot = recent.snapshot(total);
+        assert_eq!(snapshot.count, capacity);
+        assert_eq!(snapshot.into_samples(), newest(total, capacity));
+    }
+
+    #[test]
+    fn snapshot_is_short_before_the_buffer_fills() {
+        let total = BLOCK + 300;
+        let recent = filled(4 * BLOCK, total);
+        let snapshot = recent.snapshot(2 * BLOCK);
+        assert_eq!(snapshot.count, total);
+        assert_eq!(snapshot.into_samples(), newest(total, total));
+    }
+
+    #[test]
+    fn shrinking_hands_back_old_blocks() {
+        let total = 6 * BLOCK + 100;
+        let mut recent = filled(6 * BLOCK, total);
+
+        let dropped = recent.set_capacity(BLOCK);
+        assert_eq!(dropped.len(), 5);
+        // The oldest went first
+        assert_eq!(dropped[0][0], 0.0);
+        assert_eq!(recent.blocks_len, recent.blocks.iter().map(|block| block.len()).sum::<usize>());
+        assert_eq!(recent.snapshot(total).into_samples(), newest(total, BLOCK));
+
+        // Growing keeps what is there until more arrives
+        assert!(recent.set_capacity(4 * BLOCK).is_empty());
+        assert_eq!(recent.snapshot(total).into_samples(), newest(total, BLOCK + 100));
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut recent = filled(2 * BLOCK, 3 * BLOCK + 10);
+        assert_eq!(recent.set_capacity(0).len(), 2);
+        assert!(recent.blocks.is_empty());
+        assert!(recent.current.is_empty());
+
+        recent.push_slice(&[1.0; 64]);
+        assert_eq!(recent.snapshot(64).count, 0);
+        assert!(recent.snapshot(64).into_samples().is_empty());
+    }
+}
@@ -0,0 +1,806 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use tauri_specta::Event;
+
+use crate::audio_file::{write_flac_from_wav, AudioBuffer, FlacOptions, OpusFileWriter, OpusOptions, WavEncoding};
+use crate::calculate_rms;
+use crate::dsp::channels::select_channels;
+use crate::dsp::levels::peak;
+use crate::events;
+use crate::lifecycle::{self, LifecycleState, Subsystem};
+use crate::naming::resolve_recording_path;
+use crate::stream::{InputStream, RecordTap, StreamShared};
+use crate::settings::SettingsStore;
+use crate::validate;
+use crate::AudioState;
+
+/// How often `record://status` events are emitted while recording.
+const STATUS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often free space on the target volume is checked.
+const DISK_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How often WAV headers are rewritten to cover the audio written so far,
+/// which bounds what a crash can lose (see `recovery::recover_recording`).
+const HEADER_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Free space at which `record://disk-space` warnings fire by default.
+const DEFAULT_DISK_WARNINGS_MB: [u64; 2] = [2048, 512];
+
+/// Free space left untouched by default; the recording is stopped and
+/// finalized before the volume drops below it.
+const DEFAULT_DISK_RESERVE_MB: u64 = 100;
+
+/// Longest recording kept in memory unless the caller sets a limit.
+const DEFAULT_MEMORY_LIMIT_MS: f64 = 5.0 * 60.0 * 1000.0;
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "record://status")]
+pub(crate) struct RecordStatus {
+    stream_id: String,
+    /// None while recording to memory.
+    file_path: Option<String>,
+    elapsed_ms: f32,
+    level: f32,
+    peak: f32,
+    bytes_written: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    Stopped,
+    MaxDuration,
+    DiskFull,
+    MemoryLimit,
+}
+
+impl StopReason {
+    fn describe(self) -> &'static str {
+        match self {
+            StopReason::Stopped => "Stopped",
+            StopReason::MaxDuration => "Reached the maximum duration",
+            StopReason::DiskFull => "The disk is full",
+            StopReason::MemoryLimit => "Reached the memory limit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "record://disk-space")]
+pub(crate) struct DiskSpaceEvent {
+    stream_id: String,
+    file_path: String,
+    free_bytes: u64,
+    threshold_bytes: u64,
+    /// The reserve was reached and the recording is being stopped.
+    stopping: bool,
+}
+
+/// Watches free space on the volume a recording is written to.
+struct DiskMonitor {
+    file_path: String,
+    dir: PathBuf,
+    /// Warning thresholds not yet reported, largest first.
+    warnings: Vec<u64>,
+    reserve: u64,
+    last_check: Instant,
+}
+
+impl DiskMonitor {
+    fn new(file_path: &str, warnings_mb: &[u64], reserve_mb: u64) -> Self {
+        let dir = match Path::new(file_path).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut warnings: Vec<u64> = warnings_mb.iter().map(|mb| mb * 1024 * 1024).collect();
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+
+        DiskMonitor {
+            file_path: file_path.to_string(),
+            dir,
+            warnings,
+            reserve: reserve_mb * 1024 * 1024,
+            last_check: Instant::now(),
+        }
+    }
+
+    /// Returns `(free, threshold, reserve_reached)` when a warning threshold
+    /// or the reserve has just been crossed. Errors reading free space are
+    /// ignored.
+    fn check(&mut self) -> Option<(u64, u64, bool)> {
+        if self.last_check.elapsed() < DISK_CHECK_INTERVAL {
+            return None;
+        }
+        self.last_check = Instant::now();
+
+        let free = fs2::available_space(&self.dir).ok()?;
+        if free <= self.reserve {
+            return Some((free, self.reserve, true));
+        }
+
+        let crossed = self.warnings.iter().rposition(|&threshold| free <= threshold)?;
+        let threshold = self.warnings[crossed];
+        self.warnings.drain(..=crossed);
+        Some((free, threshold, false))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "record://finished")]
+pub(crate) struct RecordFinished {
+    stream_id: String,
+    file_path: Option<String>,
+    duration_ms: f64,
+    reason: StopReason,
+    /// Set when the file could not be written or finalized.
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct RecordOptions {
+    /// 1-based device inputs to record; all of the stream's by default.
+    pub channels: Option<Vec<u16>>,
+    /// WAV sample encoding, 16-bit PCM by default.
+    pub encoding: Option<WavEncoding>,
+    /// Used when recording to an `.opus` file.
+    pub opus: Option<OpusOptions>,
+    /// Used when recording to a `.flac` file.
+    pub flac: Option<FlacOptions>,
+    /// Stops and finalizes the file once this much has been written.
+    pub max_duration_ms: Option<f64>,
+    /// Free space levels, in MB, that trigger a warning event.
+    pub disk_warning_mb: Option<Vec<u64>>,
+    /// Free space, in MB, below which the recording is stopped.
+    pub disk_reserve_mb: Option<u64>,
+    /// Fills `{session}` in the file path.
+    pub session: Option<String>,
+    /// Id of a recording preset. It supplies the format and codec settings,
+    /// while anything set in these options still takes precedence.
+    pub preset: Option<String>,
+    /// Capture into memory instead of a file. The take is only written once
+    /// `save_recording` is called, and is replaced by the next one.
+    pub to_memory: bool,
+    /// Where a recording to memory stops; five minutes by default.
+    pub memory_limit_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Wav,
+    Flac,
+    Opus,
+}
+
+impl RecordFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            RecordFormat::Wav => "wav",
+            RecordFormat::Flac => "flac",
+            RecordFormat::Opus => "opus",
+        }
+    }
+}
+
+/// A named set of recording parameters, stored in the settings file.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct RecordingPreset {
+    pub id: String,
+    pub name: String,
+    /// Stream rate the preset is meant for. Recording a stream running at
+    /// another rate fails rather than silently ignoring it.
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    pub format: RecordFormat,
+    #[serde(default)]
+    pub encoding: Option<WavEncoding>,
+    #[serde(default)]
+    pub flac: Option<FlacOptions>,
+    #[serde(default)]
+    pub opus: Option<OpusOptions>,
+}
+
+fn builtin_presets() -> Vec<RecordingPreset> {
+    vec![
+        RecordingPreset {
+            id: "voice-wav".to_string(),
+            name: "Voice 48k/16-bit WAV".to_string(),
+            sample_rate: Some(48000),
+            format: RecordFormat::Wav,
+            encoding: Some(WavEncoding::Pcm16),
+            flac: None,
+            opus: None,
+        },
+        RecordingPreset {
+            id: "music-flac".to_string(),
+            name: "Music 96k/24-bit FLAC".to_string(),
+            sample_rate: Some(96000),
+            format: RecordFormat::Flac,
+            encoding: None,
+            flac: Some(FlacOptions {
+                bits_per_sample: 24,
+                ..FlacOptions::default()
+            }),
+            opus: None,
+        },
+        RecordingPreset {
+            id: "speech-opus".to_string(),
+            name: "Speech Opus 32 kbps".to_string(),
+            sample_rate: Some(48000),
+            format: RecordFormat::Opus,
+            encoding: None,
+            flac: None,
+            opus: Some(OpusOptions {
+                bitrate_kbps: 32,
+                voice: true,
+            }),
+        },
+    ]
+}
+
+/// Built-in presets followed by the user's; a user preset with a built-in
+/// id replaces it.
+fn all_presets(settings: &SettingsStore) -> Vec<RecordingPreset> {
+    let user = settings.get().recording_presets;
+    let mut presets: Vec<RecordingPreset> = builtin_presets()
+        .into_iter()
+        .filter(|builtin| !user.iter().any(|p| p.id == builtin.id))
+        .collect();
+    presets.extend(user);
+    presets
+}
+
+impl RecordOptions {
+    /// Fills unset codec options from the preset and returns the file path
+    /// with the preset's extension.
+    fn apply_preset(&mut self, preset: &RecordingPreset, sample_rate: u32, file_path: &str) -> Result<String, String> {
+        if let Some(rate) = preset.sample_rate.filter(|&rate| rate != sample_rate) {
+            return Err(format!(
+                "Preset '{}' records at {} Hz but the stream runs at {} Hz",
+                preset.name, rate, sample_rate
+            ));
+        }
+
+        self.encoding = self.encoding.or(preset.encoding);
+        self.flac = self.flac.take().or_else(|| preset.flac.clone());
+        self.opus = self.opus.take().or_else(|| preset.opus.clone());
+
+        let path = Path::new(file_path).with_extension(preset.format.extension());
+        Ok(path.to_string_lossy().into_owned())
+    }
+}
+
+/// A FLAC recording goes to a float WAV next to the target first and is
+/// encoded when it is finalized, since the encoder works on whole files.
+pub struct FlacRecord {
+    wav: hound::WavWriter<BufWriter<File>>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    options: FlacOptions,
+}
+
+/// A recording held in memory, handed to the stream as its scratch take
+/// when finalized.
+pub struct MemoryTake {
+    buffer: AudioBuffer,
+    shared: Arc<StreamShared>,
+}
+
+/// The file a recording is written to. `.opus` paths are encoded as Ogg
+/// Opus and `.flac` paths as FLAC; everything else is written as WAV.
+pub enum RecordWriter {
+    Wav(hound::WavWriter<BufWriter<File>>, WavEncoding),
+    Flac(Box<FlacRecord>),
+    Opus(Box<OpusFileWriter>),
+    Memory(Box<MemoryTake>),
+}
+
+impl RecordWriter {
+    pub fn create(
+        path: &str,
+        sample_rate: u32,
+        channels: u16,
+        encoding: Option<WavEncoding>,
+        opus: Option<OpusOptions>,
+        flac: Option<FlacOptions>,
+    ) -> Result<Self, String> {
+        validate::output_file(Path::new(path))?;
+        let extension = Path::new(path).extension().map(|ext| ext.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("opus") => {
+                let writer = OpusFileWriter::create(Path::new(path), sample_rate, channels, &opus.unwrap_or_default())?;
+                return Ok(RecordWriter::Opus(Box::new(writer)));
+            }
+            Some("flac") => {
+                let temp_path = PathBuf::from(format!("{}.part.wav", path));
+                let wav = hound::WavWriter::create(&temp_path, WavEncoding::Float32.spec(sample_rate, channels))
+                    .map_err(|e| format!("Failed to create WAV file: {}", e))?;
+                return Ok(RecordWriter::Flac(Box::new(FlacRecord {
+                    wav,
+                    temp_path,
+                    path: PathBuf::from(path),
+                    options: flac.unwrap_or_default(),
+                })));
+            }
+            _ => {}
+        }
+
+        let encoding = encoding.unwrap_or(WavEncoding::Pcm16);
+        let writer = hound::WavWriter::create(path, encoding.spec(sample_rate, channels))
+            .map_err(|e| format!("Failed to create WAV file: {}", e))?;
+        Ok(RecordWriter::Wav(writer, encoding))
+    }
+
+    pub fn memory(sample_rate: u32, channels: u16, shared: Arc<StreamShared>) -> Self {
+        RecordWriter::Memory(Box::new(MemoryTake {
+            buffer: AudioBuffer {
+                samples: Vec::new(),
+                sample_rate,
+                channels,
+            },
+            shared,
+        }))
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), String> {
+        match self {
+            RecordWriter::Wav(writer, encoding) => {
+                for &sample in samples {
+                    encoding.write_sample(writer, sample)?;
+                }
+                Ok(())
+            }
+            RecordWriter::Flac(record) => {
+                for &sample in samples {
+                    WavEncoding::Float32.write_sample(&mut record.wav, sample)?;
+                }
+                Ok(())
+            }
+            RecordWriter::Opus(writer) => writer.write(samples),
+            RecordWriter::Memory(take) => {
+                take.buffer.samples.extend_from_slice(samples);
+                Ok(())
+            }
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        match self {
+            RecordWriter::Wav(writer, _) => writer.len() as u64 * (writer.spec().bits_per_sample / 8) as u64,
+            // Size of the intermediate WAV; the FLAC will be smaller
+            RecordWriter::Flac(record) => record.wav.len() as u64 * 4,
+            RecordWriter::Opus(writer) => writer.bytes_written(),
+            RecordWriter::Memory(take) => take.buffer.samples.len() as u64 * 4,
+        }
+    }
+
+    /// Pushes buffered audio to disk and updates the WAV header to match.
+    /// Ogg pages are self-delimiting, so Opus needs nothing here.
+    pub fn flush(&mut self) -> Result<(), String> {
+        let wav = match self {
+            RecordWriter::Wav(writer, _) => writer,
+            RecordWriter::Flac(record) => &mut record.wav,
+            RecordWriter::Opus(_) | RecordWriter::Memory(_) => return Ok(()),
+        };
+        wav.flush().map_err(|e| format!("Failed to flush WAV file: {}", e))
+    }
+
+    pub fn finalize(self) -> Result<(), String> {
+        match self {
+            RecordWriter::Wav(writer, _) => writer.finalize()
+                .map_err(|e| format!("Failed to finalize WAV file: {}", e)),
+            RecordWriter::Flac(record) => {
+                let FlacRecord { wav, temp_path, path, options } = *record;
+                wav.finalize().map_err(|e| format!("Failed to finalize WAV file: {}", e))?;
+                write_flac_from_wav(&temp_path, &path, &options)?;
+                fs::remove_file(&temp_path).map_err(|e| format!("Failed to remove temporary file: {}", e))
+            }
+            RecordWriter::Opus(writer) => writer.finalize(),
+            RecordWriter::Memory(take) => {
+                let MemoryTake { buffer, shared } = *take;
+                *shared.scratch_take.lock().unwrap() = Some(buffer);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// An audio file being written from a monitored input stream on a background thread.
+pub struct Recording {
+    writer_thread: JoinHandle<Result<(), String>>,
+}
+
+impl Recording {
+    /// Starts writing `stream` to `file_path`. A `finished` event is emitted
+    /// when the writer ends, whether stopped or on its own.
+    pub fn start(
+        app: AppHandle,
+        stream_id: &str,
+        stream: &InputStream,
+        file_path: Option<String>,
+        options: RecordOptions,
+    ) -> Result<Self, String> {
+        let picks = match options.channels {
+            Some(channels) => channel_positions(&channels, &stream.input_channels)?,
+            None => (0..stream.channels as usize).collect(),
+        };
+        let stream_channels = stream.channels as usize;
+        let all_channels = picks.iter().copied().eq(0..stream_channels);
+
+        let channel_count = picks.len() as u16;
+        let mut limit = options.max_duration_ms.map(|ms| (ms, StopReason::MaxDuration));
+        let (mut writer, mut disk) = match &file_path {
+            Some(path) => {
+                let writer = RecordWriter::create(
+                    path,
+                    stream.sample_rate,
+                    channel_count,
+                    options.encoding,
+                    options.opus,
+                    options.flac,
+                )?;
+                let disk = DiskMonitor::new(
+                    path,
+                    options.disk_warning_mb.as_deref().unwrap_or(&DEFAULT_DISK_WARNINGS_MB),
+                    options.disk_reserve_mb.unwrap_or(DEFAULT_DISK_RESERVE_MB),
+                );
+                (writer, Some(disk))
+            }
+            None => {
+                let memory_ms = options.memory_limit_ms.unwrap_or(DEFAULT_MEMORY_LIMIT_MS);
+                if limit.is_none_or(|(ms, _)| memory_ms < ms) {
+                    limit = Some((memory_ms, StopReason::MemoryLimit));
+                }
+                let writer = RecordWriter::memory(stream.sample_rate, channel_count, Arc::clone(&stream.shared));
+                (writer, None)
+            }
+        };
+        let max_samples = limit
+            .map(|(ms, _)| (ms.max(0.0) / 1000.0 * stream.sample_rate as f64) as u64 * channel_count as u64);
+
+        let (tx, rx) = mpsc::channel::<Vec<f32>>();
+
+        let mut status = RecordStatus {
+            stream_id: stream_id.to_string(),
+            file_path: file_path.clone(),
+            elapsed_ms: 0.0,
+            level: 0.0,
+            peak: 0.0,
+            bytes_written: 0,
+        };
+        let samples_per_ms = (stream.sample_rate as f32 * channel_count as f32) / 1000.0;
+
+        lifecycle::transition(&app, Subsystem::Recording, stream_id, LifecycleState::Running, None);
+        let shared = Arc::clone(&stream.shared);
+        let writer_thread = thread::spawn(move || {
+            let mut samples_written: u64 = 0;
+            let mut last_status = Instant::now();
+            let mut last_flush = Instant::now();
+            let mut reason = StopReason::Stopped;
+
+            let result = loop {
+                match rx.recv_timeout(STATUS_INTERVAL) {
+                    Ok(mut samples) => {
+                        if !all_channels {
+                            samples = select_channels(&samples, stream_channels, &picks);
+                        }
+
+                        if let Some(max) = max_samples {
+                            samples.truncate(max.saturating_sub(samples_written) as usize);
+                        }
+                        if let Err(e) = writer.write(&samples) {
+                            break Err(e);
+                        }
+
+                        samples_written += samples.len() as u64;
+                        status.level = calculate_rms(&samples);
+                        status.peak = status.peak.max(peak(&samples));
+
+                        if max_samples.is_some_and(|max| samples_written >= max) {
+                            // Detach so the callback stops queuing audio for us
+                            shared.record_tap.lock().unwrap().take();
+                            reason = limit.map_or(StopReason::MaxDuration, |(_, reason)| reason);
+                            break Ok(());
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break Ok(()),
+                }
+
+                if last_status.elapsed() >= STATUS_INTERVAL {
+                    status.elapsed_ms = samples_written as f32 / samples_per_ms;
+                    status.bytes_written = writer.bytes_written();
+                    events::emit(&app, "record://status", &status.stream_id, status.clone());
+                    last_status = Instant::now();
+                }
+
+                if last_flush.elapsed() >= HEADER_FLUSH_INTERVAL {
+                    if let Err(e) = writer.flush() {
+                        break Err(e);
+                    }
+                    last_flush = Instant::now();
+                }
+
+                let Some(disk) = disk.as_mut() else { continue };
+                if let Some((free_bytes, threshold_bytes, stopping)) = disk.check() {
+                    events::emit(&app, "record://disk-space", &status.stream_id, DiskSpaceEvent {
+                        stream_id: status.stream_id.clone(),
+                        file_path: disk.file_path.clone(),
+                        free_bytes,
+                        threshold_bytes,
+                        stopping,
+                    });
+
+                    if stopping {
+                        shared.record_tap.lock().unwrap().take();
+                        reason = StopReason::DiskFull;
+                        break Ok(());
+                    }
+                }
+            };
+
+            let result = result.and_then(|_| writer.finalize());
+            events::discard(&app, "record://status", &status.stream_id);
+            let (state, detail) = match &result {
+                Ok(_) => (LifecycleState::Completed, reason.describe().to_string()),
+                Err(e) => (LifecycleState::Failed, e.clone()),
+            };
+            lifecycle::update(&app, Subsystem::Recording, &status.stream_id, state, Some(detail));
+            let key = status.stream_id.clone();
+            events::emit(&app, "record://finished", &key, RecordFinished {
+                stream_id: status.stream_id,
+                file_path: status.file_path,
+                duration_ms: samples_written as f64 / samples_per_ms as f64,
+                reason,
+                error: result.as_ref().err().cloned(),
+            });
+            result
+        });
+
+        *stream.shared.record_tap.lock().unwrap() = Some(RecordTap { file_path, tx });
+
+        Ok(Recording { writer_thread })
+    }
+
+    /// True once the writer has ended on its own, e.g. at the maximum
+    /// duration or when the disk fills up.
+    pub fn is_finished(&self) -> bool {
+        self.writer_thread.is_finished()
+    }
+
+    /// Waits for the writer thread to drain and finalize the file. The record
+    /// tap must already be detached, otherwise this blocks forever.
+    pub fn finish(self) -> Result<(), String> {
+        self.writer_thread
+            .join()
+            .map_err(|_| "Recording thread panicked".to_string())?
+    }
+}
+
+/// Maps 1-based device channel numbers onto their positions within a stream.
+pub fn channel_positions(channels: &[u16], input_channels: &[u16]) -> Result<Vec<usize>, String> {
+    if channels.is_empty() {
+        return Err("At least one channel must be selected".to_string());
+    }
+
+    channels
+        .iter()
+        .map(|channel| {
+            input_channels
+                .iter()
+                .position(|c| c == channel)
+                .ok_or_else(|| format!("Channel {} is not part of the monitored stream", channel))
+        })
+        .collect()
+}
+
+/// Looks up a preset by id and applies it to `options`, returning the file
+/// path with the preset's extension.
+fn apply_preset_option(
+    options: &mut RecordOptions,
+    settings: &SettingsStore,
+    sample_rate: u32,
+    file_path: String,
+) -> Result<String, String> {
+    let Some(id) = options.preset.clone() else {
+        return Ok(file_path);
+    };
+    let preset = all_presets(settings)
+        .into_iter()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Unknown recording preset '{}'", id))?;
+    options.apply_preset(&preset, sample_rate, &file_path)
+}
+
+/// `file_path` may use the placeholders described in
+/// `naming::resolve_recording_path`; with a preset its extension follows the
+/// preset's format. Returns the path actually written to, or None when
+/// recording to memory, where `file_path` is not needed.
+#[tauri::command]
+#[specta::specta]
+pub fn start_recording(
+    stream_id: String,
+    file_path: Option<String>,
+    options: Option<RecordOptions>,
+    app: AppHandle,
+    state: State<AudioState>,
+    settings: State<SettingsStore>,
+) -> Result<Option<String>, String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    if stream.recording.as_ref().is_some_and(|r| !r.is_finished()) {
+        return Err(format!("Stream '{}' is already recording", stream_id));
+    }
+    if stream.shared.record_tap.lock().unwrap().is_some() {
+        return Err(format!("Stream '{}' is part of a dual recording", stream_id));
+    }
+    // Collect a recording that already stopped on its own
+    stream.finish_recording()?;
+
+    let options = options.unwrap_or_default();
+    if options.to_memory {
+        let recording = Recording::start(app, &stream_id, stream, None, options)?;
+        stream.recording = Some(recording);
+        return Ok(None);
+    }
+
+    let mut options = options;
+    let file_path = file_path.ok_or_else(|| "A file path is required unless recording to memory".to_string())?;
+    let file_path = apply_preset_option(&mut options, &settings, stream.sample_rate, file_path)?;
+
+    let session = options.session.as_deref().unwrap_or("session");
+    let file_path = resolve_recording_path(&file_path, &stream.device_name, session)?
+        .to_string_lossy()
+        .into_owned();
+
+    let recording = Recording::start(app, &stream_id, stream, Some(file_path.clone()), options)?;
+    stream.recording = Some(recording);
+    Ok(Some(file_path))
+}
+
+/// Takes a stream's recording off it. Finishing it, which for a long FLAC
+/// take means encoding the whole file, is left to the caller once the
+/// streams lock is released.
+pub fn detach_recording(stream_id: &str, state: &AudioState) -> Result<Recording, String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(stream_id))?;
+    stream.detach_recording().ok_or_else(|| format!("Stream '{}' is not recording", stream_id))
+}
+
+/// Stops recording and waits for the file to be finalized.
+#[tauri::command]
+#[specta::specta]
+pub async fn stop_recording(stream_id: String, state: State<'_, AudioState>) -> Result<(), String> {
+    let recording = detach_recording(&stream_id, &state)?;
+    tauri::async_runtime::spawn_blocking(move || recording.finish())
+        .await
+        .map_err(|e| format!("Recording task failed: {}", e))?
+}
+
+/// Writes the stream's last recording to memory to `file_path`, which takes
+/// placeholders like `start_recording`. `format` replaces the extension;
+/// the codec settings and preset in `options` apply as when recording to a
+/// file. The take is kept until it is saved or discarded, so a failed save
+/// can be retried. Returns the path written to.
+#[tauri::command]
+#[specta::specta]
+pub async fn save_recording(
+    stream_id: String,
+    file_path: String,
+    format: Option<RecordFormat>,
+    options: Option<RecordOptions>,
+    state: State<'_, AudioState>,
+    settings: State<'_, SettingsStore>,
+) -> Result<String, String> {
+    let (take, file_path, options) = {
+        let mut streams = state.streams.lock().unwrap();
+        let stream = streams
+            .get_mut(&stream_id)
+            .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+        if stream.recording.as_ref().is_some_and(|r| !r.is_finished()) {
+            return Err(format!("Stream '{}' is still recording", stream_id));
+        }
+        // Collect a recording to memory that already stopped on its own
+        stream.finish_recording()?;
+
+        let take = stream.shared.scratch_take.lock().unwrap().take()
+            .ok_or_else(|| format!("Stream '{}' has no unsaved recording", stream_id))?;
+
+        let mut options = options.unwrap_or_default();
+        let file_path = match format {
+            Some(format) => Path::new(&file_path).with_extension(format.extension()).to_string_lossy().into_owned(),
+            None => file_path,
+        };
+        let saved = apply_preset_option(&mut options, &settings, take.sample_rate, file_path).and_then(|path| {
+            let session = options.session.as_deref().unwrap_or("session");
+            resolve_recording_path(&path, &stream.device_name, session)
+        });
+        match saved {
+            Ok(path) => (take, path.to_string_lossy().into_owned(), options),
+            Err(e) => {
+                *stream.shared.scratch_take.lock().unwrap() = Some(take);
+                return Err(e);
+            }
+        }
+    };
+
+    let path = file_path.clone();
+    let (take, result) = tauri::async_runtime::spawn_blocking(move || {
+        let result = RecordWriter::create(&path, take.sample_rate, take.channels, options.encoding, options.opus, options.flac)
+            .and_then(|mut writer| {
+                writer.write(&take.samples)?;
+                writer.finalize()
+            });
+        (take, result)
+    })
+    .await
+    .map_err(|e| format!("Save task failed: {}", e))?;
+
+    if let Err(e) = result {
+        // Hand the take back unless another one replaced it meanwhile
+        if let Some(stream) = state.streams.lock().unwrap().get(&stream_id) {
+            stream.shared.scratch_take.lock().unwrap().get_or_insert(take);
+        }
+        return Err(e);
+    }
+    Ok(file_path)
+}
+
+/// Drops the stream's unsaved recording to memory.
+#[tauri::command]
+#[specta::specta]
+pub fn discard_recording(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    let discarded = stream.shared.scratch_take.lock().unwrap().take();
+    discarded
+        .map(|_| ())
+        .ok_or_else(|| format!("Stream '{}' has no unsaved recording", stream_id))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_recording_presets(settings: State<SettingsStore>) -> Vec<RecordingPreset> {
+    all_presets(&settings)
+}
+
+/// Adds or replaces a user preset, keyed by id.
+#[tauri::command]
+#[specta::specta]
+pub fn save_recording_preset(preset: RecordingPreset, settings: State<SettingsStore>) -> Result<(), String> {
+    validate::id("Preset", &preset.id)?;
+    preset.sample_rate.map(validate::sample_rate).transpose()?;
+
+    settings.update(|s| {
+        s.recording_presets.retain(|p| p.id != preset.id);
+        s.recording_presets.push(preset);
+    })
+}
+
+/// Removes a user preset. Built-in presets come back if they were overridden.
+#[tauri::command]
+#[specta::specta]
+pub fn delete_recording_preset(id: String, settings: State<SettingsStore>) -> Result<(), String> {
+    if !settings.get().recording_presets.iter().any(|p| p.id == id) {
+        return Err(format!("No user preset with id '{}'", id));
+    }
+    settings.update(|s| s.recording_presets.retain(|p| p.id != id))
+}
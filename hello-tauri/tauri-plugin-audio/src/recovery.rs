@@ -0,0 +1,105 @@
+use serde::Serialize;
+use specta::Type;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct RecoveryReport {
+    /// False when the header already matched the audio in the file.
+    pub repaired: bool,
+    pub duration_ms: f64,
+    pub data_bytes: u64,
+}
+
+fn read_u32(file: &mut File, offset: u64) -> Result<u32, String> {
+    let mut bytes = [0u8; 4];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut bytes))
+        .map_err(|e| format!("Failed to read WAV file: {}", e))?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_u32(file: &mut File, offset: u64, value: u32) -> Result<(), String> {
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.write_all(&value.to_le_bytes()))
+        .map_err(|e| format!("Failed to write WAV file: {}", e))
+}
+
+/// Whether the four bytes at `offset` look like a chunk id, which tells a
+/// real chunk after the audio apart from samples written past a stale size.
+fn chunk_id_at(file: &mut File, offset: u64) -> bool {
+    let mut id = [0u8; 4];
+    file.seek(SeekFrom::Start(offset)).is_ok()
+        && file.read_exact(&mut id).is_ok()
+        && id.iter().all(|b| b.is_ascii_alphanumeric() || *b == b' ')
+}
+
+/// Rewrites the RIFF and `data` sizes of a WAV whose writer never got to
+/// finalize it, so everything that reached the disk becomes playable. A
+/// trailing partial frame is cut off. The audio itself is not touched.
+pub fn recover_wav(path: &Path) -> Result<RecoveryReport, String> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open WAV file: {}", e))?;
+    let file_len = file.metadata().map_err(|e| format!("Failed to read WAV file: {}", e))?.len();
+
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header).map_err(|_| "Not a WAV file".to_string())?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err("Not a WAV file".to_string());
+    }
+
+    let mut format = None;
+    let mut offset = 12u64;
+    while offset + 8 <= file_len {
+        let mut id = [0u8; 4];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut id))
+            .map_err(|e| format!("Failed to read WAV file: {}", e))?;
+        let size = read_u32(&mut file, offset + 4)? as u64;
+        let body = offset + 8;
+
+        if &id == b"fmt " {
+            let sample_rate = read_u32(&mut file, body + 4)?;
+            let block_align = (read_u32(&mut file, body + 12)? & 0xffff) as u64;
+            format = Some((sample_rate, block_align.max(1)));
+        }
+
+        if &id == b"data" {
+            let (sample_rate, block_align) = format.ok_or_else(|| "WAV file has no format chunk".to_string())?;
+            let available = file_len - body;
+            let stale = size == 0 || size > available || (size < available && !chunk_id_at(&mut file, body + size + (size & 1)));
+
+            let data_bytes = if stale { available - available % block_align } else { size };
+            if stale {
+                write_u32(&mut file, offset + 4, data_bytes.min(u32::MAX as u64) as u32)?;
+                write_u32(&mut file, 4, (body + data_bytes - 8).min(u32::MAX as u64) as u32)?;
+                file.set_len(body + data_bytes)
+                    .map_err(|e| format!("Failed to write WAV file: {}", e))?;
+            }
+
+            return Ok(RecoveryReport {
+                repaired: stale,
+                duration_ms: (data_bytes / block_align) as f64 * 1000.0 / sample_rate.max(1) as f64,
+                data_bytes,
+            });
+        }
+
+        offset = body + size + (size & 1);
+    }
+
+    Err("WAV file has no audio data".to_string())
+}
+
+/// Repairs a WAV recording that was cut short by a crash or power loss.
+/// Recordings are flushed every second, so at most the last second is lost.
+#[tauri::command]
+#[specta::specta]
+pub async fn recover_recording(path: String) -> Result<RecoveryReport, String> {
+    tauri::async_runtime::spawn_blocking(move || recover_wav(Path::new(&path)))
+        .await
+        .map_err(|e| format!("Recovery task failed: {}", e))?
+}
@@ -0,0 +1,378 @@
+use serde::Deserialize;
+use specta::Type;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::audio_file::read_audio_file;
+use crate::dsp::fft::amplitude_to_db;
+use crate::spectral::{file_fft_size, stft};
+use crate::validate;
+use crate::waveform::{waveform_overview, WaveformOverview};
+
+const DEFAULT_SPECTROGRAM_SIZE: (u32, u32) = (1200, 400);
+const IMAGE_SIZE_RANGE: (u32, u32) = (16, 8192);
+const DEFAULT_DB_RANGE: (f32, f32) = (-120.0, 0.0);
+/// Lowest frequency on a logarithmic axis; the linear axis starts at 0 Hz.
+const LOG_AXIS_MIN_HZ: f32 = 20.0;
+
+const DEFAULT_WAVEFORM_SIZE: (u32, u32) = (1200, 300);
+const DEFAULT_WAVEFORM_COLOR: &str = "#2563eb";
+/// Opacity of each channel when overlaid, so the others show through.
+const OVERLAY_OPACITY: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum Colormap {
+    #[default]
+    Magma,
+    Inferno,
+    Viridis,
+    Grayscale,
+}
+
+impl Colormap {
+    /// Evenly spaced colors from the low to the high end of the map.
+    fn stops(self) -> &'static [[u8; 3]] {
+        match self {
+            Colormap::Magma => &[
+                [0, 0, 4], [28, 16, 68], [79, 18, 123], [129, 37, 129], [181, 54, 122],
+                [229, 80, 100], [251, 135, 97], [254, 194, 135], [252, 253, 191],
+            ],
+            Colormap::Inferno => &[
+                [0, 0, 4], [31, 12, 72], [85, 15, 109], [136, 34, 106], [186, 54, 85],
+                [227, 89, 51], [249, 140, 10], [249, 201, 50], [252, 255, 164],
+            ],
+            Colormap::Viridis => &[
+                [68, 1, 84], [72, 40, 120], [62, 74, 137], [49, 104, 142], [38, 130, 142],
+                [31, 158, 137], [53, 183, 121], [110, 206, 88], [181, 222, 43], [253, 231, 37],
+            ],
+            Colormap::Grayscale => &[[0, 0, 0], [255, 255, 255]],
+        }
+    }
+
+    /// Color for `t` in [0, 1], interpolated between the stops.
+    fn color(self, t: f32) -> [u8; 3] {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f32;
+        let (from, to) = (stops[index], stops[index + 1]);
+        [0, 1, 2].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * fraction).round() as u8)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct SpectrogramImageOptions {
+    /// Defaults to 1200 pixels; time runs left to right.
+    pub width: Option<u32>,
+    /// Defaults to 400 pixels; frequency rises from bottom to top.
+    pub height: Option<u32>,
+    /// Level at the low end of the colormap, -120 dBFS by default.
+    pub min_db: Option<f32>,
+    /// Level at the high end of the colormap, 0 dBFS by default.
+    pub max_db: Option<f32>,
+    pub colormap: Colormap,
+    /// FFT length; a power of two from 256 to 65536, 4096 by default.
+    pub fft_size: Option<usize>,
+    /// Logarithmic frequency axis from 20 Hz instead of a linear one.
+    pub log_frequency: bool,
+}
+
+/// Writes 8-bit pixels of `color` type, row by row from the top, as a PNG.
+fn write_png(path: &Path, width: u32, height: u32, color: png::ColorType, pixels: &[u8]) -> Result<(), String> {
+    validate::output_file(path)?;
+    let file = File::create(path).map_err(|e| format!("Failed to create image file: {}", e))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|e| format!("Failed to write PNG: {}", e))?;
+    writer.write_image_data(pixels).map_err(|e| format!("Failed to write PNG: {}", e))?;
+    writer.finish().map_err(|e| format!("Failed to write PNG: {}", e))
+}
+
+/// FFT bins shown by each image row, top row first. Rows narrower than a
+/// bin repeat it; wider rows cover several and show the loudest.
+fn row_bins(height: usize, bins: usize, bin_hz: f32, log_frequency: bool) -> Vec<(usize, usize)> {
+    let nyquist = bin_hz * (bins - 1) as f32;
+    let frequency = |t: f32| {
+        if log_frequency {
+            LOG_AXIS_MIN_HZ * (nyquist / LOG_AXIS_MIN_HZ).powf(t)
+        } else {
+            nyquist * t
+        }
+    };
+    let bin = |hz: f32| ((hz / bin_hz).round() as usize).min(bins - 1);
+
+    (0..height)
+        .rev()
+        .map(|row| {
+            let low = bin(frequency(row as f32 / height as f32));
+            let high = bin(frequency((row + 1) as f32 / height as f32));
+            (low, high.max(low))
+        })
+        .collect()
+}
+
+fn render_spectrogram_file(file_path: &Path, image_path: &Path, options: &SpectrogramImageOptions) -> Result<(), String> {
+    let (default_width, default_height) = DEFAULT_SPECTROGRAM_SIZE;
+    let width = validate::range("Image width", options.width.unwrap_or(default_width), IMAGE_SIZE_RANGE, "pixels")?;
+    let height = validate::range("Image height", options.height.unwrap_or(default_height), IMAGE_SIZE_RANGE, "pixels")?;
+    let min_db = options.min_db.unwrap_or(DEFAULT_DB_RANGE.0);
+    let max_db = options.max_db.unwrap_or(DEFAULT_DB_RANGE.1);
+    if min_db >= max_db {
+        return Err(validate::error(
+            validate::ErrorCode::OutOfRange,
+            format!("Minimum level {} dB must be below the maximum {} dB", min_db, max_db),
+        ));
+    }
+    let fft_size = file_fft_size(options.fft_size)?;
+    validate::output_file(image_path)?;
+
+    let audio = read_audio_file(file_path)?;
+    let mono = audio.to_mono();
+    if mono.len() < fft_size {
+        return Err("File is shorter than one FFT frame".to_string());
+    }
+
+    // About one frame per column, overlapping as much as that takes
+    let hop = (mono.len() - fft_size) / (width as usize - 1);
+    let mut frames = Vec::new();
+    stft(&mono, fft_size, hop, width as usize, |amplitudes| frames.push(amplitudes));
+
+    let bins = fft_size / 2 + 1;
+    let bin_hz = audio.sample_rate as f32 / fft_size as f32;
+    let rows = row_bins(height as usize, bins, bin_hz, options.log_frequency);
+
+    let (width, height) = (width as usize, height as usize);
+    let mut rgb = vec![0; width * height * 3];
+    for x in 0..width {
+        let frame = &frames[x * frames.len() / width];
+        for (y, &(low, high)) in rows.iter().enumerate() {
+            let amplitude = frame[low..=high].iter().fold(0.0f32, |max, &a| max.max(a));
+            let t = (amplitude_to_db(amplitude) - min_db) / (max_db - min_db);
+            let pixel = (y * width + x) * 3;
+            rgb[pixel..pixel + 3].copy_from_slice(&options.colormap.color(t));
+        }
+    }
+
+    write_png(image_path, width as u32, height as u32, png::ColorType::Rgb, &rgb)
+}
+
+/// Renders a file's spectrogram to a PNG at `image_path`, for sharing or
+/// embedding in reports.
+#[tauri::command]
+#[specta::specta]
+pub async fn render_spectrogram(
+    file_path: String,
+    image_path: String,
+    options: Option<SpectrogramImageOptions>,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        render_spectrogram_file(Path::new(&file_path), Path::new(&image_path), &options)
+    })
+    .await
+    .map_err(|e| format!("Spectrogram task failed: {}", e))?
+}
+
+/// How a multichannel file's channels share the image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelLayout {
+    /// One lane per channel, first channel at the top.
+    #[default]
+    Stacked,
+    /// Every channel in one lane, drawn translucent over each other.
+    Overlaid,
+    /// One lane outlining the highest and lowest peak of any channel.
+    Combined,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct WaveformImageOptions {
+    /// Defaults to 1200 pixels.
+    pub width: Option<u32>,
+    /// Defaults to 300 pixels, shared between the lanes.
+    pub height: Option<u32>,
+    /// Waveform color as `#rrggbb`.
+    pub color: Option<String>,
+    /// Background as `#rrggbb`; transparent when unset.
+    pub background: Option<String>,
+    pub layout: ChannelLayout,
+}
+
+fn parse_color(color: &str) -> Result<[u8; 3], String> {
+    let invalid = || format!("Invalid color '{}', expected #rrggbb", color);
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6).ok_or_else(invalid)?;
+
+    let mut rgb = [0; 3];
+    for (i, channel) in rgb.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(rgb)
+}
+
+/// One filled shape per column: the top and bottom edge of the waveform in
+/// pixels, drawn at `opacity`.
+struct WaveformShape {
+    edges: Vec<(f32, f32)>,
+    opacity: f32,
+}
+
+/// Lays the overview's peaks out as shapes `width` columns wide across the
+/// lanes of `layout`.
+fn waveform_shapes(overview: &WaveformOverview, width: usize, height: f32, layout: ChannelLayout) -> Vec<WaveformShape> {
+    let lanes: Vec<Vec<[f32; 2]>> = match layout {
+        ChannelLayout::Combined => {
+            let buckets = overview.peaks.first().map_or(0, Vec::len);
+            vec![(0..buckets)
+                .map(|bucket| {
+                    overview.peaks.iter().fold([0.0f32, 0.0f32], |[min, max], channel| {
+                        [min.min(channel[bucket][0]), max.max(channel[bucket][1])]
+                    })
+                })
+                .collect()]
+        }
+        _ => overview.peaks.clone(),
+    };
+
+    let (lane_height, opacity) = match layout {
+        ChannelLayout::Stacked => (height / lanes.len().max(1) as f32, 1.0),
+        ChannelLayout::Overlaid if lanes.len() > 1 => (height, OVERLAY_OPACITY),
+        _ => (height, 1.0),
+    };
+
+    lanes
+        .iter()
+        .enumerate()
+        .filter(|(_, peaks)| !peaks.is_empty())
+        .map(|(lane, peaks)| {
+            let top = if layout == ChannelLayout::Stacked { lane as f32 * lane_height } else { 0.0 };
+            let y = |value: f32| top + (1.0 - value.clamp(-1.0, 1.0)) / 2.0 * lane_height;
+            let edges = (0..width)
+                .map(|x| {
+                    let [min, max] = peaks[x * peaks.len() / width];
+                    // Silence still shows as a one pixel line
+                    let (upper, lower) = (y(max), y(min));
+                    let grow = (1.0 - (lower - upper)).max(0.0) / 2.0;
+                    (upper - grow, lower + grow)
+                })
+                .collect();
+            WaveformShape { edges, opacity }
+        })
+        .collect()
+}
+
+/// Rasterizes `shapes` with anti-aliased edges into RGBA pixels.
+fn waveform_pixels(shapes: &[WaveformShape], width: usize, height: usize, color: [u8; 3], background: Option<[u8; 3]>) -> Vec<u8> {
+    let mut rgba = match background {
+        Some([r, g, b]) => [r, g, b, 255].repeat(width * height),
+        None => vec![0; width * height * 4],
+    };
+
+    for shape in shapes {
+        for (x, &(upper, lower)) in shape.edges.iter().enumerate() {
+            let first = upper.floor().max(0.0) as usize;
+            let last = (lower.ceil() as usize).min(height);
+            for y in first..last {
+                let coverage = (lower.min(y as f32 + 1.0) - upper.max(y as f32)).clamp(0.0, 1.0);
+                let alpha = coverage * shape.opacity;
+                let pixel = &mut rgba[(y * width + x) * 4..][..4];
+
+                // Source-over onto straight alpha
+                let below = pixel[3] as f32 / 255.0 * (1.0 - alpha);
+                let out = alpha + below;
+                if out > 0.0 {
+                    for c in 0..3 {
+                        pixel[c] = ((color[c] as f32 * alpha + pixel[c] as f32 * below) / out).round() as u8;
+                    }
+                }
+                pixel[3] = (out * 255.0).round() as u8;
+            }
+        }
+    }
+
+    rgba
+}
+
+/// Draws `shapes` as filled SVG paths, one per shape.
+fn waveform_svg(shapes: &[WaveformShape], width: usize, height: usize, color: &str, background: Option<&str>) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n",
+        w = width,
+        h = height,
+    );
+    if let Some(background) = background {
+        let _ = writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", background);
+    }
+
+    for shape in shapes {
+        let mut path = String::new();
+        // Along the top edge left to right, back along the bottom
+        for (x, &(upper, _)) in shape.edges.iter().enumerate() {
+            let _ = write!(path, "{}{} {:.2}", if x == 0 { "M" } else { " L" }, x as f32 + 0.5, upper);
+        }
+        for (x, &(_, lower)) in shape.edges.iter().enumerate().rev() {
+            let _ = write!(path, " L{} {:.2}", x as f32 + 0.5, lower);
+        }
+        let _ = writeln!(svg, "  <path d=\"{} Z\" fill=\"{}\" fill-opacity=\"{}\"/>", path, color, shape.opacity);
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_waveform_file(
+    app: &AppHandle,
+    file_path: &str,
+    image_path: &Path,
+    options: &WaveformImageOptions,
+) -> Result<(), String> {
+    let (default_width, default_height) = DEFAULT_WAVEFORM_SIZE;
+    let width = validate::range("Image width", options.width.unwrap_or(default_width), IMAGE_SIZE_RANGE, "pixels")?;
+    let height = validate::range("Image height", options.height.unwrap_or(default_height), IMAGE_SIZE_RANGE, "pixels")?;
+    let color = options.color.as_deref().unwrap_or(DEFAULT_WAVEFORM_COLOR);
+    let rgb = parse_color(color)?;
+    let background = options.background.as_deref().map(parse_color).transpose()?;
+    validate::output_file(image_path)?;
+
+    // One peak pair per column, from the same cached overview the
+    // frontend draws
+    let overview = waveform_overview(app, file_path, width as usize)?;
+    let shapes = waveform_shapes(&overview, width as usize, height as f32, options.layout);
+
+    let svg = image_path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    if svg {
+        let contents = waveform_svg(&shapes, width as usize, height as usize, color, options.background.as_deref());
+        fs::write(image_path, contents).map_err(|e| format!("Failed to write image file: {}", e))
+    } else {
+        let rgba = waveform_pixels(&shapes, width as usize, height as usize, rgb, background);
+        write_png(image_path, width, height, png::ColorType::Rgba, &rgba)
+    }
+}
+
+/// Renders a file's waveform to `image_path`, as SVG when the path ends in
+/// `.svg` and as PNG otherwise.
+#[tauri::command]
+#[specta::specta]
+pub async fn render_waveform(
+    file_path: String,
+    image_path: String,
+    options: Option<WaveformImageOptions>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        render_waveform_file(&app, &file_path, Path::new(&image_path), &options)
+    })
+    .await
+    .map_err(|e| format!("Waveform task failed: {}", e))?
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use specta::Type;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::audio_file::{is_supported_audio_file, read_audio_file, AudioBuffer};
+use crate::calculate_rms;
+use crate::dsp::fft::amplitude_to_db;
+use crate::dsp::levels::{is_clipped, peak};
+use crate::dsp::loudness::LoudnessMeter;
+use crate::events;
+use crate::lifecycle::Operation;
+
+/// One row of a folder scan. Level fields are `None` when the file could
+/// not be analyzed, in which case `error` says why.
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct FileLevels {
+    pub path: String,
+    pub duration_ms: Option<f32>,
+    pub peak_db: Option<f32>,
+    pub rms_db: Option<f32>,
+    pub integrated_lufs: Option<f64>,
+    pub clipped_samples: Option<u64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "scan://file")]
+pub(crate) struct ScanProgress {
+    index: usize,
+    total: usize,
+    result: FileLevels,
+}
+
+fn measure(path: &Path, audio: &AudioBuffer) -> FileLevels {
+    let mut meter = LoudnessMeter::new(audio.sample_rate, audio.channels as usize);
+    meter.process(&audio.samples);
+
+    let frames = audio.samples.len() / audio.channels.max(1) as usize;
+
+    FileLevels {
+        path: path.display().to_string(),
+        duration_ms: Some(frames as f32 * 1000.0 / audio.sample_rate as f32),
+        peak_db: Some(amplitude_to_db(peak(&audio.samples))),
+        rms_db: Some(amplitude_to_db(calculate_rms(&audio.samples))),
+        integrated_lufs: meter.integrated(),
+        clipped_samples: Some(audio.samples.iter().filter(|&&s| is_clipped(s)).count() as u64),
+        error: None,
+    }
+}
+
+fn analyze_file(path: &Path) -> FileLevels {
+    match read_audio_file(path) {
+        Ok(audio) => measure(path, &audio),
+        Err(error) => FileLevels {
+            path: path.display().to_string(),
+            duration_ms: None,
+            peak_db: None,
+            rms_db: None,
+            integrated_lufs: None,
+            clipped_samples: None,
+            error: Some(error),
+        },
+    }
+}
+
+fn collect_audio_files(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if recursive {
+                collect_audio_files(&path, recursive, files)?;
+            }
+        } else if is_supported_audio_file(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Analyzes every supported audio file in `dir` for peak, RMS, integrated
+/// loudness and clipping. Each result is emitted as a `scan://file` event as
+/// soon as it is ready; the full table is returned at the end.
+#[tauri::command]
+#[specta::specta]
+pub async fn scan_folder_levels(dir: String, recursive: bool, app: AppHandle) -> Result<Vec<FileLevels>, String> {
+    let operation = Operation::start(&app, "scan", &dir);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let mut files = Vec::new();
+        collect_audio_files(Path::new(&dir), recursive, &mut files)?;
+        files.sort();
+
+        let total = files.len();
+        let results = files
+            .iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let result = analyze_file(path);
+                events::emit(&app, "scan://file", "", ScanProgress {
+                    index,
+                    total,
+                    result: result.clone(),
+                });
+                result
+            })
+            .collect();
+
+        Ok(results)
+    })
+    .await
+    .map_err(|e| format!("Scan task failed: {}", e))
+    .and_then(|result| result);
+    operation.finish(result)
+}
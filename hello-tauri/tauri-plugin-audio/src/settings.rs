@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::analysis::MeteringSettings;
+use crate::events::EventRates;
+#[cfg(desktop)]
+use crate::hotkeys::Hotkey;
+use crate::playback::PlaybackFades;
+use crate::recent::RecentAudioSettings;
+use crate::recording::RecordingPreset;
+use crate::soundboard::Pad;
+
+/// User settings persisted as JSON in the app config directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Type)]
+#[serde(default)]
+pub struct Settings {
+    /// Friendly labels keyed by the raw device name reported by the driver.
+    pub device_aliases: HashMap<String, String>,
+    /// User-defined recording presets, alongside the built-in ones.
+    pub recording_presets: Vec<RecordingPreset>,
+    /// Soundboard pad assignments, in pad order.
+    pub soundboard: Vec<Pad>,
+    /// Global shortcuts, registered at startup. Mobile has none.
+    #[cfg(desktop)]
+    pub hotkeys: Vec<Hotkey>,
+    /// Fades used by the player and the clip output.
+    pub playback_fades: PlaybackFades,
+    /// Rate limits for busy event topics.
+    pub event_rates: EventRates,
+    /// Output latency offsets in ms keyed by raw device name, replacing
+    /// the Bluetooth estimate.
+    pub output_latency_ms: HashMap<String, f64>,
+    /// Rate and FFT size of the live analyzers.
+    pub metering: MeteringSettings,
+    /// Audio kept per stream for grabbing what just happened.
+    pub recent_audio: RecentAudioSettings,
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    /// Loads settings from `path`, falling back to defaults when the file is
+    /// missing or unreadable so a bad file never prevents startup.
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!("Ignoring invalid settings file {}: {}", path.display(), e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+
+        SettingsStore {
+            path,
+            settings: Mutex::new(settings),
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Applies `change` and writes the result to disk straight away.
+    pub fn update(&self, change: impl FnOnce(&mut Settings)) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        change(&mut settings);
+        self.write(&settings)
+    }
+
+    /// Writes the current settings to disk, e.g. before exiting.
+    pub fn flush(&self) -> Result<(), String> {
+        self.write(&self.settings.lock().unwrap())
+    }
+
+    fn write(&self, settings: &Settings) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+        }
+
+        let contents = serde_json::to_string_pretty(settings)
+            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+        fs::write(&self.path, contents)
+            .map_err(|e| format!("Failed to write settings file: {}", e))
+    }
+}
+
+/// Assigns a friendly label to a device. An empty alias removes it.
+#[tauri::command]
+#[specta::specta]
+pub fn set_device_alias(device_name: String, alias: String, settings: State<SettingsStore>) -> Result<(), String> {
+    let alias = alias.trim().to_string();
+
+    settings.update(|s| {
+        if alias.is_empty() {
+            s.device_aliases.remove(&device_name);
+        } else {
+            s.device_aliases.insert(device_name, alias);
+        }
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_device_aliases(settings: State<SettingsStore>) -> HashMap<String, String> {
+    settings.get().device_aliases
+}
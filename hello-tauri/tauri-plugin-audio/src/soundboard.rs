@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::clips::{preload, ClipCache};
+use crate::settings::SettingsStore;
+use crate::validate;
+
+/// What triggering a pad does while it is still sounding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Cut the sounding instance and start again from the top.
+    #[default]
+    Restart,
+    /// Start another instance over the one still sounding.
+    Overlap,
+    /// Leave the sounding instance alone and don't start another.
+    Ignore,
+}
+
+/// A file assigned to a soundboard pad, as saved in the settings.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Pad {
+    pub number: u32,
+    pub path: String,
+    #[serde(default)]
+    pub gain_db: f32,
+    /// Triggering a pad silences the other pads in its group, like an open
+    /// and closed hi-hat.
+    #[serde(default)]
+    pub choke_group: Option<u32>,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct PadInfo {
+    #[serde(flatten)]
+    pub pad: Pad,
+    /// Set once the file is loaded and the pad can be triggered.
+    pub duration_ms: Option<f64>,
+    /// Why the file could not be loaded.
+    pub error: Option<String>,
+}
+
+/// Allowed range for a pad's gain, in dB.
+const PAD_GAIN_RANGE_DB: (f32, f32) = (-60.0, 12.0);
+
+fn voice_key(number: u32) -> String {
+    format!("pad_{}", number)
+}
+
+#[derive(Default)]
+pub struct SoundboardState {
+    /// Clip ids of the loaded pads, keyed by pad number.
+    loaded: Mutex<HashMap<u32, String>>,
+}
+
+impl SoundboardState {
+    fn info(&self, pad: Pad, cache: &ClipCache) -> PadInfo {
+        let clip = self.loaded.lock().unwrap().get(&pad.number).and_then(|id| cache.info(id));
+        PadInfo {
+            error: clip.is_none().then(|| "Not loaded".to_string()),
+            duration_ms: clip.map(|clip| clip.duration_ms),
+            pad,
+        }
+    }
+
+    /// Installs a newly loaded clip on a pad, unloading the one it replaces.
+    fn install(&self, number: u32, clip_id: String, cache: &ClipCache) {
+        if let Some(previous) = self.loaded.lock().unwrap().insert(number, clip_id) {
+            cache.unload(&previous);
+        }
+    }
+}
+
+fn find_pad(settings: &SettingsStore, number: u32) -> Result<Pad, String> {
+    settings.get().soundboard.into_iter()
+        .find(|pad| pad.number == number)
+        .ok_or_else(|| format!("Pad {} has nothing assigned", number))
+}
+
+/// Assigns a file to a pad, replacing what was there, and loads it into the
+/// clip cache. The assignment is saved and restored by `load_soundboard`.
+#[tauri::command]
+#[specta::specta]
+pub async fn assign_pad(
+    pad: Pad,
+    settings: State<'_, SettingsStore>,
+    cache: State<'_, ClipCache>,
+    soundboard: State<'_, SoundboardState>,
+) -> Result<PadInfo, String> {
+    validate::range("Pad gain", pad.gain_db, PAD_GAIN_RANGE_DB, "dB")?;
+
+    let clip = preload(&cache, pad.path.clone()).await?;
+    soundboard.install(pad.number, clip.id, &cache);
+
+    let saved = pad.clone();
+    settings.update(|s| {
+        s.soundboard.retain(|p| p.number != saved.number);
+        s.soundboard.push(saved);
+        s.soundboard.sort_by_key(|p| p.number);
+    })?;
+    Ok(soundboard.info(pad, &cache))
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn clear_pad(
+    number: u32,
+    settings: State<SettingsStore>,
+    cache: State<ClipCache>,
+    soundboard: State<SoundboardState>,
+) -> Result<(), String> {
+    let key = voice_key(number);
+    cache.stop(|k| k == key);
+    if let Some(clip_id) = soundboard.loaded.lock().unwrap().remove(&number) {
+        cache.unload(&clip_id);
+    }
+    settings.update(|s| s.soundboard.retain(|p| p.number != number))
+}
+
+/// Loads every saved pad that isn't loaded yet, e.g. at startup. Pads whose
+/// file can't be read are reported with an error and stay assigned.
+#[tauri::command]
+#[specta::specta]
+pub async fn load_soundboard(
+    settings: State<'_, SettingsStore>,
+    cache: State<'_, ClipCache>,
+    soundboard: State<'_, SoundboardState>,
+) -> Result<Vec<PadInfo>, String> {
+    let mut infos = Vec::new();
+    for pad in settings.get().soundboard {
+        let loaded = soundboard.loaded.lock().unwrap().get(&pad.number)
+            .is_some_and(|id| cache.info(id).is_some());
+        if loaded {
+            infos.push(soundboard.info(pad, &cache));
+            continue;
+        }
+
+        match preload(&cache, pad.path.clone()).await {
+            Ok(clip) => {
+                soundboard.install(pad.number, clip.id, &cache);
+                infos.push(soundboard.info(pad, &cache));
+            }
+            Err(e) => infos.push(PadInfo { pad, duration_ms: None, error: Some(e) }),
+        }
+    }
+    Ok(infos)
+}
+
+/// Plays a pad according to its overlap policy, choking the other pads in
+/// its group. Returns false when an `ignore` pad was still sounding.
+#[tauri::command]
+#[specta::specta]
+pub fn trigger_pad(
+    number: u32,
+    settings: State<SettingsStore>,
+    cache: State<ClipCache>,
+    soundboard: State<SoundboardState>,
+) -> Result<bool, String> {
+    let pad = find_pad(&settings, number)?;
+    let clip_id = soundboard.loaded.lock().unwrap().get(&number).cloned()
+        .ok_or_else(|| format!("Pad {} is not loaded", number))?;
+
+    let key = voice_key(number);
+    let choked: Vec<String> = match pad.choke_group {
+        Some(group) => settings.get().soundboard.iter()
+            .filter(|p| p.choke_group == Some(group) && p.number != number)
+            .map(|p| voice_key(p.number))
+            .collect(),
+        None => Vec::new(),
+    };
+    let restart = pad.overlap == OverlapPolicy::Restart;
+    let stop = |k: &str| (restart && k == key) || choked.iter().any(|c| c == k);
+
+    cache.trigger(&clip_id, &key, pad.gain_db, stop, pad.overlap == OverlapPolicy::Ignore)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_pad(number: u32, cache: State<ClipCache>) -> Result<(), String> {
+    let key = voice_key(number);
+    cache.stop(|k| k == key);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_soundboard(
+    settings: State<SettingsStore>,
+    cache: State<ClipCache>,
+    soundboard: State<SoundboardState>,
+) -> Result<Vec<PadInfo>, String> {
+    Ok(settings.get().soundboard.into_iter()
+        .map(|pad| soundboard.info(pad, &cache))
+        .collect())
+}
@@ -0,0 +1,144 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+
+use crate::audio_file::read_audio_file;
+use crate::dsp::fft::{amplitude_to_db, SpectrumAnalyzer};
+
+const DEFAULT_FFT_SIZE: usize = 4096;
+/// Spectrogram rows are capped by widening the hop, keeping payloads sane
+/// for long files.
+const MAX_SPECTROGRAM_FRAMES: usize = 4000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SpectrumMode {
+    #[default]
+    Average,
+    Spectrogram,
+    Both,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Type)]
+#[serde(default)]
+pub struct SpectrumOptions {
+    pub mode: SpectrumMode,
+    /// FFT length (frequency resolution); a power of two from 256 to 65536.
+    pub fft_size: Option<usize>,
+    /// Hop between frames (time resolution); defaults to half the FFT size.
+    pub hop_ms: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SpectrumAnalysis {
+    pub sample_rate: u32,
+    pub fft_size: usize,
+    pub bin_hz: f32,
+    /// Time between spectrogram rows after any hop widening.
+    pub hop_ms: f32,
+    /// Power-averaged magnitude per bin in dBFS.
+    pub average_db: Option<Vec<f32>>,
+    /// `spectrogram_db[frame][bin]` in dBFS.
+    pub spectrogram_db: Option<Vec<Vec<f32>>>,
+}
+
+/// Short-time Fourier transform over a mono signal. Calls `frame` with each
+/// frame's amplitudes and returns the hop actually used, in samples.
+pub fn stft(
+    samples: &[f32],
+    fft_size: usize,
+    hop: usize,
+    max_frames: usize,
+    mut frame: impl FnMut(Vec<f32>),
+) -> usize {
+    let available = samples.len().saturating_sub(fft_size) / hop.max(1) + 1;
+    let hop = if available > max_frames {
+        samples.len().saturating_sub(fft_size) / (max_frames - 1).max(1)
+    } else {
+        hop
+    }
+    .max(1);
+
+    let mut analyzer = SpectrumAnalyzer::new(fft_size);
+    let mut start = 0;
+    while start + fft_size <= samples.len() {
+        frame(analyzer.amplitudes(&samples[start..start + fft_size]));
+        start += hop;
+    }
+
+    hop
+}
+
+/// The requested FFT length for file analysis, or the 4096 default.
+pub fn file_fft_size(requested: Option<usize>) -> Result<usize, String> {
+    let fft_size = requested.unwrap_or(DEFAULT_FFT_SIZE);
+    if !fft_size.is_power_of_two() || !(256..=65536).contains(&fft_size) {
+        return Err("FFT size must be a power of two between 256 and 65536".to_string());
+    }
+    Ok(fft_size)
+}
+
+pub fn analyze_spectrum_file(path: &Path, options: &SpectrumOptions) -> Result<SpectrumAnalysis, String> {
+    let fft_size = file_fft_size(options.fft_size)?;
+
+    let audio = read_audio_file(path)?;
+    let mono = audio.to_mono();
+    if mono.len() < fft_size {
+        return Err("File is shorter than one FFT frame".to_string());
+    }
+
+    let hop = match options.hop_ms {
+        Some(ms) if ms > 0.0 => (ms / 1000.0 * audio.sample_rate as f32) as usize,
+        Some(_) => return Err("Hop must be positive".to_string()),
+        None => fft_size / 2,
+    };
+
+    let want_average = options.mode != SpectrumMode::Spectrogram;
+    let want_spectrogram = options.mode != SpectrumMode::Average;
+
+    let bins = fft_size / 2 + 1;
+    let mut power_sum = vec![0.0f64; bins];
+    let mut frames = 0usize;
+    let mut spectrogram = Vec::new();
+
+    let max_frames = if want_spectrogram { MAX_SPECTROGRAM_FRAMES } else { usize::MAX };
+    let hop = stft(&mono, fft_size, hop, max_frames, |amplitudes| {
+        if want_average {
+            for (sum, a) in power_sum.iter_mut().zip(&amplitudes) {
+                *sum += (*a as f64) * (*a as f64);
+            }
+        }
+        if want_spectrogram {
+            spectrogram.push(amplitudes.iter().map(|&a| amplitude_to_db(a)).collect());
+        }
+        frames += 1;
+    });
+
+    let average_db = want_average.then(|| {
+        power_sum
+            .iter()
+            .map(|&sum| amplitude_to_db((sum / frames.max(1) as f64).sqrt() as f32))
+            .collect()
+    });
+
+    Ok(SpectrumAnalysis {
+        sample_rate: audio.sample_rate,
+        fft_size,
+        bin_hz: audio.sample_rate as f32 / fft_size as f32,
+        hop_ms: hop as f32 * 1000.0 / audio.sample_rate as f32,
+        average_db,
+        spectrogram_db: want_spectrogram.then_some(spectrogram),
+    })
+}
+
+/// Averaged spectrum and/or spectrogram of a file, for inspecting recordings
+/// after the fact.
+#[tauri::command]
+#[specta::specta]
+pub async fn analyze_spectrum(file_path: String, options: Option<SpectrumOptions>) -> Result<SpectrumAnalysis, String> {
+    let options = options.unwrap_or_default();
+
+    tauri::async_runtime::spawn_blocking(move || analyze_spectrum_file(Path::new(&file_path), &options))
+        .await
+        .map_err(|e| format!("Spectrum task failed: {}", e))?
+}
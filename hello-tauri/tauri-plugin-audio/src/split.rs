@@ -0,0 +1,133 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio_file::{read_audio_file, write_audio_file, write_wav, AudioBuffer, WavEncoding};
+use crate::markers::Region;
+use crate::naming::sanitize_file_name;
+use crate::qc::find_silent_frames;
+
+/// Silence kept on each side of a segment so the cut does not clip the
+/// start of a note or the tail of a fade.
+const SEGMENT_PADDING_MS: f64 = 250.0;
+/// Audio between silences shorter than this (clicks, needle drops) is
+/// not written out as a segment.
+const MIN_SEGMENT_MS: f64 = 1000.0;
+
+const DEFAULT_NAMING_TEMPLATE: &str = "{stem} - {index} - {name}";
+
+fn ms_to_frames(ms: f64, sample_rate: u32) -> usize {
+    (ms / 1000.0 * sample_rate as f64) as usize
+}
+
+/// Frame ranges of the audible parts between silences, padded into the
+/// surrounding silence.
+fn segments_between(audio: &AudioBuffer, silences: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let padding = ms_to_frames(SEGMENT_PADDING_MS, audio.sample_rate);
+    let min_frames = ms_to_frames(MIN_SEGMENT_MS, audio.sample_rate);
+    let frames = audio.frames();
+
+    let mut segments = Vec::new();
+    let mut cursor = 0;
+    for &(silence_start, silence_end) in silences.iter().chain([(frames, frames)].iter()) {
+        if silence_start - cursor >= min_frames {
+            let start = cursor.saturating_sub(padding);
+            let end = (silence_start + padding).min(silence_end);
+            segments.push((start, end));
+        }
+        cursor = silence_end;
+    }
+
+    segments
+}
+
+/// Writes each stretch of audio between silences in `src` to its own
+/// numbered WAV file in `dst_dir`, returning the paths written.
+#[tauri::command]
+#[specta::specta]
+pub async fn split_on_silence(
+    src: String,
+    dst_dir: String,
+    threshold_db: f32,
+    min_silence_ms: f64,
+    encoding: Option<WavEncoding>,
+) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let src = Path::new(&src);
+        let audio = read_audio_file(src)?;
+        let silences = find_silent_frames(&audio, threshold_db, min_silence_ms);
+        let segments = segments_between(&audio, &silences);
+
+        let dst_dir = PathBuf::from(dst_dir);
+        fs::create_dir_all(&dst_dir)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+        let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("segment");
+        let mut written = Vec::new();
+        for (index, (start, end)) in segments.into_iter().enumerate() {
+            let path = dst_dir.join(format!("{} - {:02}.wav", stem, index + 1));
+            write_wav(&path, &audio.slice(start, end), encoding.unwrap_or_default())?;
+            written.push(path.display().to_string());
+        }
+
+        Ok(written)
+    })
+    .await
+    .map_err(|e| format!("Split task failed: {}", e))?
+}
+
+/// Fills `{stem}`, `{index}` and `{name}` in a naming template and strips
+/// characters that are not allowed in file names.
+fn render_file_name(template: &str, stem: &str, index: usize, name: &str) -> String {
+    let rendered = template
+        .replace("{stem}", stem)
+        .replace("{index}", &format!("{:02}", index))
+        .replace("{name}", name);
+
+    sanitize_file_name(&rendered)
+}
+
+/// Writes every region of `path` to its own file in `dst_dir`, named from
+/// `naming_template`. `format` is the output extension, WAV by default.
+/// Returns the paths written, in region order.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_regions(
+    path: String,
+    regions: Vec<Region>,
+    dst_dir: String,
+    naming_template: Option<String>,
+    format: Option<String>,
+    encoding: Option<WavEncoding>,
+) -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let src = Path::new(&path);
+        let audio = read_audio_file(src)?;
+        let template = naming_template.unwrap_or_else(|| DEFAULT_NAMING_TEMPLATE.to_string());
+        let encoding = encoding.unwrap_or_default();
+        let format = format.unwrap_or_else(|| "wav".to_string());
+
+        let dst_dir = PathBuf::from(dst_dir);
+        fs::create_dir_all(&dst_dir)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+        let stem = src.file_stem().and_then(|s| s.to_str()).unwrap_or("region");
+        let mut written = Vec::new();
+        for (index, region) in regions.iter().enumerate() {
+            if region.end_ms <= region.start_ms {
+                return Err(format!("Region '{}' ends before it starts", region.name));
+            }
+
+            let start = ms_to_frames(region.start_ms, audio.sample_rate);
+            let end = ms_to_frames(region.end_ms, audio.sample_rate);
+            let file_name = render_file_name(&template, stem, index + 1, &region.name);
+            let path = dst_dir.join(format!("{}.{}", file_name, format));
+
+            write_audio_file(&path, &audio.slice(start, end), encoding)?;
+            written.push(path.display().to_string());
+        }
+
+        Ok(written)
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))?
+}
@@ -0,0 +1,799 @@
+use serde::Serialize;
+use specta::Type;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+use crate::audio_file::AudioBuffer;
+use crate::backend::{Device, InputTimestamp, Stream, StreamError};
+use crate::calculate_rms;
+use crate::config::{negotiate_input_config, ConfigRequest, NegotiatedConfig};
+use crate::dsp::channels::{parse_channel_numbers, select_channels};
+use crate::dsp::dynamics::{Dynamics, DynamicsProcessor};
+use crate::dsp::gain::{db_to_linear, GainRamp};
+use crate::dsp::stereo::{mid_side, MidSide};
+use crate::dsp::loudness::LoudnessMeter;
+use crate::dsp::ring::RingBuffer;
+use crate::events;
+use crate::insert::InsertChain;
+use crate::lifecycle::{self, LifecycleState, Subsystem};
+use crate::passthrough::{LatencyBuffer, Passthrough};
+use crate::recent::{recent_capacity, RecentBuffer};
+use crate::recording::Recording;
+use crate::settings::SettingsStore;
+use crate::sync::SyncClock;
+use crate::ticker::Ticker;
+use crate::transcription::live::LiveTranscription;
+
+/// How often the stream thread checks for conditions to report to the frontend.
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Mono samples kept per stream for on-demand analysis (FFT size).
+pub const ANALYSIS_WINDOW: usize = 8192;
+
+/// Stereo frames kept per stream for the goniometer.
+pub const STEREO_WINDOW: usize = 2048;
+
+/// How long a running stream may go without callbacks before it counts as stalled.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Smallest gap between buffers that counts as an xrun, on top of the
+/// buffer's own duration.
+const MIN_XRUN_GAP: Duration = Duration::from_millis(2);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Type)]
+pub struct XrunCounts {
+    /// Gaps in the capture timeline longer than the audio delivered.
+    pub timing_gaps: u64,
+    /// Errors reported by the driver through the stream error callback.
+    pub driver_errors: u64,
+}
+
+impl XrunCounts {
+    fn total(&self) -> u64 {
+        self.timing_gaps + self.driver_errors
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://device-lost")]
+pub(crate) struct DeviceLostEvent {
+    stream_id: String,
+    /// The recording that was cut short, if one was running. It is finalized
+    /// so everything captured before the disconnect stays readable.
+    recording_file: Option<String>,
+}
+
+/// Where the callback forwards samples while a recording is running.
+pub struct RecordTap {
+    /// None while recording to memory.
+    pub file_path: Option<String>,
+    pub tx: mpsc::Sender<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://stream-stalled")]
+pub(crate) struct StreamStalledEvent {
+    stream_id: String,
+    stalled_ms: f64,
+    /// Whether the watchdog is about to reopen the stream.
+    restarting: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://stream-restarted")]
+pub(crate) struct StreamRestartedEvent {
+    stream_id: String,
+    /// Set when reopening failed; the stream is dead until restarted by hand.
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "audio://xrun")]
+pub(crate) struct XrunEvent {
+    stream_id: String,
+    timing_gaps: u64,
+    driver_errors: u64,
+}
+
+/// A dynamics processor on a stream, keyed by the stream's own level or by
+/// another stream's.
+pub struct Sidechain {
+    /// `None` keys from the stream itself. A closed key stream reads as silence.
+    pub key: Option<Weak<StreamShared>>,
+    pub dynamics: Dynamics,
+}
+
+/// Running totals updated at the end of every callback.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallbackStats {
+    frames: u64,
+    callbacks: u64,
+    min_duration: Option<Duration>,
+    max_duration: Duration,
+    total_duration: Duration,
+}
+
+impl CallbackStats {
+    fn record(&mut self, frames: usize, duration: Duration) {
+        self.frames += frames as u64;
+        self.callbacks += 1;
+        self.min_duration = Some(self.min_duration.map_or(duration, |min| min.min(duration)));
+        self.max_duration = self.max_duration.max(duration);
+        self.total_duration += duration;
+    }
+}
+
+/// Snapshot of a stream's runtime statistics for debugging glitchy setups.
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct StreamStats {
+    pub frames_processed: u64,
+    pub callbacks: u64,
+    pub callbacks_per_second: f64,
+    pub min_callback_us: f64,
+    pub avg_callback_us: f64,
+    pub max_callback_us: f64,
+    pub uptime_ms: f64,
+}
+
+/// State shared between a running input callback and the command handlers.
+pub struct StreamShared {
+    pub volume: Mutex<f32>,
+    /// Software input gain applied before metering and recording.
+    pub gain_db: Mutex<f32>,
+    /// Convert the first two channels to mid/side before metering and
+    /// recording.
+    pub mid_side: Mutex<bool>,
+    /// Gate, EQ, compressor, limiter and plugin inserts, run after the gain.
+    pub insert_chain: Mutex<Option<InsertChain>>,
+    pub dynamics: Mutex<Option<Sidechain>>,
+    pub muted: Mutex<bool>,
+    pub soloed: Mutex<bool>,
+    /// False while muted, or while another stream is soloed. Silenced
+    /// streams meter, record and pass through as silence.
+    pub audible: Mutex<bool>,
+    pub xruns: Mutex<XrunCounts>,
+    /// Set by the error callback once the device has disappeared.
+    pub device_lost: Mutex<bool>,
+    pub stats: Mutex<CallbackStats>,
+    /// Sample position and host timestamps of the callbacks.
+    pub sync: Mutex<SyncClock>,
+    /// Let the watchdog reopen the stream when it stalls.
+    pub auto_restart: Mutex<bool>,
+    /// Most recent mono-mixed samples, used by the live analyzers.
+    pub analysis: Mutex<RingBuffer>,
+    /// Interleaved L/R pairs of the first two channels, for stereo displays.
+    pub stereo: Mutex<RingBuffer>,
+    /// Interleaved samples of the last few seconds, for `get_recent_audio`.
+    pub recent: Mutex<RecentBuffer>,
+    /// EBU R128 meter over everything captured since start or the last reset.
+    pub loudness: Mutex<LoudnessMeter>,
+    pub record_tap: Mutex<Option<RecordTap>>,
+    /// Mono-mixed copy of the input for live transcription.
+    pub transcript_tap: Mutex<Option<mpsc::Sender<Vec<f32>>>>,
+    /// Last recording made to memory, until it is saved or discarded.
+    pub scratch_take: Mutex<Option<AudioBuffer>>,
+    /// Queue feeding an output device while passthrough is on.
+    pub passthrough: Mutex<Option<Arc<Mutex<LatencyBuffer>>>>,
+    /// Queue feeding the mix bus while the stream is part of the mix.
+    pub mix_send: Mutex<Option<Arc<Mutex<LatencyBuffer>>>>,
+}
+
+impl StreamShared {
+    fn new(sample_rate: u32, channels: usize, recent_seconds: f32) -> Self {
+        StreamShared {
+            volume: Mutex::new(0.0),
+            gain_db: Mutex::new(0.0),
+            mid_side: Mutex::new(false),
+            insert_chain: Mutex::new(None),
+            dynamics: Mutex::new(None),
+            muted: Mutex::new(false),
+            soloed: Mutex::new(false),
+            audible: Mutex::new(true),
+            xruns: Mutex::default(),
+            device_lost: Mutex::new(false),
+            stats: Mutex::default(),
+            sync: Mutex::new(SyncClock::new(sample_rate)),
+            auto_restart: Mutex::new(false),
+            analysis: Mutex::new(RingBuffer::new(ANALYSIS_WINDOW)),
+            stereo: Mutex::new(RingBuffer::new(STEREO_WINDOW * 2)),
+            recent: Mutex::new(RecentBuffer::new(recent_capacity(recent_seconds, sample_rate, channels), channels)),
+            loudness: Mutex::new(LoudnessMeter::new(sample_rate, channels)),
+            record_tap: Mutex::new(None),
+            transcript_tap: Mutex::new(None),
+            scratch_take: Mutex::new(None),
+            passthrough: Mutex::new(None),
+            mix_send: Mutex::new(None),
+        }
+    }
+
+    fn process(&self, samples: Vec<f32>, channels: usize) {
+        *self.volume.lock().unwrap() = calculate_rms(&samples);
+
+        let mono: Vec<f32> = samples
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        self.analysis.lock().unwrap().push_slice(&mono);
+        if let Some(tx) = self.transcript_tap.lock().unwrap().as_ref() {
+            let _ = tx.send(mono.clone());
+        }
+        self.loudness.lock().unwrap().process(&samples);
+        self.recent.lock().unwrap().push_slice(&samples);
+
+        if channels >= 2 {
+            let pairs: Vec<f32> = samples
+                .chunks_exact(channels)
+                .flat_map(|frame| [frame[0], frame[1]])
+                .collect();
+            self.stereo.lock().unwrap().push_slice(&pairs);
+        }
+
+        if let Some(buffer) = self.passthrough.lock().unwrap().as_ref() {
+            buffer.lock().unwrap().push(&samples);
+        }
+        if let Some(buffer) = self.mix_send.lock().unwrap().as_ref() {
+            buffer.lock().unwrap().push(&samples);
+        }
+
+        if let Some(tap) = self.record_tap.lock().unwrap().as_ref() {
+            let _ = tap.tx.send(samples);
+        }
+    }
+}
+
+/// A running input stream. Dropping it stops the underlying device stream.
+pub struct InputStream {
+    /// The device config the stream was opened with.
+    pub config: NegotiatedConfig,
+    pub device_name: String,
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// 1-based device channel numbers carried by this stream, in stream order.
+    pub input_channels: Vec<u16>,
+    pub shared: Arc<StreamShared>,
+    pub recording: Option<Recording>,
+    /// Emits goniometer points while enabled.
+    pub goniometer: Option<Ticker>,
+    /// Emits oscilloscope peaks while enabled.
+    pub oscilloscope: Option<Ticker>,
+    pub passthrough: Option<Passthrough>,
+    pub live_transcription: Option<LiveTranscription>,
+    started_at: Instant,
+    control_tx: mpsc::Sender<StreamControl>,
+}
+
+/// Requests to the thread owning the device stream.
+enum StreamControl {
+    Pause,
+    Resume,
+    Stop,
+}
+
+impl InputStream {
+    /// Opens `device` with the config closest to `request` and starts
+    /// capturing. With `channels` set, only those 1-based device inputs are
+    /// kept; otherwise every channel is used.
+    pub fn open(
+        app: AppHandle,
+        stream_id: &str,
+        device: Device,
+        channels: Option<Vec<u16>>,
+        mut request: ConfigRequest,
+    ) -> Result<Self, String> {
+        if request.channels.is_none() {
+            // Make sure the highest selected input is actually opened
+            request.channels = channels.as_ref().and_then(|c| c.iter().max().copied());
+        }
+
+        let (config, negotiated) = negotiate_input_config(&device, &request)?;
+        let device_name = device.name().unwrap_or_default();
+        let sample_format = config.sample_format();
+        let config = negotiated.stream_config();
+
+        let input_channels = channels.unwrap_or_else(|| (1..=config.channels).collect());
+        let picks = parse_channel_numbers(&input_channels, config.channels)?;
+
+        let recent = app.try_state::<SettingsStore>().map(|s| s.get().recent_audio).unwrap_or_default();
+        let shared = Arc::new(StreamShared::new(config.sample_rate.0, input_channels.len(), recent.seconds));
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let (control_tx, control_rx) = mpsc::channel::<StreamControl>();
+
+        // Device streams are not Send, so each one is owned by its own thread
+        // and kept alive until a stop is requested or the handle is dropped.
+        // While alive, the thread also reports stream problems as events.
+        let stream_shared = Arc::clone(&shared);
+        let stream_config = config.clone();
+        let stream_id = stream_id.to_string();
+        thread::spawn(move || {
+            let start = || {
+                let callback_shared = Arc::clone(&stream_shared);
+                start_stream(&device, &stream_config, sample_format, picks.clone(), callback_shared)
+            };
+
+            let mut stream = match start() {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+
+            let _ = ready_tx.send(Ok(()));
+
+            let mut reported_xruns = XrunCounts::default();
+            let mut reported_device_lost = false;
+            let mut reported_stall = false;
+            let mut last_callbacks = 0;
+            let mut last_progress = Instant::now();
+            let mut paused = false;
+
+            loop {
+                match control_rx.recv_timeout(HOUSEKEEPING_INTERVAL) {
+                    Ok(StreamControl::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+                    Ok(StreamControl::Pause) => {
+                        paused = true;
+                        if let Err(e) = stream.pause() {
+                            let reason = format!("Failed to pause stream: {}", e);
+                            update_stream_state(&app, &stream_id, StreamState::Error, Some(reason));
+                        }
+                        continue;
+                    }
+                    Ok(StreamControl::Resume) => {
+                        // Callbacks take a moment to come back after resuming
+                        paused = false;
+                        last_progress = Instant::now();
+                        if let Err(e) = stream.play() {
+                            let reason = format!("Failed to resume stream: {}", e);
+                            update_stream_state(&app, &stream_id, StreamState::Error, Some(reason));
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+
+                let device_lost = *stream_shared.device_lost.lock().unwrap();
+                if device_lost && !reported_device_lost {
+                    // Dropping the tap lets the writer thread finalize the file
+                    let tap = stream_shared.record_tap.lock().unwrap().take();
+                    events::emit(&app, "audio://device-lost", &stream_id, DeviceLostEvent {
+                        stream_id: stream_id.clone(),
+                        recording_file: tap.and_then(|tap| tap.file_path),
+                    });
+                    let reason = Some("The device was disconnected".to_string());
+                    update_stream_state(&app, &stream_id, StreamState::Error, reason);
+                    reported_device_lost = true;
+                }
+
+                let xruns = *stream_shared.xruns.lock().unwrap();
+                if xruns.total() > reported_xruns.total() {
+                    events::emit(&app, "audio://xrun", &stream_id, XrunEvent {
+                        stream_id: stream_id.clone(),
+                        timing_gaps: xruns.timing_gaps,
+                        driver_errors: xruns.driver_errors,
+                    });
+                    reported_xruns = xruns;
+                }
+
+                // Watchdog: a stream that is supposedly running but no longer
+                // delivers callbacks (wedged driver, sleeping device)
+                let callbacks = stream_shared.stats.lock().unwrap().callbacks;
+                if callbacks != last_callbacks {
+                    if reported_stall {
+                        let reason = Some("Callbacks resumed".to_string());
+                        update_stream_state(&app, &stream_id, StreamState::Running, reason);
+                    }
+                    last_callbacks = callbacks;
+                    last_progress = Instant::now();
+                    reported_stall = false;
+                    continue;
+                }
+
+                if device_lost || paused || reported_stall || last_progress.elapsed() < STALL_TIMEOUT {
+                    continue;
+                }
+
+                let restarting = *stream_shared.auto_restart.lock().unwrap();
+                let stalled_ms = last_progress.elapsed().as_secs_f64() * 1000.0;
+                events::emit(&app, "audio://stream-stalled", &stream_id, StreamStalledEvent {
+                    stream_id: stream_id.clone(),
+                    stalled_ms,
+                    restarting,
+                });
+                let reason = Some(format!("No audio callbacks for {:.0} ms", stalled_ms));
+                update_stream_state(&app, &stream_id, StreamState::Error, reason);
+                reported_stall = true;
+
+                if restarting {
+                    drop(stream);
+                    let result = start();
+                    events::emit(&app, "audio://stream-restarted", &stream_id, StreamRestartedEvent {
+                        stream_id: stream_id.clone(),
+                        error: result.as_ref().err().cloned(),
+                    });
+
+                    match result {
+                        Ok(restarted) => {
+                            let reason = Some("Restarted after stalling".to_string());
+                            update_stream_state(&app, &stream_id, StreamState::Running, reason);
+                            stream = restarted;
+                        }
+                        Err(e) => {
+                            update_stream_state(&app, &stream_id, StreamState::Error, Some(e));
+                            return;
+                        }
+                    }
+
+                    // Give the new stream a full timeout before judging it
+                    last_progress = Instant::now();
+                    reported_stall = false;
+                }
+            }
+        });
+
+        ready_rx.recv()
+            .map_err(|_| "Stream thread exited unexpectedly".to_string())??;
+
+        Ok(InputStream {
+            config: negotiated,
+            device_name,
+            sample_rate: config.sample_rate.0,
+            channels: input_channels.len() as u16,
+            input_channels,
+            shared,
+            recording: None,
+            goniometer: None,
+            oscilloscope: None,
+            passthrough: None,
+            live_transcription: None,
+            started_at: Instant::now(),
+            control_tx,
+        })
+    }
+
+    pub fn stats(&self) -> StreamStats {
+        let stats = *self.shared.stats.lock().unwrap();
+        let uptime = self.started_at.elapsed();
+        let avg_duration = match stats.callbacks {
+            0 => Duration::ZERO,
+            callbacks => stats.total_duration / callbacks as u32,
+        };
+
+        StreamStats {
+            frames_processed: stats.frames,
+            callbacks: stats.callbacks,
+            callbacks_per_second: stats.callbacks as f64 / uptime.as_secs_f64().max(f64::EPSILON),
+            min_callback_us: stats.min_duration.unwrap_or_default().as_secs_f64() * 1e6,
+            avg_callback_us: avg_duration.as_secs_f64() * 1e6,
+            max_callback_us: stats.max_duration.as_secs_f64() * 1e6,
+            uptime_ms: uptime.as_secs_f64() * 1000.0,
+        }
+    }
+
+    /// Stops or restarts the device delivering audio, keeping the stream
+    /// and its settings.
+    pub fn set_paused(&self, paused: bool) -> Result<(), String> {
+        let control = if paused { StreamControl::Pause } else { StreamControl::Resume };
+        self.control_tx.send(control).map_err(|_| "Stream thread has exited".to_string())
+    }
+
+    /// Detaches the recording tap and hands back the recording, so it can
+    /// be finalized without holding the streams lock.
+    pub fn detach_recording(&mut self) -> Option<Recording> {
+        let recording = self.recording.take()?;
+        self.shared.record_tap.lock().unwrap().take();
+        Some(recording)
+    }
+
+    /// Detaches the recording tap and waits for the file to be finalized.
+    pub fn finish_recording(&mut self) -> Result<(), String> {
+        self.detach_recording().map_or(Ok(()), Recording::finish)
+    }
+}
+
+impl Drop for InputStream {
+    fn drop(&mut self) {
+        let _ = self.finish_recording();
+        // Also ends a dual recording using this stream
+        self.shared.record_tap.lock().unwrap().take();
+        let _ = self.control_tx.send(StreamControl::Stop);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamState {
+    /// Never opened.
+    Idle,
+    Starting,
+    Running,
+    Paused,
+    /// Stalled, restarting, or the device is gone.
+    Error,
+    Stopped,
+}
+
+impl StreamState {
+    fn can_become(self, next: StreamState) -> bool {
+        use StreamState::*;
+        match (self, next) {
+            // Starting again replaces whatever was running
+            (Starting, Starting) => false,
+            (_, Starting) => true,
+            (Starting, Running | Error) => true,
+            (Running, Paused) | (Paused, Running) => true,
+            (Running | Paused, Error) | (Error, Running) => true,
+            (Running | Paused | Error, Stopped) => true,
+            _ => false,
+        }
+    }
+
+    fn lifecycle(self) -> LifecycleState {
+        match self {
+            StreamState::Starting => LifecycleState::Starting,
+            StreamState::Running => LifecycleState::Running,
+            StreamState::Paused => LifecycleState::Paused,
+            StreamState::Error => LifecycleState::Error,
+            StreamState::Idle | StreamState::Stopped => LifecycleState::Stopped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct StreamStatus {
+    pub state: StreamState,
+    /// Why the stream last changed state, when there was more to it than
+    /// being asked to.
+    pub reason: Option<String>,
+}
+
+/// The state machine of every stream opened through the UI, including
+/// those that have since stopped. Streams opened internally, e.g. for
+/// measurements, aren't tracked.
+#[derive(Default)]
+pub struct StreamStates {
+    states: Mutex<HashMap<String, StreamStatus>>,
+}
+
+impl StreamStates {
+    pub fn get(&self, stream_id: &str) -> StreamStatus {
+        self.states.lock().unwrap().get(stream_id).cloned()
+            .unwrap_or(StreamStatus { state: StreamState::Idle, reason: None })
+    }
+
+    pub fn all(&self) -> Vec<(String, StreamStatus)> {
+        self.states.lock().unwrap().iter().map(|(id, status)| (id.clone(), status.clone())).collect()
+    }
+
+    /// Moves `stream_id` to `state`, failing if the stream can't get there
+    /// from where it is.
+    pub fn set(&self, app: &AppHandle, stream_id: &str, state: StreamState, reason: Option<String>) -> Result<(), String> {
+        let current = self.get(stream_id).state;
+        if !current.can_become(state) {
+            return Err(format!("Stream '{}' cannot go from {:?} to {:?}", stream_id, current, state));
+        }
+        self.states.lock().unwrap().insert(stream_id.to_string(), StreamStatus { state, reason: reason.clone() });
+        lifecycle::transition(app, Subsystem::Stream, stream_id, state.lifecycle(), reason);
+        Ok(())
+    }
+}
+
+/// Reports a change noticed by a stream's own thread. Ignored for streams
+/// that aren't tracked or when the change doesn't apply, e.g. a report
+/// from a stream that has already been replaced.
+fn update_stream_state(app: &AppHandle, stream_id: &str, state: StreamState, reason: Option<String>) {
+    let Some(states) = app.try_state::<StreamStates>() else { return };
+    if states.get(stream_id).state != StreamState::Idle {
+        let _ = states.set(app, stream_id, state, reason);
+    }
+}
+
+/// Flags buffers whose capture timestamp lands noticeably later than the end
+/// of the previous buffer, meaning the driver dropped audio in between.
+struct XrunDetector {
+    sample_rate: u32,
+    last_capture: Option<Duration>,
+    last_duration: Duration,
+}
+
+impl XrunDetector {
+    fn new(sample_rate: u32) -> Self {
+        XrunDetector {
+            sample_rate,
+            last_capture: None,
+            last_duration: Duration::ZERO,
+        }
+    }
+
+    fn check(&mut self, capture: Duration, frames: usize) -> bool {
+        let gap = self
+            .last_capture
+            .and_then(|last| capture.checked_sub(last))
+            .map(|delta| delta.saturating_sub(self.last_duration))
+            .unwrap_or_default();
+        let tolerance = (self.last_duration / 2).max(MIN_XRUN_GAP);
+
+        self.last_capture = Some(capture);
+        self.last_duration = Duration::from_secs_f64(frames as f64 / self.sample_rate as f64);
+
+        gap > tolerance
+    }
+}
+
+fn start_stream(
+    device: &Device,
+    config: &cpal::StreamConfig,
+    sample_format: cpal::SampleFormat,
+    picks: Vec<usize>,
+    shared: Arc<StreamShared>,
+) -> Result<Stream, String> {
+    let stream = build_stream(device, config, sample_format, picks, shared)?;
+    stream.play().map_err(|e| format!("Failed to play stream: {}", e))?;
+    Ok(stream)
+}
+
+fn build_stream(
+    device: &Device,
+    config: &cpal::StreamConfig,
+    sample_format: cpal::SampleFormat,
+    picks: Vec<usize>,
+    shared: Arc<StreamShared>,
+) -> Result<Stream, String> {
+    let error_shared = Arc::clone(&shared);
+    let err_fn = move |err| match err {
+        StreamError::DeviceNotAvailable => *error_shared.device_lost.lock().unwrap() = true,
+        StreamError::Driver => error_shared.xruns.lock().unwrap().driver_errors += 1,
+    };
+
+    let device_channels = config.channels as usize;
+    let all_channels = picks.iter().copied().eq(0..device_channels);
+    let mut gain = GainRamp::new(config.sample_rate.0);
+    let mut dynamics = DynamicsProcessor::new(config.sample_rate.0);
+    let mut mute = GainRamp::new(config.sample_rate.0);
+    let mut xrun_detector = XrunDetector::new(config.sample_rate.0);
+    shared.sync.lock().unwrap().restart();
+
+    let data_fn = move |data: &[f32], timestamp: InputTimestamp| {
+        let callback_start = Instant::now();
+        let frames = data.len() / device_channels;
+
+        let gap = xrun_detector.check(timestamp.capture, frames);
+        if gap {
+            shared.xruns.lock().unwrap().timing_gaps += 1;
+        }
+        shared.sync.lock().unwrap().record(timestamp, frames, gap);
+
+        let mut samples = if all_channels {
+            data.to_vec()
+        } else {
+            select_channels(data, device_channels, &picks)
+        };
+
+        let target_gain = db_to_linear(*shared.gain_db.lock().unwrap());
+        gain.apply(&mut samples, picks.len(), target_gain);
+        if let Some(chain) = shared.insert_chain.lock().unwrap().as_mut() {
+            chain.process(&mut samples, picks.len());
+        }
+        if let Some(sidechain) = shared.dynamics.lock().unwrap().as_ref() {
+            let key_level = match &sidechain.key {
+                Some(key) => key.upgrade().map_or(0.0, |key| *key.volume.lock().unwrap()),
+                None => calculate_rms(&samples),
+            };
+            dynamics.process(&mut samples, picks.len(), &sidechain.dynamics, key_level);
+        }
+        if *shared.mid_side.lock().unwrap() {
+            mid_side(&mut samples, picks.len(), MidSide::Encode);
+        }
+        let audible = *shared.audible.lock().unwrap();
+        mute.apply(&mut samples, picks.len(), if audible { 1.0 } else { 0.0 });
+        shared.process(samples, picks.len());
+
+        shared.stats.lock().unwrap().record(frames, callback_start.elapsed());
+    };
+
+    device.build_input_stream(config, sample_format, Box::new(data_fn), Box::new(err_fn))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_file::{read_audio_file, WavEncoding};
+    use crate::backend::{AudioBackend, MockBackend, MockClock};
+    use crate::dsp::fft::SpectrumAnalyzer;
+    use crate::recording::RecordWriter;
+    use cpal::{BufferSize, SampleFormat, SampleRate, StreamConfig};
+    use std::{env, fs, process};
+
+    const SAMPLE_RATE: u32 = 48000;
+    /// 10 ms buffers, so 100 ticks are a second of audio.
+    const BUFFER_FRAMES: u32 = 480;
+    /// RMS of the mock input's -6 dBFS tones.
+    const TONE_RMS: f32 = 0.353_553_4;
+
+    /// Opens the stereo mock input with buffers delivered by `clock`.
+    fn open_mock_input(clock: &Arc<MockClock>, recent_seconds: f32) -> (Stream, Arc<StreamShared>) {
+        let backend = MockBackend::with_clock(Arc::clone(clock));
+        let device = backend.default_input_device().unwrap();
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: SampleRate(SAMPLE_RATE),
+            buffer_size: BufferSize::Fixed(BUFFER_FRAMES),
+        };
+        let shared = Arc::new(StreamShared::new(SAMPLE_RATE, 2, recent_seconds));
+        let stream = start_stream(&device, &config, SampleFormat::F32, vec![0, 1], Arc::clone(&shared)).unwrap();
+        (stream, shared)
+    }
+
+    #[test]
+    fn mock_input_levels_and_metering() {
+        let clock = Arc::new(MockClock::default());
+        let (_stream, shared) = open_mock_input(&clock, 0.0);
+        clock.tick(100);
+
+        let stats = *shared.stats.lock().unwrap();
+        assert_eq!(stats.callbacks, 100);
+        assert_eq!(stats.frames, 100 * BUFFER_FRAMES as u64);
+        assert_eq!(shared.xruns.lock().unwrap().total(), 0);
+
+        let volume = *shared.volume.lock().unwrap();
+        assert!((volume - TONE_RMS).abs() < 0.02, "volume {}", volume);
+
+        // Two -6 dBFS channels, a touch louder after K-weighting
+        let momentary = shared.loudness.lock().unwrap().report().momentary_lufs.unwrap();
+        assert!((-7.2..-6.0).contains(&momentary), "momentary {} LUFS", momentary);
+
+        let window = shared.analysis.lock().unwrap().latest(ANALYSIS_WINDOW);
+        let amplitudes = SpectrumAnalyzer::new(ANALYSIS_WINDOW).amplitudes(&window);
+        let peak = (0..amplitudes.len()).max_by(|&a, &b| amplitudes[a].total_cmp(&amplitudes[b])).unwrap();
+        let peak_hz = peak as f32 * SAMPLE_RATE as f32 / ANALYSIS_WINDOW as f32;
+        // Both tones are mixed into the mono analysis at the same level
+        assert!([440.0, 880.0].iter().any(|hz| (peak_hz - hz).abs() < 10.0), "peak at {} Hz", peak_hz);
+    }
+
+    #[test]
+    fn paused_mock_input_delivers_nothing() {
+        let clock = Arc::new(MockClock::default());
+        let (stream, shared) = open_mock_input(&clock, 0.0);
+        clock.tick(5);
+        stream.pause().unwrap();
+        clock.tick(5);
+        assert_eq!(shared.stats.lock().unwrap().callbacks, 5);
+
+        stream.play().unwrap();
+        clock.tick(5);
+        assert_eq!(shared.stats.lock().unwrap().callbacks, 10);
+        assert_eq!(shared.xruns.lock().unwrap().total(), 0);
+    }
+
+    #[test]
+    fn records_mock_input_to_file() {
+        let clock = Arc::new(MockClock::default());
+        let (_stream, shared) = open_mock_input(&clock, 1.0);
+        let (tx, rx) = mpsc::channel();
+        *shared.record_tap.lock().unwrap() = Some(RecordTap { file_path: None, tx });
+        clock.tick(50);
+        shared.record_tap.lock().unwrap().take();
+
+        let path = env::temp_dir().join(format!("mock-input-{}.wav", process::id()));
+        let mut writer =
+            RecordWriter::create(&path.to_string_lossy(), SAMPLE_RATE, 2, Some(WavEncoding::Float32), None, None).unwrap();
+        for samples in rx {
+            writer.write(&samples).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let recorded = read_audio_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(recorded.sample_rate, SAMPLE_RATE);
+        assert_eq!(recorded.channels, 2);
+        assert_eq!(recorded.frames(), 50 * BUFFER_FRAMES as usize);
+
+        // The file holds exactly what the stream kept as recent audio
+        let recent = shared.recent.lock().unwrap().snapshot(recorded.samples.len()).into_samples();
+        assert_eq!(recorded.samples, recent);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, Window};
+
+use crate::validate::{self, ErrorCode};
+
+/// Every topic the plugin emits, all of them through `events::emit`.
+const TOPICS: [&str; 23] = [
+    "audio://device-lost",
+    "audio://goniometer",
+    "audio://oscilloscope",
+    "audio://spectrum",
+    "audio://stream-restarted",
+    "audio://stream-stalled",
+    "audio://xrun",
+    "dual-record://finished",
+    "hotkey://failed",
+    "lifecycle://state-changed",
+    "measure://snr-phase",
+    "overdub://finished",
+    "playback://ended",
+    "playback://position",
+    "record://disk-space",
+    "record://finished",
+    "record://status",
+    "scan://file",
+    "transcript://final",
+    "transcript://keyword",
+    "transcript://partial",
+    "transcription://download-progress",
+    "transcription://progress",
+];
+
+/// Topics that are only produced for windows subscribed to them.
+const SUBSCRIPTION_ONLY: [&str; 1] = ["audio://spectrum"];
+
+/// A window's interest in a topic, for one key (stream id, file path) or
+/// for all of them.
+type Subscription = (String, Option<String>);
+
+/// Which windows want which event topics. Topics nobody has subscribed to
+/// go to every window as before; once a window subscribes, the topic only
+/// goes to subscribers, so the main window has to subscribe as well if it
+/// still wants it.
+#[derive(Default)]
+pub struct Subscriptions {
+    topics: Mutex<HashMap<String, HashSet<Subscription>>>,
+}
+
+impl Subscriptions {
+    /// Labels of the windows to send `topic` for `key` to, or None to send
+    /// it to every window.
+    pub fn targets(&self, topic: &str, key: &str) -> Option<Vec<String>> {
+        let topics = self.topics.lock().unwrap();
+        let subscribers = topics.get(topic)?;
+        let mut labels: Vec<String> = subscribers
+            .iter()
+            .filter(|(_, wanted)| wanted.as_deref().is_none_or(|wanted| wanted == key))
+            .map(|(label, _)| label.clone())
+            .collect();
+        labels.sort();
+        labels.dedup();
+        Some(labels)
+    }
+
+    fn remove_window(&self, label: &str) {
+        let mut topics = self.topics.lock().unwrap();
+        for subscribers in topics.values_mut() {
+            subscribers.retain(|(window, _)| window != label);
+        }
+        topics.retain(|_, subscribers| !subscribers.is_empty());
+    }
+}
+
+/// Whether anyone would receive `topic` for `key`, so producers can skip
+/// the work of preparing it.
+pub fn wanted(app: &AppHandle, topic: &str, key: &str) -> bool {
+    let Some(subscriptions) = app.try_state::<Subscriptions>() else { return true };
+    match subscriptions.targets(topic, key) {
+        Some(labels) => !labels.is_empty(),
+        None => !SUBSCRIPTION_ONLY.contains(&topic),
+    }
+}
+
+/// Drops the subscriptions of a window that has closed.
+pub fn window_closed(app: &AppHandle, label: &str) {
+    if let Some(subscriptions) = app.try_state::<Subscriptions>() {
+        subscriptions.remove_window(label);
+    }
+}
+
+/// Sends `topic` to the calling window, for `key` only when given.
+#[tauri::command]
+#[specta::specta]
+pub fn subscribe(
+    topic: String,
+    key: Option<String>,
+    window: Window,
+    subscriptions: State<Subscriptions>,
+) -> Result<(), String> {
+    if !TOPICS.contains(&topic.as_str()) {
+        return Err(validate::error(ErrorCode::InvalidId, format!("Unknown event topic '{}'", topic)));
+    }
+    let mut topics = subscriptions.topics.lock().unwrap();
+    topics.entry(topic).or_default().insert((window.label().to_string(), key));
+    Ok(())
+}
+
+/// Undoes a `subscribe` with the same topic and key.
+#[tauri::command]
+#[specta::specta]
+pub fn unsubscribe(topic: String, key: Option<String>, window: Window, subscriptions: State<Subscriptions>) {
+    let mut topics = subscriptions.topics.lock().unwrap();
+    if let Some(subscribers) = topics.get_mut(&topic) {
+        subscribers.remove(&(window.label().to_string(), key));
+        if subscribers.is_empty() {
+            topics.remove(&topic);
+        }
+    }
+}
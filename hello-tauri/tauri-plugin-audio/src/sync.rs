@@ -0,0 +1,179 @@
+use serde::Serialize;
+use specta::Type;
+use std::time::Duration;
+use tauri::State;
+
+use crate::backend::InputTimestamp;
+use crate::dual::DualRecordingState;
+use crate::AudioState;
+
+/// Capture time a rate measurement needs before it is reported. Shorter
+/// spans are dominated by timestamp jitter.
+const MIN_MEASUREMENT: Duration = Duration::from_secs(10);
+
+/// Timing reported by the host for a stream's input callbacks.
+pub struct SyncClock {
+    sample_rate: u32,
+    /// Frames delivered since the stream opened.
+    sample_position: u64,
+    /// Callback and capture time of the first buffer since opening.
+    first: Option<(Duration, Duration)>,
+    last: Option<(Duration, Duration)>,
+    /// Capture time and sample position where the rate measurement starts.
+    measured_from: Option<(Duration, u64)>,
+    /// Frames and capture time spanned by the measurement so far.
+    measured: (u64, Duration),
+}
+
+impl SyncClock {
+    pub fn new(sample_rate: u32) -> Self {
+        SyncClock {
+            sample_rate,
+            sample_position: 0,
+            first: None,
+            last: None,
+            measured_from: None,
+            measured: (0, Duration::ZERO),
+        }
+    }
+
+    /// Starts the timeline over for a reopened device, whose timestamps
+    /// may count from somewhere else. The sample position carries on.
+    pub fn restart(&mut self) {
+        *self = SyncClock { sample_position: self.sample_position, ..SyncClock::new(self.sample_rate) };
+    }
+
+    /// Takes the timestamps of a buffer of `frames`. A `gap` (dropped audio)
+    /// restarts the rate measurement, as the frames no longer add up.
+    pub fn record(&mut self, timestamp: InputTimestamp, frames: usize, gap: bool) {
+        let now = (timestamp.callback, timestamp.capture);
+        self.first.get_or_insert(now);
+        self.last = Some(now);
+
+        if gap {
+            self.measured_from = None;
+        }
+        let (from, position) = *self.measured_from.get_or_insert((timestamp.capture, self.sample_position));
+        if let Some(span) = timestamp.capture.checked_sub(from) {
+            self.measured = (self.sample_position - position, span);
+        }
+        self.sample_position += frames as u64;
+    }
+
+    pub fn status(&self) -> ClockSync {
+        let since_first = |pick: fn(&(Duration, Duration)) -> Duration| {
+            let (first, last) = (self.first.as_ref()?, self.last.as_ref()?);
+            pick(last).checked_sub(pick(first)).map(|d| d.as_secs_f64() * 1000.0)
+        };
+        let input_latency_ms = self
+            .last
+            .and_then(|(callback, capture)| callback.checked_sub(capture))
+            .map(|d| d.as_secs_f64() * 1000.0);
+
+        let (frames, span) = self.measured;
+        let measured_rate = (span >= MIN_MEASUREMENT).then(|| frames as f64 / span.as_secs_f64());
+
+        ClockSync {
+            sample_position: self.sample_position,
+            callback_ms: since_first(|&(callback, _)| callback),
+            capture_ms: since_first(|&(_, capture)| capture),
+            input_latency_ms,
+            measured_rate,
+            drift_ppm: measured_rate.map(|rate| (rate / self.sample_rate as f64 - 1.0) * 1e6),
+            measured_s: span.as_secs_f64(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct ClockSync {
+    /// Frames delivered since the stream opened.
+    pub sample_position: u64,
+    /// Host time of the latest callback and of the capture of its first
+    /// frame, measured from the first buffer. Both stay None on hosts that
+    /// don't timestamp callbacks.
+    pub callback_ms: Option<f64>,
+    pub capture_ms: Option<f64>,
+    /// How long before the latest callback its audio was captured.
+    pub input_latency_ms: Option<f64>,
+    /// Frames per second of host time, once enough has been measured.
+    pub measured_rate: Option<f64>,
+    /// How much faster the device runs than its nominal rate, against the
+    /// host clock.
+    pub drift_ppm: Option<f64>,
+    /// Capture time the measurement covers. Dropped audio restarts it.
+    pub measured_s: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct StreamSync {
+    pub stream_id: String,
+    pub device_name: String,
+    pub sample_rate: u32,
+    #[serde(flatten)]
+    pub clock: ClockSync,
+    /// Drift the passthrough to an output device is correcting for.
+    pub passthrough_drift_ppm: Option<f64>,
+}
+
+/// How much faster `b`'s clock runs than `a`'s, from both measured against
+/// the host clock.
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct DevicePairDrift {
+    pub a: String,
+    pub b: String,
+    pub drift_ppm: f64,
+    /// How far apart a recording of both drifts each hour, in ms.
+    pub divergence_ms_per_hour: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct SyncStatus {
+    pub streams: Vec<StreamSync>,
+    /// Every pair of streams on different devices with a measured rate.
+    pub pairs: Vec<DevicePairDrift>,
+    /// Drift the running dual recording is correcting for.
+    pub dual_recording_drift_ppm: Option<f64>,
+}
+
+/// The clock information the host gives for every monitored stream, and
+/// the drift between their devices, for tracking down why recordings made
+/// on two devices slowly move apart.
+#[tauri::command]
+#[specta::specta]
+pub fn get_sync_status(state: State<AudioState>, dual: State<DualRecordingState>) -> SyncStatus {
+    let mut streams: Vec<StreamSync> = state
+        .streams
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(stream_id, stream)| StreamSync {
+            stream_id: stream_id.clone(),
+            device_name: stream.device_name.clone(),
+            sample_rate: stream.sample_rate,
+            clock: stream.shared.sync.lock().unwrap().status(),
+            passthrough_drift_ppm: stream.passthrough.as_ref().map(|p| p.buffer.lock().unwrap().drift_ppm()),
+        })
+        .collect();
+    streams.sort_by(|a, b| a.stream_id.cmp(&b.stream_id));
+
+    let mut pairs = Vec::new();
+    for (i, a) in streams.iter().enumerate() {
+        for b in &streams[i + 1..] {
+            if a.device_name == b.device_name {
+                continue;
+            }
+            if let (Some(a_rate), Some(b_rate)) = (a.clock.measured_rate, b.clock.measured_rate) {
+                let drift_ppm = (b_rate / b.sample_rate as f64 / (a_rate / a.sample_rate as f64) - 1.0) * 1e6;
+                pairs.push(DevicePairDrift {
+                    a: a.stream_id.clone(),
+                    b: b.stream_id.clone(),
+                    drift_ppm,
+                    divergence_ms_per_hour: drift_ppm.abs() * 3.6,
+                });
+            }
+        }
+    }
+
+    SyncStatus { streams, pairs, dual_recording_drift_ppm: dual.drift_ppm() }
+}
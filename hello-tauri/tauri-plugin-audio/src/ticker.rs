@@ -0,0 +1,28 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Calls `tick` on a background thread at a fixed interval until dropped.
+pub struct Ticker {
+    stop_tx: mpsc::Sender<()>,
+}
+
+impl Ticker {
+    pub fn spawn(interval: Duration, mut tick: impl FnMut() + Send + 'static) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stop_rx.recv_timeout(interval) {
+                tick();
+            }
+        });
+
+        Ticker { stop_tx }
+    }
+}
+
+impl Drop for Ticker {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+    }
+}
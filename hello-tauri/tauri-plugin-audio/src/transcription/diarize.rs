@@ -0,0 +1,167 @@
+use serde::Deserialize;
+use specta::Type;
+
+use super::{TranscriptSegment, WHISPER_SAMPLE_RATE};
+use crate::dsp::cepstrum::MfccAnalyzer;
+
+/// Analysis frame (32 ms) and hop (10 ms) at Whisper's rate.
+const FRAME: usize = 512;
+const HOP: usize = 160;
+
+const MEL_BANDS: usize = 26;
+const COEFFICIENTS: usize = 12;
+
+/// Frames this far below a segment's loudest frame are pauses and left
+/// out of its embedding.
+const VOICED_RANGE_DB: f32 = 30.0;
+
+/// Segments with fewer voiced frames are too short to tell who spoke.
+const MIN_EMBEDDING_FRAMES: usize = 20;
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct DiarizationSettings {
+    /// Number of speakers, when known; otherwise it is estimated.
+    pub speakers: Option<usize>,
+    /// Cosine distance up to which groups of segments are taken to be the
+    /// same speaker, when the count is estimated. Lower splits more.
+    pub threshold: f32,
+}
+
+impl Default for DiarizationSettings {
+    fn default() -> Self {
+        DiarizationSettings { speakers: None, threshold: 0.7 }
+    }
+}
+
+/// Mean and spread of the voiced frames' cepstra: a rough voice print.
+fn embedding(analyzer: &mut MfccAnalyzer, speech: &[f32]) -> Option<Vec<f32>> {
+    let frames: Vec<(Vec<f32>, f32)> = speech.windows(FRAME).step_by(HOP).map(|frame| analyzer.frame(frame)).collect();
+    let loudest = frames.iter().map(|(_, energy)| *energy).fold(0.0f32, f32::max);
+    let floor = loudest * 10f32.powf(-VOICED_RANGE_DB / 10.0);
+    let voiced: Vec<&Vec<f32>> = frames.iter().filter(|(_, energy)| *energy > floor).map(|(c, _)| c).collect();
+    if voiced.len() < MIN_EMBEDDING_FRAMES {
+        return None;
+    }
+
+    let count = voiced.len() as f32;
+    let mean: Vec<f32> = (0..COEFFICIENTS).map(|k| voiced.iter().map(|c| c[k]).sum::<f32>() / count).collect();
+    let spread = (0..COEFFICIENTS).map(|k| (voiced.iter().map(|c| (c[k] - mean[k]).powi(2)).sum::<f32>() / count).sqrt());
+    Some(mean.iter().copied().chain(spread).collect())
+}
+
+/// Scales every dimension to zero mean and unit variance across the
+/// recording, so the room and mic drop out and only differences remain.
+fn standardize(embeddings: &mut [Vec<f32>]) {
+    let Some(dimensions) = embeddings.first().map(Vec::len) else {
+        return;
+    };
+    let count = embeddings.len() as f32;
+    for d in 0..dimensions {
+        let mean = embeddings.iter().map(|e| e[d]).sum::<f32>() / count;
+        let deviation = (embeddings.iter().map(|e| (e[d] - mean).powi(2)).sum::<f32>() / count).sqrt();
+        for e in embeddings.iter_mut() {
+            e[d] = (e[d] - mean) / deviation.max(1e-6);
+        }
+    }
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    1.0 - dot / (norm(a) * norm(b)).max(f32::EPSILON)
+}
+
+/// Average-linkage agglomerative clustering. Stops at `speakers` clusters
+/// if given, otherwise once the closest pair is further apart than
+/// `threshold`. Returns a cluster index per embedding.
+fn cluster(embeddings: &[Vec<f32>], speakers: Option<usize>, threshold: f32) -> Vec<usize> {
+    let count = embeddings.len();
+    let mut distances: Vec<Vec<f32>> = embeddings
+        .iter()
+        .map(|a| embeddings.iter().map(|b| cosine_distance(a, b)).collect())
+        .collect();
+    let mut members: Vec<Vec<usize>> = (0..count).map(|i| vec![i]).collect();
+    let mut active: Vec<usize> = (0..count).collect();
+    let target = speakers.unwrap_or(1).max(1);
+
+    while active.len() > target {
+        let mut closest = (f32::INFINITY, 0, 0);
+        for (n, &a) in active.iter().enumerate() {
+            for &b in &active[n + 1..] {
+                if distances[a][b] < closest.0 {
+                    closest = (distances[a][b], a, b);
+                }
+            }
+        }
+        let (distance, a, b) = closest;
+        if speakers.is_none() && distance > threshold {
+            break;
+        }
+
+        // Lance-Williams update for average linkage
+        let (size_a, size_b) = (members[a].len() as f32, members[b].len() as f32);
+        for &other in &active {
+            if other != a && other != b {
+                let merged = (distances[a][other] * size_a + distances[b][other] * size_b) / (size_a + size_b);
+                distances[a][other] = merged;
+                distances[other][a] = merged;
+            }
+        }
+        let moved = std::mem::take(&mut members[b]);
+        members[a].extend(moved);
+        active.retain(|&i| i != b);
+    }
+
+    let mut labels = vec![0; count];
+    for (label, &cluster) in active.iter().enumerate() {
+        for &member in &members[cluster] {
+            labels[member] = label;
+        }
+    }
+    labels
+}
+
+/// Labels each segment of `speech` (mono at Whisper's rate) with the
+/// speaker it sounds like, numbered in order of first appearance. Segments
+/// too short to judge take the speaker of the nearest judged one.
+pub fn label_speakers(speech: &[f32], segments: &mut [TranscriptSegment], settings: &DiarizationSettings) {
+    let mut analyzer = MfccAnalyzer::new(FRAME, WHISPER_SAMPLE_RATE, MEL_BANDS, COEFFICIENTS);
+    let to_sample = |ms: u64| ((ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize).min(speech.len());
+
+    let mut judged = Vec::new();
+    let mut embeddings = Vec::new();
+    for (index, segment) in segments.iter().enumerate() {
+        let (start, end) = (to_sample(segment.start_ms), to_sample(segment.end_ms));
+        if let Some(embedding) = embedding(&mut analyzer, &speech[start..end.max(start)]) {
+            judged.push(index);
+            embeddings.push(embedding);
+        }
+    }
+    if embeddings.is_empty() {
+        return;
+    }
+    standardize(&mut embeddings);
+    let clusters = cluster(&embeddings, settings.speakers, settings.threshold);
+
+    // Name speakers by first appearance
+    let mut order: Vec<usize> = Vec::new();
+    for &cluster in &clusters {
+        if !order.contains(&cluster) {
+            order.push(cluster);
+        }
+    }
+    let name = |cluster: usize| {
+        let number = order.iter().position(|&c| c == cluster).unwrap_or(0) + 1;
+        format!("Speaker {}", number)
+    };
+
+    for (index, segment) in segments.iter_mut().enumerate() {
+        let nearest = judged
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, &judged)| judged.abs_diff(index))
+            .map(|(n, _)| clusters[n]);
+        segment.speaker = nearest.map(name);
+    }
+}
@@ -0,0 +1,80 @@
+use super::TranscriptSegment;
+
+/// Lowercased words with punctuation dropped, so "Hello, World!" matches
+/// the phrase "hello world".
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn contains(haystack: &[String], needle: &[String]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// A configured phrase heard in the transcript.
+pub struct KeywordMatch {
+    pub phrase: String,
+    /// The segment it was heard in, or the whole utterance when the phrase
+    /// runs across segments.
+    pub text: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Watches transcribed utterances for whole-word phrases, reporting each
+/// phrase once per utterance even as partials are transcribed again.
+#[derive(Default)]
+pub struct KeywordSpotter {
+    phrases: Vec<(String, Vec<String>)>,
+    utterance: u64,
+    alerted: Vec<usize>,
+}
+
+impl KeywordSpotter {
+    pub fn new(phrases: &[String]) -> Self {
+        let mut spotter = KeywordSpotter::default();
+        spotter.set_phrases(phrases);
+        spotter
+    }
+
+    pub fn set_phrases(&mut self, phrases: &[String]) {
+        self.phrases = phrases
+            .iter()
+            .map(|phrase| (phrase.trim().to_string(), words(phrase)))
+            .filter(|(_, words)| !words.is_empty())
+            .collect();
+        self.alerted.clear();
+    }
+
+    /// Phrases newly heard in `segments`, the transcript so far of
+    /// `utterance`.
+    pub fn spot(&mut self, utterance: u64, segments: &[TranscriptSegment]) -> Vec<KeywordMatch> {
+        if utterance != self.utterance {
+            self.utterance = utterance;
+            self.alerted.clear();
+        }
+        let (Some(first), Some(last)) = (segments.first(), segments.last()) else {
+            return Vec::new();
+        };
+
+        let segment_words: Vec<Vec<String>> = segments.iter().map(|segment| words(&segment.text)).collect();
+        let all_words: Vec<String> = segment_words.concat();
+        let mut matches = Vec::new();
+        for (index, (phrase, needle)) in self.phrases.iter().enumerate() {
+            if self.alerted.contains(&index) || !contains(&all_words, needle) {
+                continue;
+            }
+            self.alerted.push(index);
+
+            let within = segments.iter().zip(&segment_words).find(|(_, words)| contains(words, needle));
+            let (text, start_ms, end_ms) = match within {
+                Some((segment, _)) => (segment.text.clone(), segment.start_ms, segment.end_ms),
+                None => (super::join_text(segments), first.start_ms, last.end_ms),
+            };
+            matches.push(KeywordMatch { phrase: phrase.clone(), text, start_ms, end_ms });
+        }
+        matches
+    }
+}
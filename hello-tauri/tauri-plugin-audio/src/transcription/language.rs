@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use whisper_rs::{WhisperContext, WhisperState};
+
+use super::{load_speech, thread_count, Transcriber, WHISPER_SAMPLE_RATE};
+use crate::calculate_rms;
+use crate::dsp::fft::amplitude_to_db;
+use crate::lifecycle::Operation;
+
+/// Whisper listens to 30 seconds at a time, so the language is decided
+/// per window of that length.
+const WINDOW_MS: u64 = 30_000;
+
+/// Windows quieter than this hold no speech to judge.
+const SILENT_WINDOW_DB: f32 = -50.0;
+
+/// Alternatives reported besides the detected language.
+const CANDIDATES: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LanguageCandidate {
+    pub code: String,
+    pub name: String,
+    pub probability: f32,
+}
+
+/// A stretch of the recording in one language.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct LanguageSpan {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub language: String,
+    pub probability: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct LanguageDetection {
+    /// The language heard most, over the whole recording.
+    pub language: String,
+    pub candidates: Vec<LanguageCandidate>,
+    /// More than one when the language changes partway.
+    pub spans: Vec<LanguageSpan>,
+}
+
+fn code(id: usize) -> String {
+    whisper_rs::get_lang_str(id as i32).unwrap_or_default().to_string()
+}
+
+/// Detects the language of each 30 second window of `speech` and merges
+/// neighbours that agree. `None` if every window is silent.
+pub(super) fn detect(
+    context: &WhisperContext,
+    state: &mut WhisperState,
+    speech: &[f32],
+) -> Result<Option<LanguageDetection>, String> {
+    let total_ms = speech.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+    if !context.is_multilingual() {
+        // English-only models can't hear anything else
+        return Ok(Some(LanguageDetection {
+            language: "en".to_string(),
+            candidates: vec![LanguageCandidate { code: "en".to_string(), name: "english".to_string(), probability: 1.0 }],
+            spans: vec![LanguageSpan { start_ms: 0, end_ms: total_ms, language: "en".to_string(), probability: 1.0 }],
+        }));
+    }
+
+    let threads = thread_count();
+    state
+        .pcm_to_mel(speech, threads)
+        .map_err(|e| format!("Failed to analyze speech: {}", e))?;
+
+    let window = (WINDOW_MS * WHISPER_SAMPLE_RATE as u64 / 1000) as usize;
+    let mut totals: Vec<f32> = Vec::new();
+    let mut spans: Vec<LanguageSpan> = Vec::new();
+    let mut judged = 0;
+    for (index, samples) in speech.chunks(window).enumerate() {
+        let start_ms = index as u64 * WINDOW_MS;
+        let end_ms = (start_ms + WINDOW_MS).min(total_ms);
+        if amplitude_to_db(calculate_rms(samples)) < SILENT_WINDOW_DB {
+            // Silence belongs to whatever was spoken before it
+            if let Some(span) = spans.last_mut() {
+                span.end_ms = end_ms;
+            }
+            continue;
+        }
+
+        let (id, probabilities) = state
+            .lang_detect(start_ms as usize, threads)
+            .map_err(|e| format!("Language detection failed: {}", e))?;
+        totals.resize(probabilities.len(), 0.0);
+        totals.iter_mut().zip(&probabilities).for_each(|(total, p)| *total += p);
+        judged += 1;
+
+        let language = code(id.max(0) as usize);
+        let probability = probabilities.get(id.max(0) as usize).copied().unwrap_or_default();
+        match spans.last_mut() {
+            Some(span) if span.language == language => {
+                span.end_ms = end_ms;
+                span.probability = span.probability.max(probability);
+            }
+            Some(_) => spans.push(LanguageSpan { start_ms, end_ms, language, probability }),
+            // Leading silence joins the first language heard
+            None => spans.push(LanguageSpan { start_ms: 0, end_ms, language, probability }),
+        }
+    }
+    if judged == 0 {
+        return Ok(None);
+    }
+
+    let mut ranked: Vec<(usize, f32)> = totals.iter().map(|total| total / judged as f32).enumerate().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let candidates: Vec<LanguageCandidate> = ranked
+        .into_iter()
+        .take(CANDIDATES)
+        .map(|(id, probability)| LanguageCandidate {
+            code: code(id),
+            name: whisper_rs::get_lang_str_full(id as i32).unwrap_or_default().to_string(),
+            probability,
+        })
+        .collect();
+
+    Ok(Some(LanguageDetection {
+        language: candidates[0].code.clone(),
+        candidates,
+        spans,
+    }))
+}
+
+/// Finds the language (or languages) spoken in the recording at `path`
+/// with a downloaded multilingual Whisper `model`.
+#[tauri::command]
+#[specta::specta]
+pub async fn detect_language(path: String, model: String, app: AppHandle) -> Result<LanguageDetection, String> {
+    let operation = Operation::start(&app, "language-detection", &path);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let context = app.state::<Transcriber>().context(&app, &model)?;
+        let speech = load_speech(Path::new(&path))?;
+        let mut state = context
+            .create_state()
+            .map_err(|e| format!("Failed to start language detection: {}", e))?;
+        detect(&context, &mut state, &speech)?.ok_or_else(|| "No speech found to detect the language of".to_string())
+    })
+    .await
+    .map_err(|e| format!("Language detection failed: {}", e))
+    .and_then(|result| result);
+    operation.finish(result)
+}
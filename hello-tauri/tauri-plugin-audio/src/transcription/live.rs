@@ -0,0 +1,406 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+use whisper_rs::{WhisperContext, WhisperState};
+
+use super::keywords::KeywordSpotter;
+use super::{full_params, join_text, parse_language, segments, TranscriptSegment, Transcriber, WHISPER_SAMPLE_RATE};
+use crate::calculate_rms;
+use crate::dsp::fft::amplitude_to_db;
+use crate::dsp::resample::StreamResampler;
+use crate::events;
+use crate::stream::StreamShared;
+use crate::validate;
+use crate::AudioState;
+
+/// Voice activity is decided per frame of this many samples (30 ms).
+const VAD_FRAME: usize = 480;
+
+/// Audio kept from before speech is detected, so the first syllable isn't
+/// cut off.
+const PRE_ROLL_FRAMES: usize = 10;
+
+/// How fast the noise floor estimate may rise per frame, so that it
+/// follows a noisier room without climbing onto sustained speech.
+const NOISE_RISE_DB: f32 = 0.01;
+
+/// Frames quieter than this never count as speech.
+const MIN_SPEECH_DB: f32 = -60.0;
+
+/// Utterances with fewer voiced frames are dropped as noise.
+const MIN_VOICED_FRAMES: usize = 8;
+
+/// Shorter audio is padded with silence before inference.
+const MIN_INFERENCE_SAMPLES: usize = WHISPER_SAMPLE_RATE as usize;
+
+#[derive(Debug, Clone, Deserialize, Type)]
+#[serde(default)]
+pub struct LiveTranscriptionSettings {
+    /// How far above the tracked noise floor a frame has to be to count as
+    /// speech.
+    pub threshold_db: f32,
+    /// Quiet time that ends an utterance.
+    pub silence_ms: u32,
+    /// How often the utterance in progress is transcribed again.
+    pub partial_interval_ms: u32,
+    /// Utterances are cut at this length even without a pause.
+    pub max_utterance_s: f32,
+    /// Phrases that raise a `transcript://keyword` alert when heard.
+    pub keywords: Vec<String>,
+}
+
+impl Default for LiveTranscriptionSettings {
+    fn default() -> Self {
+        LiveTranscriptionSettings {
+            threshold_db: 10.0,
+            silence_ms: 600,
+            partial_interval_ms: 1000,
+            max_utterance_s: 20.0,
+            keywords: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "transcript://final")]
+pub(crate) struct LiveTranscriptEvent {
+    stream_id: String,
+    /// Counts up per utterance; a final replaces the partials of the same
+    /// utterance.
+    utterance: u64,
+    /// Times are from the start of live transcription.
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+    segments: Vec<TranscriptSegment>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "transcript://keyword")]
+pub(crate) struct KeywordAlert {
+    stream_id: String,
+    utterance: u64,
+    phrase: String,
+    text: String,
+    start_ms: u64,
+    end_ms: u64,
+}
+
+/// Energy-based voice activity detection against a tracked noise floor.
+struct VoiceDetector {
+    threshold_db: f32,
+    noise_db: Option<f32>,
+}
+
+impl VoiceDetector {
+    fn is_speech(&mut self, frame: &[f32]) -> bool {
+        let level = amplitude_to_db(calculate_rms(frame));
+        let noise = self.noise_db.get_or_insert(level);
+        // Falls at once, rises slowly
+        *noise = if level < *noise { level } else { *noise + NOISE_RISE_DB };
+        level > MIN_SPEECH_DB && level > *noise + self.threshold_db
+    }
+}
+
+struct Utterance {
+    id: u64,
+    /// Position of its first sample on the transcription timeline.
+    start: u64,
+    samples: Vec<f32>,
+    voiced_frames: usize,
+    quiet_frames: usize,
+    /// Length when the last partial was run.
+    transcribed: usize,
+}
+
+/// State of the background thread that turns the tapped input into
+/// transcript events.
+struct LiveWorker {
+    app: AppHandle,
+    stream_id: String,
+    whisper: WhisperState,
+    language: Option<String>,
+    settings: LiveTranscriptionSettings,
+    keywords: Arc<Mutex<KeywordSpotter>>,
+    resampler: StreamResampler,
+    detector: VoiceDetector,
+    /// Resampled input not yet filling a whole frame.
+    pending: Vec<f32>,
+    pre_roll: VecDeque<Vec<f32>>,
+    /// Samples seen since transcription started, at Whisper's rate.
+    position: u64,
+    utterance: Option<Utterance>,
+    next_utterance: u64,
+}
+
+impl LiveWorker {
+    fn run(mut self, rx: mpsc::Receiver<Vec<f32>>) {
+        while let Ok(samples) = rx.recv() {
+            self.feed(&samples);
+            // Catch up on whatever arrived during inference before
+            // spending time on a partial
+            loop {
+                match rx.try_recv() {
+                    Ok(samples) => self.feed(&samples),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return self.finish_utterance(),
+                }
+            }
+            self.partial();
+        }
+        self.finish_utterance();
+    }
+
+    fn feed(&mut self, samples: &[f32]) {
+        match self.resampler.process(samples) {
+            Ok(resampled) => self.pending.extend_from_slice(&resampled),
+            Err(e) => {
+                eprintln!("Live transcription: {}", e);
+                return;
+            }
+        }
+
+        let frames = self.pending.len() / VAD_FRAME;
+        let pending = std::mem::take(&mut self.pending);
+        for frame in pending.chunks_exact(VAD_FRAME).take(frames) {
+            self.frame(frame);
+        }
+        self.pending = pending[frames * VAD_FRAME..].to_vec();
+    }
+
+    fn frame(&mut self, frame: &[f32]) {
+        let speech = self.detector.is_speech(frame);
+        self.position += frame.len() as u64;
+
+        let Some(utterance) = self.utterance.as_mut() else {
+            if !speech {
+                if self.pre_roll.len() == PRE_ROLL_FRAMES {
+                    self.pre_roll.pop_front();
+                }
+                self.pre_roll.push_back(frame.to_vec());
+                return;
+            }
+
+            let samples: Vec<f32> = self.pre_roll.drain(..).flatten().chain(frame.iter().copied()).collect();
+            self.next_utterance += 1;
+            self.utterance = Some(Utterance {
+                id: self.next_utterance,
+                start: self.position - samples.len() as u64,
+                samples,
+                voiced_frames: 1,
+                quiet_frames: 0,
+                transcribed: 0,
+            });
+            return;
+        };
+
+        utterance.samples.extend_from_slice(frame);
+        if speech {
+            utterance.voiced_frames += 1;
+            utterance.quiet_frames = 0;
+        } else {
+            utterance.quiet_frames += 1;
+        }
+
+        let quiet_ms = (utterance.quiet_frames * VAD_FRAME) as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let length_s = utterance.samples.len() as f32 / WHISPER_SAMPLE_RATE as f32;
+        if quiet_ms >= self.settings.silence_ms as u64 || length_s >= self.settings.max_utterance_s {
+            self.finish_utterance();
+        }
+    }
+
+    /// Transcribes the utterance so far, if it has grown enough.
+    fn partial(&mut self) {
+        let interval = self.settings.partial_interval_ms as usize * WHISPER_SAMPLE_RATE as usize / 1000;
+        let Some(utterance) = self.utterance.as_ref() else {
+            return;
+        };
+        if utterance.voiced_frames < MIN_VOICED_FRAMES || utterance.samples.len() < utterance.transcribed + interval {
+            return;
+        }
+
+        let (id, start, samples) = (utterance.id, utterance.start, utterance.samples.clone());
+        if let Some(utterance) = self.utterance.as_mut() {
+            utterance.transcribed = samples.len();
+        }
+        self.emit("transcript://partial", id, start, &samples);
+    }
+
+    fn finish_utterance(&mut self) {
+        let Some(utterance) = self.utterance.take() else {
+            return;
+        };
+        if utterance.voiced_frames >= MIN_VOICED_FRAMES {
+            self.emit("transcript://final", utterance.id, utterance.start, &utterance.samples);
+        }
+    }
+
+    fn emit(&mut self, event: &str, utterance: u64, start: u64, samples: &[f32]) {
+        let start_ms = start * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let segments = match self.transcribe(samples, start_ms) {
+            Ok(segments) => segments,
+            Err(e) => {
+                eprintln!("Live transcription: {}", e);
+                return;
+            }
+        };
+
+        // Checked on partials too, so alerts don't wait for the pause
+        for found in self.keywords.lock().unwrap().spot(utterance, &segments) {
+            events::emit(&self.app, "transcript://keyword", &self.stream_id, KeywordAlert {
+                stream_id: self.stream_id.clone(),
+                utterance,
+                phrase: found.phrase,
+                text: found.text,
+                start_ms: found.start_ms,
+                end_ms: found.end_ms,
+            });
+        }
+
+        let transcript = LiveTranscriptEvent {
+            stream_id: self.stream_id.clone(),
+            utterance,
+            start_ms,
+            end_ms: start_ms + samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64,
+            text: join_text(&segments),
+            segments,
+        };
+        if event == "transcript://final" {
+            events::discard(&self.app, "transcript://partial", &self.stream_id);
+        }
+        events::emit(&self.app, event, &self.stream_id, transcript);
+    }
+
+    fn transcribe(&mut self, samples: &[f32], offset_ms: u64) -> Result<Vec<TranscriptSegment>, String> {
+        let mut audio = samples.to_vec();
+        audio.resize(audio.len().max(MIN_INFERENCE_SAMPLES), 0.0);
+
+        let mut params = full_params(self.language.as_deref());
+        // Each utterance stands alone, so one misheard word doesn't carry
+        // over into the next
+        params.set_no_context(true);
+        params.set_suppress_blank(true);
+        params.set_suppress_nst(true);
+        self.whisper
+            .full(params, &audio)
+            .map_err(|e| format!("Transcription failed: {}", e))?;
+
+        let length_ms = samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let mut segments = segments(&self.whisper, offset_ms)?;
+        // Padding can push the last segment past the audio
+        segments.retain(|segment| segment.start_ms < offset_ms + length_ms);
+        for segment in &mut segments {
+            segment.end_ms = segment.end_ms.min(offset_ms + length_ms);
+        }
+        Ok(segments)
+    }
+}
+
+/// Live transcription attached to a monitored stream. Dropping it detaches
+/// the tap; the worker transcribes what it already has and exits.
+pub struct LiveTranscription {
+    shared: Arc<StreamShared>,
+    keywords: Arc<Mutex<KeywordSpotter>>,
+}
+
+impl LiveTranscription {
+    fn start(shared: &Arc<StreamShared>, worker: LiveWorker) -> Self {
+        let (tx, rx) = mpsc::channel();
+        *shared.transcript_tap.lock().unwrap() = Some(tx);
+        let keywords = Arc::clone(&worker.keywords);
+        thread::spawn(move || worker.run(rx));
+        LiveTranscription { shared: Arc::clone(shared), keywords }
+    }
+}
+
+impl Drop for LiveTranscription {
+    fn drop(&mut self) {
+        self.shared.transcript_tap.lock().unwrap().take();
+    }
+}
+
+/// Starts captioning a monitored stream: speech is picked out of the
+/// input, and each utterance is emitted as `transcript://partial` events
+/// while it is spoken and one `transcript://final` once it ends. Each
+/// configured keyword heard raises a `transcript://keyword` alert.
+#[tauri::command]
+#[specta::specta]
+pub async fn start_live_transcription(
+    stream_id: String,
+    model: String,
+    language: Option<String>,
+    settings: Option<LiveTranscriptionSettings>,
+    app: AppHandle,
+) -> Result<(), String> {
+    let language = parse_language(language)?;
+    let settings = settings.unwrap_or_default();
+
+    // Loading a model can take seconds, so it happens off the main thread
+    let loader = app.clone();
+    let context: Arc<WhisperContext> =
+        tauri::async_runtime::spawn_blocking(move || loader.state::<Transcriber>().context(&loader, &model))
+            .await
+            .map_err(|e| format!("Failed to load Whisper model: {}", e))??;
+    let whisper = context
+        .create_state()
+        .map_err(|e| format!("Failed to start transcription: {}", e))?;
+
+    let state = app.state::<AudioState>();
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    let worker = LiveWorker {
+        app: app.clone(),
+        stream_id: stream_id.clone(),
+        whisper,
+        language,
+        detector: VoiceDetector { threshold_db: settings.threshold_db, noise_db: None },
+        keywords: Arc::new(Mutex::new(KeywordSpotter::new(&settings.keywords))),
+        settings,
+        resampler: StreamResampler::new(stream.sample_rate, WHISPER_SAMPLE_RATE, 1)?,
+        pending: Vec::new(),
+        pre_roll: VecDeque::with_capacity(PRE_ROLL_FRAMES),
+        position: 0,
+        utterance: None,
+        next_utterance: 0,
+    };
+    // The old tap has to go before the new one is attached
+    stream.live_transcription = None;
+    stream.live_transcription = Some(LiveTranscription::start(&stream.shared, worker));
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn stop_live_transcription(stream_id: String, state: State<AudioState>) -> Result<(), String> {
+    let mut streams = state.streams.lock().unwrap();
+    let stream = streams
+        .get_mut(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?;
+
+    stream.live_transcription = None;
+    Ok(())
+}
+
+/// Replaces the phrases watched for on a stream's live transcription.
+#[tauri::command]
+#[specta::specta]
+pub fn set_live_keywords(stream_id: String, keywords: Vec<String>, state: State<AudioState>) -> Result<(), String> {
+    let streams = state.streams.lock().unwrap();
+    let live = streams
+        .get(&stream_id)
+        .ok_or_else(|| validate::stream_not_monitored(&stream_id))?
+        .live_transcription
+        .as_ref()
+        .ok_or_else(|| format!("Stream '{}' is not being transcribed", stream_id))?;
+
+    live.keywords.lock().unwrap().set_phrases(&keywords);
+    Ok(())
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters, WhisperState};
+
+use crate::audio_file::{read_audio_file, AudioBuffer};
+use crate::events;
+use crate::lifecycle::Operation;
+use diarize::{label_speakers, DiarizationSettings};
+use language::LanguageSpan;
+
+pub mod diarize;
+pub mod keywords;
+pub mod language;
+pub mod live;
+pub mod models;
+pub mod subtitles;
+
+/// Rate Whisper models are trained on; everything is resampled to it.
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Most threads given to one transcription.
+const MAX_THREADS: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct TranscriptSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+    /// Set when the transcript was diarized.
+    pub speaker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct Transcript {
+    /// Code of the spoken language, as given or as detected.
+    pub language: Option<String>,
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+    /// Where each language was heard, when it was detected.
+    #[serde(default)]
+    pub language_spans: Vec<LanguageSpan>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "transcription://progress")]
+pub(crate) struct TranscriptionProgress {
+    path: String,
+    percent: i32,
+}
+
+struct LoadedModel {
+    name: String,
+    context: Arc<WhisperContext>,
+}
+
+/// Keeps the last used model in memory, since loading the larger ones
+/// takes several seconds.
+#[derive(Default)]
+pub struct Transcriber {
+    loaded: Mutex<Option<LoadedModel>>,
+}
+
+impl Transcriber {
+    fn context(&self, app: &AppHandle, model: &str) -> Result<Arc<WhisperContext>, String> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(loaded) = loaded.as_ref().filter(|loaded| loaded.name == model) {
+            return Ok(Arc::clone(&loaded.context));
+        }
+
+        let path = models::model_path(app, model)?;
+        if !path.exists() {
+            return Err(format!("Whisper model '{}' has not been downloaded", model));
+        }
+        let path = path.to_str().ok_or("Model path is not valid UTF-8")?;
+        let context = WhisperContext::new_with_params(path, WhisperContextParameters::default())
+            .map_err(|e| format!("Failed to load Whisper model '{}': {}", model, e))?;
+
+        let context = Arc::new(context);
+        *loaded = Some(LoadedModel { name: model.to_string(), context: Arc::clone(&context) });
+        Ok(context)
+    }
+
+    /// Frees `model` if it is the one held in memory.
+    fn unload(&self, model: &str) {
+        let mut loaded = self.loaded.lock().unwrap();
+        if loaded.as_ref().is_some_and(|loaded| loaded.name == model) {
+            *loaded = None;
+        }
+    }
+}
+
+fn thread_count() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get()).min(MAX_THREADS)
+}
+
+/// Decoding parameters shared by every transcription. `language` of
+/// `None` lets the model detect it.
+fn full_params(language: Option<&str>) -> FullParams<'_, '_> {
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_n_threads(thread_count() as i32);
+    params.set_language(Some(language.unwrap_or("auto")));
+    params.set_print_special(false);
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_timestamps(false);
+    params
+}
+
+/// Checks a language code from the frontend; empty or "auto" means detect.
+fn parse_language(language: Option<String>) -> Result<Option<String>, String> {
+    match language.map(|code| code.trim().to_ascii_lowercase()) {
+        Some(code) if code.is_empty() || code == "auto" => Ok(None),
+        Some(code) if whisper_rs::get_lang_id(&code).is_none() => Err(format!("Unknown language '{}'", code)),
+        code => Ok(code),
+    }
+}
+
+/// The segments decoded into `state`, with times `offset_ms` later.
+fn segments(state: &WhisperState, offset_ms: u64) -> Result<Vec<TranscriptSegment>, String> {
+    let failed = |e| format!("Failed to read transcript: {}", e);
+    let count = state.full_n_segments().map_err(failed)?;
+    (0..count)
+        .map(|segment| {
+            // Whisper times are in centiseconds
+            let start = state.full_get_segment_t0(segment).map_err(failed)?;
+            let end = state.full_get_segment_t1(segment).map_err(failed)?;
+            Ok(TranscriptSegment {
+                start_ms: offset_ms + start.max(0) as u64 * 10,
+                end_ms: offset_ms + end.max(0) as u64 * 10,
+                text: state.full_get_segment_text_lossy(segment).map_err(failed)?.trim().to_string(),
+                speaker: None,
+            })
+        })
+        .collect()
+}
+
+/// The segments' text as one paragraph.
+fn join_text(segments: &[TranscriptSegment]) -> String {
+    segments
+        .iter()
+        .map(|segment| segment.text.as_str())
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Mono audio at Whisper's rate.
+fn load_speech(path: &Path) -> Result<Vec<f32>, String> {
+    let audio = read_audio_file(path)?;
+    let mono = AudioBuffer {
+        samples: audio.to_mono(),
+        sample_rate: audio.sample_rate,
+        channels: 1,
+    };
+    Ok(mono.resampled(WHISPER_SAMPLE_RATE)?.samples)
+}
+
+/// A stretch of the recording decoded in one language.
+struct LanguageRun {
+    start: usize,
+    end: usize,
+    language: Option<String>,
+}
+
+/// Transcribes the recording at `path` with a downloaded Whisper `model`,
+/// emitting `transcription://progress` as it goes. `language` is a code
+/// such as "en"; leave it out to detect it, which also follows recordings
+/// that switch language partway. With `diarization` set, each segment is
+/// also labelled with its speaker.
+#[tauri::command]
+#[specta::specta]
+pub async fn transcribe_file(
+    path: String,
+    language: Option<String>,
+    model: String,
+    diarization: Option<DiarizationSettings>,
+    app: AppHandle,
+) -> Result<Transcript, String> {
+    let language = parse_language(language)?;
+    let operation = Operation::start(&app, "transcription", &path);
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let context = app.state::<Transcriber>().context(&app, &model)?;
+        let speech = load_speech(Path::new(&path))?;
+        let mut state = context
+            .create_state()
+            .map_err(|e| format!("Failed to start transcription: {}", e))?;
+
+        let to_sample = |ms: u64| ((ms * WHISPER_SAMPLE_RATE as u64 / 1000) as usize).min(speech.len());
+        let (language, language_spans) = match language {
+            Some(code) => (Some(code), Vec::new()),
+            None => match language::detect(&context, &mut state, &speech)? {
+                Some(detection) => (Some(detection.language), detection.spans),
+                // Nothing to go on; leave it to the decoder
+                None => (None, Vec::new()),
+            },
+        };
+        let runs = if language_spans.len() > 1 {
+            language_spans
+                .iter()
+                .map(|span| LanguageRun {
+                    start: to_sample(span.start_ms),
+                    end: to_sample(span.end_ms),
+                    language: Some(span.language.clone()),
+                })
+                .collect()
+        } else {
+            vec![LanguageRun { start: 0, end: speech.len(), language: language.clone() }]
+        };
+
+        let mut segments = Vec::new();
+        for run in &runs {
+            let mut params = full_params(run.language.as_deref());
+            // Progress runs over the whole file, not per run
+            let length = speech.len().max(1) as f32;
+            let (done, share) = (run.start as f32 / length, (run.end - run.start) as f32 / length);
+            let progress_app = app.clone();
+            let progress_path = path.clone();
+            params.set_progress_callback_safe(move |percent| {
+                events::emit(&progress_app, "transcription://progress", &progress_path, TranscriptionProgress {
+                    path: progress_path.clone(),
+                    percent: ((done + share * percent as f32 / 100.0) * 100.0).round() as i32,
+                });
+            });
+
+            state
+                .full(params, &speech[run.start..run.end])
+                .map_err(|e| format!("Transcription failed: {}", e))?;
+            let offset_ms = run.start as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+            segments.extend(self::segments(&state, offset_ms)?);
+        }
+
+        if let Some(settings) = &diarization {
+            label_speakers(&speech, &mut segments, settings);
+        }
+        let text = join_text(&segments);
+
+        Ok(Transcript { language, text, segments, language_spans })
+    })
+    .await
+    .map_err(|e| format!("Transcription failed: {}", e))
+    .and_then(|result| result);
+    operation.finish(result)
+}
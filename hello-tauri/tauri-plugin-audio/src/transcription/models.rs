@@ -0,0 +1,184 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use specta::Type;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+use tauri_specta::Event;
+
+use super::Transcriber;
+use crate::events;
+use crate::lifecycle::Operation;
+
+/// Where whisper.cpp publishes its converted models.
+const MODEL_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Models that can be downloaded, smallest first. `.en` models only
+/// understand English but are more accurate at it.
+const MODELS: [&str; 10] = [
+    "tiny",
+    "tiny.en",
+    "base",
+    "base.en",
+    "small",
+    "small.en",
+    "medium",
+    "medium.en",
+    "large-v3-turbo",
+    "large-v3",
+];
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct WhisperModel {
+    pub name: String,
+    pub english_only: bool,
+    pub downloaded: bool,
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Type, Event)]
+#[tauri_specta(event_name = "transcription://download-progress")]
+pub(crate) struct ModelDownloadProgress {
+    model: String,
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {}", e))?;
+    Ok(dir.join("whisper-models"))
+}
+
+/// Where `name` is stored once downloaded.
+pub fn model_path(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    if !MODELS.contains(&name) {
+        return Err(format!("Unknown Whisper model '{}'", name));
+    }
+    Ok(models_dir(app)?.join(format!("ggml-{}.bin", name)))
+}
+
+fn describe(app: &AppHandle, name: &str) -> Result<WhisperModel, String> {
+    let size_bytes = fs::metadata(model_path(app, name)?).ok().map(|meta| meta.len());
+    Ok(WhisperModel {
+        name: name.to_string(),
+        english_only: name.ends_with(".en"),
+        downloaded: size_bytes.is_some(),
+        size_bytes,
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn list_whisper_models(app: AppHandle) -> Result<Vec<WhisperModel>, String> {
+    MODELS.iter().map(|name| describe(&app, name)).collect()
+}
+
+/// Downloads `name` into the app data directory, emitting
+/// `transcription://download-progress` as it arrives. The file only takes
+/// its final name once its length matches the one announced and its
+/// SHA-256 the one Hugging Face publishes, so an interrupted or corrupted
+/// download is never mistaken for a model.
+#[tauri::command]
+#[specta::specta]
+pub async fn download_whisper_model(name: String, app: AppHandle) -> Result<WhisperModel, String> {
+    let operation = Operation::start(&app, "model-download", &name);
+    operation.finish(download_model(&name, &app).await)
+}
+
+/// The SHA-256 Hugging Face publishes for a file it keeps in LFS storage.
+/// It is sent as the `X-Linked-Etag` of the redirect to the storage, so
+/// that redirect isn't followed. `None` when no hash is published.
+async fn published_sha256(url: &str) -> Option<String> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .ok()?;
+    let response = client.head(url).send().await.ok()?;
+    let etag = response.headers().get("x-linked-etag")?.to_str().ok()?;
+    let hash = etag.trim_start_matches("W/").trim_matches('"').to_ascii_lowercase();
+    (hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_hexdigit())).then_some(hash)
+}
+
+async fn download_model(name: &str, app: &AppHandle) -> Result<WhisperModel, String> {
+    let path = model_path(app, name)?;
+    let partial = path.with_extension("bin.part");
+    fs::create_dir_all(models_dir(app)?).map_err(|e| format!("Failed to create model directory: {}", e))?;
+
+    let url = format!("{}/ggml-{}.bin", MODEL_BASE_URL, name);
+    let expected_sha256 = published_sha256(&url).await;
+    let mut response = reqwest::get(&url)
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download model '{}': {}", name, e))?;
+    let total_bytes = response.content_length();
+
+    let mut file = fs::File::create(&partial).map_err(|e| format!("Failed to create model file: {}", e))?;
+    let mut downloaded_bytes = 0;
+    let mut hasher = Sha256::new();
+    let result = loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(format!("Failed to download model '{}': {}", name, e)),
+        };
+        if let Err(e) = file.write_all(&chunk) {
+            break Err(format!("Failed to write model file: {}", e));
+        }
+        hasher.update(&chunk);
+        downloaded_bytes += chunk.len() as u64;
+
+        events::emit(app, "transcription://download-progress", name, ModelDownloadProgress {
+            model: name.to_string(),
+            downloaded_bytes,
+            total_bytes,
+        });
+    };
+    drop(file);
+
+    // A connection closed early ends the body like a complete one does
+    let result = result.and_then(|()| match total_bytes {
+        Some(total) if total != downloaded_bytes => Err(format!(
+            "Download of model '{}' ended after {} of {} bytes",
+            name, downloaded_bytes, total
+        )),
+        _ => Ok(()),
+    });
+    let result = result.and_then(|()| {
+        let sha256 = format!("{:x}", hasher.finalize());
+        match expected_sha256 {
+            Some(expected) if expected != sha256 => Err(format!(
+                "Downloaded model '{}' is corrupt: SHA-256 {} does not match {}",
+                name, sha256, expected
+            )),
+            _ => Ok(()),
+        }
+    });
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &path).map_err(|e| format!("Failed to save model file: {}", e))?;
+
+    events::emit(app, "transcription://download-progress", name, ModelDownloadProgress {
+        model: name.to_string(),
+        downloaded_bytes,
+        total_bytes: Some(downloaded_bytes),
+    });
+    describe(app, name)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn delete_whisper_model(name: String, app: AppHandle, transcriber: State<Transcriber>) -> Result<WhisperModel, String> {
+    transcriber.unload(&name);
+    let path = model_path(&app, &name)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("Failed to delete model file: {}", e))?;
+    }
+    describe(&app, &name)
+}
@@ -0,0 +1,114 @@
+use serde::Deserialize;
+use specta::Type;
+use std::fmt::Write;
+use std::fs;
+
+use super::{Transcript, TranscriptSegment};
+
+/// Longest subtitle line, the usual broadcast limit.
+const MAX_LINE_CHARS: usize = 42;
+
+/// Cues are kept on screen at least this long so they can be read.
+const MIN_CUE_MS: u64 = 700;
+
+#[derive(Debug, Clone, Copy, Deserialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+/// `hh:mm:ss,mmm` for SRT, `hh:mm:ss.mmm` for WebVTT.
+fn format_time(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// Breaks `text` into lines of at most `MAX_LINE_CHARS`, at spaces.
+fn wrap(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= MAX_LINE_CHARS => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+/// Segments with text, timed so each is readable and none overlaps the
+/// next.
+fn cues(segments: &[TranscriptSegment]) -> Vec<(u64, u64, &TranscriptSegment)> {
+    let spoken: Vec<&TranscriptSegment> = segments.iter().filter(|s| !s.text.trim().is_empty()).collect();
+    spoken
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let start = segment.start_ms;
+            let mut end = segment.end_ms.max(start + MIN_CUE_MS);
+            if let Some(next) = spoken.get(i + 1) {
+                end = end.min(next.start_ms.max(start + 1));
+            }
+            (start, end, *segment)
+        })
+        .collect()
+}
+
+fn to_srt(transcript: &Transcript) -> String {
+    let mut out = String::new();
+    for (index, (start, end, segment)) in cues(&transcript.segments).into_iter().enumerate() {
+        let _ = writeln!(out, "{}", index + 1);
+        let _ = writeln!(out, "{} --> {}", format_time(start, ','), format_time(end, ','));
+        let text = match &segment.speaker {
+            Some(speaker) => format!("{}: {}", speaker, segment.text.trim()),
+            None => segment.text.trim().to_string(),
+        };
+        for line in wrap(&text) {
+            let _ = writeln!(out, "{}", line);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn to_vtt(transcript: &Transcript) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for (start, end, segment) in cues(&transcript.segments) {
+        let _ = writeln!(out, "{} --> {}", format_time(start, '.'), format_time(end, '.'));
+        // Cue text may not contain markup characters or blank lines
+        let text = segment.text.trim().replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        for (n, line) in wrap(&text).iter().enumerate() {
+            match (&segment.speaker, n) {
+                (Some(speaker), 0) => {
+                    let _ = writeln!(out, "<v {}>{}", speaker.replace('>', ""), line);
+                }
+                _ => {
+                    let _ = writeln!(out, "{}", line);
+                }
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes `transcript` to `path` as SRT or WebVTT subtitles, one cue per
+/// segment, with speaker labels when it was diarized.
+#[tauri::command]
+#[specta::specta]
+pub fn export_subtitles(transcript: Transcript, path: String, format: SubtitleFormat) -> Result<(), String> {
+    let contents = match format {
+        SubtitleFormat::Srt => to_srt(&transcript),
+        SubtitleFormat::Vtt => to_vtt(&transcript),
+    };
+    fs::write(&path, contents).map_err(|e| format!("Failed to write subtitles: {}", e))
+}
@@ -0,0 +1,167 @@
+use serde::Serialize;
+use specta::Type;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// What a rejected command argument was wrong with. The checks below run
+/// before any file or device is touched, so the frontend can branch on the
+/// code instead of parsing whatever hound or cpal would have reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    EmptyPath,
+    FileNotFound,
+    NotAFile,
+    /// The folder an output file would be written to does not exist.
+    DirectoryNotFound,
+    UnsupportedSampleRate,
+    UnsupportedChannelCount,
+    OutOfRange,
+    InvalidId,
+    DeviceNotFound,
+    StreamNotMonitored,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::EmptyPath => "empty_path",
+            ErrorCode::FileNotFound => "file_not_found",
+            ErrorCode::NotAFile => "not_a_file",
+            ErrorCode::DirectoryNotFound => "directory_not_found",
+            ErrorCode::UnsupportedSampleRate => "unsupported_sample_rate",
+            ErrorCode::UnsupportedChannelCount => "unsupported_channel_count",
+            ErrorCode::OutOfRange => "out_of_range",
+            ErrorCode::InvalidId => "invalid_id",
+            ErrorCode::DeviceNotFound => "device_not_found",
+            ErrorCode::StreamNotMonitored => "stream_not_monitored",
+        }
+    }
+}
+
+/// Formats a failure as `<code>: <message>`, the code in snake case.
+pub fn error(code: ErrorCode, message: impl Display) -> String {
+    format!("{}: {}", code.as_str(), message)
+}
+
+/// Sample rates accepted for streams, files and resampling, in Hz.
+pub const SAMPLE_RATE_RANGE: (u32, u32) = (8_000, 384_000);
+
+/// Most channels a stream or file may have.
+pub const MAX_CHANNELS: u16 = 64;
+
+/// Allowed range for input, mix and clip gains, in dB.
+pub const GAIN_RANGE_DB: (f32, f32) = (-60.0, 24.0);
+
+/// Longest id accepted from the frontend.
+const MAX_ID_LEN: usize = 128;
+
+/// A file that is about to be read. Errors other than a missing file, such
+/// as a permission problem, are left for the reader to report.
+pub fn input_file(path: &Path) -> Result<(), String> {
+    if path.as_os_str().is_empty() {
+        return Err(error(ErrorCode::EmptyPath, "No file path given"));
+    }
+
+    match fs::metadata(path) {
+        Ok(meta) if !meta.is_file() => Err(error(ErrorCode::NotAFile, format!("{} is not a file", path.display()))),
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            Err(error(ErrorCode::FileNotFound, format!("File not found: {}", path.display())))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// A file that is about to be created or overwritten.
+pub fn output_file(path: &Path) -> Result<(), String> {
+    if path.as_os_str().is_empty() {
+        return Err(error(ErrorCode::EmptyPath, "No file path given"));
+    }
+    if path.is_dir() {
+        return Err(error(ErrorCode::NotAFile, format!("{} is a folder", path.display())));
+    }
+
+    match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) if !parent.is_dir() => {
+            Err(error(ErrorCode::DirectoryNotFound, format!("Folder not found: {}", parent.display())))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub fn sample_rate(rate: u32) -> Result<u32, String> {
+    let (min, max) = SAMPLE_RATE_RANGE;
+    if !(min..=max).contains(&rate) {
+        return Err(error(
+            ErrorCode::UnsupportedSampleRate,
+            format!("Sample rate must be between {} and {} Hz, got {}", min, max, rate),
+        ));
+    }
+    Ok(rate)
+}
+
+pub fn channel_count(channels: u16) -> Result<u16, String> {
+    if !(1..=MAX_CHANNELS).contains(&channels) {
+        return Err(error(
+            ErrorCode::UnsupportedChannelCount,
+            format!("Channel count must be between 1 and {}, got {}", MAX_CHANNELS, channels),
+        ));
+    }
+    Ok(channels)
+}
+
+/// Checks `value` against inclusive bounds; NaN is always rejected.
+/// `what` starts the message and `unit`, when not empty, follows the bounds.
+pub fn range<T: PartialOrd + Display + Copy>(what: &str, value: T, (min, max): (T, T), unit: &str) -> Result<T, String> {
+    if !(min..=max).contains(&value) {
+        let unit = if unit.is_empty() { String::new() } else { format!(" {}", unit) };
+        return Err(error(ErrorCode::OutOfRange, format!("{} must be between {} and {}{}", what, min, max, unit)));
+    }
+    Ok(value)
+}
+
+pub fn gain_db(gain_db: f32) -> Result<f32, String> {
+    range("Gain", gain_db, GAIN_RANGE_DB, "dB")
+}
+
+pub fn pan(pan: f32) -> Result<f32, String> {
+    range("Pan", pan, (-1.0, 1.0), "")
+}
+
+/// An id chosen by the frontend: not blank, at most `MAX_ID_LEN` characters
+/// and free of control characters. `kind` names it in the message.
+pub fn id(kind: &str, id: &str) -> Result<(), String> {
+    if id.trim().is_empty() {
+        return Err(error(ErrorCode::InvalidId, format!("{} id cannot be empty", kind)));
+    }
+    if id.chars().count() > MAX_ID_LEN || id.chars().any(char::is_control) {
+        return Err(error(
+            ErrorCode::InvalidId,
+            format!("{} id must be at most {} printable characters", kind, MAX_ID_LEN),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a device id from `get_audio_devices`, `<prefix><index>`, into
+/// its index.
+pub fn device_index(device_id: &str, prefix: &str) -> Result<usize, String> {
+    device_id
+        .strip_prefix(prefix)
+        .and_then(|index| index.parse().ok())
+        .ok_or_else(|| error(ErrorCode::InvalidId, format!("Invalid device ID '{}'", device_id)))
+}
+
+pub fn device_not_found(device_id: &str) -> String {
+    error(ErrorCode::DeviceNotFound, format!("Device '{}' not found", device_id))
+}
+
+/// The error for a stream id with no stream behind it.
+pub fn stream_not_monitored(stream_id: &str) -> String {
+    match id("Stream", stream_id) {
+        Err(e) => e,
+        Ok(()) => error(ErrorCode::StreamNotMonitored, format!("Stream '{}' is not being monitored", stream_id)),
+    }
+}
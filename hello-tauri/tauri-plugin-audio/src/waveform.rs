@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::audio_file::{read_audio_file, AudioBuffer};
+
+const DEFAULT_OVERVIEW_POINTS: usize = 2000;
+const MAX_OVERVIEW_POINTS: usize = 100_000;
+
+/// Min/max peak pairs per bucket and channel, for drawing a file's waveform.
+#[derive(Debug, Clone, Serialize, Deserialize, Type)]
+pub struct WaveformOverview {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration_ms: f32,
+    /// `peaks[channel][bucket] = [min, max]`
+    pub peaks: Vec<Vec<[f32; 2]>>,
+}
+
+/// What a cache file was computed from; a mismatch means it is stale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Type)]
+struct CacheKey {
+    path: String,
+    modified_ms: u128,
+    points: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize, Type)]
+struct CacheEntry {
+    key: CacheKey,
+    overview: WaveformOverview,
+}
+
+pub fn compute_overview(audio: &AudioBuffer, points: usize) -> WaveformOverview {
+    let channels = audio.channels.max(1) as usize;
+    let frames = audio.samples.len() / channels;
+    let points = points.clamp(1, frames.max(1));
+
+    let mut peaks = vec![Vec::with_capacity(points); channels];
+    for bucket in 0..points {
+        let start = bucket * frames / points;
+        let end = ((bucket + 1) * frames / points).max(start + 1).min(frames);
+
+        for (channel, channel_peaks) in peaks.iter_mut().enumerate() {
+            let (min, max) = (start..end)
+                .map(|frame| audio.samples[frame * channels + channel])
+                .fold((0.0f32, 0.0f32), |(min, max), s| (min.min(s), max.max(s)));
+            channel_peaks.push([min, max]);
+        }
+    }
+
+    WaveformOverview {
+        sample_rate: audio.sample_rate,
+        channels: audio.channels,
+        duration_ms: frames as f32 * 1000.0 / audio.sample_rate as f32,
+        peaks,
+    }
+}
+
+fn cache_key(path: &Path, points: usize) -> Result<CacheKey, String> {
+    let modified = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+
+    Ok(CacheKey {
+        path: path.display().to_string(),
+        modified_ms: modified.duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0),
+        points,
+    })
+}
+
+fn cache_file(cache_dir: &Path, key: &CacheKey) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    key.path.hash(&mut hasher);
+    key.points.hash(&mut hasher);
+    cache_dir.join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn load_cached(file: &Path, key: &CacheKey) -> Option<WaveformOverview> {
+    let contents = fs::read_to_string(file).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+    (entry.key == *key).then_some(entry.overview)
+}
+
+fn store_cached(file: &Path, key: CacheKey, overview: &WaveformOverview) -> Result<(), String> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create cache directory: {}", e))?;
+    }
+
+    let entry = CacheEntry {
+        key,
+        overview: overview.clone(),
+    };
+    let contents = serde_json::to_string(&entry).map_err(|e| format!("Failed to serialize overview: {}", e))?;
+    fs::write(file, contents).map_err(|e| format!("Failed to write overview cache: {}", e))
+}
+
+/// Returns per-channel peak data for `file_path`, reusing a cached copy when
+/// the file has not been modified since it was computed.
+pub fn waveform_overview(app: &AppHandle, file_path: &str, points: usize) -> Result<WaveformOverview, String> {
+    let path = Path::new(file_path);
+    let key = cache_key(path, points)?;
+
+    let cache = app
+        .path()
+        .app_cache_dir()
+        .ok()
+        .map(|dir| cache_file(&dir.join("waveforms"), &key));
+
+    if let Some(overview) = cache.as_deref().and_then(|file| load_cached(file, &key)) {
+        return Ok(overview);
+    }
+
+    let overview = compute_overview(&read_audio_file(path)?, points);
+
+    if let Some(file) = cache {
+        // A failed cache write only costs a rescan next time
+        if let Err(e) = store_cached(&file, key, &overview) {
+            eprintln!("{}", e);
+        }
+    }
+
+    Ok(overview)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_waveform_overview(
+    file_path: String,
+    points: Option<usize>,
+    app: AppHandle,
+) -> Result<WaveformOverview, String> {
+    let points = points.unwrap_or(DEFAULT_OVERVIEW_POINTS).clamp(1, MAX_OVERVIEW_POINTS);
+
+    tauri::async_runtime::spawn_blocking(move || waveform_overview(&app, &file_path, points))
+        .await
+        .map_err(|e| format!("Overview task failed: {}", e))?
+}
@@ -0,0 +1,17 @@
+// Writes the frontend's command and event bindings without starting the app,
+// so a clean checkout can type-check and build the frontend.
+// Run as `deno task bindings` from the template directory.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let dir = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("src/bindings"));
+    match tauri_plugin_audio::export_bindings(&dir) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,8 +1,4 @@
-interface WavData {
-  samples: number[];
-  sample_rate: number;
-  duration_ms: number;
-}
+import type { WavData } from "../bindings/audio.ts";
 
 interface RenderParams {
   wavData: WavData | null;
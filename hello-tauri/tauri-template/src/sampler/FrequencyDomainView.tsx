@@ -4,12 +4,7 @@ import { MagnitudeLegend } from "./MagnitudeLegend.tsx";
 import { useState, useCallback, useEffect, useMemo, useRef } from "react";
 import { renderSpectrogram, type SpectrogramData } from "./SpectrogramRenderer.tsx";
 import { computeCQT } from "./cqt/cqt.ts";
-
-interface WavData {
-  samples: number[];
-  sample_rate: number;
-  duration_ms: number;
-}
+import type { WavData } from "../bindings/audio.ts";
 
 interface FrequencyDomainViewProps {
   canvasWidth: number; // Canvas width in px
@@ -2,18 +2,12 @@ import { Stack, Group, ActionIcon, Text } from "@mantine/core";
 import { IconFolder } from "@tabler/icons-react";
 import { useState, useEffect } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
-import { invoke } from "@tauri-apps/api/core";
+import { commands, type WavData } from "../bindings/audio.ts";
 import { TimeDomainView } from "./TimeDomainView";
 // import { FrequencyDomainView } from "./FrequencyDomainView";
 import { ScopeView } from "./scope/ScopeView";
 import AnalyzerService from "./scope/analyzer-service";
 
-interface WavData {
-  samples: number[];
-  sample_rate: number;
-  duration_ms: number;
-}
-
 interface SamplerProps {
   color0: string;
   color1: string;
@@ -44,7 +38,7 @@ export function Sampler({ }: SamplerProps) {
 
     const loadWavFile = async () => {
       try {
-        const data = await invoke<WavData>("read_wav_file", { filePath: selectedFile });
+        const data = await commands.readWavFile(selectedFile, null, null);
         setWavData(data);
       } catch (error) {
         console.error("Failed to load WAV file:", error);
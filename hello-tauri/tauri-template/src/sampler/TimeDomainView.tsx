@@ -1,15 +1,9 @@
 import { Card, Group, Text, Stack, AngleSlider } from "@mantine/core";
 import { CanvasChart } from "./CanvasChart.tsx";
 import { useState, useEffect, useCallback } from "react";
-import { invoke } from "@tauri-apps/api/core";
+import { commands, type WavData } from "../bindings/audio.ts";
 import { renderAmplitudeEnvelope } from "./AmplitudeEnvelopeRenderer.tsx";
 
-interface WavData {
-  samples: number[];
-  sample_rate: number;
-  duration_ms: number;
-}
-
 interface TimeDomainViewProps {
   canvasWidth: number; // Canvas width in px
   canvasHeight: number; // Canvas height in px
@@ -41,7 +35,7 @@ export function TimeDomainView({
 
     const loadWavFile = async () => {
       try {
-        const data = await invoke<WavData>("read_wav_file", { filePath: wavFilePath });
+        const data = await commands.readWavFile(wavFilePath, null, null);
         setWavData(data);
       } catch (error) {
         console.error("Failed to load WAV file:", error);
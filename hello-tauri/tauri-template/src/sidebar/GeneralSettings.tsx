@@ -1,11 +1,6 @@
 import { useState, useEffect } from "react";
 import { Box, Combobox, Group, Progress, Stack, Text, useCombobox, InputBase } from "@mantine/core";
-import { invoke } from "@tauri-apps/api/core";
-
-interface AudioDevice {
-  name: string;
-  id: string;
-}
+import { commands, type AudioDevice } from "../bindings/audio.ts";
 
 export function GeneralSettings() {
   const [devices, setDevices] = useState<AudioDevice[]>([]);
@@ -32,7 +27,7 @@ export function GeneralSettings() {
     const interval = setInterval(async () => {
       if (primaryDevice) {
         try {
-          const volume = await invoke<number>("get_volume", { isPrimary: true });
+          const volume = await commands.getVolume(true);
           setPrimaryVolume(volume);
         } catch (error) {
           console.error("Failed to get primary volume:", error);
@@ -43,7 +38,7 @@ export function GeneralSettings() {
 
       if (secondaryDevice) {
         try {
-          const volume = await invoke<number>("get_volume", { isPrimary: false });
+          const volume = await commands.getVolume(false);
           setSecondaryVolume(volume);
         } catch (error) {
           console.error("Failed to get secondary volume:", error);
@@ -61,16 +56,13 @@ export function GeneralSettings() {
     const handlePrimaryChange = async () => {
       if (primaryDevice) {
         try {
-          await invoke("start_monitoring", {
-            deviceId: primaryDevice,
-            isPrimary: true
-          });
+          await commands.startMonitoring(primaryDevice, true, null, null);
         } catch (error) {
           console.error("Failed to start monitoring primary device:", error);
         }
       } else {
         try {
-          await invoke("stop_monitoring", { isPrimary: true });
+          await commands.stopMonitoring(true);
         } catch (error) {
           console.error("Failed to stop monitoring primary device:", error);
         }
@@ -84,16 +76,13 @@ export function GeneralSettings() {
     const handleSecondaryChange = async () => {
       if (secondaryDevice) {
         try {
-          await invoke("start_monitoring", {
-            deviceId: secondaryDevice,
-            isPrimary: false
-          });
+          await commands.startMonitoring(secondaryDevice, false, null, null);
         } catch (error) {
           console.error("Failed to start monitoring secondary device:", error);
         }
       } else {
         try {
-          await invoke("stop_monitoring", { isPrimary: false });
+          await commands.stopMonitoring(false);
         } catch (error) {
           console.error("Failed to stop monitoring secondary device:", error);
         }
@@ -105,21 +94,26 @@ export function GeneralSettings() {
 
   const loadAudioDevices = async () => {
     try {
-      const deviceList = await invoke<AudioDevice[]>("get_audio_devices");
-      setDevices(deviceList);
+      const deviceList = await commands.getAudioDevices();
+      setDevices(deviceList.filter(d => d.direction === "input"));
     } catch (error) {
       console.error("Failed to load audio devices:", error);
     }
   };
 
-  const primaryDeviceName = devices.find(d => d.id === primaryDevice)?.name || "None";
-  const secondaryDeviceName = devices.find(d => d.id === secondaryDevice)?.name || "None";
+  const deviceLabel = (id: string | null) => {
+    const device = devices.find(d => d.id === id);
+    return device ? device.alias ?? device.name : "None";
+  };
+
+  const primaryDeviceName = deviceLabel(primaryDevice);
+  const secondaryDeviceName = deviceLabel(secondaryDevice);
 
   const primaryOptions = [
     <Combobox.Option value="none" key="none">None</Combobox.Option>,
     ...devices.map((device) => (
       <Combobox.Option value={device.id} key={device.id}>
-        {device.name}
+        {device.alias ?? device.name}
       </Combobox.Option>
     ))
   ];
@@ -128,7 +122,7 @@ export function GeneralSettings() {
     <Combobox.Option value="none" key="none">None</Combobox.Option>,
     ...devices.map((device) => (
       <Combobox.Option value={device.id} key={device.id}>
-        {device.name}
+        {device.alias ?? device.name}
       </Combobox.Option>
     ))
   ];